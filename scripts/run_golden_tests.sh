@@ -0,0 +1,73 @@
+#!/usr/bin/env bash
+# Run every test script headlessly and compare screenshots and camera logs
+# against the goldens in $GOLDEN_DIR/<test>/ (default scripts/golden).
+# Goldens are rendered from the game data and aren't checked in; see
+# scripts/TESTS.md. A script without goldens fails the run.
+# Usage: [GOLDEN_DIR=DIR] bash scripts/run_golden_tests.sh '<path_to_pop_base>' [--update]
+
+set -o pipefail
+
+BASE="$1"
+if [ -z "$BASE" ]; then
+    echo "Usage: $0 '<path_to_populous_base_dir>' [--update]"
+    exit 1
+fi
+UPDATE=""
+if [ "$2" = "--update" ]; then
+    UPDATE="--update-golden"
+fi
+
+SCRIPT_DIR="$(cd "$(dirname "$0")" && pwd)"
+GOLDEN_ROOT="${GOLDEN_DIR:-$SCRIPT_DIR/golden}"
+OUT_ROOT="/tmp/pop3_golden"
+FAILED_LIST=""
+FAILED=0
+PASSED=0
+MISSING=0
+
+cargo build --release || exit 1
+
+for script in "$SCRIPT_DIR"/test_*.txt; do
+    name="$(basename "$script" .txt)"
+    out="$OUT_ROOT/$name"
+    golden="$GOLDEN_ROOT/$name"
+    echo "=== Running $name ==="
+
+    rm -rf "$out"
+    if [ -z "$UPDATE" ] && [ ! -d "$golden" ]; then
+        echo "MISSING: $name - no goldens in $golden (run with --update to create them)"
+        MISSING=$((MISSING + 1))
+        FAILED_LIST="$FAILED_LIST  - $name (no goldens)\n"
+        echo ""
+        continue
+    fi
+
+    if cargo run --release -q -- --base "$BASE" --level 1 --script "$script" \
+            --headless "$out" --golden "$golden" $UPDATE 2>&1 \
+            | grep -E "^(golden|updated|headless run failed)"; then
+        echo "PASS: $name"
+        PASSED=$((PASSED + 1))
+    else
+        echo "FAIL: $name (output and diff images in $out)"
+        FAILED=$((FAILED + 1))
+        FAILED_LIST="$FAILED_LIST  - $name\n"
+    fi
+    echo ""
+done
+
+echo "=== Summary ==="
+echo "Passed: $PASSED"
+echo "Failed: $FAILED"
+echo "No goldens: $MISSING"
+
+if [ "$FAILED" -gt 0 ] || [ "$MISSING" -gt 0 ]; then
+    echo ""
+    echo "Failed tests:"
+    echo -e "$FAILED_LIST"
+    exit 1
+fi
+if [ "$PASSED" -eq 0 ]; then
+    echo "No test scripts compared."
+    exit 1
+fi
+echo "All tests passed."
@@ -0,0 +1,65 @@
+// Output device backend — pulls mixed audio from a shared Mixer on the
+// device's callback thread.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+use super::mixer::Mixer;
+
+pub struct AudioDevice {
+    mixer: Arc<Mutex<Mixer>>,
+    _stream: cpal::Stream,
+}
+
+impl AudioDevice {
+    /// Open the default output device. Returns None when there is no device
+    /// or it doesn't take f32 output; the game then runs silent.
+    pub fn open() -> Option<Self> {
+        let host = cpal::default_host();
+        let device = host.default_output_device()?;
+        let supported = device.default_output_config().ok()?;
+        if supported.sample_format() != cpal::SampleFormat::F32 {
+            log::warn!("[audio] unsupported output format {:?}", supported.sample_format());
+            return None;
+        }
+        let config: cpal::StreamConfig = supported.into();
+        let channels = config.channels.max(1) as usize;
+
+        let mixer = Arc::new(Mutex::new(Mixer::new(config.sample_rate.0)));
+        let shared = Arc::clone(&mixer);
+        let mut scratch: Vec<i16> = Vec::new();
+        let stream = device.build_output_stream(
+            &config,
+            move |out: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                let frames = out.len() / channels;
+                scratch.resize(frames * 2, 0);
+                match shared.lock() {
+                    Ok(mut mixer) => mixer.render(&mut scratch),
+                    Err(_) => scratch.fill(0),
+                }
+                for (frame, stereo) in out.chunks_exact_mut(channels).zip(scratch.chunks_exact(2)) {
+                    for (ch, o) in frame.iter_mut().enumerate() {
+                        let s = match (channels, ch) {
+                            (1, _) => (stereo[0] as i32 + stereo[1] as i32) / 2,
+                            (_, 0) => stereo[0] as i32,
+                            (_, 1) => stereo[1] as i32,
+                            _ => 0,
+                        };
+                        *o = s as f32 / 32768.0;
+                    }
+                }
+            },
+            |err| log::warn!("[audio] stream error: {}", err),
+            None,
+        ).ok()?;
+        stream.play().ok()?;
+        log::info!("[audio] output {} Hz, {} channels", config.sample_rate.0, channels);
+        Some(Self { mixer, _stream: stream })
+    }
+
+    /// Lock the mixer to load banks, move the listener or play sounds.
+    pub fn mixer(&self) -> MutexGuard<'_, Mixer> {
+        self.mixer.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
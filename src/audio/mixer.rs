@@ -0,0 +1,338 @@
+// Software mixer — plays SDT samples on a fixed set of voices and renders
+// interleaved 16-bit stereo.
+
+use crate::data::sdt::{SoundBank, SdtEntry, write_wav, SampleFormat};
+use crate::engine::movement::WorldCoord;
+use crate::engine::sound::SoundEvent;
+use crate::engine::state::rng::GameRng;
+use super::sound_table::{SoundId, SoundTable, game_sound_id};
+use super::positional::{Listener, positional_volume_pan, PAN_CENTRE};
+
+pub type VoiceId = u32;
+
+/// Output rate used unless the device asks for another.
+pub const MIXER_SAMPLE_RATE: u32 = 22050;
+/// Voices mixed at once.
+pub const MAX_VOICES: usize = 16;
+/// Full volume / pan scale.
+const VOLUME_MAX: i32 = 127;
+
+// Sound_Play flags.
+/// Not positioned: plays centred at full amplitude.
+pub const PLAY_2D: u16 = 0x0001;
+/// Restart from the beginning when the sample ends.
+pub const PLAY_LOOP: u16 = 0x0004;
+/// May steal a voice from a non-priority sound when all are busy.
+pub const PLAY_PRIORITY: u16 = 0x0010;
+/// Keeps playing while the game is paused.
+pub const PLAY_IGNORE_PAUSE: u16 = 0x0800;
+
+/// A decoded bank sample: mono, signed 16-bit.
+pub struct Sample {
+    pub rate: u32,
+    pub data: Vec<i16>,
+}
+
+impl Sample {
+    /// Decode an SDT entry (8-bit unsigned or 16-bit signed PCM, any
+    /// channel count) to mono 16-bit.
+    pub fn from_entry(entry: &SdtEntry) -> Self {
+        let channels = entry.format.channels.max(1) as usize;
+        let decoded: Vec<i16> = if entry.format.bits_per_sample <= 8 {
+            entry.data.iter().map(|&b| ((b as i16) - 128) << 8).collect()
+        } else {
+            entry.data.chunks_exact(2).map(|c| i16::from_le_bytes([c[0], c[1]])).collect()
+        };
+        let data = decoded.chunks_exact(channels)
+            .map(|frame| (frame.iter().map(|&s| s as i32).sum::<i32>() / channels as i32) as i16)
+            .collect();
+        Self { rate: entry.sample_rate, data }
+    }
+}
+
+/// A playing sound.
+/// Original: SoundControl (0x2A bytes) in the active sounds list.
+pub struct Voice {
+    pub id: VoiceId,
+    pub sound_id: SoundId,
+    pub sample: usize,
+    /// Playback position in the sample, 16.16 fixed point.
+    pub position: u64,
+    /// Position advance per output frame, 16.16 fixed point.
+    pub step: u32,
+    /// Current volume, 0-127.
+    pub volume: u8,
+    /// Stereo pan, 0 (left) - 127 (right).
+    pub pan: u8,
+    /// Base amplitude, 0-127.
+    pub amplitude: u8,
+    pub flags: u16,
+    /// Source position; None for 2D sounds.
+    pub source: Option<WorldCoord>,
+}
+
+pub struct Mixer {
+    pub output_rate: u32,
+    /// Master volume, 0-127.
+    pub master_volume: u8,
+    pub paused: bool,
+    samples: Vec<Sample>,
+    table: SoundTable,
+    voices: Vec<Voice>,
+    listener: Listener,
+    next_id: VoiceId,
+    // Separate RNG for variant/pitch picks, as the original keeps its own
+    // (0x0088420a) so audio never perturbs the simulation.
+    rng: GameRng,
+}
+
+impl Mixer {
+    pub fn new(output_rate: u32) -> Self {
+        Self {
+            output_rate: output_rate.max(1),
+            master_volume: VOLUME_MAX as u8,
+            paused: false,
+            samples: Vec::new(),
+            table: SoundTable::identity(0),
+            voices: Vec::new(),
+            listener: Listener::default(),
+            next_id: 0,
+            rng: GameRng::new(0x5EED),
+        }
+    }
+
+    /// Replace the sample set with a bank's entries; sound ids map to
+    /// bank entries one-to-one.
+    pub fn load_bank(&mut self, bank: &SoundBank) {
+        self.voices.clear();
+        self.samples = bank.entries.iter().map(Sample::from_entry).collect();
+        self.table = SoundTable::identity(self.samples.len());
+        log::info!("[audio] loaded {} samples", self.samples.len());
+    }
+
+    pub fn set_samples(&mut self, samples: Vec<Sample>, table: SoundTable) {
+        self.voices.clear();
+        self.samples = samples;
+        self.table = table;
+    }
+
+    pub fn voices(&self) -> &[Voice] {
+        &self.voices
+    }
+
+    pub fn listener(&self) -> &Listener {
+        &self.listener
+    }
+
+    /// Move the listener and re-attenuate positioned voices.
+    pub fn set_listener(&mut self, listener: Listener) {
+        self.listener = listener;
+        for voice in &mut self.voices {
+            if let Some(source) = voice.source {
+                voice.volume = positional_volume_pan(source, &self.listener, voice.amplitude)
+                    .map_or(0, |(v, p)| { voice.pan = p; v });
+            }
+        }
+    }
+
+    /// Start a sound. Positioned sounds out of earshot aren't started unless
+    /// looping. Returns the voice, or None if the sound is unknown, silent
+    /// or no voice is free.
+    /// Original: Sound_Play @ 0x00417300
+    pub fn play(&mut self, sound_id: SoundId, source: Option<WorldCoord>, flags: u16) -> Option<VoiceId> {
+        let info = *self.table.get(sound_id)?;
+        let sample = self.table.pick_sample(sound_id, &mut self.rng)?;
+        let sample_rate = self.samples.get(sample)?.rate;
+
+        let source = if flags & PLAY_2D != 0 { None } else { source };
+        let (volume, pan) = match source {
+            Some(pos) => match positional_volume_pan(pos, &self.listener, info.amplitude) {
+                Some(vp) => vp,
+                None if flags & PLAY_LOOP != 0 => (0, PAN_CENTRE),
+                None => return None,
+            },
+            None => (info.amplitude.min(VOLUME_MAX as u8), PAN_CENTRE),
+        };
+
+        if self.voices.len() >= MAX_VOICES {
+            let victim = if flags & PLAY_PRIORITY != 0 {
+                self.voices.iter().position(|v| v.flags & PLAY_PRIORITY == 0)
+            } else {
+                None
+            };
+            self.voices.remove(victim?);
+        }
+
+        let mut step = ((sample_rate as u64) << 16) / self.output_rate as u64;
+        if info.pitch_variation > 0 {
+            let range = info.pitch_variation as i64 * 2 + 1;
+            let pct = 100 + (self.rng.next() as i64 % range) - info.pitch_variation as i64;
+            step = (step as i64 * pct / 100).max(1) as u64;
+        }
+
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.voices.push(Voice {
+            id,
+            sound_id,
+            sample,
+            position: 0,
+            step: step.min(u32::MAX as u64) as u32,
+            volume,
+            pan,
+            amplitude: info.amplitude,
+            flags,
+            source,
+        });
+        Some(id)
+    }
+
+    pub fn stop(&mut self, id: VoiceId) {
+        self.voices.retain(|v| v.id != id);
+    }
+
+    pub fn stop_all(&mut self) {
+        self.voices.clear();
+    }
+
+    /// Play the sounds the simulation raised.
+    pub fn handle_events(&mut self, events: &[SoundEvent]) {
+        for event in events {
+            let flags = if event.position.is_none() { PLAY_2D } else { 0 };
+            self.play(game_sound_id(event.sound), event.position, flags);
+        }
+    }
+
+    /// Mix into `out`, interleaved stereo (L, R). Finished one-shot voices
+    /// are released.
+    pub fn render(&mut self, out: &mut [i16]) {
+        let mut acc = vec![0i32; out.len()];
+        let master = self.master_volume as i32;
+        let paused = self.paused;
+        let samples = &self.samples;
+        self.voices.retain_mut(|voice| {
+            if paused && voice.flags & PLAY_IGNORE_PAUSE == 0 {
+                return true;
+            }
+            let sample = match samples.get(voice.sample) {
+                Some(s) if !s.data.is_empty() => s,
+                _ => return false,
+            };
+            let len = sample.data.len() as u64;
+            let gain = voice.volume as i64 * master as i64;
+            let left = gain * (VOLUME_MAX - voice.pan as i32) as i64;
+            let right = gain * voice.pan as i64;
+            let scale = (VOLUME_MAX * VOLUME_MAX) as i64 * PAN_CENTRE as i64;
+            for frame in acc.chunks_exact_mut(2) {
+                let mut idx = voice.position >> 16;
+                if idx >= len {
+                    if voice.flags & PLAY_LOOP == 0 {
+                        return false;
+                    }
+                    voice.position %= len << 16;
+                    idx = voice.position >> 16;
+                }
+                let s = sample.data[idx as usize] as i64;
+                // Centre pan (64) gives each side about half; scale so a
+                // centred full-volume voice reaches full scale per side.
+                frame[0] += (s * left / scale) as i32;
+                frame[1] += (s * right / scale) as i32;
+                voice.position += voice.step as u64;
+            }
+            voice.position >> 16 < len || voice.flags & PLAY_LOOP != 0
+        });
+        for (o, a) in out.iter_mut().zip(acc) {
+            *o = a.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+        }
+    }
+
+    /// Render `frames` stereo frames into a WAV file image.
+    pub fn render_wav(&mut self, frames: usize) -> Vec<u8> {
+        let mut buf = vec![0i16; frames * 2];
+        self.render(&mut buf);
+        let bytes: Vec<u8> = buf.iter().flat_map(|s| s.to_le_bytes()).collect();
+        write_wav(self.output_rate, SampleFormat { channels: 2, bits_per_sample: 16 }, &bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::sdt::parse_wav;
+    use crate::engine::sound::GameSound;
+    use crate::audio::sound_table::SoundInfo;
+
+    fn tone(len: usize) -> Sample {
+        Sample { rate: MIXER_SAMPLE_RATE, data: vec![16000; len] }
+    }
+
+    fn mixer_with(samples: Vec<Sample>) -> Mixer {
+        let mut mixer = Mixer::new(MIXER_SAMPLE_RATE);
+        let table = SoundTable::identity(samples.len());
+        mixer.set_samples(samples, table);
+        mixer
+    }
+
+    #[test]
+    fn one_shot_plays_then_releases() {
+        let mut mixer = mixer_with(vec![tone(100)]);
+        mixer.play(0, None, PLAY_2D).unwrap();
+        let mut out = vec![0i16; 2 * 150];
+        mixer.render(&mut out);
+        assert!(out[0] > 0 && out[1] > 0);
+        // Centred: both sides within a pan step of each other
+        assert!((out[0] as i32 - out[1] as i32).abs() < out[0] as i32 / 32);
+        assert_eq!(out[2 * 120], 0);
+        assert!(mixer.voices().is_empty());
+    }
+
+    #[test]
+    fn looping_voice_keeps_playing() {
+        let mut mixer = mixer_with(vec![tone(10)]);
+        mixer.play(0, None, PLAY_2D | PLAY_LOOP).unwrap();
+        let mut out = vec![0i16; 2 * 100];
+        mixer.render(&mut out);
+        assert!(out[2 * 99] > 0);
+        assert_eq!(mixer.voices().len(), 1);
+    }
+
+    #[test]
+    fn positioned_sound_is_quieter_far_away_and_silent_out_of_range() {
+        let mut mixer = mixer_with(vec![tone(1000)]);
+        mixer.set_listener(Listener::new(WorldCoord::new(0x1000, 0x1000), 0));
+        let near = mixer.play(0, Some(WorldCoord::new(0x1000, 0x1000)), 0).unwrap();
+        let far = mixer.play(0, Some(WorldCoord::new(0x2800, 0x1000)), 0).unwrap();
+        let vol = |m: &Mixer, id| m.voices().iter().find(|v| v.id == id).unwrap().volume;
+        assert!(vol(&mixer, far) < vol(&mixer, near));
+        assert_eq!(mixer.play(0, Some(WorldCoord::new(0x6000, 0x1000)), 0), None);
+    }
+
+    #[test]
+    fn voices_are_limited_and_priority_steals() {
+        let mut mixer = mixer_with(vec![tone(1000)]);
+        for _ in 0..MAX_VOICES {
+            mixer.play(0, None, PLAY_2D).unwrap();
+        }
+        assert_eq!(mixer.play(0, None, PLAY_2D), None);
+        assert!(mixer.play(0, None, PLAY_2D | PLAY_PRIORITY).is_some());
+        assert_eq!(mixer.voices().len(), MAX_VOICES);
+    }
+
+    #[test]
+    fn events_render_to_wav() {
+        let mut samples: Vec<Sample> = (0..0x80).map(|_| tone(0)).collect();
+        samples[game_sound_id(GameSound::CombatStrike) as usize] = tone(500);
+        let table = SoundTable::from_entries((0..0x80).map(|i| SoundInfo {
+            sample: i, variants: 1, amplitude: 127, pitch_variation: 0,
+        }).collect());
+        let mut mixer = Mixer::new(MIXER_SAMPLE_RATE);
+        mixer.set_samples(samples, table);
+        mixer.handle_events(&[SoundEvent::global(GameSound::CombatStrike)]);
+        let wav = mixer.render_wav(200);
+        let (rate, format, pcm) = parse_wav(&wav).unwrap();
+        assert_eq!(rate, MIXER_SAMPLE_RATE);
+        assert_eq!(format.channels, 2);
+        assert_eq!(pcm.len(), 200 * 4);
+        assert!(pcm.iter().any(|&b| b != 0));
+    }
+}
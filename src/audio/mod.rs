@@ -0,0 +1,20 @@
+// Audio — software mixer for SDT sound bank samples with 3D positioning
+// relative to the camera.
+//
+// Modelled on Sound_Play (0x00417300) and its sound control structure:
+// each playing sound is a voice with volume, pan and flags, attenuated and
+// panned by FUN_004183b0's distance/angle formula. The mixer renders into
+// plain sample buffers so it runs (and is tested) offline; the device
+// backend behind the `audio-device` feature only pulls from it.
+
+pub mod sound_table;
+pub mod positional;
+pub mod mixer;
+#[cfg(feature = "audio-device")]
+pub mod device;
+
+pub use sound_table::{SoundId, SoundInfo, SoundTable, game_sound_id};
+pub use positional::{Listener, positional_volume_pan};
+pub use mixer::{Mixer, Sample, Voice, VoiceId};
+#[cfg(feature = "audio-device")]
+pub use device::AudioDevice;
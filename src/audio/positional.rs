@@ -0,0 +1,86 @@
+// Positional audio — volume and stereo pan of a world-space sound as heard
+// from the camera.
+
+use crate::engine::movement::{WorldCoord, atan2};
+use crate::engine::units::coords::toroidal_delta;
+use crate::render::camera::Camera;
+
+/// Squared distance at which sounds fall silent (~0x3000 world units).
+pub const MAX_AUDIBLE_DIST_SQ: i64 = 0x9000000;
+/// Pan of a centred (2D) sound.
+pub const PAN_CENTRE: u8 = 64;
+
+/// Where the camera hears from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Listener {
+    pub position: WorldCoord,
+    /// Camera heading, 11-bit game angle.
+    pub angle: u16,
+}
+
+impl Listener {
+    pub fn new(position: WorldCoord, angle: u16) -> Self {
+        Self { position, angle: angle & 0x7FF }
+    }
+
+    /// Listener at `position` facing the camera's heading (degrees).
+    pub fn from_camera(camera: &Camera, position: WorldCoord) -> Self {
+        let angle = (camera.angle_z as i32).rem_euclid(360) * 2048 / 360;
+        Self::new(position, angle as u16)
+    }
+}
+
+impl Default for Listener {
+    fn default() -> Self {
+        Self::new(WorldCoord::new(0, 0), 0)
+    }
+}
+
+/// Volume (0..=amplitude) and pan (0-127) of a sound at `source`.
+/// Returns None when it's out of earshot.
+/// Original: FUN_004183b0
+pub fn positional_volume_pan(source: WorldCoord, listener: &Listener, amplitude: u8) -> Option<(u8, u8)> {
+    // camera - entity
+    let dx = toroidal_delta(source.x, listener.position.x);
+    let dz = toroidal_delta(source.z, listener.position.z);
+    let dist_sq = dx as i64 * dx as i64 + dz as i64 * dz as i64;
+    if dist_sq >= MAX_AUDIBLE_DIST_SQ {
+        return None;
+    }
+    let volume = (((MAX_AUDIBLE_DIST_SQ - dist_sq) / 0x900) * amplitude as i64) >> 16;
+
+    let angle = atan2(dx, -dz) as i32;
+    let pan_angle = ((angle & 0x7FF) - ((listener.angle as i32 + 0x200) & 0x7FF)) & 0x7FF;
+    let pan = if pan_angle < 0x400 { pan_angle >> 3 } else { (0x7FF - pan_angle) >> 3 };
+    Some((volume.clamp(0, 127) as u8, pan as u8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volume_falls_off_with_distance() {
+        let listener = Listener::new(WorldCoord::new(0x1000, 0x1000), 0);
+        let (near, _) = positional_volume_pan(WorldCoord::new(0x1000, 0x1000), &listener, 100).unwrap();
+        let (mid, _) = positional_volume_pan(WorldCoord::new(0x2000, 0x1000), &listener, 100).unwrap();
+        assert_eq!(near, 100);
+        assert!(mid < near && mid > 0);
+        assert_eq!(positional_volume_pan(WorldCoord::new(0x5000, 0x1000), &listener, 100), None);
+    }
+
+    #[test]
+    fn distance_wraps_around_the_world() {
+        let listener = Listener::new(WorldCoord::new(0x7F00, 0), 0);
+        assert!(positional_volume_pan(WorldCoord::new(-0x7F00, 0), &listener, 100).is_some());
+    }
+
+    #[test]
+    fn pan_stays_in_range_and_follows_heading() {
+        let source = WorldCoord::new(0x1400, 0x1000);
+        let a = positional_volume_pan(source, &Listener::new(WorldCoord::new(0x1000, 0x1000), 0), 100).unwrap().1;
+        let b = positional_volume_pan(source, &Listener::new(WorldCoord::new(0x1000, 0x1000), 0x400), 100).unwrap().1;
+        assert!(a <= 127 && b <= 127);
+        assert_ne!(a, b);
+    }
+}
@@ -0,0 +1,84 @@
+// Sound data table — maps sound ids to bank samples and playback settings.
+
+use crate::engine::sound::GameSound;
+use crate::engine::state::rng::GameRng;
+
+pub type SoundId = u16;
+
+/// Default amplitude of a sound (SoundControl base_volume).
+pub const SOUND_BASE_AMPLITUDE: u8 = 100;
+
+/// Per-sound entry.
+/// Original: sound data table @ 0x005a5c70, 0x0C bytes per sound id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SoundInfo {
+    /// First bank sample of the sound.
+    pub sample: u16,
+    /// Number of consecutive samples to pick from at random (>= 1).
+    pub variants: u8,
+    /// Base amplitude, 0-127.
+    pub amplitude: u8,
+    /// Random pitch variation, +/- percent.
+    pub pitch_variation: u8,
+}
+
+pub struct SoundTable {
+    entries: Vec<SoundInfo>,
+}
+
+impl SoundTable {
+    /// Table mapping each sound id to the bank sample of the same index.
+    /// Stands in for the original table until it is extracted from the binary.
+    pub fn identity(sample_count: usize) -> Self {
+        let entries = (0..sample_count.min(u16::MAX as usize))
+            .map(|i| SoundInfo {
+                sample: i as u16,
+                variants: 1,
+                amplitude: SOUND_BASE_AMPLITUDE,
+                pitch_variation: 0,
+            })
+            .collect();
+        Self { entries }
+    }
+
+    pub fn from_entries(entries: Vec<SoundInfo>) -> Self {
+        Self { entries }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, id: SoundId) -> Option<&SoundInfo> {
+        self.entries.get(id as usize)
+    }
+
+    /// Choose the bank sample for a sound, picking a random variant.
+    pub fn pick_sample(&self, id: SoundId, rng: &mut GameRng) -> Option<usize> {
+        let info = self.get(id)?;
+        let variant = if info.variants > 1 { rng.next() % info.variants as u32 } else { 0 };
+        Some(info.sample as usize + variant as usize)
+    }
+}
+
+/// Sound id played for a gameplay event.
+///
+/// Unverified: none of these ids is confirmed against the original's
+/// per-event calls to Sound_Play. Strikes and deaths use the start of
+/// 0x73-0x86, which docs/specs/audio.md lists as combat sounds in one table
+/// and unit acknowledgements in another; shaman events use 0x19
+/// ("shaman-related"); the rest reuse ids Effect_Init plays.
+pub fn game_sound_id(sound: GameSound) -> SoundId {
+    match sound {
+        GameSound::CombatStrike => 0x73,
+        GameSound::UnitDeath => 0x74,
+        GameSound::ShamanDeath => 0x19,
+        GameSound::ShamanReincarnated => 0x19,
+        GameSound::Discovery => 0xA2,
+        GameSound::Splash => 0xB2,
+    }
+}
@@ -0,0 +1,345 @@
+//! Lockstep loopback test — runs several multiplayer peers in one process,
+//! each with its own world, talking over UDP on 127.0.0.1. Every player
+//! keeps ordering its tribe's units around; at the end each peer's unit
+//! state is compared tick by tick against player 0's:
+//!   lockstep_check --base POP --level 1 --players 3 --ticks 600   (exit 1 on desync)
+//!   lockstep_check --base POP --drop 20                           (lose 20% of datagrams)
+//!
+//! `--desync` moves one of player 1's units behind the simulation's back to
+//! check the checksums catch it; with `--rebuild` player 1 then rebuilds
+//! from the host's input history and the run should end in sync:
+//!   lockstep_check --base POP --desync 100 --rebuild
+//!
+//! `--teams` allies players the way a 2v2 session would:
+//!   lockstep_check --base POP --players 4 --teams 0,1,0,1
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use clap::{Arg, ArgAction, Command};
+
+use pop3::data::level::{LevelPaths, LevelRes};
+use pop3::engine::movement::WorldCoord;
+use pop3::engine::net::{loopback, Lockstep, Transport, UdpTransport};
+use pop3::engine::state::rng::GameRng;
+use pop3::engine::state::state_machine::GameState;
+use pop3::engine::state::tick::{GameRules, GameWorld, ManualTimeSource, TickSubsystems};
+use pop3::engine::state::traits::NoOp;
+use pop3::engine::trace::{capture_frame, TraceFrame};
+use pop3::engine::units::UnitCoordinator;
+use pop3::engine::GameCommand;
+
+/// Ticks between each player's orders.
+const ORDER_INTERVAL: u32 = 40;
+
+fn cli() -> Command {
+    Command::new("lockstep_check")
+        .about("Run multiplayer peers over loopback UDP and check they stay in sync")
+        .args([
+            Arg::new("base")
+                .long("base")
+                .action(ArgAction::Set)
+                .value_name("BASE_PATH")
+                .value_parser(clap::value_parser!(PathBuf))
+                .required(true)
+                .help("Path to POP3 directory"),
+            Arg::new("level")
+                .long("level")
+                .action(ArgAction::Set)
+                .value_name("LEVEL")
+                .value_parser(clap::value_parser!(u8).range(1..255))
+                .default_value("1")
+                .help("Level to play"),
+            Arg::new("players")
+                .long("players")
+                .action(ArgAction::Set)
+                .value_name("COUNT")
+                .value_parser(clap::value_parser!(u8).range(2..=4))
+                .default_value("2")
+                .help("Number of peers"),
+            Arg::new("ticks")
+                .long("ticks")
+                .action(ArgAction::Set)
+                .value_name("TICKS")
+                .value_parser(clap::value_parser!(u32))
+                .default_value("600")
+                .help("Ticks every peer runs"),
+            Arg::new("drop")
+                .long("drop")
+                .action(ArgAction::Set)
+                .value_name("PERCENT")
+                .value_parser(clap::value_parser!(u32).range(0..100))
+                .default_value("0")
+                .help("Share of outgoing datagrams to lose"),
+            Arg::new("seed")
+                .long("seed")
+                .action(ArgAction::Set)
+                .value_name("SEED")
+                .value_parser(clap::value_parser!(u32))
+                .default_value("4660")
+                .help("Seed for the host's level start, the players' orders, frame pacing and losses"),
+            Arg::new("desync")
+                .long("desync")
+                .action(ArgAction::Set)
+                .value_name("TICK")
+                .value_parser(clap::value_parser!(u32).range(1..))
+                .help("Move one of player 1's units on its own machine at this tick"),
+            Arg::new("rebuild")
+                .long("rebuild")
+                .action(ArgAction::SetTrue)
+                .help("Rebuild a desynced peer from the host's input history"),
+            Arg::new("teams")
+                .long("teams")
+                .action(ArgAction::Set)
+                .value_name("TEAMS")
+                .value_delimiter(',')
+                .value_parser(clap::value_parser!(u8))
+                .help("Each player's team, in player order; teammates are allied"),
+            Arg::new("avoidance")
+                .long("avoidance")
+                .action(ArgAction::SetTrue)
+                .help("Play with collision avoidance between units turned on"),
+        ])
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(2);
+}
+
+/// Transport that loses a share of what it sends.
+struct Lossy {
+    inner: UdpTransport,
+    rng: GameRng,
+    percent: u32,
+    dropped: u32,
+}
+
+impl Transport for Lossy {
+    fn broadcast(&mut self, bytes: &[u8]) {
+        if self.rng.next_percent() < self.percent {
+            self.dropped += 1;
+            return;
+        }
+        self.inner.broadcast(bytes);
+    }
+
+    fn recv(&mut self) -> Option<Vec<u8>> {
+        self.inner.recv()
+    }
+}
+
+struct Peer {
+    base: PathBuf,
+    level: u8,
+    world: GameWorld,
+    coord: UnitCoordinator,
+    net: Lockstep<Lossy>,
+    time: ManualTimeSource,
+    frames: BTreeMap<u32, TraceFrame>,
+    /// Local randomness for this player's orders — input, not simulation.
+    rng: GameRng,
+    rounds: u32,
+    orders: u32,
+    /// Tick at which to tamper with this peer's state.
+    desync_at: Option<u32>,
+}
+
+impl Peer {
+    /// One render frame: maybe issue orders, then let the tick loop run.
+    fn frame(&mut self, ticks: u32) {
+        let tick = self.world.game_tick;
+        if tick.is_multiple_of(ORDER_INTERVAL) && tick / ORDER_INTERVAL == self.rounds {
+            self.rounds += 1;
+            self.order_some_units();
+        }
+        if self.desync_at.is_some_and(|at| tick >= at) {
+            self.desync_at = None;
+            self.nudge_a_unit();
+        }
+        if self.net.rebuild_ready() {
+            self.rebuild();
+        }
+
+        let (mut a, mut c, mut e, mut f, mut g, mut h, mut i, mut j, mut k) =
+            (NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp);
+        let mut subs = TickSubsystems {
+            terrain: &mut a, objects: &mut self.coord, water: &mut c,
+            network: &mut self.net, actions: &mut e, game_time: &mut f,
+            single_player: &mut g, tutorial: &mut h, ai: &mut i,
+            population: &mut j, mana: &mut k,
+        };
+        self.time.advance(1000 / self.world.game_speed as u64);
+        if self.world.simulation_tick(&self.time, &mut subs) > 0 && self.world.game_tick <= ticks {
+            self.frames.insert(self.world.game_tick, capture_frame(&self.coord, self.world.game_tick));
+        }
+    }
+
+    /// Reload the level and replay the host's history up to where we were.
+    /// The reset is the game's: the same coordinator reloaded with the
+    /// session's level seed, and the world rewound.
+    fn rebuild(&mut self) {
+        self.world.rewind();
+        load(&mut self.coord, &self.base, self.level, self.net.level_seed().expect("level seed agreed at start"));
+        self.world.tribes.start_level(self.coord.tribe_populations());
+        self.coord.set_pathfinding_mode(self.world.pathfinding);
+        self.coord.set_avoidance(self.world.unit_avoidance);
+        let ticks = self.net.begin_rebuild();
+        let (mut a, mut c, mut e, mut f, mut g, mut h, mut i, mut j, mut k) =
+            (NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp);
+        let mut subs = TickSubsystems {
+            terrain: &mut a, objects: &mut self.coord, water: &mut c,
+            network: &mut self.net, actions: &mut e, game_time: &mut f,
+            single_player: &mut g, tutorial: &mut h, ai: &mut i,
+            population: &mut j, mana: &mut k,
+        };
+        let run = self.world.fast_forward(ticks, &mut subs);
+        // What we captured on the way here was the desynced state
+        self.frames.retain(|&t, _| t > run);
+        println!("player {} rebuilt {} ticks from the host's history", self.net.player(), run);
+    }
+
+    fn nudge_a_unit(&mut self) {
+        let tribe = self.net.player();
+        if let Some(unit) = self.coord.units.iter_mut().find(|u| u.alive && u.tribe_index == tribe) {
+            unit.movement.position.x = unit.movement.position.x.wrapping_add(0x100);
+            println!("player {} moved unit {} at tick {}", tribe, unit.id, self.world.game_tick);
+        }
+    }
+
+    fn order_some_units(&mut self) {
+        let tribe = self.net.player();
+        let own: Vec<_> = self.coord.units.iter()
+            .filter(|u| u.alive && u.tribe_index == tribe)
+            .map(|u| (u.id, u.movement.position))
+            .collect();
+        if own.is_empty() {
+            return;
+        }
+        let picked: Vec<_> = own.iter().filter(|_| self.rng.next_percent() < 50).copied().collect();
+        let Some(&(_, anchor)) = picked.first() else { return };
+        let offset = |rng: &mut GameRng| (rng.next() % 0x1000) as i16 - 0x800;
+        let target = WorldCoord::new(anchor.x.wrapping_add(offset(&mut self.rng)), anchor.z.wrapping_add(offset(&mut self.rng)));
+        self.net.queue(GameCommand::SelectMultiple(picked.iter().map(|&(id, _)| id).collect()));
+        self.net.queue(GameCommand::OrderMove { x: target.x as f32, z: target.z as f32 });
+        self.orders += 1;
+    }
+}
+
+fn load(coord: &mut UnitCoordinator, base: &Path, level: u8, seed: u32) {
+    let dat = LevelPaths::dat_path(&base.join("levels"), level);
+    if !dat.exists() {
+        fail(format!("{}: not found", dat.display()));
+    }
+    let level_res = LevelRes::new(base, level, None);
+    let shores = level_res.landscape.make_shores();
+    coord.load_level(seed, &level_res.units, &shores.height, level_res.landscape.land_size());
+}
+
+fn main() {
+    let matches = cli().get_matches();
+    let base: &PathBuf = matches.get_one("base").unwrap();
+    let level = *matches.get_one::<u8>("level").unwrap();
+    let players = *matches.get_one::<u8>("players").unwrap();
+    let ticks = *matches.get_one::<u32>("ticks").unwrap();
+    let percent = *matches.get_one::<u32>("drop").unwrap();
+    let seed = *matches.get_one::<u32>("seed").unwrap();
+    let desync = matches.get_one::<u32>("desync").copied();
+    let rebuild = matches.get_flag("rebuild");
+    let avoidance = matches.get_flag("avoidance");
+    let teams: Vec<u8> = matches.get_many::<u8>("teams").into_iter().flatten().copied().collect();
+    if !teams.is_empty() && teams.len() != players as usize {
+        fail(format!("{} teams for {} players", teams.len(), players));
+    }
+
+    // Every player proposes its own level seed; the host's wins
+    let transports = loopback(players as usize).unwrap_or_else(|e| fail(e));
+    let mut nets: Vec<_> = transports.into_iter().enumerate().map(|(i, inner)| {
+        let lossy = Lossy { inner, rng: GameRng::new(seed ^ (0x100 + i as u32)), percent, dropped: 0 };
+        Lockstep::new(lossy, i as u8, players).with_rebuild(rebuild).with_level_seed(seed.wrapping_add(i as u32))
+            .with_rules(GameRules { avoidance, ..Default::default() })
+    }).collect();
+    let start = Instant::now();
+    // Poll every peer each round, like separate machines would
+    while nets.iter_mut().map(|net| net.poll_level_seed().unwrap_or_else(|e| fail(e))).filter(Option::is_none).count() > 0 {
+        if start.elapsed() > Duration::from_secs(10) {
+            fail("no level seed from the host".to_string());
+        }
+        std::thread::sleep(Duration::from_micros(100));
+    }
+
+    let mut peers: Vec<Peer> = nets.into_iter().enumerate().map(|(i, net)| {
+        let player = i as u8;
+        let mut world = GameWorld::new(20);
+        world.state = GameState::InGame;
+        world.flags.set_multiplayer(true);
+        world.player_tribe = player;
+        world.tribes.set_teams(&teams);
+        world.set_rules(net.rules());
+        let mut coord = UnitCoordinator::new();
+        load(&mut coord, base, level, net.level_seed().unwrap());
+        world.tribes.start_level(coord.tribe_populations());
+        coord.set_pathfinding_mode(world.pathfinding);
+        coord.set_avoidance(world.unit_avoidance);
+        Peer {
+            base: base.clone(),
+            level,
+            world,
+            coord,
+            net,
+            time: ManualTimeSource::new(),
+            frames: BTreeMap::new(),
+            rng: GameRng::new(seed.wrapping_add(i as u32)),
+            rounds: 0,
+            orders: 0,
+            desync_at: desync.filter(|_| player == 1),
+        }
+    }).collect();
+
+    // Peers get frames unevenly, like separate machines would
+    let mut pacing = GameRng::new(seed);
+    let start = Instant::now();
+    while peers.iter().any(|p| p.world.game_tick < ticks) {
+        if start.elapsed() > Duration::from_secs(120) {
+            fail(format!("stuck at ticks {:?}", peers.iter().map(|p| p.world.game_tick).collect::<Vec<_>>()));
+        }
+        // Peers that are done keep playing: the others may still need
+        // their resends
+        for peer in &mut peers {
+            if pacing.next_percent() < 80 {
+                peer.frame(ticks);
+            }
+        }
+        std::thread::sleep(Duration::from_micros(100));
+    }
+
+    let (reference, others) = peers.split_first().unwrap();
+    let mut desynced = false;
+    for peer in others {
+        let player = peer.net.player();
+        // Catch-up frames run several ticks but capture only the last, so
+        // compare the ticks both peers captured
+        let diverged = reference.frames.iter()
+            .find(|(tick, frame)| peer.frames.get(tick).is_some_and(|theirs| theirs != *frame));
+        let Some((&tick, frame)) = diverged else { continue };
+        desynced = true;
+        println!("player {} diverged from player 0 at tick {}", player, tick);
+        for (a, b) in frame.units.iter().zip(&peer.frames[&tick].units).filter(|(a, b)| a != b).take(10) {
+            println!("  unit {}: {:?} vs {:?}", a.id, a, b);
+        }
+    }
+    for peer in &peers {
+        for report in peer.net.desync_reports() {
+            println!("player {}: {}", peer.net.player(), report);
+        }
+    }
+    let dropped: u32 = peers.iter().map(|p| p.net.transport().dropped).sum();
+    let orders: u32 = peers.iter().map(|p| p.orders).sum();
+    println!("level {}: {} players ran {} ticks ({} units, {} orders, {} datagrams dropped) in {:.1}s: {}",
+        level, players, ticks, reference.coord.units.len(), orders, dropped, start.elapsed().as_secs_f32(),
+        if desynced { "DESYNC" } else { "in sync" });
+    if desynced {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,166 @@
+//! Pathfinder cross-validation — runs the original wall-following
+//! pathfinder (or, with `--mode enhanced`, the jump point search route
+//! provider) and the A* reference on random start/goal pairs of each level
+//! and reports failure rate, path-length ratio and visited-cell counts.
+//!
+//! Pairs are seeded, so two runs over the same levels agree exactly:
+//!   pathfind_check --base POP --json report.json          (save a baseline)
+//!   pathfind_check --base POP --baseline report.json      (exit 1 on change)
+
+use std::path::PathBuf;
+
+use clap::{Arg, ArgAction, Command};
+use serde_json::{json, Map, Value};
+
+use pop3::data::level::{Landscape, LevelPaths};
+use pop3::engine::movement::crossval::{compare_to_baseline, cross_validate_with, random_pairs};
+use pop3::engine::movement::PathfindingMode;
+use pop3::engine::units::UnitCoordinator;
+
+fn cli() -> Command {
+    Command::new("pathfind_check")
+        .about("Compare a route provider's pathfinder against an A* reference")
+        .args([
+            Arg::new("base")
+                .long("base")
+                .action(ArgAction::Set)
+                .value_name("BASE_PATH")
+                .value_parser(clap::value_parser!(PathBuf))
+                .required(true)
+                .help("Path to POP3 directory"),
+            Arg::new("level")
+                .long("level")
+                .action(ArgAction::Append)
+                .value_name("LEVEL")
+                .value_parser(clap::value_parser!(u8).range(1..255))
+                .help("Level number (repeatable; default: every level found)"),
+            Arg::new("pairs")
+                .long("pairs")
+                .action(ArgAction::Set)
+                .value_name("COUNT")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("2000")
+                .help("Random start/goal pairs per level"),
+            Arg::new("seed")
+                .long("seed")
+                .action(ArgAction::Set)
+                .value_name("SEED")
+                .value_parser(clap::value_parser!(u32))
+                .default_value("1")
+                .help("Seed for the pair generator"),
+            Arg::new("mode")
+                .long("mode")
+                .action(ArgAction::Set)
+                .value_name("MODE")
+                .value_parser(["original", "enhanced"])
+                .default_value("original")
+                .help("Route provider to test: original wall follower or enhanced jump point search"),
+            Arg::new("show-failures")
+                .long("show-failures")
+                .action(ArgAction::Set)
+                .value_name("COUNT")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("5")
+                .help("Failure cases to print per level"),
+            Arg::new("json")
+                .long("json")
+                .action(ArgAction::Set)
+                .value_name("JSON_PATH")
+                .value_parser(clap::value_parser!(PathBuf))
+                .help("Write the full report, including every failure case"),
+            Arg::new("baseline")
+                .long("baseline")
+                .action(ArgAction::Set)
+                .value_name("JSON_PATH")
+                .value_parser(clap::value_parser!(PathBuf))
+                .help("Compare against a saved report and exit 1 on any difference"),
+        ])
+}
+
+fn main() {
+    let matches = cli().get_matches();
+    let base: &PathBuf = matches.get_one("base").unwrap();
+    let pair_count = *matches.get_one::<usize>("pairs").unwrap();
+    let seed = *matches.get_one::<u32>("seed").unwrap();
+    let show = *matches.get_one::<usize>("show-failures").unwrap();
+    let mode = matches.get_one::<String>("mode")
+        .and_then(|s| PathfindingMode::from_name(s))
+        .unwrap_or_default();
+
+    let levels_dir = base.join("levels");
+    let levels: Vec<u8> = match matches.get_many::<u8>("level") {
+        Some(levels) => levels.copied().collect(),
+        None => (1..255).filter(|&n| LevelPaths::dat_path(&levels_dir, n).exists()).collect(),
+    };
+    if levels.is_empty() {
+        eprintln!("no levels found in {}", levels_dir.display());
+        std::process::exit(2);
+    }
+
+    let tested = match mode {
+        PathfindingMode::Original => "wf",
+        PathfindingMode::Enhanced => "jps",
+    };
+    println!("{:>5} {:>9} {:>14} {:>5} {:>27} {:>13}",
+        "level", "reachable", "failures", "wrap", "length ratio mean/med/p95/max",
+        format!("visited {}/a*", tested));
+    let mut per_level = Map::new();
+    for level in levels {
+        let dat = LevelPaths::dat_path(&levels_dir, level);
+        if !dat.exists() {
+            eprintln!("{}: not found", dat.display());
+            std::process::exit(2);
+        }
+        let landscape = Landscape::<128>::from_file(&dat);
+        let region_map = UnitCoordinator::terrain_region_map(&landscape.height, 128);
+        let pairs = random_pairs(&region_map, pair_count, seed);
+        let report = cross_validate_with(mode, &region_map, &pairs);
+
+        let ratio = report.ratio_summary().map_or("-".to_string(), |r| {
+            format!("{:.3}/{:.3}/{:.3}/{:.3}", r.mean, r.median, r.p95, r.max)
+        });
+        let wrap_only = report.failures.iter().filter(|f| f.needs_wrap).count();
+        println!("{:>5} {:>9} {:>6} ({:>5.2}%) {:>5} {:>27} {:>6.0}/{:<6.0}",
+            level, report.reachable, report.failures.len(), report.failure_rate() * 100.0, wrap_only,
+            ratio, report.wall_visited as f64 / report.pairs.max(1) as f64,
+            report.astar_visited as f64 / report.pairs.max(1) as f64);
+        if report.false_paths > 0 {
+            println!("      {} paths found between unconnected cells", report.false_paths);
+        }
+        for f in report.failures.iter().take(show) {
+            println!("      no path ({}, {}) -> ({}, {}), optimal {:.1} cells{}",
+                f.start.x >> 1, f.start.z >> 1, f.goal.x >> 1, f.goal.z >> 1, f.optimal,
+                if f.needs_wrap { ", across the map edge" } else { "" });
+        }
+        per_level.insert(level.to_string(), report.to_json());
+    }
+
+    let report = json!({ "mode": mode.name(), "pairs": pair_count, "seed": seed, "levels": per_level });
+    if let Some(path) = matches.get_one::<PathBuf>("json") {
+        let text = serde_json::to_string_pretty(&report).unwrap();
+        if let Err(e) = std::fs::write(path, text) {
+            eprintln!("{}: {}", path.display(), e);
+            std::process::exit(2);
+        }
+    }
+    if let Some(path) = matches.get_one::<PathBuf>("baseline") {
+        let baseline: Value = match std::fs::read_to_string(path).map_err(|e| e.to_string())
+            .and_then(|text| serde_json::from_str(&text).map_err(|e| e.to_string()))
+        {
+            Ok(baseline) => baseline,
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                std::process::exit(2);
+            }
+        };
+        let diffs = compare_to_baseline(&report, &baseline);
+        for diff in &diffs {
+            eprintln!("baseline: {}", diff);
+        }
+        if !diffs.is_empty() {
+            eprintln!("baseline: {} difference(s)", diffs.len());
+            std::process::exit(1);
+        }
+        println!("baseline: matches {}", path.display());
+    }
+}
@@ -7,21 +7,30 @@ use std::collections::HashSet;
 use image::{RgbImage, RgbaImage, Rgb, GrayImage, ImageFormat, ImageOutputFormat, ImageBuffer, DynamicImage};
 use clap::{arg, Arg, ArgAction, Command};
 
-use pop3::data::level::{GlobeTextureParams, LevelPaths, LevelRes, ObjectPaths, read_pal};
-use pop3::data::psfb::ContainerPSFB;
+use pop3::data::level::{GlobeTextureParams, Landscape, LevelPaths, LevelRes, ObjectPaths, read_pal, write_level_landscape};
+use pop3::data::psfb::{ContainerPSFB, PsfbBuilder, PSFB_TRANSPARENT, quantize_rgba};
+use pop3::data::sdt::SoundBank;
 use pop3::data::landscape::common::{LandPos, LandscapeFull};
 use pop3::data::landscape::minimap::texture_minimap;
 use pop3::data::landscape::globe::texture_globe;
 use pop3::data::landscape::land::texture_land;
 use pop3::data::landscape::disp::texture_bigf0;
 use pop3::data::landscape::water::texture_water;
+use pop3::data::landscape::heightmap::{heightmap_image, landscape_from_heightmap};
 use pop3::data::pls::decode;
 use pop3::data::bl320::{read_bl320, read_bl160};
-use pop3::data::types::{BinDeserializer, Image, AllocatorIter, ULCentreComposer, URCentreComposer, LayeredStorageSource, LayerComposer};
+use pop3::data::types::{BinDeserializer, BinSerializer, Image, AllocatorIter, ULCentreComposer, URCentreComposer, LayeredStorageSource, LayerComposer};
 use pop3::data::types::{ImageInfo, ImageArea};
 use pop3::data::types::{image_allocator_1d_horizontal, image_allocator_1d_vertical, image_allocator_2d};
-use pop3::data::objects::{ObjectRaw, Shape, PointRaw, FaceRaw};
+use pop3::data::objects::{ObjectRaw, Shape, PointRaw, FaceRaw, Object3D, ObjectBank, ShapeFootprints};
+use pop3::data::object_import::{load_mesh, import_object};
+use pop3::data::object_export::{ExportMesh, bl320_atlas_rgba, write_mtl, write_obj, write_gltf};
 use pop3::data::animation::{AnimationsData, AnimationSequence, AnimationFrame};
+use pop3::data::anim_export::{ClipFormat, DEFAULT_GAME_SPEED, composite_clips, unit_anim_ids, write_clip};
+use pop3::render::sprites::convert_palette;
+use pop3::render::camera::{Camera, Screen};
+use pop3::render::software::LevelScene;
+use pop3::render::terrain::DEFAULT_CURVATURE_SCALE;
 
 /******************************************************************************/
 
@@ -128,6 +137,145 @@ fn draw_sprites_img(psfb: &ContainerPSFB, start: usize, num: usize, palette: &Op
     draw_image(palette, image)
 }
 
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap().to_str().unwrap().to_string()
+}
+
+/// Export every model of an objects bank as `{prefix}_{index}.obj` (or
+/// `.gltf` + `.bin`), with the BL320 atlas they reference as `{prefix}_atlas.png`.
+fn export_objects(objects: &[Option<Object3D>], atlas: (usize, usize, Vec<u8>), prefix: &Path, gltf: bool) {
+    let prefix = prefix.to_str().unwrap();
+    let atlas_path = PathBuf::from(format!("{prefix}_atlas.png"));
+    let (width, height, rgba) = atlas;
+    RgbaImage::from_raw(width as u32, height as u32, rgba).unwrap()
+        .save_with_format(&atlas_path, ImageFormat::Png).unwrap();
+    println!("{}", atlas_path.display());
+    let atlas_file = file_name(&atlas_path);
+
+    let mtl_path = PathBuf::from(format!("{prefix}.mtl"));
+    if !gltf {
+        write_mtl(&mut File::create(&mtl_path).unwrap(), &atlas_file).unwrap();
+        println!("{}", mtl_path.display());
+    }
+    for (index, object) in objects.iter().enumerate() {
+        let Some(object) = object else { continue };
+        let mesh = ExportMesh::from_object(&format!("object_{index:03}"), object);
+        if gltf {
+            let bin_path = PathBuf::from(format!("{prefix}_{index:03}.bin"));
+            let gltf_path = PathBuf::from(format!("{prefix}_{index:03}.gltf"));
+            let (json, bin) = write_gltf(&mesh, &file_name(&bin_path), &atlas_file);
+            std::fs::write(&bin_path, bin).unwrap();
+            std::fs::write(&gltf_path, json).unwrap();
+            println!("{}", gltf_path.display());
+        } else {
+            let obj_path = PathBuf::from(format!("{prefix}_{index:03}.obj"));
+            write_obj(&mut File::create(&obj_path).unwrap(), &mesh, &file_name(&mtl_path)).unwrap();
+            println!("{}", obj_path.display());
+        }
+    }
+}
+
+/// Import meshes into objects bank `bank_num`: a single file goes to
+/// `index`, a directory's `*_NNN.obj/.gltf/.glb` files to index NNN. The
+/// bank and SHAPES.DAT are read from `base` and written to `out`.
+fn import_objects(base: &Path, bank_num: &str, input: &Path, index: Option<usize>, coord_scale: Option<u32>, out: &Path) {
+    let paths = ObjectPaths::from_default_dir(base, bank_num);
+    let mut bank = ObjectBank::from_paths(&paths);
+    let mut shapes = ShapeFootprints::from_file(&paths.shapes);
+    let meshes: Vec<(usize, PathBuf)> = if input.is_dir() {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(input).unwrap()
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().and_then(|e| e.to_str())
+                .is_some_and(|e| ["obj", "gltf", "glb"].contains(&e.to_ascii_lowercase().as_str())))
+            .collect();
+        files.sort();
+        files.into_iter().filter_map(|p| Some((file_index(&p)?, p))).collect()
+    } else {
+        vec![(index.expect("--index is required for a single mesh"), input.to_path_buf())]
+    };
+    let mut failed = false;
+    for (index, path) in meshes {
+        let result = load_mesh(&path)
+            .and_then(|mesh| import_object(&mut bank, &mut shapes, index, &mesh, coord_scale));
+        match result {
+            Ok(()) => println!("{} -> object {index}", path.display()),
+            Err(e) => {
+                eprintln!("Rejected {}: {e}", path.display());
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+    std::fs::create_dir_all(out).unwrap();
+    let out_paths = ObjectPaths::from_base(out, bank_num);
+    bank.write(&out_paths).unwrap();
+    shapes.to_file(&out_paths.shapes).unwrap();
+    println!("Wrote {} objects to {}", bank.objects.len(), out.display());
+}
+
+/// Index for a file named like `prefix_12.png`, or None if the stem
+/// doesn't end in a number.
+fn file_index(path: &Path) -> Option<usize> {
+    let stem = path.file_stem()?.to_str()?;
+    let digits = stem.len() - stem.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    stem[stem.len() - digits..].parse().ok()
+}
+
+/// Build a PSFB bank from the PNGs in `dir`. Numbered files replace that
+/// sprite index, the rest are appended in name order.
+fn pack_sprites(dir: &Path, pal: &[u8], template: Option<&ContainerPSFB>, transparent: u8) -> ContainerPSFB {
+    let mut builder = template.map(PsfbBuilder::from_container).unwrap_or_default();
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir).unwrap()
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case("png")))
+        .collect();
+    paths.sort();
+    for path in paths {
+        let img = image::open(&path).unwrap().to_rgba8();
+        let (width, height) = (img.width() as usize, img.height() as usize);
+        let sprite = quantize_rgba(img.as_raw(), width, height, pal, transparent);
+        let ok = match file_index(&path) {
+            Some(index) => builder.set_image(index, &sprite, transparent),
+            None => builder.push_image(&sprite, transparent),
+        };
+        if !ok {
+            eprintln!("Skipping {path:?}: {width}x{height} is too large");
+        }
+    }
+    builder.build()
+}
+
+/// Export `anim_ids` for a person subtype as `{prefix}_{anim}_d{direction}.gif`
+/// (or `.png`), composited from HSPR0-0.DAT with the pal0-0 palette.
+fn export_anims(data_dir: &Path, subtype: u8, tribe: u8, anim_ids: &[u16], speed: u32, format: ClipFormat, prefix: &Path) {
+    let palette = convert_palette(&std::fs::read(data_dir.join("pal0-0.dat")).unwrap());
+    let container = ContainerPSFB::from_file(&data_dir.join("HSPR0-0.DAT")).unwrap();
+    let sequences = AnimationSequence::from_data(&AnimationsData::from_path(data_dir));
+    let prefix = prefix.to_str().unwrap();
+    for &anim_id in anim_ids {
+        let clips = composite_clips(&sequences, &container, &palette, anim_id, subtype, tribe, speed);
+        for clip in &clips {
+            let path = PathBuf::from(format!("{prefix}_{anim_id:03}_d{}.{}", clip.direction, format.extension()));
+            if let Err(e) = write_clip(clip, format, File::create(&path).unwrap()) {
+                eprintln!("{}: {e}", path.display());
+                continue;
+            }
+            println!("{} ({} frames, {} ms)", path.display(), clip.frames.len(), clip.delay_ms);
+        }
+    }
+}
+
+fn write_sounds(bank: &SoundBank, prefix: &Path) {
+    for e in &bank.entries {
+        let stem = e.name.trim_end_matches(".wav").trim_end_matches(".WAV");
+        let name = format!("{:}_{:03}_{:}.wav", prefix.to_str().unwrap(), e.id, stem);
+        println!("{}", name);
+        std::fs::write(Path::new(&name), e.to_wav()).unwrap();
+    }
+}
+
 struct AnimationsConfig {
     img_size: usize,
     with_tribe: bool,
@@ -237,6 +385,114 @@ fn cli() -> Command {
                 .args(&args)
                 .arg_required_else_help(true),
         )
+        .subcommand(
+            Command::new("heightmap-export")
+                .about("Write level heights as a 16-bit grayscale PNG")
+                .arg(arg!(<num> "Level number"))
+                .args(&args)
+                .args([
+                    Arg::new("raw")
+                        .long("raw")
+                        .action(ArgAction::SetTrue)
+                        .help("Store height values as they are instead of stretching 0..0x400 to full range"),
+                    Arg::new("out")
+                        .long("out")
+                        .action(ArgAction::Set)
+                        .value_name("PNG_PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .required(true)
+                        .help("Output PNG file"),
+                ])
+                .arg_required_else_help(true),
+        )
+        .subcommand(
+            Command::new("heightmap-import")
+                .about("Write heights from a grayscale PNG into a copy of a level DAT")
+                .arg(arg!(<num> "Level number"))
+                .args(&args)
+                .args([
+                    Arg::new("input")
+                        .long("input")
+                        .action(ArgAction::Set)
+                        .value_name("PNG_PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .required(true)
+                        .help("128x128 grayscale heightmap"),
+                    Arg::new("raw")
+                        .long("raw")
+                        .action(ArgAction::SetTrue)
+                        .help("Pixel values are heights (as written by heightmap-export --raw)"),
+                    Arg::new("out")
+                        .long("out")
+                        .action(ArgAction::Set)
+                        .value_name("DAT_PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .required(true)
+                        .help("Output level DAT; the other sections are copied from the level"),
+                ])
+                .arg_required_else_help(true),
+        )
+        .subcommand(
+            Command::new("render")
+                .about("Render a level view offscreen with the software renderer")
+                .arg(arg!(<num> "Level number"))
+                .args(&args)
+                .args([
+                    Arg::new("width")
+                        .long("width")
+                        .action(ArgAction::Set)
+                        .value_name("PIXELS")
+                        .value_parser(clap::value_parser!(u32).range(1..))
+                        .default_value("800")
+                        .help("Image width"),
+                    Arg::new("height")
+                        .long("height")
+                        .action(ArgAction::Set)
+                        .value_name("PIXELS")
+                        .value_parser(clap::value_parser!(u32).range(1..))
+                        .default_value("600")
+                        .help("Image height"),
+                    Arg::new("angle_x")
+                        .long("angle_x")
+                        .action(ArgAction::Set)
+                        .value_name("DEGREES")
+                        .value_parser(clap::value_parser!(i16))
+                        .allow_hyphen_values(true)
+                        .default_value("-55")
+                        .help("Camera tilt"),
+                    Arg::new("angle_z")
+                        .long("angle_z")
+                        .action(ArgAction::Set)
+                        .value_name("DEGREES")
+                        .value_parser(clap::value_parser!(i16))
+                        .allow_hyphen_values(true)
+                        .default_value("0")
+                        .help("Camera rotation"),
+                    Arg::new("zoom")
+                        .long("zoom")
+                        .action(ArgAction::Set)
+                        .value_name("ZOOM")
+                        .value_parser(clap::value_parser!(f32))
+                        .default_value("1.0")
+                        .help("Camera zoom"),
+                    Arg::new("no_curvature")
+                        .long("no_curvature")
+                        .action(ArgAction::SetTrue)
+                        .help("Render flat terrain"),
+                    Arg::new("no_lighting")
+                        .long("no_lighting")
+                        .action(ArgAction::SetTrue)
+                        .help("Draw buildings and units unlit"),
+                    Arg::new("out")
+                        .long("out")
+                        .action(ArgAction::Set)
+                        .value_name("PNG_PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .required(true)
+                        .help("Output PNG file"),
+                ])
+                .arg_required_else_help(true),
+        )
         .subcommand(
             Command::new("water")
                 .about("Create water texture image")
@@ -281,6 +537,63 @@ fn cli() -> Command {
                 .arg(arg!(<num> "Bank num"))
                 .arg_required_else_help(true),
         )
+        .subcommand(
+            Command::new("objects-export")
+                .about("Export objects bank models as OBJ or glTF")
+                .arg(arg!(<num> "Bank num"))
+                .args(&args)
+                .args([
+                    Arg::new("format")
+                        .long("format")
+                        .action(ArgAction::Set)
+                        .value_name("FORMAT")
+                        .value_parser(["obj", "gltf"])
+                        .help("Output format (default obj)"),
+                    Arg::new("prefix")
+                        .long("prefix")
+                        .action(ArgAction::Set)
+                        .value_name("PREFIX_PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .required(true)
+                        .help("Prefix for generated files"),
+                ])
+                .arg_required_else_help(true),
+        )
+        .subcommand(
+            Command::new("objects-import")
+                .about("Pack OBJ/glTF meshes into an objects bank")
+                .arg(arg!(<num> "Bank num"))
+                .args(&args)
+                .args([
+                    Arg::new("input")
+                        .long("input")
+                        .action(ArgAction::Set)
+                        .value_name("PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .required(true)
+                        .help("Mesh file, or directory of NAME_<index>.obj/.gltf files"),
+                    Arg::new("index")
+                        .long("index")
+                        .action(ArgAction::Set)
+                        .value_name("OBJECT_INDEX")
+                        .value_parser(clap::value_parser!(usize))
+                        .help("Object index for a single mesh file"),
+                    Arg::new("coord_scale")
+                        .long("coord_scale")
+                        .action(ArgAction::Set)
+                        .value_name("SCALE")
+                        .value_parser(clap::value_parser!(u32))
+                        .help("Object coord_scale (default: smallest that fits, at least 300)"),
+                    Arg::new("out")
+                        .long("out")
+                        .action(ArgAction::Set)
+                        .value_name("DIR_PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .required(true)
+                        .help("Directory for the written OBJS0/PNTS0/FACS0 and SHAPES.DAT files"),
+                ])
+                .arg_required_else_help(true),
+        )
         .subcommand(
             Command::new("units")
                 .about("Units commands")
@@ -338,6 +651,52 @@ fn cli() -> Command {
                         .help("Do not show type images"),
                 ]).arg_required_else_help(true),
         )
+        .subcommand(
+            Command::new("anims-export")
+                .about("Export unit animations as animated GIF or APNG, one file per direction")
+                .args(&args)
+                .args([
+                    Arg::new("unit")
+                        .long("unit")
+                        .action(ArgAction::Set)
+                        .value_name("SUBTYPE")
+                        .value_parser(clap::value_parser!(u8).range(1..=8))
+                        .required(true)
+                        .help("Person subtype (2 brave, 3 warrior, 4 preacher, 5 spy, 6 firewarrior, 7 shaman)"),
+                    Arg::new("anim")
+                        .long("anim")
+                        .action(ArgAction::Set)
+                        .value_name("ANIM_ID")
+                        .value_parser(clap::value_parser!(u16))
+                        .help("Single animation id (default: every animation of the unit)"),
+                    Arg::new("tribe")
+                        .long("tribe")
+                        .action(ArgAction::Set)
+                        .value_name("TRIBE")
+                        .value_parser(clap::value_parser!(u8).range(0..4))
+                        .help("Tribe colours (default 0)"),
+                    Arg::new("speed")
+                        .long("speed")
+                        .action(ArgAction::Set)
+                        .value_name("TICKS_PER_SEC")
+                        .value_parser(clap::value_parser!(u32).range(1..))
+                        .help("Game speed used for frame timing (default 20)"),
+                    Arg::new("format")
+                        .long("format")
+                        .action(ArgAction::Set)
+                        .value_name("FORMAT")
+                        .value_parser(["gif", "apng"])
+                        .help("Output format (default gif)"),
+                    Arg::new("prefix")
+                        .long("prefix")
+                        .action(ArgAction::Set)
+                        .value_name("PREFIX_PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .required(true)
+                        .help("Prefix for generated files"),
+                ])
+                .arg_required_else_help(true),
+        )
         .subcommand(
             Command::new("pls")
                 .about("Decode pls files")
@@ -389,6 +748,72 @@ fn cli() -> Command {
                         .help("Prefix for generated images"),
                 ]).arg_required_else_help(true),
         )
+        .subcommand(
+            Command::new("psfb-pack")
+                .about("Build a psfb file from a directory of PNG sprites")
+                .args([
+                    Arg::new("dir")
+                        .long("dir")
+                        .action(ArgAction::Set)
+                        .value_name("DIR_PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .required(true)
+                        .help("Directory with PNG sprites (NAME_<index>.png)"),
+                    Arg::new("palette")
+                        .long("palette")
+                        .action(ArgAction::Set)
+                        .value_name("PALETTE_PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .required(true)
+                        .help("Path to palette file the sprites are quantized to"),
+                    Arg::new("template")
+                        .long("template")
+                        .action(ArgAction::Set)
+                        .value_name("FILE_PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("Existing PSFB file whose sprites are kept unless replaced"),
+                    Arg::new("transparent")
+                        .long("transparent")
+                        .action(ArgAction::Set)
+                        .value_name("INDEX")
+                        .value_parser(clap::value_parser!(u8))
+                        .help("Palette index used for transparent pixels (default 255)"),
+                    Arg::new("out")
+                        .long("out")
+                        .action(ArgAction::Set)
+                        .value_name("FILE_PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("Output file (stdout if not set)"),
+                ]).arg_required_else_help(true),
+        )
+        .subcommand(
+            Command::new("sdt")
+                .about("Read sounds from sdt sound bank")
+                .args([
+                    Arg::new("path")
+                        .long("path")
+                        .action(ArgAction::Set)
+                        .value_name("FILE_PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("Path to SDT file"),
+                    Arg::new("info")
+                        .long("info")
+                        .action(ArgAction::SetTrue)
+                        .help("Show file info"),
+                    Arg::new("id")
+                        .long("id")
+                        .action(ArgAction::Set)
+                        .value_name("SOUND_ID")
+                        .value_parser(clap::value_parser!(u32))
+                        .help("Write a single sound as WAV to stdout"),
+                    Arg::new("prefix")
+                        .long("prefix")
+                        .action(ArgAction::Set)
+                        .value_name("PREFIX_PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("Prefix for generated WAV files"),
+                ]).arg_required_else_help(true),
+        )
 }
 
 enum TextureType {
@@ -499,6 +924,61 @@ fn main() {
             let level_type = sub_matches.get_one::<String>("landtype");
             make_texture_land(TextureType::Minimap, level_num, base_path, level_type, None);
         }
+        Some(("heightmap-export", sub_matches)) => {
+            let level_num: u8 = sub_matches.get_one::<String>("num").expect("required").parse().unwrap();
+            let base: PathBuf = sub_matches.get_one("base").cloned().unwrap_or_else(|| base_path.to_path_buf());
+            let raw = sub_matches.get_flag("raw");
+            let out: PathBuf = sub_matches.get_one("out").cloned().unwrap();
+            let landscape = Landscape::<128>::from_file(&LevelPaths::dat_path(&base.join("levels"), level_num));
+            heightmap_image(&landscape, raw).save_with_format(&out, ImageFormat::Png).unwrap();
+            println!("{}", out.display());
+        }
+        Some(("heightmap-import", sub_matches)) => {
+            let level_num: u8 = sub_matches.get_one::<String>("num").expect("required").parse().unwrap();
+            let base: PathBuf = sub_matches.get_one("base").cloned().unwrap_or_else(|| base_path.to_path_buf());
+            let input: PathBuf = sub_matches.get_one("input").cloned().unwrap();
+            let raw = sub_matches.get_flag("raw");
+            let out: PathBuf = sub_matches.get_one("out").cloned().unwrap();
+            let img = image::open(&input).unwrap();
+            match landscape_from_heightmap::<128>(&img, raw) {
+                Ok(landscape) => {
+                    let dat_path = LevelPaths::dat_path(&base.join("levels"), level_num);
+                    if dat_path != out {
+                        std::fs::copy(&dat_path, &out).unwrap();
+                    }
+                    write_level_landscape(&out, &landscape).unwrap();
+                    println!("{}", out.display());
+                }
+                Err(e) => {
+                    eprintln!("{}: {e}", input.display());
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(("render", sub_matches)) => {
+            let level_num: u8 = sub_matches.get_one::<String>("num").expect("required").parse().unwrap();
+            let base: PathBuf = sub_matches.get_one("base").cloned().unwrap_or_else(|| base_path.to_path_buf());
+            let level_type = sub_matches.get_one::<String>("landtype");
+            let out: PathBuf = sub_matches.get_one("out").cloned().unwrap();
+            let screen = Screen {
+                width: *sub_matches.get_one::<u32>("width").unwrap(),
+                height: *sub_matches.get_one::<u32>("height").unwrap(),
+            };
+            let mut camera = Camera::new();
+            camera.angle_x = *sub_matches.get_one::<i16>("angle_x").unwrap();
+            camera.angle_z = *sub_matches.get_one::<i16>("angle_z").unwrap();
+            let zoom = *sub_matches.get_one::<f32>("zoom").unwrap();
+            let curvature_scale = if sub_matches.get_flag("no_curvature") { 0.0 } else { DEFAULT_CURVATURE_SCALE };
+
+            let mut scene = LevelScene::load(&base, level_num, level_type.map(|s| s.as_str()));
+            match sub_matches.get_one::<String>("move").and_then(|s| parse_move(s)) {
+                Some((x, y)) => scene.landscape.set_shift(x as usize, y as usize),
+                None => scene.center_on_tribe0_shaman(),
+            }
+            let img = scene.render(&screen, &camera, zoom, curvature_scale, !sub_matches.get_flag("no_lighting"));
+            img.save_with_format(&out, ImageFormat::Png).unwrap();
+            println!("{}", out.display());
+        }
         Some(("water", sub_matches)) => {
             let level_num = sub_matches.get_one::<String>("num").expect("required").parse().unwrap();
             let offset = sub_matches.get_one::<String>("offset").expect("required").parse().unwrap();
@@ -551,6 +1031,27 @@ fn main() {
                 println!("  {:?}", face);
             }
         }
+        Some(("objects-export", sub_matches)) => {
+            let bank_num = sub_matches.get_one::<String>("num").expect("required");
+            let base: PathBuf = sub_matches.get_one("base").cloned().unwrap_or_else(|| base_path.to_path_buf());
+            let level_type = sub_matches.get_one::<String>("landtype").map_or("0", |s| s.as_str());
+            let gltf = sub_matches.get_one::<String>("format").is_some_and(|f| f == "gltf");
+            let prefix: PathBuf = sub_matches.get_one("prefix").cloned().unwrap();
+            let paths = LevelPaths::from_default_dir(&base, level_type);
+            let pal = read_pal(&paths);
+            let atlas = bl320_atlas_rgba(&paths.bl320, &pal);
+            let objects = Object3D::from_file_all(&base, bank_num);
+            export_objects(&objects, atlas, &prefix, gltf);
+        }
+        Some(("objects-import", sub_matches)) => {
+            let bank_num = sub_matches.get_one::<String>("num").expect("required");
+            let base: PathBuf = sub_matches.get_one("base").cloned().unwrap_or_else(|| base_path.to_path_buf());
+            let input: PathBuf = sub_matches.get_one("input").cloned().unwrap();
+            let index: Option<usize> = sub_matches.get_one("index").copied();
+            let coord_scale: Option<u32> = sub_matches.get_one("coord_scale").copied();
+            let out: PathBuf = sub_matches.get_one("out").cloned().unwrap();
+            import_objects(&base, bank_num, &input, index, coord_scale, &out);
+        }
         Some(("units", sub_matches)) => {
             let level_num = sub_matches.get_one::<String>("num").expect("required").parse().unwrap();
             let level_res = LevelRes::new(base_path, level_num, None);
@@ -643,6 +1144,19 @@ fn main() {
                 write_dyn_img_stdout(&img, DEFAULT_IMG_FORMAT);
             }
         }
+        Some(("anims-export", sub_matches)) => {
+            let base: PathBuf = sub_matches.get_one("base").cloned().unwrap_or_else(|| base_path.to_path_buf());
+            let subtype: u8 = sub_matches.get_one("unit").copied().unwrap();
+            let tribe: u8 = sub_matches.get_one("tribe").copied().unwrap_or(0);
+            let speed: u32 = sub_matches.get_one("speed").copied().unwrap_or(DEFAULT_GAME_SPEED);
+            let format = sub_matches.get_one::<String>("format").and_then(|f| ClipFormat::from_name(f)).unwrap_or(ClipFormat::Gif);
+            let prefix: PathBuf = sub_matches.get_one("prefix").cloned().unwrap();
+            let anim_ids = match sub_matches.get_one::<u16>("anim") {
+                Some(id) => vec![*id],
+                None => unit_anim_ids(subtype),
+            };
+            export_anims(&base.join("data"), subtype, tribe, &anim_ids, speed, format, &prefix);
+        }
         Some(("pls", sub_matches)) => {
             let path = sub_matches.get_one::<PathBuf>("pls_path").expect("required");
             let pls_data = decode_pls(path);
@@ -705,6 +1219,44 @@ fn main() {
                 }
             }
         }
+        Some(("psfb-pack", sub_matches)) => {
+            let dir: PathBuf = sub_matches.get_one("dir").cloned().unwrap();
+            let palette_path: PathBuf = sub_matches.get_one("palette").cloned().unwrap();
+            let template_path: Option<PathBuf> = sub_matches.get_one("template").cloned();
+            let transparent: u8 = sub_matches.get_one("transparent").copied().unwrap_or(PSFB_TRANSPARENT);
+            let out_path: Option<PathBuf> = sub_matches.get_one("out").cloned();
+            let pal = std::fs::read(palette_path).unwrap();
+            let template = template_path.and_then(|p| ContainerPSFB::from_file(&p));
+            let c = pack_sprites(&dir, &pal, template.as_ref(), transparent);
+            eprintln!("Packed {} sprites, {} bytes", c.len(), c.size());
+            match out_path {
+                Some(path) => c.to_file(&path).unwrap(),
+                None => c.to_writer(&mut std::io::stdout()).unwrap(),
+            }
+        }
+        Some(("sdt", sub_matches)) => {
+            let file_path: PathBuf = sub_matches.get_one("path").cloned().unwrap();
+            let info: bool = sub_matches.get_flag("info");
+            let id: Option<u32> = sub_matches.get_one("id").copied();
+            let prefix_opt: Option<PathBuf> = sub_matches.get_one("prefix").cloned();
+            if let Some(bank) = SoundBank::from_file(&file_path) {
+                if info {
+                    println!("SDT file '{file_path:?}': ");
+                    println!("    sounds count = {:?}", bank.len());
+                    for e in &bank.entries {
+                        println!(" Sound id={:?} name={:?} offset={:?}/0x{:x}, size={:?}, rate={:?}, channels={:?}, bits={:?}, duration={:.2}s"
+                                 , e.id, e.name, e.offset, e.offset, e.size, e.sample_rate
+                                 , e.format.channels, e.format.bits_per_sample, e.duration_secs());
+                    }
+                } else if let Some(id) = id {
+                    if let Some(e) = bank.get(id as usize) {
+                        std::io::stdout().write_all(&e.to_wav()).unwrap();
+                    }
+                } else if let Some(prefix) = prefix_opt {
+                    write_sounds(&bank, &prefix);
+                }
+            }
+        }
         _ => {}
     }
 }
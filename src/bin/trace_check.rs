@@ -0,0 +1,177 @@
+//! Trace validation — replays a unit trace captured from the original game
+//! (see src/engine/trace.rs for the format) on the same level and reports
+//! the first tick and field where our simulation diverges:
+//!   trace_check --base POP --trace capture.jsonl            (exit 1 on divergence)
+//!   trace_check --base POP --trace capture.jsonl --ignore rng --tolerance 16
+//!
+//! `--record` writes our own simulation in the same format instead, taking
+//! the level, seed and orders from `--trace` when given:
+//!   trace_check --base POP --level 1 --ticks 600 --record ours.jsonl
+//!
+//! Building footprints are not placed on the region map, so routes near
+//! buildings may differ from the game for that reason alone.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use clap::{Arg, ArgAction, Command};
+
+use pop3::data::level::{LevelPaths, LevelRes};
+use pop3::engine::trace::{match_units, record, replay, CompareOptions, Trace, TraceField, TraceOrder};
+use pop3::engine::units::UnitCoordinator;
+
+fn cli() -> Command {
+    Command::new("trace_check")
+        .about("Replay a captured unit trace and report where the simulation diverges")
+        .args([
+            Arg::new("base")
+                .long("base")
+                .action(ArgAction::Set)
+                .value_name("BASE_PATH")
+                .value_parser(clap::value_parser!(PathBuf))
+                .required(true)
+                .help("Path to POP3 directory"),
+            Arg::new("trace")
+                .long("trace")
+                .action(ArgAction::Set)
+                .value_name("TRACE_PATH")
+                .value_parser(clap::value_parser!(PathBuf))
+                .required_unless_present("record")
+                .help("Captured trace (JSON Lines)"),
+            Arg::new("ignore")
+                .long("ignore")
+                .action(ArgAction::Set)
+                .value_name("FIELDS")
+                .value_delimiter(',')
+                .value_parser(["unit", "position", "state", "health", "rng"])
+                .help("Fields not to compare (comma separated)"),
+            Arg::new("tolerance")
+                .long("tolerance")
+                .action(ArgAction::Set)
+                .value_name("UNITS")
+                .value_parser(clap::value_parser!(u16))
+                .default_value("0")
+                .help("Allowed per-axis position difference in world units"),
+            Arg::new("show")
+                .long("show")
+                .action(ArgAction::Set)
+                .value_name("COUNT")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("10")
+                .help("Differences printed for the diverging tick"),
+            Arg::new("record")
+                .long("record")
+                .action(ArgAction::Set)
+                .value_name("TRACE_PATH")
+                .value_parser(clap::value_parser!(PathBuf))
+                .help("Write our own simulation as a trace instead of replaying"),
+            Arg::new("level")
+                .long("level")
+                .action(ArgAction::Set)
+                .value_name("LEVEL")
+                .value_parser(clap::value_parser!(u8).range(1..255))
+                .help("Level to record (default: the trace's level, else 1)"),
+            Arg::new("ticks")
+                .long("ticks")
+                .action(ArgAction::Set)
+                .value_name("TICKS")
+                .value_parser(clap::value_parser!(u32))
+                .help("Ticks to record (default: the trace's last tick, else 600)"),
+            Arg::new("seed")
+                .long("seed")
+                .action(ArgAction::Set)
+                .value_name("SEED")
+                .value_parser(clap::value_parser!(u32))
+                .help("RNG seed to record with (default: the trace's, else 0x1234)"),
+        ])
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(2);
+}
+
+/// Coordinator with `level` loaded the way the game does it, RNG seeded
+/// before persons are created.
+fn load(base: &Path, level: u8, seed: u32) -> UnitCoordinator {
+    let levels_dir = base.join("levels");
+    let dat = LevelPaths::dat_path(&levels_dir, level);
+    if !dat.exists() {
+        fail(format!("{}: not found", dat.display()));
+    }
+    let level_res = LevelRes::new(base, level, None);
+    let shores = level_res.landscape.make_shores();
+    let mut coord = UnitCoordinator::new();
+    coord.load_level(seed, &level_res.units, &shores.height, level_res.landscape.land_size());
+    coord
+}
+
+fn main() {
+    let matches = cli().get_matches();
+    let base: &PathBuf = matches.get_one("base").unwrap();
+    let trace = matches.get_one::<PathBuf>("trace").map(|path| {
+        std::fs::read_to_string(path).map_err(|e| e.to_string())
+            .and_then(|text| Trace::parse(&text))
+            .unwrap_or_else(|e| fail(format!("{}: {}", path.display(), e)))
+    });
+
+    if let Some(out) = matches.get_one::<PathBuf>("record") {
+        let level = matches.get_one::<u8>("level").copied()
+            .or(trace.as_ref().map(|t| t.level)).unwrap_or(1);
+        let seed = matches.get_one::<u32>("seed").copied()
+            .or(trace.as_ref().map(|t| t.rng_seed)).unwrap_or(0x1234);
+        let ticks = matches.get_one::<u32>("ticks").copied()
+            .or(trace.as_ref().and_then(|t| t.frames.last()).map(|f| f.tick)).unwrap_or(600);
+        let mut coord = load(base, level, seed);
+
+        // Re-address the captured orders to our unit ids
+        let mut orders = Vec::new();
+        if let Some(trace) = &trace {
+            let ids = match_units(&coord, &trace.frames[0]).unwrap_or_else(|e| fail(e));
+            for frame in &trace.frames {
+                for order in &frame.orders {
+                    let units = order.units.iter().filter_map(|id| ids.get(id).map(|&u| u as u32)).collect();
+                    orders.push((frame.tick, TraceOrder { units, target: order.target }));
+                }
+            }
+        }
+        let ours = record(&mut coord, level, seed, ticks, &orders);
+        if let Err(e) = std::fs::write(out, ours.to_jsonl()) {
+            fail(format!("{}: {}", out.display(), e));
+        }
+        println!("recorded {} ticks of level {} ({} units) to {}", ticks, level, coord.units.len(), out.display());
+        return;
+    }
+
+    let trace = trace.unwrap();
+    let options = CompareOptions {
+        ignore: matches.get_many::<String>("ignore").into_iter().flatten()
+            .filter_map(|name| TraceField::from_name(name)).collect(),
+        tolerance: *matches.get_one::<u16>("tolerance").unwrap(),
+    };
+    let show = *matches.get_one::<usize>("show").unwrap();
+    let mut coord = load(base, trace.level, trace.rng_seed);
+    let report = replay(&mut coord, &trace, &options).unwrap_or_else(|e| fail(e));
+
+    let last_tick = trace.frames.last().map_or(0, |f| f.tick);
+    let Some(first) = report.first_divergence() else {
+        println!("level {}: {} frames match through tick {}", trace.level, report.frames_matched, last_tick);
+        return;
+    };
+    println!("level {}: diverged at tick {} ({} of {} frames matched{})", trace.level, first.tick,
+        report.frames_matched, trace.frames.len(),
+        report.last_good_tick.map_or(String::new(), |t| format!(", last good tick {}", t)));
+    println!("first divergence: {}", first);
+    let mut per_field: HashMap<&str, usize> = HashMap::new();
+    for d in &report.divergences {
+        *per_field.entry(d.field.name()).or_default() += 1;
+    }
+    let mut counts: Vec<_> = per_field.into_iter().collect();
+    counts.sort();
+    println!("differences at tick {}: {}", first.tick,
+        counts.iter().map(|(f, n)| format!("{} {}", n, f)).collect::<Vec<_>>().join(", "));
+    for d in report.divergences.iter().skip(1).take(show.saturating_sub(1)) {
+        println!("  {}", d);
+    }
+    std::process::exit(1);
+}
@@ -0,0 +1,296 @@
+use std::io::Write;
+
+use image::{Delay, Frame, RgbaImage};
+use image::codecs::gif::{GifEncoder, Repeat};
+
+use crate::data::animation::{AnimationSequence, STORED_DIRECTIONS, DIRS_PER_ANIM, SHAMAN_ANIMS,
+                             anim_shape, composite_frame, compute_global_bbox, discover_unit_combos};
+use crate::data::constants::PERSON_SUBTYPE_SHAMAN;
+use crate::data::psfb::{ContainerPSFB, PSFB_TRANSPARENT};
+use crate::engine::state::constants::TICK_BASE_MS;
+use crate::engine::units::animation::{ANIM_SPEED_MULTIPLIER, PERSON_ANIMATION_TABLE};
+use crate::render::sprites::get_source_direction;
+
+/******************************************************************************/
+// Export of unit animations as animated GIF / APNG clips.
+//
+// Each clip is one animation seen from one of the 8 display directions.
+// Directions 5-7 are stored mirrored (see get_source_direction), so clips
+// use a frame box symmetric about the sprite anchor and flip whole frames:
+// the unit then stays on the same spot in every direction. Frame timing is
+// the in-game one: ANIM_SPEED_MULTIPLIER ticks per frame at a given game
+// speed (ticks per second).
+
+/// Game speed the viewer runs at by default (ticks per second).
+pub const DEFAULT_GAME_SPEED: u32 = 20;
+/// Largest frame side, as for the unit atlases.
+const MAX_FRAME_SIZE: i32 = 512;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ClipFormat {
+    Gif,
+    Apng,
+}
+
+impl ClipFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "gif" => Some(Self::Gif),
+            "apng" | "png" => Some(Self::Apng),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Gif => "gif",
+            Self::Apng => "png",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimClip {
+    pub anim_id: u16,
+    pub direction: usize,
+    pub width: u32,
+    pub height: u32,
+    pub delay_ms: u32,
+    /// RGBA frames, width * height * 4 bytes each.
+    pub frames: Vec<Vec<u8>>,
+}
+
+/// Distinct animation ids a person subtype uses (column of g_PersonAnimationTable).
+pub fn unit_anim_ids(subtype: u8) -> Vec<u16> {
+    let col = (subtype as usize).min(8);
+    let mut ids = Vec::new();
+    for row in &PERSON_ANIMATION_TABLE {
+        let id = row[col];
+        if id >= 0 && !ids.contains(&(id as u16)) {
+            ids.push(id as u16);
+        }
+    }
+    ids
+}
+
+/// Milliseconds a frame stays on screen for `subtype` at `game_speed` ticks/sec.
+pub fn frame_delay_ms(subtype: u8, game_speed: u32) -> u32 {
+    let speed_idx = (subtype as usize).min(ANIM_SPEED_MULTIPLIER.len() - 1);
+    let ticks_per_frame = ANIM_SPEED_MULTIPLIER[speed_idx] as u32 + 1;
+    ticks_per_frame * TICK_BASE_MS as u32 / game_speed.max(1)
+}
+
+/// Widen a (min_x, min_y, max_x, max_y) box to be symmetric about x = 0.
+pub fn mirror_bbox(bbox: (i32, i32, i32, i32)) -> (i32, i32, i32, i32) {
+    let half = (-bbox.0).max(bbox.2).min(MAX_FRAME_SIZE / 2);
+    let min_y = bbox.1.max(bbox.3 - MAX_FRAME_SIZE);
+    (-half, min_y, half.max(1), bbox.3)
+}
+
+fn flip_horizontal(rgba: &mut [u8], width: usize) {
+    for row in rgba.chunks_exact_mut(width * 4) {
+        for x in 0..width / 2 {
+            for c in 0..4 {
+                row.swap(x * 4 + c, (width - 1 - x) * 4 + c);
+            }
+        }
+    }
+}
+
+/// Composite all 8 directions of `anim_id` for `tribe`.
+/// Shaman animations listed in SHAMAN_ANIMS come from their per-tribe sprites.
+pub fn composite_clips(
+    sequences: &[AnimationSequence],
+    container: &ContainerPSFB,
+    palette: &[[u8; 4]],
+    anim_id: u16,
+    subtype: u8,
+    tribe: u8,
+    game_speed: u32,
+) -> Vec<AnimClip> {
+    let delay_ms = frame_delay_ms(subtype, game_speed);
+    let shaman = SHAMAN_ANIMS.iter().find(|(id, _, _)| subtype == PERSON_SUBTYPE_SHAMAN && *id == anim_id);
+    let stored: Vec<(u32, u32, Vec<Vec<u8>>)> = match shaman {
+        Some((_, starts, frames_per_dir)) => {
+            let start = starts[(tribe as usize).min(starts.len() - 1)] as usize;
+            (0..STORED_DIRECTIONS)
+                .map(|dir| direct_frames(container, palette, start + dir * frames_per_dir, *frames_per_dir))
+                .collect()
+        }
+        None => {
+            let (base, _) = anim_shape(anim_id);
+            let end = (base + STORED_DIRECTIONS).min(sequences.len());
+            if base >= end {
+                return Vec::new();
+            }
+            let unit_combo = discover_unit_combos(sequences, base).first().copied();
+            let (min_x, min_y, max_x, max_y) = mirror_bbox(compute_global_bbox(&sequences[base..end], container));
+            let (fw, fh) = ((max_x - min_x) as usize, (max_y - min_y).max(1) as usize);
+            (base..end).map(|seq_idx| {
+                let frames = sequences[seq_idx].frames.iter()
+                    .map(|frame| composite_frame(&frame.sprites, container, palette, tribe, unit_combo,
+                                                 fw, fh, min_x, min_y))
+                    .collect();
+                (fw as u32, fh as u32, frames)
+            }).collect()
+        }
+    };
+
+    let mut clips = Vec::with_capacity(DIRS_PER_ANIM);
+    for direction in 0..DIRS_PER_ANIM {
+        let (src_dir, mirrored) = get_source_direction(direction);
+        let Some((width, height, frames)) = stored.get(src_dir) else { continue };
+        if frames.is_empty() {
+            continue;
+        }
+        let mut frames = frames.clone();
+        if mirrored {
+            frames.iter_mut().for_each(|f| flip_horizontal(f, *width as usize));
+        }
+        clips.push(AnimClip { anim_id, direction, width: *width, height: *height, delay_ms, frames });
+    }
+    clips
+}
+
+/// Frames drawn from consecutive whole sprites, centred in a common box.
+fn direct_frames(container: &ContainerPSFB, palette: &[[u8; 4]], start: usize, count: usize) -> (u32, u32, Vec<Vec<u8>>) {
+    let infos: Vec<_> = (start..start + count).filter_map(|i| container.get_info(i).map(|info| (i, info))).collect();
+    let fw = infos.iter().map(|(_, info)| info.width as usize).max().unwrap_or(0);
+    let fh = infos.iter().map(|(_, info)| info.height as usize).max().unwrap_or(0);
+    if fw == 0 || fh == 0 {
+        return (0, 0, Vec::new());
+    }
+    let mut frames = Vec::with_capacity(infos.len());
+    for (index, info) in infos {
+        let mut rgba = vec![0u8; fw * fh * 4];
+        if let Some(image) = container.get_image(index) {
+            let (sw, sh) = (info.width as usize, info.height as usize);
+            let (ox, oy) = ((fw - sw) / 2, (fh - sh) / 2);
+            for y in 0..sh {
+                for x in 0..sw {
+                    let src = image.data[y * sw + x];
+                    if src == PSFB_TRANSPARENT { continue; }
+                    let c = palette.get(src as usize).unwrap_or(&[255, 0, 255, 255]);
+                    let off = ((oy + y) * fw + ox + x) * 4;
+                    rgba[off..off + 3].copy_from_slice(&c[..3]);
+                    rgba[off + 3] = 255;
+                }
+            }
+        }
+        frames.push(rgba);
+    }
+    (fw as u32, fh as u32, frames)
+}
+
+/******************************************************************************/
+
+/// Write `clip` as a looping animated GIF.
+pub fn write_gif<W: Write>(clip: &AnimClip, writer: W) -> Result<(), String> {
+    let mut encoder = GifEncoder::new(writer);
+    encoder.set_repeat(Repeat::Infinite).map_err(|e| e.to_string())?;
+    for rgba in &clip.frames {
+        let buffer = RgbaImage::from_raw(clip.width, clip.height, rgba.clone())
+            .ok_or_else(|| "frame size mismatch".to_string())?;
+        let frame = Frame::from_parts(buffer, 0, 0, Delay::from_numer_denom_ms(clip.delay_ms, 1));
+        encoder.encode_frame(frame).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Write `clip` as a looping animated PNG.
+pub fn write_apng<W: Write>(clip: &AnimClip, writer: W) -> Result<(), String> {
+    let mut encoder = png::Encoder::new(writer, clip.width, clip.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(clip.frames.len() as u32, 0).map_err(|e| e.to_string())?;
+    encoder.set_dispose_op(png::DisposeOp::Background).map_err(|e| e.to_string())?;
+    encoder.set_blend_op(png::BlendOp::Source).map_err(|e| e.to_string())?;
+    let mut png_writer = encoder.write_header().map_err(|e| e.to_string())?;
+    let delay = clip.delay_ms.min(u16::MAX as u32) as u16;
+    for rgba in &clip.frames {
+        png_writer.set_frame_delay(delay, 1000).map_err(|e| e.to_string())?;
+        png_writer.write_image_data(rgba).map_err(|e| e.to_string())?;
+    }
+    png_writer.finish().map_err(|e| e.to_string())
+}
+
+pub fn write_clip<W: Write>(clip: &AnimClip, format: ClipFormat, writer: W) -> Result<(), String> {
+    match format {
+        ClipFormat::Gif => write_gif(clip, writer),
+        ClipFormat::Apng => write_apng(clip, writer),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::animation::{AnimationElement, AnimationFrame};
+    use crate::data::psfb::PsfbBuilder;
+    use crate::data::types::{BinDeserializer, BinSerializer, Image};
+
+    fn two_frame_clip() -> AnimClip {
+        let mut a = vec![0u8; 2 * 2 * 4];
+        a[..4].copy_from_slice(&[255, 0, 0, 255]);
+        let mut b = vec![0u8; 2 * 2 * 4];
+        b[12..].copy_from_slice(&[0, 0, 255, 255]);
+        AnimClip { anim_id: 15, direction: 0, width: 2, height: 2, delay_ms: 150, frames: vec![a, b] }
+    }
+
+    #[test]
+    fn delay_follows_ticks_per_frame() {
+        // Brave: 3 ticks per frame, 50 ms per tick at 20 ticks/sec.
+        assert_eq!(frame_delay_ms(2, DEFAULT_GAME_SPEED), 150);
+        assert_eq!(frame_delay_ms(5, 10), 100);
+        assert!(unit_anim_ids(2).contains(&15) && unit_anim_ids(2).contains(&21));
+    }
+
+    #[test]
+    fn mirrored_directions_flip_about_anchor() {
+        let t = PSFB_TRANSPARENT;
+        let mut builder = PsfbBuilder::new();
+        builder.push_image(&Image::new(2, 1, vec![1, t]), t);
+        let container = ContainerPSFB::from_reader(&mut builder.build().to_bytes().as_slice()).unwrap();
+        let elem = AnimationElement { sprite_index: 0, coord_x: 0, coord_y: 0, tribe: 0, flags: 0,
+                                      uvar5: 0, original_flags: 0 };
+        let (base, _) = anim_shape(15);
+        let sequences: Vec<AnimationSequence> = (0..base + STORED_DIRECTIONS).map(|index| AnimationSequence {
+            index,
+            frames: vec![AnimationFrame { index: 0, width: 2, height: 1, sprites: vec![elem] }],
+        }).collect();
+        let palette = vec![[9, 9, 9, 255]; 256];
+
+        let clips = composite_clips(&sequences, &container, &palette, 15, 2, 0, DEFAULT_GAME_SPEED);
+        assert_eq!(clips.len(), DIRS_PER_ANIM);
+        let (w, h) = (clips[1].width as usize, clips[1].height as usize);
+        assert_eq!((w, h), (4, 1));
+        let opaque = |clip: &AnimClip| -> Vec<usize> {
+            (0..w).filter(|x| clip.frames[0][x * 4 + 3] == 255).collect()
+        };
+        // Pixel right of the anchor in direction 1, left of it in its mirror 7.
+        assert_eq!(opaque(&clips[1]), vec![2]);
+        assert_eq!(opaque(&clips[7]), vec![1]);
+    }
+
+    #[test]
+    fn encoders_keep_frames_and_timing() {
+        let clip = two_frame_clip();
+
+        let mut apng = Vec::new();
+        write_apng(&clip, &mut apng).unwrap();
+        let mut reader = png::Decoder::new(apng.as_slice()).read_info().unwrap();
+        assert_eq!(reader.info().animation_control().unwrap().num_frames, 2);
+        let mut buf = vec![0u8; reader.output_buffer_size()];
+        reader.next_frame(&mut buf).unwrap();
+        let fctl = reader.info().frame_control().unwrap();
+        assert_eq!((fctl.delay_num, fctl.delay_den), (150, 1000));
+        assert_eq!(buf, clip.frames[0]);
+
+        let mut gif = Vec::new();
+        write_gif(&clip, &mut gif).unwrap();
+        use image::AnimationDecoder;
+        let frames = image::codecs::gif::GifDecoder::new(gif.as_slice()).unwrap()
+            .into_frames().collect_frames().unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1].delay().numer_denom_ms(), (150, 1));
+    }
+}
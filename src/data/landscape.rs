@@ -4,6 +4,7 @@ pub mod globe;
 pub mod land;
 pub mod water;
 pub mod disp;
+pub mod heightmap;
 
 /******************************************************************************/
 
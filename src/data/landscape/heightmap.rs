@@ -0,0 +1,124 @@
+use image::{DynamicImage, ImageBuffer, Luma};
+
+use crate::data::level::Landscape;
+
+/******************************************************************************/
+// Heightmaps as 16-bit grayscale images, pixel (x, y) = height[y][x] (the
+// minimap orientation).
+//
+// By default heights are stretched so MAX_LAND_HEIGHT is white; the mapping
+// rounds back to the same heights, so export -> import is lossless. Raw mode
+// stores the height values themselves.
+
+/// Highest height the land textures cover: get_height and the bigf0 rows
+/// saturate above it.
+pub const MAX_LAND_HEIGHT: u16 = 0x400;
+
+pub type HeightmapImage = ImageBuffer<Luma<u16>, Vec<u16>>;
+
+fn height_to_pixel(height: u16, raw: bool) -> u16 {
+    if raw {
+        height
+    } else {
+        let h = height.min(MAX_LAND_HEIGHT) as u32;
+        ((h * u16::MAX as u32 + MAX_LAND_HEIGHT as u32 / 2) / MAX_LAND_HEIGHT as u32) as u16
+    }
+}
+
+fn pixel_to_height(pixel: u16, raw: bool) -> u16 {
+    if raw {
+        pixel
+    } else {
+        ((pixel as u32 * MAX_LAND_HEIGHT as u32 + u16::MAX as u32 / 2) / u16::MAX as u32) as u16
+    }
+}
+
+pub fn heightmap_image<const N: usize>(landscape: &Landscape<N>, raw: bool) -> HeightmapImage {
+    ImageBuffer::from_fn(N as u32, N as u32, |x, y| {
+        Luma([height_to_pixel(landscape.height[y as usize][x as usize], raw)])
+    })
+}
+
+/// Heights from an N x N grayscale image (8-bit images are widened to
+/// 16 bits first). Fails on a size mismatch or, in raw mode, on heights
+/// above MAX_LAND_HEIGHT.
+pub fn landscape_from_heightmap<const N: usize>(img: &DynamicImage, raw: bool) -> Result<Landscape<N>, String> {
+    if img.width() as usize != N || img.height() as usize != N {
+        return Err(format!("heightmap is {}x{}, expected {}x{}", img.width(), img.height(), N, N));
+    }
+    let gray = img.to_luma16();
+    let mut landscape = Landscape::<N>::new();
+    let mut out_of_range = 0usize;
+    let mut highest = 0u16;
+    for (x, y, p) in gray.enumerate_pixels() {
+        let height = pixel_to_height(p.0[0], raw);
+        if height > MAX_LAND_HEIGHT {
+            out_of_range += 1;
+            highest = highest.max(height);
+        }
+        landscape.height[y as usize][x as usize] = height;
+    }
+    if out_of_range > 0 {
+        return Err(format!("{} points above max height 0x{:x} (highest 0x{:x})",
+                           out_of_range, MAX_LAND_HEIGHT, highest));
+    }
+    Ok(landscape)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::types::BinSerializer;
+    use crate::data::level::write_level_landscape;
+
+    fn sample() -> Landscape<128> {
+        let mut landscape = Landscape::<128>::new();
+        for y in 0..128 {
+            for x in 0..128 {
+                landscape.height[y][x] = ((x * 7 + y * 13) % (MAX_LAND_HEIGHT as usize + 1)) as u16;
+            }
+        }
+        landscape
+    }
+
+    #[test]
+    fn scaled_round_trip_is_lossless() {
+        let landscape = sample();
+        for raw in [false, true] {
+            let img = DynamicImage::ImageLuma16(heightmap_image(&landscape, raw));
+            let back = landscape_from_heightmap::<128>(&img, raw).unwrap();
+            assert_eq!(back.height, landscape.height);
+        }
+        assert_eq!(height_to_pixel(0, false), 0);
+        assert_eq!(height_to_pixel(MAX_LAND_HEIGHT, false), u16::MAX);
+    }
+
+    #[test]
+    fn rejects_bad_size_and_range() {
+        let small = DynamicImage::ImageLuma16(ImageBuffer::new(64, 64));
+        assert!(landscape_from_heightmap::<128>(&small, false).is_err());
+
+        let mut img = heightmap_image(&sample(), true);
+        img.put_pixel(3, 4, Luma([MAX_LAND_HEIGHT + 1]));
+        let err = landscape_from_heightmap::<128>(&DynamicImage::ImageLuma16(img), true).err().unwrap();
+        assert!(err.contains("1 points"), "{err}");
+    }
+
+    #[test]
+    fn dat_write_keeps_other_sections() {
+        let landscape = sample();
+        let mut dat = vec![0u8; 0x8000];
+        dat.extend((0..0x100u32).map(|i| i as u8));
+        let path = std::env::temp_dir().join(format!("pop3_heightmap_{}.dat", std::process::id()));
+        std::fs::write(&path, &dat).unwrap();
+
+        write_level_landscape(&path, &landscape).unwrap();
+        let written = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(written.len(), dat.len());
+        assert_eq!(&written[0x8000..], &dat[0x8000..]);
+        assert_eq!(&written[..0x8000], landscape.to_bytes().as_slice());
+        let back = Landscape::<128>::from_reader(&mut written.as_slice());
+        assert_eq!(back.height, landscape.height);
+    }
+}
@@ -1,8 +1,8 @@
 use std::path::{Path, PathBuf};
 use std::fs::{File, OpenOptions};
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 
-use crate::data::types::BinDeserializer;
+use crate::data::types::{BinDeserializer, BinSerializer};
 use crate::data::units::{UnitRaw, TribeConfigRaw};
 
 /******************************************************************************/
@@ -475,6 +475,31 @@ impl<const N: usize> Landscape<N> {
     }
 }
 
+/// Writes the DAT height section (N*N little-endian u16, column major).
+impl<const N: usize> BinSerializer for Landscape<N> {
+    fn to_writer<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let mut s = Self{height: self.height};
+        s.flip();
+        for i in 0..N*N {
+            writer.write_all(&s.height[i%N][i/N].to_le_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// Replace the height section at the start of a level DAT, keeping the
+/// rest of the file as it is.
+pub fn write_level_landscape<const N: usize>(dat_path: &Path, landscape: &Landscape<N>) -> std::io::Result<()> {
+    let heights = landscape.to_bytes();
+    let mut data = std::fs::read(dat_path)?;
+    if data.len() < heights.len() {
+        return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof,
+                                       format!("{} is too short for a {}x{} landscape", dat_path.display(), N, N)));
+    }
+    data[..heights.len()].copy_from_slice(&heights);
+    std::fs::write(dat_path, data)
+}
+
 impl<const N: usize> Default for Landscape<N> {
     fn default() -> Self {
         Self::new()
@@ -5,6 +5,10 @@ pub mod landscape;
 pub mod pls;
 pub mod bl320;
 pub mod objects;
+pub mod object_export;
+pub mod object_import;
 pub mod psfb;
+pub mod sdt;
 pub mod units;
 pub mod animation;
+pub mod anim_export;
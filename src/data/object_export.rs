@@ -0,0 +1,294 @@
+use std::io::Write;
+use std::path::Path;
+
+use crate::data::bl320::read_bl320;
+use crate::data::objects::{Face, Object3D, XYZ_SCALE};
+use crate::data::types::image_allocator_1d_vertical;
+
+/******************************************************************************/
+// Export of OBJS bank models to Wavefront OBJ and glTF 2.0.
+//
+// Positions are in landscape cells (point * coord_scale / 300 / 300, the
+// scale the renderer applies), Y up. Texture coordinates point into the
+// BL320 atlas the way objects_tex.wgsl samples it: texture index i is the
+// 32x32 tile at column i % 8, row i / 8 of the 256-wide, vertically
+// stacked atlas. Faces with a texture index outside 0..=255 are drawn
+// untextured in the game and get a plain grey material here.
+
+pub const ATLAS_COLUMNS: usize = 8;
+pub const ATLAS_ROWS: usize = 32;
+/// Colour the renderer gives untextured faces.
+pub const UNTEXTURED_GREY: f32 = 0.6;
+
+pub fn is_textured(texture_index: i16) -> bool {
+    (0..(ATLAS_COLUMNS * ATLAS_ROWS) as i16).contains(&texture_index)
+}
+
+/// Atlas UV (top-left origin) of a face UV inside texture tile `texture_index`.
+pub fn atlas_uv(texture_index: i16, u: f32, v: f32) -> [f32; 2] {
+    if !is_textured(texture_index) {
+        return [u, v];
+    }
+    let column = texture_index as usize % ATLAS_COLUMNS;
+    let row = texture_index as usize / ATLAS_COLUMNS;
+    [(column as f32 + u) / ATLAS_COLUMNS as f32, (row as f32 + v) / ATLAS_ROWS as f32]
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ExportVertex {
+    pub position: [f32; 3],
+    /// Atlas UV, top-left origin.
+    pub uv: [f32; 2],
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportFace {
+    pub texture_index: i16,
+    /// 3 or 4 vertices, in the bank's winding order.
+    pub vertices: Vec<ExportVertex>,
+}
+
+impl ExportFace {
+    pub fn is_textured(&self) -> bool {
+        is_textured(self.texture_index)
+    }
+
+    /// Split into triangles the way `mk_pop_object` does.
+    pub fn triangles(&self) -> Vec<[ExportVertex; 3]> {
+        let v = &self.vertices;
+        match v.len() {
+            3 => vec![[v[0], v[1], v[2]]],
+            4 => vec![[v[0], v[1], v[2]], [v[2], v[3], v[0]]],
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportMesh {
+    pub name: String,
+    pub faces: Vec<ExportFace>,
+}
+
+impl ExportMesh {
+    pub fn from_object(name: &str, object: &Object3D) -> Self {
+        Self::from_faces(name, object.iter_face(), object.coord_scale())
+    }
+
+    pub fn from_faces<I: Iterator<Item = Face>>(name: &str, faces: I, coord_scale: f32) -> Self {
+        let scale = coord_scale * XYZ_SCALE;
+        let faces = faces.map(|face| {
+            let vertices = face.vertex[..face.vertex_num].iter().map(|v| ExportVertex {
+                position: [v.x * scale, v.y * scale, v.z * scale],
+                uv: atlas_uv(face.texture_index, v.u, v.v),
+            }).collect();
+            ExportFace { texture_index: face.texture_index, vertices }
+        }).collect();
+        Self { name: name.to_string(), faces }
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.faces.iter().map(|f| f.triangles().len()).sum()
+    }
+}
+
+/******************************************************************************/
+
+/// BL320 atlas as RGBA: palette index 0 is the transparent key colour.
+pub fn bl320_atlas_rgba(path: &Path, pal: &[u8]) -> (usize, usize, Vec<u8>) {
+    let allocator = image_allocator_1d_vertical();
+    let image = read_bl320(&allocator, path).get_image();
+    let rgba = image.data.iter().flat_map(|&i| {
+        let p = i as usize * 4;
+        let c = pal.get(p..p + 3).unwrap_or(&[0, 0, 0]);
+        [c[0], c[1], c[2], if i == 0 { 0 } else { 255 }]
+    }).collect();
+    (image.width, image.height, rgba)
+}
+
+/******************************************************************************/
+
+/// Material library for OBJ exports: one textured material and one plain.
+pub fn write_mtl<W: Write>(writer: &mut W, atlas_file: &str) -> std::io::Result<()> {
+    writeln!(writer, "newmtl textured")?;
+    writeln!(writer, "Kd 1.0 1.0 1.0")?;
+    writeln!(writer, "map_Kd {atlas_file}")?;
+    writeln!(writer, "map_d {atlas_file}")?;
+    writeln!(writer)?;
+    writeln!(writer, "newmtl untextured")?;
+    writeln!(writer, "Kd {g} {g} {g}", g = UNTEXTURED_GREY)
+}
+
+/// Write a mesh as OBJ. Faces keep their quads; each face's texture index
+/// is recorded as a `# tex` comment ahead of it.
+pub fn write_obj<W: Write>(writer: &mut W, mesh: &ExportMesh, mtl_file: &str) -> std::io::Result<()> {
+    writeln!(writer, "mtllib {mtl_file}")?;
+    writeln!(writer, "o {}", mesh.name)?;
+    for face in &mesh.faces {
+        for v in &face.vertices {
+            writeln!(writer, "v {} {} {}", v.position[0], v.position[1], v.position[2])?;
+        }
+    }
+    for face in &mesh.faces {
+        for v in &face.vertices {
+            // OBJ texture space has its origin at the bottom left
+            writeln!(writer, "vt {} {}", v.uv[0], 1.0 - v.uv[1])?;
+        }
+    }
+    let mut index = 1;
+    let mut textured = None;
+    for face in &mesh.faces {
+        if textured != Some(face.is_textured()) {
+            textured = Some(face.is_textured());
+            writeln!(writer, "usemtl {}", if face.is_textured() { "textured" } else { "untextured" })?;
+        }
+        writeln!(writer, "# tex {}", face.texture_index)?;
+        write!(writer, "f")?;
+        for _ in &face.vertices {
+            write!(writer, " {index}/{index}")?;
+            index += 1;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/******************************************************************************/
+
+/// glTF 2.0 export: returns the JSON document and the binary buffer it
+/// references as `bin_file`. Textured and untextured faces become separate
+/// primitives of one mesh.
+pub fn write_gltf(mesh: &ExportMesh, bin_file: &str, atlas_file: &str) -> (String, Vec<u8>) {
+    let mut bin: Vec<u8> = Vec::new();
+    let mut views = Vec::new();
+    let mut accessors = Vec::new();
+    let mut primitives = Vec::new();
+
+    for (material, textured) in [(0, true), (1, false)] {
+        let tris: Vec<[ExportVertex; 3]> = mesh.faces.iter()
+            .filter(|f| f.is_textured() == textured)
+            .flat_map(|f| f.triangles())
+            .collect();
+        if tris.is_empty() {
+            continue;
+        }
+        let verts: Vec<&ExportVertex> = tris.iter().flatten().collect();
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for v in &verts {
+            for i in 0..3 {
+                min[i] = min[i].min(v.position[i]);
+                max[i] = max[i].max(v.position[i]);
+            }
+        }
+
+        let pos_offset = bin.len();
+        for v in &verts {
+            for c in v.position {
+                bin.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+        let uv_offset = bin.len();
+        for v in &verts {
+            for c in v.uv {
+                bin.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+        let count = verts.len();
+        let pos_view = views.len();
+        views.push(format!(r#"{{"buffer":0,"byteOffset":{pos_offset},"byteLength":{},"target":34962}}"#, count * 12));
+        views.push(format!(r#"{{"buffer":0,"byteOffset":{uv_offset},"byteLength":{},"target":34962}}"#, count * 8));
+        let pos_acc = accessors.len();
+        accessors.push(format!(
+            r#"{{"bufferView":{pos_view},"componentType":5126,"count":{count},"type":"VEC3","min":[{},{},{}],"max":[{},{},{}]}}"#,
+            min[0], min[1], min[2], max[0], max[1], max[2]));
+        accessors.push(format!(r#"{{"bufferView":{},"componentType":5126,"count":{count},"type":"VEC2"}}"#, pos_view + 1));
+        let attributes = if textured {
+            format!(r#""POSITION":{pos_acc},"TEXCOORD_0":{}"#, pos_acc + 1)
+        } else {
+            format!(r#""POSITION":{pos_acc}"#)
+        };
+        primitives.push(format!(r#"{{"attributes":{{{attributes}}},"material":{material}}}"#));
+    }
+
+    let json = format!(
+        concat!(
+            r#"{{"asset":{{"version":"2.0","generator":"pop3 object_export"}},"#,
+            r#""scene":0,"scenes":[{{"nodes":[0]}}],"#,
+            r#""nodes":[{{"name":"{name}","mesh":0}}],"#,
+            r#""meshes":[{{"name":"{name}","primitives":[{primitives}]}}],"#,
+            r#""materials":[{{"name":"textured","pbrMetallicRoughness":{{"baseColorTexture":{{"index":0}},"metallicFactor":0.0}},"alphaMode":"MASK"}},"#,
+            r#"{{"name":"untextured","pbrMetallicRoughness":{{"baseColorFactor":[{g},{g},{g},1.0],"metallicFactor":0.0}}}}],"#,
+            r#""textures":[{{"source":0,"sampler":0}}],"#,
+            r#""images":[{{"uri":"{atlas}"}}],"#,
+            r#""samplers":[{{"magFilter":9728,"minFilter":9728}}],"#,
+            r#""buffers":[{{"uri":"{bin_file}","byteLength":{bin_len}}}],"#,
+            r#""bufferViews":[{views}],"#,
+            r#""accessors":[{accessors}]}}"#,
+        ),
+        name = mesh.name,
+        primitives = primitives.join(","),
+        g = UNTEXTURED_GREY,
+        atlas = atlas_file,
+        bin_file = bin_file,
+        bin_len = bin.len(),
+        views = views.join(","),
+        accessors = accessors.join(","),
+    );
+    (json, bin)
+}
+
+/******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::objects::Vertex;
+
+    fn face(texture_index: i16, points: &[(f32, f32, f32)]) -> Face {
+        let mut f = Face::new(texture_index, points.len());
+        for (v, &(x, y, z)) in f.vertex.iter_mut().zip(points) {
+            *v = Vertex { x, y, z, u: 0.5, v: 0.25 };
+        }
+        f
+    }
+
+    fn sample_mesh() -> ExportMesh {
+        let faces = vec![
+            face(9, &[(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (1.0, 1.0, 0.0), (0.0, 1.0, 0.0)]),
+            face(-1, &[(0.0, 0.0, 0.0), (0.0, 0.0, 1.0), (0.0, 1.0, 1.0)]),
+        ];
+        ExportMesh::from_faces("hut", faces.into_iter(), 600.0)
+    }
+
+    #[test]
+    fn mesh_applies_coord_scale_and_atlas_tiles() {
+        let mesh = sample_mesh();
+        assert_eq!(mesh.triangle_count(), 3);
+        let v = mesh.faces[0].vertices[2];
+        assert_eq!(v.position, [2.0, 2.0, 0.0]);
+        // tile 9 = column 1, row 1
+        assert_eq!(v.uv, [(1.0 + 0.5) / 8.0, (1.0 + 0.25) / 32.0]);
+        assert!(!mesh.faces[1].is_textured());
+    }
+
+    #[test]
+    fn obj_keeps_quads_and_materials() {
+        let mut out = Vec::new();
+        write_obj(&mut out, &sample_mesh(), "hut.mtl").unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text.lines().filter(|l| l.starts_with("v ")).count(), 7);
+        assert!(text.contains("f 1/1 2/2 3/3 4/4"));
+        assert!(text.contains("usemtl untextured\n# tex -1\nf 5/5 6/6 7/7"));
+    }
+
+    #[test]
+    fn gltf_buffer_matches_accessors() {
+        let (json, bin) = write_gltf(&sample_mesh(), "hut.bin", "atlas.png");
+        // textured: 6 verts * (12 + 8), untextured: 3 verts * (12 + 8)
+        assert_eq!(bin.len(), 9 * 20);
+        assert!(json.contains(&format!(r#""byteLength":{}"#, bin.len())));
+        assert!(json.contains(r#""TEXCOORD_0":1"#));
+        assert!(json.contains(r#"{"attributes":{"POSITION":2},"material":1}"#));
+    }
+}
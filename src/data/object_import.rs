@@ -0,0 +1,467 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::data::object_export::{ExportFace, ExportMesh, ExportVertex, ATLAS_COLUMNS, ATLAS_ROWS, is_textured};
+use crate::data::objects::{FaceRaw, ObjectBank, PointRaw, ShapeFootprints, UV_SCALE, XYZ_SCALE};
+
+/******************************************************************************/
+// Import of OBJ/glTF meshes into OBJS banks — the inverse of object_export.
+//
+// Meshes are in landscape cells, Y up, with texture coordinates in the
+// BL320 atlas. Each face's texture index comes from an exported `# tex`
+// comment when present, otherwise from the atlas tile its UVs fall in;
+// faces without UVs (or with the `untextured` material) are untextured.
+// Anything the original structures can't hold is rejected rather than
+// clamped.
+
+/// Coordinate scale giving one point unit per 1/300 of a model unit, the
+/// scale the renderer treats as 1:1.
+pub const DEFAULT_COORD_SCALE: u32 = 300;
+/// FaceRaw holds 3 or 4 points.
+pub const MAX_FACE_POINTS: usize = 4;
+/// facs_num, pnts_num and the face point indices are u16.
+pub const MAX_OBJECT_FACES: usize = u16::MAX as usize;
+pub const MAX_OBJECT_POINTS: usize = u16::MAX as usize;
+
+/// Texture-tile UV of an atlas UV.
+pub fn tile_uv(texture_index: i16, atlas_uv: [f32; 2]) -> [f32; 2] {
+    if !is_textured(texture_index) {
+        return [0.0, 0.0];
+    }
+    let column = texture_index as usize % ATLAS_COLUMNS;
+    let row = texture_index as usize / ATLAS_COLUMNS;
+    [atlas_uv[0] * ATLAS_COLUMNS as f32 - column as f32, atlas_uv[1] * ATLAS_ROWS as f32 - row as f32]
+}
+
+/// Atlas tile the centre of a face's UVs lies in.
+pub fn texture_from_uvs(uvs: &[[f32; 2]]) -> i16 {
+    if uvs.is_empty() {
+        return -1;
+    }
+    let n = uvs.len() as f32;
+    let u = uvs.iter().map(|uv| uv[0]).sum::<f32>() / n;
+    let v = uvs.iter().map(|uv| uv[1]).sum::<f32>() / n;
+    let column = (u * ATLAS_COLUMNS as f32).floor();
+    let row = (v * ATLAS_ROWS as f32).floor();
+    if !(0.0..ATLAS_COLUMNS as f32).contains(&column) || !(0.0..ATLAS_ROWS as f32).contains(&row) {
+        return -1;
+    }
+    (row as usize * ATLAS_COLUMNS + column as usize) as i16
+}
+
+/******************************************************************************/
+
+fn obj_index(token: Option<&str>, count: usize, line: usize) -> Result<Option<usize>, String> {
+    let Some(token) = token.filter(|t| !t.is_empty()) else { return Ok(None) };
+    let i: i64 = token.parse().map_err(|_| format!("line {line}: bad index '{token}'"))?;
+    let index = if i < 0 { count as i64 + i } else { i - 1 };
+    if index < 0 || index >= count as i64 {
+        return Err(format!("line {line}: index {i} out of range"));
+    }
+    Ok(Some(index as usize))
+}
+
+/// Parse a Wavefront OBJ. All objects in the file become one mesh.
+pub fn parse_obj(text: &str, name: &str) -> Result<ExportMesh, String> {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut faces = Vec::new();
+    let mut untextured = false;
+    let mut explicit_tex: Option<i16> = None;
+
+    for (n, raw) in text.lines().enumerate() {
+        let line = n + 1;
+        let mut tokens = raw.split_whitespace();
+        let floats = |tokens: std::str::SplitWhitespace, count: usize| -> Result<Vec<f32>, String> {
+            let values: Vec<f32> = tokens.take(count).map(|t| t.parse::<f32>())
+                .collect::<Result<_, _>>().map_err(|_| format!("line {line}: bad number"))?;
+            if values.len() < count {
+                return Err(format!("line {line}: expected {count} values"));
+            }
+            Ok(values)
+        };
+        match tokens.next() {
+            Some("v") => {
+                let v = floats(tokens, 3)?;
+                positions.push([v[0], v[1], v[2]]);
+            }
+            Some("vt") => {
+                let v = floats(tokens, 2)?;
+                // OBJ texture space has its origin at the bottom left
+                uvs.push([v[0], 1.0 - v[1]]);
+            }
+            Some("usemtl") => untextured = tokens.next() == Some("untextured"),
+            Some("#") if tokens.next() == Some("tex") => {
+                explicit_tex = tokens.next().and_then(|t| t.parse().ok());
+            }
+            Some("f") => {
+                let mut corners = Vec::new();
+                for corner in tokens {
+                    let mut parts = corner.split('/');
+                    let v = obj_index(parts.next(), positions.len(), line)?
+                        .ok_or_else(|| format!("line {line}: face corner without a vertex"))?;
+                    let vt = obj_index(parts.next(), uvs.len(), line)?;
+                    corners.push((v, vt));
+                }
+                if !(3..=MAX_FACE_POINTS).contains(&corners.len()) {
+                    return Err(format!("line {line}: face with {} corners, the format allows 3 or 4", corners.len()));
+                }
+                let face_uvs: Vec<[f32; 2]> = corners.iter().filter_map(|&(_, vt)| vt.map(|i| uvs[i])).collect();
+                let texture_index = match explicit_tex.take() {
+                    Some(t) => t,
+                    None if untextured || face_uvs.len() != corners.len() => -1,
+                    None => texture_from_uvs(&face_uvs),
+                };
+                let vertices = corners.iter().map(|&(v, vt)| ExportVertex {
+                    position: positions[v],
+                    uv: vt.map_or([0.0, 0.0], |i| uvs[i]),
+                }).collect();
+                faces.push(ExportFace { texture_index, vertices });
+            }
+            _ => {}
+        }
+    }
+    Ok(ExportMesh { name: name.to_string(), faces })
+}
+
+fn mat_mul(a: &[[f32; 4]; 4], b: &[[f32; 4]; 4]) -> [[f32; 4]; 4] {
+    let mut out = [[0.0; 4]; 4];
+    for (c, col) in out.iter_mut().enumerate() {
+        for (r, v) in col.iter_mut().enumerate() {
+            *v = (0..4).map(|k| a[k][r] * b[c][k]).sum();
+        }
+    }
+    out
+}
+
+fn transform_point(m: &[[f32; 4]; 4], p: [f32; 3]) -> [f32; 3] {
+    let mut out = [0.0; 3];
+    for (r, v) in out.iter_mut().enumerate() {
+        *v = m[0][r] * p[0] + m[1][r] * p[1] + m[2][r] * p[2] + m[3][r];
+    }
+    out
+}
+
+fn gltf_node_faces(node: gltf::Node, parent: &[[f32; 4]; 4], buffers: &[Vec<u8>], faces: &mut Vec<ExportFace>) -> Result<(), String> {
+    let matrix = mat_mul(parent, &node.transform().matrix());
+    if let Some(mesh) = node.mesh() {
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                return Err(format!("mesh {}: only triangle primitives are supported", mesh.index()));
+            }
+            let textured = primitive.material().pbr_metallic_roughness().base_color_texture().is_some();
+            let reader = primitive.reader(|b| buffers.get(b.index()).map(|d| d.as_slice()));
+            let positions: Vec<[f32; 3]> = reader.read_positions()
+                .ok_or_else(|| format!("mesh {}: primitive without positions", mesh.index()))?
+                .map(|p| transform_point(&matrix, p))
+                .collect();
+            let uvs: Option<Vec<[f32; 2]>> = reader.read_tex_coords(0).map(|t| t.into_f32().collect());
+            let indices: Vec<u32> = match reader.read_indices() {
+                Some(i) => i.into_u32().collect(),
+                None => (0..positions.len() as u32).collect(),
+            };
+            for tri in indices.chunks_exact(3) {
+                let vertices: Vec<ExportVertex> = tri.iter().map(|&i| ExportVertex {
+                    position: positions.get(i as usize).copied().unwrap_or_default(),
+                    uv: uvs.as_ref().and_then(|u| u.get(i as usize).copied()).unwrap_or_default(),
+                }).collect();
+                let texture_index = match &uvs {
+                    Some(_) if textured => texture_from_uvs(&vertices.iter().map(|v| v.uv).collect::<Vec<_>>()),
+                    _ => -1,
+                };
+                faces.push(ExportFace { texture_index, vertices });
+            }
+        }
+    }
+    for child in node.children() {
+        gltf_node_faces(child, &matrix, buffers, faces)?;
+    }
+    Ok(())
+}
+
+/// Load a glTF/GLB file; every mesh in the default scene (or the first)
+/// becomes part of one mesh, with node transforms applied.
+pub fn load_gltf(path: &Path) -> Result<ExportMesh, String> {
+    let gltf = gltf::Gltf::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut buffers = Vec::new();
+    for buffer in gltf.buffers() {
+        let data = match buffer.source() {
+            gltf::buffer::Source::Bin => gltf.blob.clone().ok_or("GLB without a binary chunk")?,
+            gltf::buffer::Source::Uri(uri) if uri.starts_with("data:") => {
+                return Err("embedded data URIs aren't supported, export with a separate .bin".to_string());
+            }
+            gltf::buffer::Source::Uri(uri) => std::fs::read(dir.join(uri)).map_err(|e| format!("{uri}: {e}"))?,
+        };
+        buffers.push(data);
+    }
+    let scene = gltf.default_scene().or_else(|| gltf.scenes().next()).ok_or("no scene")?;
+    let identity = [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]];
+    let mut faces = Vec::new();
+    for node in scene.nodes() {
+        gltf_node_faces(node, &identity, &buffers, &mut faces)?;
+    }
+    let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("object");
+    Ok(ExportMesh { name: name.to_string(), faces })
+}
+
+/// Load an .obj, .gltf or .glb file.
+pub fn load_mesh(path: &Path) -> Result<ExportMesh, String> {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    match ext.as_str() {
+        "obj" => {
+            let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+            let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("object");
+            parse_obj(&text, name)
+        }
+        "gltf" | "glb" => load_gltf(path),
+        _ => Err(format!("{}: unknown mesh format", path.display())),
+    }
+}
+
+/******************************************************************************/
+
+/// A mesh converted to bank structures.
+pub struct ImportedObject {
+    pub faces: Vec<FaceRaw>,
+    pub points: Vec<PointRaw>,
+    pub coord_scale: u32,
+}
+
+/// Convert a mesh to bank faces and points. With no `coord_scale` the
+/// smallest scale >= DEFAULT_COORD_SCALE that fits the points in i16 is used.
+pub fn build_object(mesh: &ExportMesh, coord_scale: Option<u32>) -> Result<ImportedObject, String> {
+    if mesh.faces.is_empty() {
+        return Err(format!("{}: no faces", mesh.name));
+    }
+    if mesh.faces.len() > MAX_OBJECT_FACES {
+        return Err(format!("{}: {} faces, the format allows {MAX_OBJECT_FACES}", mesh.name, mesh.faces.len()));
+    }
+    let extent = mesh.faces.iter().flat_map(|f| &f.vertices)
+        .flat_map(|v| v.position)
+        .fold(0.0f32, |m, c| m.max(c.abs()));
+    if !extent.is_finite() {
+        return Err(format!("{}: non-finite vertex position", mesh.name));
+    }
+    // cells = point * XYZ_SCALE * coord_scale * XYZ_SCALE
+    let to_points = |scale: u32| 1.0 / (XYZ_SCALE * XYZ_SCALE * scale as f32);
+    let coord_scale = match coord_scale {
+        Some(s) if s > 0 => s,
+        Some(_) => return Err("coord_scale must be positive".to_string()),
+        None => ((extent / (i16::MAX as f32 * XYZ_SCALE * XYZ_SCALE)).ceil() as u32).max(DEFAULT_COORD_SCALE),
+    };
+    let k = to_points(coord_scale);
+    if extent * k > i16::MAX as f32 {
+        return Err(format!("{}: extent {extent:.2} cells doesn't fit i16 points at coord_scale {coord_scale}", mesh.name));
+    }
+
+    let mut points = Vec::new();
+    let mut point_index: HashMap<[i16; 3], u16> = HashMap::new();
+    let mut faces = Vec::with_capacity(mesh.faces.len());
+    for (fi, face) in mesh.faces.iter().enumerate() {
+        if !(3..=MAX_FACE_POINTS).contains(&face.vertices.len()) {
+            return Err(format!("{}: face {fi} has {} corners, the format allows 3 or 4", mesh.name, face.vertices.len()));
+        }
+        if !(-1..=255).contains(&face.texture_index) {
+            return Err(format!("{}: face {fi} texture index {} out of range", mesh.name, face.texture_index));
+        }
+        let mut corners = Vec::with_capacity(face.vertices.len());
+        for v in &face.vertices {
+            let p = v.position.map(|c| (c * k).round() as i16);
+            let next = points.len();
+            let index = *point_index.entry(p).or_insert_with(|| {
+                points.push(PointRaw::new(p[0], p[1], p[2]));
+                next as u16
+            });
+            if points.len() > MAX_OBJECT_POINTS {
+                return Err(format!("{}: more than {MAX_OBJECT_POINTS} points", mesh.name));
+            }
+            let uv = tile_uv(face.texture_index, v.uv);
+            if uv.iter().any(|&c| !c.is_finite() || c < -1e-4 || c / UV_SCALE > u32::MAX as f32) {
+                return Err(format!("{}: face {fi} UV {:?} outside its texture tile", mesh.name, v.uv));
+            }
+            corners.push((index, (uv[0].max(0.0) / UV_SCALE).round() as u32, (uv[1].max(0.0) / UV_SCALE).round() as u32));
+        }
+        faces.push(FaceRaw::new(face.texture_index, &corners));
+    }
+    Ok(ImportedObject { faces, points, coord_scale })
+}
+
+/******************************************************************************/
+
+/// Footprint cells of a mesh for SHAPES.DAT.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Footprint {
+    pub width: u8,
+    pub height: u8,
+    /// Object centre from the top-left cell, in tiles (2 per cell).
+    pub origin_x: u8,
+    pub origin_z: u8,
+    /// Row-major occupancy, width * height.
+    pub cells: Vec<bool>,
+}
+
+fn inside_triangle(p: (f32, f32), a: (f32, f32), b: (f32, f32), c: (f32, f32)) -> bool {
+    let cross = |o: (f32, f32), u: (f32, f32), v: (f32, f32)| (u.0 - o.0) * (v.1 - o.1) - (u.1 - o.1) * (v.0 - o.0);
+    let (d1, d2, d3) = (cross(a, b, p), cross(b, c, p), cross(c, a, p));
+    let neg = d1 < 0.0 || d2 < 0.0 || d3 < 0.0;
+    let pos = d1 > 0.0 || d2 > 0.0 || d3 > 0.0;
+    !(neg && pos)
+}
+
+/// Cells under the mesh's ground projection when placed at `rotation`
+/// (0-3, quarter turns), rotating like build_building_meshes. A cell is
+/// occupied when its centre lies under a face, or a face's centre lies in it.
+pub fn footprint(mesh: &ExportMesh, rotation: usize) -> Result<Option<Footprint>, String> {
+    let angle = -((rotation & 3) as f32) * std::f32::consts::FRAC_PI_2 - std::f32::consts::FRAC_PI_2;
+    let (sin_a, cos_a) = angle.sin_cos();
+    let project = |v: &ExportVertex| {
+        let (x, z) = (v.position[0], v.position[2]);
+        // Snap away sin/cos noise so edges on cell centres stay on them
+        let snap = |c: f32| (c * 1e4).round() / 1e4;
+        (snap(x * cos_a - z * sin_a), snap(x * sin_a + z * cos_a))
+    };
+    let tris: Vec<[(f32, f32); 3]> = mesh.faces.iter()
+        .flat_map(|f| f.triangles())
+        .map(|t| [project(&t[0]), project(&t[1]), project(&t[2])])
+        .collect();
+    if tris.is_empty() {
+        return Ok(None);
+    }
+    let cell = |c: f32| (c + 0.5).floor() as i32;
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
+    for p in tris.iter().flatten() {
+        min_x = min_x.min(cell(p.0));
+        max_x = max_x.max(cell(p.0));
+        min_y = min_y.min(cell(p.1));
+        max_y = max_y.max(cell(p.1));
+    }
+    let width = (max_x - min_x + 1) as usize;
+    let height = (max_y - min_y + 1) as usize;
+    let origin_x = -2 * min_x;
+    let origin_z = -2 * min_y;
+    if width > u8::MAX as usize || height > u8::MAX as usize || !(0..=255).contains(&origin_x) || !(0..=255).contains(&origin_z) {
+        return Err(format!("{}: footprint {width}x{height} too large for SHAPES.DAT", mesh.name));
+    }
+    let mut cells = vec![false; width * height];
+    for (i, c) in cells.iter_mut().enumerate() {
+        let centre = ((min_x + (i % width) as i32) as f32, (min_y + (i / width) as i32) as f32);
+        *c = tris.iter().any(|t| inside_triangle(centre, t[0], t[1], t[2]));
+    }
+    for t in &tris {
+        let cx = cell((t[0].0 + t[1].0 + t[2].0) / 3.0) - min_x;
+        let cy = cell((t[0].1 + t[1].1 + t[2].1) / 3.0) - min_y;
+        cells[cy as usize * width + cx as usize] = true;
+    }
+    Ok(Some(Footprint { width: width as u8, height: height as u8, origin_x: origin_x as u8, origin_z: origin_z as u8, cells }))
+}
+
+/// Convert `mesh` and store it as object `index` of `bank`, with one
+/// footprint per rotation added to `shapes` (identical ones shared).
+pub fn import_object(bank: &mut ObjectBank, shapes: &mut ShapeFootprints, index: usize, mesh: &ExportMesh, coord_scale: Option<u32>) -> Result<(), String> {
+    let object = build_object(mesh, coord_scale)?;
+    let mut fp_idx = [-1i8; 4];
+    let mut added: Vec<(Footprint, i8)> = Vec::new();
+    for (rotation, slot) in fp_idx.iter_mut().enumerate() {
+        let Some(fp) = footprint(mesh, rotation)? else { continue };
+        if let Some((_, i)) = added.iter().find(|(f, _)| *f == fp) {
+            *slot = *i;
+            continue;
+        }
+        let i = shapes.add_shape(fp.width, fp.height, fp.origin_x, fp.origin_z, &fp.cells)
+            .ok_or_else(|| format!("{}: no free SHAPES.DAT entry for its footprint", mesh.name))?;
+        let i = i8::try_from(i).map_err(|_| format!("{}: footprint index {i} doesn't fit", mesh.name))?;
+        added.push((fp, i));
+        *slot = i;
+    }
+    bank.set_object(index, &object.faces, &object.points, object.coord_scale, fp_idx);
+    log::info!("[objects] imported '{}' as object {} ({} faces, {} points, coord_scale {})",
+        mesh.name, index, object.faces.len(), object.points.len(), object.coord_scale);
+    Ok(())
+}
+
+/******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::object_export::write_obj;
+
+    fn quad(x0: f32, z0: f32, x1: f32, z1: f32, tex: i16) -> ExportFace {
+        let v = |x, z, u, v| ExportVertex { position: [x, 0.0, z], uv: crate::data::object_export::atlas_uv(tex, u, v) };
+        ExportFace { texture_index: tex, vertices: vec![v(x0, z0, 0.0, 0.0), v(x1, z0, 1.0, 0.0), v(x1, z1, 1.0, 1.0), v(x0, z1, 0.0, 1.0)] }
+    }
+
+    fn sample_mesh() -> ExportMesh {
+        let mut roof = quad(-1.0, -1.0, 1.0, 1.0, -1);
+        for v in &mut roof.vertices {
+            v.position[1] = 1.5;
+        }
+        ExportMesh { name: "hut".into(), faces: vec![quad(-1.0, -1.0, 1.0, 1.0, 42), roof] }
+    }
+
+    #[test]
+    fn obj_round_trips_through_export() {
+        let mesh = sample_mesh();
+        let mut text = Vec::new();
+        write_obj(&mut text, &mesh, "hut.mtl").unwrap();
+        let parsed = parse_obj(std::str::from_utf8(&text).unwrap(), "hut").unwrap();
+        assert_eq!(parsed.faces.len(), 2);
+        assert_eq!(parsed.faces[0].texture_index, 42);
+        assert_eq!(parsed.faces[1].texture_index, -1);
+        for (a, b) in parsed.faces[0].vertices.iter().zip(&mesh.faces[0].vertices) {
+            assert_eq!(a.position, b.position);
+            assert!((a.uv[0] - b.uv[0]).abs() < 1e-6 && (a.uv[1] - b.uv[1]).abs() < 1e-6);
+        }
+        // Without the comments the tile comes from the UVs
+        let stripped: String = std::str::from_utf8(&text).unwrap().lines()
+            .filter(|l| !l.starts_with('#')).map(|l| format!("{l}\n")).collect();
+        assert_eq!(parse_obj(&stripped, "hut").unwrap().faces[0].texture_index, 42);
+    }
+
+    #[test]
+    fn rejects_meshes_beyond_format_limits() {
+        let pentagon = "v 0 0 0\nv 1 0 0\nv 1 0 1\nv 0 0 1\nv -1 0 0\nf 1 2 3 4 5\n";
+        assert!(parse_obj(pentagon, "p").unwrap_err().contains("3 or 4"));
+
+        let mut huge = sample_mesh();
+        huge.faces[0].vertices[0].position[0] = 1000.0;
+        assert!(build_object(&huge, Some(DEFAULT_COORD_SCALE)).is_err());
+        // Auto scale grows coord_scale so the points fit
+        assert!(build_object(&huge, None).unwrap().coord_scale > DEFAULT_COORD_SCALE);
+    }
+
+    #[test]
+    fn imported_object_reads_back_from_bank() {
+        let mesh = sample_mesh();
+        let mut bank = ObjectBank::new();
+        let mut shapes = ShapeFootprints::empty();
+        import_object(&mut bank, &mut shapes, 2, &mesh, None).unwrap();
+
+        let objects = bank.to_objects();
+        assert_eq!(objects.len(), 3);
+        assert!(objects[0].is_none());
+        let object = objects[2].as_ref().unwrap();
+        assert_eq!(object.point_count(), 8);
+        let back = ExportMesh::from_object("hut", object);
+        assert_eq!(back.faces[0].texture_index, 42);
+        for (fa, fb) in back.faces.iter().zip(&mesh.faces) {
+            for (a, b) in fa.vertices.iter().zip(&fb.vertices) {
+                for i in 0..3 {
+                    assert!((a.position[i] - b.position[i]).abs() < 1e-3);
+                }
+                if fb.is_textured() {
+                    assert!((a.uv[0] - b.uv[0]).abs() < 1e-4 && (a.uv[1] - b.uv[1]).abs() < 1e-4);
+                }
+            }
+        }
+
+        // A symmetric 3x3 footprint is shared by all rotations
+        let fp = object.footprint_index(0);
+        assert!(fp >= 0);
+        assert!((0..4).all(|r| object.footprint_index(r) == fp));
+        let shape = shapes.shapes()[fp as usize];
+        assert_eq!((shape.width, shape.height, shape.origin_x, shape.origin_z), (3, 3, 2, 2));
+        assert!(shapes.is_cell_occupied(fp as usize, 1, 1));
+    }
+}
@@ -1,5 +1,5 @@
 use std::path::Path;
-use std::io::Read;
+use std::io::{Read, Write};
 use core::mem::size_of;
 use core::slice::Iter;
 
@@ -7,7 +7,7 @@ use cgmath::{Vector2, Vector3};
 
 use crate::render::model::{MeshModel, VertexModel};
 use crate::render::tex_model::{TexModel, TexVertex};
-use crate::data::types::{BinDeserializer, from_reader};
+use crate::data::types::{BinDeserializer, BinSerializer, from_reader, to_writer};
 use crate::data::level::ObjectPaths;
 
 /******************************************************************************/
@@ -44,6 +44,43 @@ impl BinDeserializer for ObjectRaw {
     }
 }
 
+impl BinSerializer for ObjectRaw {
+    fn to_writer<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        to_writer::<Self, {size_of::<Self>()}, W>(self, writer)
+    }
+}
+
+impl ObjectRaw {
+    /// Entry for a model whose faces and points occupy the given 1-based,
+    /// end-exclusive ranges of the FACS/PNTS files. Fields whose meaning
+    /// isn't known are left zero.
+    pub fn new(faces: std::ops::Range<u32>, points: std::ops::Range<u32>, coord_scale: u32, fp_idx: [i8; 4]) -> Self {
+        Self {
+            flags: 0,
+            facs_num: (faces.end - faces.start) as u16,
+            pnts_num: (points.end - points.start) as u16,
+            f1: 0,
+            morph_index: 0,
+            f2: 0,
+            coord_scale,
+            facs_ptr: faces.start,
+            facs_ptr_end: faces.end,
+            pnts_ptr: points.start,
+            pnts_ptr_end: points.end,
+            f4: 0,
+            f5: 0,
+            f6: 0,
+            f7: 0,
+            f8: 0,
+            f9: 0,
+            fp_idx,
+            f11: 0,
+            f12: 0,
+            f13: 0,
+        }
+    }
+}
+
 /******************************************************************************/
 
 #[derive(Debug, Copy, Clone)]
@@ -63,6 +100,12 @@ impl BinDeserializer for Shape {
     }
 }
 
+impl BinSerializer for Shape {
+    fn to_writer<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        to_writer::<Self, {size_of::<Self>()}, W>(self, writer)
+    }
+}
+
 /******************************************************************************/
 
 /// Parsed building footprint data from SHAPES.DAT.
@@ -99,6 +142,25 @@ impl ShapeFootprints {
         &self.shapes
     }
 
+    /// Store a footprint bitmap (row-major, `width * height` cells) in the
+    /// first unused entry (zero width or height). Returns the entry index,
+    /// or None if all SHAPE_ENTRY_COUNT entries are taken. `cell_mask`'s
+    /// meaning isn't mapped and is left zero.
+    pub fn add_shape(&mut self, width: u8, height: u8, origin_x: u8, origin_z: u8, cells: &[bool]) -> Option<usize> {
+        let index = match self.shapes.iter().position(|s| s.width == 0 || s.height == 0) {
+            Some(i) => i,
+            None if self.shapes.len() < SHAPE_ENTRY_COUNT => {
+                self.shapes.push(Shape { width: 0, height: 0, origin_x: 0, origin_z: 0, cell_mask: [0; 40], shape_ref: 0 });
+                self.shapes.len() - 1
+            }
+            None => return None,
+        };
+        let shape_ref = self.bitmap_data.len() as u32;
+        self.bitmap_data.extend(cells.iter().take(width as usize * height as usize).map(|&c| c as u8));
+        self.shapes[index] = Shape { width, height, origin_x, origin_z, cell_mask: [0; 40], shape_ref };
+        Some(index)
+    }
+
     /// Check if cell (dx, dy) within a shape's bounding box is actually occupied.
     /// Returns false for out-of-bounds or empty bitmap cells.
     pub fn is_cell_occupied(&self, shape_idx: usize, dx: usize, dy: usize) -> bool {
@@ -112,6 +174,16 @@ impl ShapeFootprints {
     }
 }
 
+impl BinSerializer for ShapeFootprints {
+    fn to_writer<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let empty = Shape { width: 0, height: 0, origin_x: 0, origin_z: 0, cell_mask: [0; 40], shape_ref: 0 };
+        for i in 0..SHAPE_ENTRY_COUNT {
+            self.shapes.get(i).unwrap_or(&empty).to_writer(writer)?;
+        }
+        writer.write_all(&self.bitmap_data)
+    }
+}
+
 /******************************************************************************/
 
 #[derive(Debug, Copy, Clone)]
@@ -128,6 +200,18 @@ impl BinDeserializer for PointRaw {
     }
 }
 
+impl BinSerializer for PointRaw {
+    fn to_writer<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        to_writer::<Self, {size_of::<Self>()}, W>(self, writer)
+    }
+}
+
+impl PointRaw {
+    pub fn new(x: i16, y: i16, z: i16) -> Self {
+        Self { x, y, z }
+    }
+}
+
 /******************************************************************************/
 
 #[derive(Debug, Copy, Clone)]
@@ -165,10 +249,50 @@ impl BinDeserializer for FaceRaw {
     }
 }
 
+impl BinSerializer for FaceRaw {
+    fn to_writer<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        to_writer::<Self, {size_of::<Self>()}, W>(self, writer)
+    }
+}
+
+impl FaceRaw {
+    /// Face over 3 or 4 of the object's points, each with its texture
+    /// coordinates in UV_SCALE units. Unknown fields are left zero.
+    pub fn new(tex_index: i16, points: &[(u16, u32, u32)]) -> Self {
+        let p = |i: usize| points.get(i).or(points.first()).copied().unwrap_or((0, 0, 0));
+        Self {
+            f0: 0,
+            tex_index,
+            flags1: 0,
+            num_points: points.len().min(4) as u8,
+            f11: 0,
+            point_1_u: p(0).1,
+            point_1_v: p(0).2,
+            point_2_u: p(1).1,
+            point_2_v: p(1).2,
+            point_3_u: p(2).1,
+            point_3_v: p(2).2,
+            point_4_u: if points.len() > 3 { p(3).1 } else { 0 },
+            point_4_v: if points.len() > 3 { p(3).2 } else { 0 },
+            point_1: p(0).0,
+            point_2: p(1).0,
+            point_3: p(2).0,
+            point_4: if points.len() > 3 { p(3).0 } else { 0 },
+            f6: 0,
+            ff1: 0,
+            ff2: 0,
+            ff3: 0,
+            ff4: 0,
+            f8: 0,
+            flags2: 0,
+        }
+    }
+}
+
 /******************************************************************************/
 
-const XYZ_SCALE: f32 = 1.0 / 300.0;
-const UV_SCALE: f32 = 4.768372e-7;
+pub const XYZ_SCALE: f32 = 1.0 / 300.0;
+pub const UV_SCALE: f32 = 4.768372e-7;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Vertex {
@@ -310,6 +434,69 @@ impl Object3D {
 
 /******************************************************************************/
 
+/// The three parallel files of an objects bank, kept raw so entries can be
+/// replaced and written back.
+pub struct ObjectBank {
+    pub objects: Vec<ObjectRaw>,
+    pub faces: Vec<FaceRaw>,
+    pub points: Vec<PointRaw>,
+}
+
+impl ObjectBank {
+    pub fn new() -> Self {
+        Self { objects: Vec::new(), faces: Vec::new(), points: Vec::new() }
+    }
+
+    pub fn from_paths(paths: &ObjectPaths) -> Self {
+        Self {
+            objects: ObjectRaw::from_file_vec(&paths.objs0_dat),
+            faces: FaceRaw::from_file_vec(&paths.facs0),
+            points: PointRaw::from_file_vec(&paths.pnts0),
+        }
+    }
+
+    /// Put a model at `index`, appending its faces and points to the bank.
+    /// Gaps before `index` are filled with empty entries.
+    pub fn set_object(&mut self, index: usize, faces: &[FaceRaw], points: &[PointRaw], coord_scale: u32, fp_idx: [i8; 4]) {
+        let face_start = self.faces.len() as u32 + 1;
+        let point_start = self.points.len() as u32 + 1;
+        self.faces.extend_from_slice(faces);
+        self.points.extend_from_slice(points);
+        let object = ObjectRaw::new(
+            face_start..face_start + faces.len() as u32,
+            point_start..point_start + points.len() as u32,
+            coord_scale, fp_idx);
+        let empty = ObjectRaw::new(1..1, 1..1, 0, [-1; 4]);
+        if self.objects.len() <= index {
+            self.objects.resize(index + 1, empty);
+        }
+        self.objects[index] = object;
+    }
+
+    pub fn to_objects(&self) -> Vec<Option<Object3D>> {
+        Object3D::create_objects_all(&self.objects, &self.faces, &self.points)
+    }
+
+    pub fn write(&self, paths: &ObjectPaths) -> std::io::Result<()> {
+        fn write_items<T: BinSerializer>(path: &Path, items: &[T]) -> std::io::Result<()> {
+            let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+            items.iter().try_for_each(|item| item.to_writer(&mut file))?;
+            file.flush()
+        }
+        write_items(&paths.objs0_dat, &self.objects)?;
+        write_items(&paths.facs0, &self.faces)?;
+        write_items(&paths.pnts0, &self.points)
+    }
+}
+
+impl Default for ObjectBank {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/******************************************************************************/
+
 pub struct FaceIter<'a, I> where I: Iterator<Item = &'a FaceRaw> {
     iter: I,
     points: &'a [PointRaw],
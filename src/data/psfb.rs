@@ -1,6 +1,7 @@
-use std::io::Read;
+use std::collections::HashMap;
+use std::io::{Read, Write};
 
-use crate::data::types::{BinDeserializer, Image, ImageStorage, ImageInfo, ImageStorageSource};
+use crate::data::types::{BinDeserializer, BinSerializer, Image, ImageStorage, ImageInfo, ImageStorageSource};
 
 /******************************************************************************/
 
@@ -72,6 +73,13 @@ impl BinDeserializer for SpritePSFB {
     }
 }
 
+const PSFB_MARKER: u32 = 0x42465350; // "PSFB"
+/// Palette index `get_image` fills unpainted pixels with; the encoder turns
+/// it into skip runs.
+pub const PSFB_TRANSPARENT: u8 = 255;
+/// Longest skip or literal run a single control byte holds.
+const PSFB_MAX_RUN: usize = 127;
+
 pub struct ContainerPSFB {
     header_size: usize,
     sprites: Vec<SpritePSFB>,
@@ -110,6 +118,27 @@ impl ContainerPSFB {
         false
     }
 
+    /// Encoded rows of a sprite, as stored in the file.
+    pub fn get_data(&self, index: usize) -> Option<&[u8]> {
+        let s = self.sprites.get(index)?;
+        let start = s.offset.checked_sub(self.header_size)?;
+        let data = self.data.get(start..)?;
+        let mut pos = 0;
+        for _ in 0..s.height {
+            loop {
+                let val = *data.get(pos)? as i8;
+                pos += 1;
+                if val == 0 {
+                    break;
+                }
+                if val > 0 {
+                    pos += val as usize;
+                }
+            }
+        }
+        data.get(..pos)
+    }
+
     pub fn get_image(&self, index: usize) -> Option<Image> {
         if let Some(s) = self.sprites.get(index) {
             let offset = s.offset - self.header_size;
@@ -126,7 +155,7 @@ impl BinDeserializer for ContainerPSFB {
         let mut buf = [0u8; 4];
         reader.read_exact(&mut buf).unwrap();
         let marker = u32::from_le_bytes(buf);
-        if marker != 0x42465350 { // note(): "PSFB" in hex
+        if marker != PSFB_MARKER {
             return None;
         }
         reader.read_exact(&mut buf).unwrap();
@@ -145,4 +174,193 @@ impl BinDeserializer for ContainerPSFB {
     }
 }
 
+impl BinSerializer for ContainerPSFB {
+    fn to_writer<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&PSFB_MARKER.to_le_bytes())?;
+        writer.write_all(&(self.sprites.len() as u32).to_le_bytes())?;
+        for s in &self.sprites {
+            writer.write_all(&s.width.to_le_bytes())?;
+            writer.write_all(&s.height.to_le_bytes())?;
+            writer.write_all(&(s.offset as u32).to_le_bytes())?;
+        }
+        writer.write_all(&self.data)
+    }
+}
+
 /******************************************************************************/
+// Encoder. Each row is a run of control bytes ended by 0: a negative byte
+// skips that many transparent pixels, a positive one is followed by that
+// many palette indices. Trailing transparent pixels aren't stored.
+
+/// Encode a palette-indexed image; pixels equal to `transparent` are left
+/// unpainted.
+pub fn encode_sprite(image: &Image, transparent: u8) -> Vec<u8> {
+    let mut out = Vec::new();
+    for row in image.data.chunks(image.width.max(1)).take(image.height) {
+        let end = row.iter().rposition(|&p| p != transparent).map_or(0, |i| i + 1);
+        let mut x = 0;
+        while x < end {
+            let opaque = row[x] != transparent;
+            let run = row[x..end].iter()
+                .take(PSFB_MAX_RUN)
+                .take_while(|&&p| (p != transparent) == opaque)
+                .count();
+            if opaque {
+                out.push(run as u8);
+                out.extend_from_slice(&row[x..x + run]);
+            } else {
+                out.push((-(run as i8)) as u8);
+            }
+            x += run;
+        }
+        out.push(0);
+    }
+    out
+}
+
+/// Builds a PSFB container sprite by sprite.
+#[derive(Default)]
+pub struct PsfbBuilder {
+    sprites: Vec<(u16, u16, Vec<u8>)>,
+}
+
+impl PsfbBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start from an existing bank, keeping its encoded sprites as-is.
+    pub fn from_container(c: &ContainerPSFB) -> Self {
+        let sprites = c.sprites.iter().map(|s| {
+            let data = c.get_data(s.index).map(|d| d.to_vec()).unwrap_or_default();
+            (s.width, s.height, data)
+        }).collect();
+        Self { sprites }
+    }
+
+    pub fn len(&self) -> usize {
+        self.sprites.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sprites.is_empty()
+    }
+
+    /// Put a sprite at `index`, padding any gap with empty sprites.
+    /// Returns false if the image is too large for the format.
+    pub fn set_image(&mut self, index: usize, image: &Image, transparent: u8) -> bool {
+        if image.width > u16::MAX as usize || image.height > u16::MAX as usize {
+            return false;
+        }
+        if self.sprites.len() <= index {
+            self.sprites.resize(index + 1, (0, 0, Vec::new()));
+        }
+        self.sprites[index] = (image.width as u16, image.height as u16, encode_sprite(image, transparent));
+        true
+    }
+
+    pub fn push_image(&mut self, image: &Image, transparent: u8) -> bool {
+        self.set_image(self.sprites.len(), image, transparent)
+    }
+
+    pub fn build(self) -> ContainerPSFB {
+        let header_size = 8 + 8 * self.sprites.len();
+        let mut sprites = Vec::with_capacity(self.sprites.len());
+        let mut data = Vec::new();
+        for (index, (width, height, rows)) in self.sprites.into_iter().enumerate() {
+            sprites.push(SpritePSFB { index, offset: header_size + data.len(), width, height });
+            data.extend_from_slice(&rows);
+        }
+        if sprites.last().is_some_and(|s| s.offset == header_size + data.len()) {
+            // The decoder peeks a byte even for empty sprites
+            data.push(0);
+        }
+        ContainerPSFB { header_size, sprites, data }
+    }
+}
+
+/// Map RGBA pixels to the nearest palette colour (4 bytes per palette
+/// entry). Pixels with alpha below half become `transparent`, which is
+/// never picked for opaque ones.
+pub fn quantize_rgba(rgba: &[u8], width: usize, height: usize, pal: &[u8], transparent: u8) -> Image {
+    let colors: Vec<(u8, [i32; 3])> = pal.chunks_exact(4).take(256).enumerate()
+        .filter(|&(i, _)| i != transparent as usize)
+        .map(|(i, c)| (i as u8, [c[0] as i32, c[1] as i32, c[2] as i32]))
+        .collect();
+    let mut cache: HashMap<[u8; 3], u8> = HashMap::new();
+    let data = rgba.chunks_exact(4).take(width * height).map(|px| {
+        if px[3] < 128 {
+            return transparent;
+        }
+        let key = [px[0], px[1], px[2]];
+        *cache.entry(key).or_insert_with(|| {
+            colors.iter()
+                .min_by_key(|(_, c)| {
+                    let d = [c[0] - px[0] as i32, c[1] - px[1] as i32, c[2] - px[2] as i32];
+                    d[0] * d[0] + d[1] * d[1] + d[2] * d[2]
+                })
+                .map_or(transparent, |&(i, _)| i)
+        })
+    }).collect();
+    Image::new(width, height, data)
+}
+
+/******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(c: &ContainerPSFB) -> ContainerPSFB {
+        ContainerPSFB::from_reader(&mut c.to_bytes().as_slice()).unwrap()
+    }
+
+    #[test]
+    fn encoded_sprites_decode_back() {
+        let t = PSFB_TRANSPARENT;
+        let a = Image::new(4, 3, vec![
+            t, 1, 2, t,
+            t, t, t, t,
+            3, 3, 3, 3,
+        ]);
+        let mut wide = vec![7u8; 300];
+        wide[10..150].fill(t);
+        let b = Image::new(300, 1, wide);
+
+        let mut builder = PsfbBuilder::new();
+        builder.push_image(&a, t);
+        builder.set_image(2, &b, t);
+        let c = round_trip(&builder.build());
+        assert_eq!(c.len(), 3);
+        assert_eq!(c.get_image(0).unwrap().data, a.data);
+        let empty = c.get_info(1).unwrap();
+        assert_eq!((empty.width, empty.height), (0, 0));
+        assert_eq!(c.get_image(2).unwrap().data, b.data);
+    }
+
+    #[test]
+    fn builder_keeps_existing_sprites() {
+        let t = PSFB_TRANSPARENT;
+        let mut builder = PsfbBuilder::new();
+        builder.push_image(&Image::new(2, 2, vec![1, t, t, 2]), t);
+        builder.push_image(&Image::new(1, 1, vec![5]), t);
+        builder.set_image(3, &Image::new(0, 0, vec![]), t);
+        let original = builder.build();
+
+        let mut builder = PsfbBuilder::from_container(&original);
+        builder.set_image(1, &Image::new(1, 2, vec![9, 9]), t);
+        let c = round_trip(&builder.build());
+        assert_eq!(c.get_data(0), original.get_data(0));
+        assert_eq!(c.get_image(0).unwrap().data, vec![1, t, t, 2]);
+        assert_eq!(c.get_image(1).unwrap().data, vec![9, 9]);
+        assert!(c.get_image(3).unwrap().data.is_empty());
+    }
+
+    #[test]
+    fn quantize_picks_nearest_and_keeps_transparency() {
+        let pal = [0, 0, 0, 0, 250, 0, 0, 0, 0, 0, 250, 0];
+        let rgba = [240, 10, 10, 255, 5, 5, 200, 255, 240, 10, 10, 0];
+        let img = quantize_rgba(&rgba, 3, 1, &pal, 0);
+        assert_eq!(img.data, vec![1, 2, 0]);
+    }
+}
@@ -0,0 +1,273 @@
+use std::io::Read;
+
+use crate::data::types::BinDeserializer;
+
+/******************************************************************************/
+// SDT sound banks (soundd2.sdt, soundd2low.sdt, popfightnew.sdt,
+// popdrones22.sdt, popdrum022.sdt), loaded by Sound_LoadSDT @ 0x00418c00.
+//
+// Layout as read here. UNVERIFIED: this is the crate's own reading of the
+// files, not taken from Sound_LoadSDT; docs/specs/audio.md names the loader
+// but doesn't give the entry layout. The reserved words and the raw-PCM
+// fallback in particular are guesses.
+//   +0x00  u32 entry count
+//   +0x04  entry table, 32 bytes per entry:
+//            +0x00  name[16] (NUL padded)
+//            +0x10  u32 data offset (from file start)
+//            +0x14  u32 data size
+//            +0x18  u32 reserved
+//            +0x1C  u32 reserved
+//   data   each entry is a RIFF/WAVE file; entries without a RIFF header
+//          are raw 8-bit mono PCM at SDT_DEFAULT_SAMPLE_RATE.
+
+pub const SDT_ENTRY_SIZE: usize = 32;
+pub const SDT_NAME_LEN: usize = 16;
+/// Sample rate assumed for headerless entries (the banks are 22 kHz).
+pub const SDT_DEFAULT_SAMPLE_RATE: u32 = 22050;
+
+const WAVE_FORMAT_PCM: u16 = 1;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SampleFormat {
+    pub channels: u16,
+    pub bits_per_sample: u16,
+}
+
+impl SampleFormat {
+    pub const MONO_8: Self = Self { channels: 1, bits_per_sample: 8 };
+
+    pub fn block_align(&self) -> u16 {
+        self.channels * self.bits_per_sample.div_ceil(8)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SdtEntry {
+    /// Index of the entry in the bank (sound id within the bank).
+    pub id: usize,
+    pub name: String,
+    pub offset: usize,
+    pub size: usize,
+    pub sample_rate: u32,
+    pub format: SampleFormat,
+    /// PCM samples (WAV header stripped).
+    pub data: Vec<u8>,
+}
+
+impl SdtEntry {
+    /// Number of sample frames.
+    pub fn frames(&self) -> usize {
+        let align = self.format.block_align().max(1) as usize;
+        self.data.len() / align
+    }
+
+    pub fn duration_secs(&self) -> f32 {
+        if self.sample_rate == 0 { return 0.0; }
+        self.frames() as f32 / self.sample_rate as f32
+    }
+
+    /// Encode the samples as a PCM WAV file.
+    pub fn to_wav(&self) -> Vec<u8> {
+        write_wav(self.sample_rate, self.format, &self.data)
+    }
+}
+
+/// Build a PCM RIFF/WAVE file.
+pub fn write_wav(sample_rate: u32, format: SampleFormat, data: &[u8]) -> Vec<u8> {
+    let block_align = format.block_align();
+    let byte_rate = sample_rate * block_align as u32;
+    let mut out = Vec::with_capacity(44 + data.len() + 1);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(36 + data.len() as u32 + (data.len() as u32 & 1)).to_le_bytes());
+    out.extend_from_slice(b"WAVE");
+    out.extend_from_slice(b"fmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&WAVE_FORMAT_PCM.to_le_bytes());
+    out.extend_from_slice(&format.channels.to_le_bytes());
+    out.extend_from_slice(&sample_rate.to_le_bytes());
+    out.extend_from_slice(&byte_rate.to_le_bytes());
+    out.extend_from_slice(&block_align.to_le_bytes());
+    out.extend_from_slice(&format.bits_per_sample.to_le_bytes());
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        out.push(0); // chunks are word aligned
+    }
+    out
+}
+
+/// Parse a PCM RIFF/WAVE file into (sample_rate, format, samples).
+pub fn parse_wav(data: &[u8]) -> Option<(u32, SampleFormat, &[u8])> {
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return None;
+    }
+    let mut fmt: Option<(u32, SampleFormat)> = None;
+    let mut pos = 12;
+    while pos + 8 <= data.len() {
+        let id = &data[pos..pos + 4];
+        let size = u32::from_le_bytes(data[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        let body_start = pos + 8;
+        let body_end = (body_start + size).min(data.len());
+        let body = &data[body_start..body_end];
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                let tag = u16::from_le_bytes([body[0], body[1]]);
+                if tag != WAVE_FORMAT_PCM { return None; }
+                let channels = u16::from_le_bytes([body[2], body[3]]);
+                let sample_rate = u32::from_le_bytes(body[4..8].try_into().unwrap());
+                let bits_per_sample = u16::from_le_bytes([body[14], body[15]]);
+                fmt = Some((sample_rate, SampleFormat { channels, bits_per_sample }));
+            }
+            b"data" => {
+                let (rate, format) = fmt?;
+                return Some((rate, format, body));
+            }
+            _ => {}
+        }
+        pos = body_start + size + (size & 1);
+    }
+    None
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+    data.get(pos..pos + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+}
+
+pub struct SoundBank {
+    pub entries: Vec<SdtEntry>,
+}
+
+impl SoundBank {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, id: usize) -> Option<&SdtEntry> {
+        self.entries.get(id)
+    }
+
+    /// Look up an entry by name (case-insensitive, as the game's files are).
+    pub fn find(&self, name: &str) -> Option<&SdtEntry> {
+        self.entries.iter().find(|e| e.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Parse a whole bank. Returns None if the entry table doesn't fit the
+    /// file; entries whose data runs past the end are truncated.
+    pub fn from_data(data: &[u8]) -> Option<Self> {
+        let count = read_u32(data, 0)? as usize;
+        let table_end = 4usize.checked_add(count.checked_mul(SDT_ENTRY_SIZE)?)?;
+        if table_end > data.len() {
+            return None;
+        }
+        let mut entries = Vec::with_capacity(count);
+        for id in 0..count {
+            let rec = &data[4 + id * SDT_ENTRY_SIZE..4 + (id + 1) * SDT_ENTRY_SIZE];
+            let name_len = rec[..SDT_NAME_LEN].iter().position(|&b| b == 0).unwrap_or(SDT_NAME_LEN);
+            let name = String::from_utf8_lossy(&rec[..name_len]).into_owned();
+            let offset = read_u32(rec, SDT_NAME_LEN)? as usize;
+            let size = read_u32(rec, SDT_NAME_LEN + 4)? as usize;
+            let start = offset.min(data.len());
+            let end = offset.saturating_add(size).min(data.len());
+            let raw = &data[start..end];
+            let (sample_rate, format, pcm) = match parse_wav(raw) {
+                Some(wav) => wav,
+                None => (SDT_DEFAULT_SAMPLE_RATE, SampleFormat::MONO_8, raw),
+            };
+            entries.push(SdtEntry {
+                id,
+                name,
+                offset,
+                size,
+                sample_rate,
+                format,
+                data: pcm.to_vec(),
+            });
+        }
+        Some(Self { entries })
+    }
+}
+
+impl BinDeserializer for SoundBank {
+    fn from_reader<R: Read>(reader: &mut R) -> Option<Self> where Self: Sized {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).ok()?;
+        Self::from_data(&data)
+    }
+}
+
+/******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bank(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut out = (entries.len() as u32).to_le_bytes().to_vec();
+        let mut offset = 4 + entries.len() * SDT_ENTRY_SIZE;
+        for (name, data) in entries {
+            let mut rec = [0u8; SDT_ENTRY_SIZE];
+            rec[..name.len()].copy_from_slice(name.as_bytes());
+            rec[16..20].copy_from_slice(&(offset as u32).to_le_bytes());
+            rec[20..24].copy_from_slice(&(data.len() as u32).to_le_bytes());
+            out.extend_from_slice(&rec);
+            offset += data.len();
+        }
+        for (_, data) in entries {
+            out.extend_from_slice(data);
+        }
+        out
+    }
+
+    #[test]
+    fn parses_wav_and_raw_entries() {
+        let stereo16 = SampleFormat { channels: 2, bits_per_sample: 16 };
+        let wav = write_wav(11025, stereo16, &[1, 2, 3, 4, 5, 6, 7, 8]);
+        let data = bank(&[("BLAST.WAV", wav), ("DRONE", vec![0x80; 10])]);
+        let bank = SoundBank::from_data(&data).unwrap();
+        assert_eq!(bank.len(), 2);
+
+        let e = bank.get(0).unwrap();
+        assert_eq!(e.name, "BLAST.WAV");
+        assert_eq!(e.sample_rate, 11025);
+        assert_eq!(e.format, stereo16);
+        assert_eq!(e.data, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(e.frames(), 2);
+
+        let e = bank.find("drone").unwrap();
+        assert_eq!(e.id, 1);
+        assert_eq!(e.sample_rate, SDT_DEFAULT_SAMPLE_RATE);
+        assert_eq!(e.format, SampleFormat::MONO_8);
+        assert_eq!(e.data.len(), 10);
+    }
+
+    #[test]
+    fn wav_export_round_trips() {
+        let entry = SdtEntry {
+            id: 0,
+            name: "X".into(),
+            offset: 0,
+            size: 0,
+            sample_rate: 22050,
+            format: SampleFormat::MONO_8,
+            data: vec![10, 20, 30],
+        };
+        let wav = entry.to_wav();
+        assert_eq!(wav.len() % 2, 0);
+        let (rate, format, pcm) = parse_wav(&wav).unwrap();
+        assert_eq!(rate, 22050);
+        assert_eq!(format, SampleFormat::MONO_8);
+        assert_eq!(pcm, &[10, 20, 30]);
+    }
+
+    #[test]
+    fn rejects_truncated_table() {
+        let mut data = 5u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&[0u8; SDT_ENTRY_SIZE]);
+        assert!(SoundBank::from_data(&data).is_none());
+    }
+}
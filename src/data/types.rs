@@ -1,6 +1,6 @@
 use std::path::Path;
 use std::fs::File;
-use std::io::Read;
+use std::io::{Read, Write};
 use std::marker::PhantomData;
 
 /******************************************************************************/
@@ -27,6 +27,21 @@ pub trait BinDeserializer {
     }
 }
 
+pub trait BinSerializer {
+    fn to_writer<W: Write>(&self, writer: &mut W) -> std::io::Result<()>;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        self.to_writer(&mut data).unwrap();
+        data
+    }
+
+    fn to_file(&self, path: &Path) -> std::io::Result<()> {
+        let mut file = File::create(path)?;
+        self.to_writer(&mut file)
+    }
+}
+
 pub fn from_reader<T, const S: usize, R: Read>(reader: &mut R) -> Option<T> where T: Copy {
     let mut data = [0u8; S];
     if let Ok(()) = reader.read_exact(&mut data) {
@@ -37,6 +52,13 @@ pub fn from_reader<T, const S: usize, R: Read>(reader: &mut R) -> Option<T> wher
     None
 }
 
+pub fn to_writer<T, const S: usize, W: Write>(item: &T, writer: &mut W) -> std::io::Result<()> where T: Copy {
+    let data = unsafe {
+        std::slice::from_raw_parts(item as *const T as *const u8, S)
+    };
+    writer.write_all(data)
+}
+
 pub fn from_reader_vec<T, const S: usize, R: Read>(reader: &mut R) -> Vec<T> where T: Copy {
     let mut items = Vec::new();
     let mut data = [0u8; S];
@@ -71,14 +93,12 @@ impl<'a, T> ImageInfo for &'a T where T: ImageInfo {
 }
 
 impl<'a, T> ImageInfo for &'a mut T where T: ImageInfo {
-    #[allow(unconditional_recursion)]
     fn width(&self) -> usize {
-        (*self).width()
+        (**self).width()
     }
 
-    #[allow(unconditional_recursion)]
     fn height(&self) -> usize {
-        (*self).height()
+        (**self).height()
     }
 }
 
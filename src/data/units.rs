@@ -72,6 +72,17 @@ impl UnitRaw {
     pub fn fd(&self) -> &[u8; 40] { &self.fd }
 }
 
+#[cfg(test)]
+impl UnitRaw {
+    /// A level object record as the level loader would read it, facing
+    /// angle 0; `fd` fills the start of the extra data, the rest is 0.
+    pub(crate) fn for_test(model: ModelType, subtype: u8, tribe_index: u8, loc_x: u16, loc_y: u16, fd: &[u8]) -> Self {
+        let mut extra = [0u8; 40];
+        extra[..fd.len()].copy_from_slice(fd);
+        Self { subtype, model: model as u8, tribe_index, loc_x, loc_y, angle: 0, f2: 0, f3: 0, fd: extra }
+    }
+}
+
 impl BinDeserializer for UnitRaw {
     fn from_reader<R: Read>(reader: &mut R) -> Option<Self> {
         from_reader::<UnitRaw, {size_of::<UnitRaw>()}, R>(reader)
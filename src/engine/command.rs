@@ -51,6 +51,8 @@ pub enum GameCommand {
     ToggleSimulation,
     IncreaseGameSpeed,
     DecreaseGameSpeed,
+    /// Replay playback: jump this many ticks forward (or back, if negative).
+    SeekReplay { ticks: i32 },
 
     // HUD
     SetHudTab(HudTab),
@@ -62,6 +64,20 @@ pub enum GameCommand {
     Quit,
 }
 
+impl GameCommand {
+    /// Commands that change the simulation. In multiplayer these go through
+    /// the lockstep layer (engine::net) so every peer applies them on the
+    /// same tick; everything else only affects the local view.
+    pub fn is_networked(&self) -> bool {
+        matches!(self,
+            GameCommand::SelectUnit(_) | GameCommand::SelectMultiple(_)
+            | GameCommand::ClearSelection | GameCommand::OrderMove { .. })
+    }
+}
+
+/// Ticks one replay seek key press jumps (10 s at the default speed).
+pub const REPLAY_SEEK_TICKS: i32 = 200;
+
 /// Translate a winit KeyCode into a GameCommand.
 /// Returns None for keys that have no game-command mapping.
 pub fn translate_key(key: KeyCode) -> Option<GameCommand> {
@@ -128,6 +144,8 @@ pub fn translate_key(key: KeyCode) -> Option<GameCommand> {
         KeyCode::F5 => Some(GameCommand::ToggleSimulation),
         KeyCode::Equal => Some(GameCommand::IncreaseGameSpeed),
         KeyCode::Minus => Some(GameCommand::DecreaseGameSpeed),
+        KeyCode::Comma => Some(GameCommand::SeekReplay { ticks: -REPLAY_SEEK_TICKS }),
+        KeyCode::Period => Some(GameCommand::SeekReplay { ticks: REPLAY_SEEK_TICKS }),
 
         // Quit
         KeyCode::Escape => Some(GameCommand::Quit),
@@ -254,6 +272,13 @@ mod tests {
         assert!(matches!(translate_key(KeyCode::Minus), Some(GameCommand::DecreaseGameSpeed)));
     }
 
+    #[test]
+    fn test_translate_replay_seek() {
+        assert!(matches!(translate_key(KeyCode::Comma), Some(GameCommand::SeekReplay { ticks: -200 })));
+        assert!(matches!(translate_key(KeyCode::Period), Some(GameCommand::SeekReplay { ticks: 200 })));
+        assert!(!GameCommand::SeekReplay { ticks: 200 }.is_networked());
+    }
+
     #[test]
     fn test_translate_unmapped_returns_none() {
         assert!(translate_key(KeyCode::Enter).is_none());
@@ -3,6 +3,11 @@ pub mod frame;
 pub mod state;
 pub mod movement;
 pub mod units;
+pub mod sound;
+pub mod script;
+pub mod trace;
+pub mod net;
+pub mod replay;
 
 pub use command::{GameCommand, translate_key};
 pub use frame::FrameState;
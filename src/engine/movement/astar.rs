@@ -0,0 +1,183 @@
+// A* reference pathfinder — not part of the original game.
+//
+// Optimal 8-connected search over the RegionMap, used to measure the
+// dual-arm wall follower in pathfinder.rs (see crossval.rs). Passability is
+// the same per-cell check the wall follower uses; diagonal steps may not cut
+// a corner, matching the corner rule of its line-of-sight optimizer.
+//
+// With `wrap` set the grid is a torus like the game world; without it the
+// map edges are walls, as for the wall follower (PathNode::on_map).
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use super::constants::REGION_GRID_SIZE;
+use super::pathfinder::PathNode;
+use super::region::RegionMap;
+use super::types::TileCoord;
+
+/// Step costs (×10 so diagonals stay integral).
+pub const STRAIGHT_COST: u32 = 10;
+pub const DIAGONAL_COST: u32 = 14;
+
+const NEIGHBOURS: [(i32, i32); 8] = [
+    (1, 0), (-1, 0), (0, 1), (0, -1),
+    (1, 1), (1, -1), (-1, 1), (-1, -1),
+];
+
+pub struct AstarResult {
+    /// Cells from start to goal inclusive, or None if the goal is unreachable.
+    pub path: Option<Vec<PathNode>>,
+    /// Cells expanded (closed) during the search.
+    pub visited: usize,
+}
+
+impl AstarResult {
+    /// Path length in cells (1 per straight step, √2 per diagonal).
+    pub fn length(&self) -> Option<f32> {
+        let path = self.path.as_ref()?;
+        Some(path.windows(2).map(|w| {
+            let diagonal = w[0].x != w[1].x && w[0].z != w[1].z;
+            if diagonal { std::f32::consts::SQRT_2 } else { 1.0 }
+        }).sum())
+    }
+}
+
+/// Shortest axis delta between two cell coordinates.
+pub fn cell_delta(from: i32, to: i32, wrap: bool) -> i32 {
+    let d = to - from;
+    let n = REGION_GRID_SIZE as i32;
+    if wrap && d.abs() > n / 2 { d - n * d.signum() } else { d }
+}
+
+/// Octile distance heuristic (admissible for the step costs above).
+pub fn heuristic(a: (i32, i32), b: (i32, i32), wrap: bool) -> u32 {
+    let dx = cell_delta(a.0, b.0, wrap).unsigned_abs();
+    let dz = cell_delta(a.1, b.1, wrap).unsigned_abs();
+    let (lo, hi) = (dx.min(dz), dx.max(dz));
+    DIAGONAL_COST * lo + STRAIGHT_COST * (hi - lo)
+}
+
+fn passable(region_map: &RegionMap, x: i32, z: i32) -> bool {
+    region_map.is_walkable(PathNode::new(x, z).to_tile())
+}
+
+/// Find the shortest path between two tiles.
+pub fn astar(region_map: &RegionMap, start: TileCoord, goal: TileCoord, wrap: bool) -> AstarResult {
+    let n = REGION_GRID_SIZE as i32;
+    let start = PathNode::from_tile(start);
+    let goal = PathNode::from_tile(goal);
+    if !passable(region_map, start.x, start.z) || !passable(region_map, goal.x, goal.z) {
+        return AstarResult { path: None, visited: 0 };
+    }
+
+    let index = |x: i32, z: i32| (z * n + x) as usize;
+    let mut cost = vec![u32::MAX; (n * n) as usize];
+    let mut parent = vec![usize::MAX; (n * n) as usize];
+    let mut closed = vec![false; (n * n) as usize];
+    let mut open = BinaryHeap::new();
+    let mut visited = 0;
+
+    cost[index(start.x, start.z)] = 0;
+    open.push(Reverse((heuristic((start.x, start.z), (goal.x, goal.z), wrap), 0u32, start.x, start.z)));
+
+    while let Some(Reverse((_, g, x, z))) = open.pop() {
+        let here = index(x, z);
+        if closed[here] { continue; }
+        closed[here] = true;
+        visited += 1;
+
+        if (x, z) == (goal.x, goal.z) {
+            let mut path = vec![PathNode::new(x, z)];
+            let mut at = here;
+            while parent[at] != usize::MAX {
+                at = parent[at];
+                path.push(PathNode::new(at as i32 % n, at as i32 / n));
+            }
+            path.reverse();
+            return AstarResult { path: Some(path), visited };
+        }
+
+        for &(dx, dz) in &NEIGHBOURS {
+            let step = |c: i32, d: i32| if wrap { (c + d).rem_euclid(n) } else { c + d };
+            let (nx, nz) = (step(x, dx), step(z, dz));
+            if !(0..n).contains(&nx) || !(0..n).contains(&nz) { continue; }
+            if !passable(region_map, nx, nz) { continue; }
+            let diagonal = dx != 0 && dz != 0;
+            if diagonal && (!passable(region_map, nx, z) || !passable(region_map, x, nz)) {
+                continue;
+            }
+            let next = index(nx, nz);
+            let g_next = g + if diagonal { DIAGONAL_COST } else { STRAIGHT_COST };
+            if closed[next] || g_next >= cost[next] { continue; }
+            cost[next] = g_next;
+            parent[next] = here;
+            let f = g_next + heuristic((nx, nz), (goal.x, goal.z), wrap);
+            open.push(Reverse((f, g_next, nx, nz)));
+        }
+    }
+    AstarResult { path: None, visited }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::movement::constants::REGION_GRID_SIZE;
+
+    fn tile(x: i32, z: i32) -> TileCoord {
+        PathNode::new(x, z).to_tile()
+    }
+
+    fn wall_map(cells: impl Iterator<Item = (i32, i32)>) -> RegionMap {
+        let mut map = RegionMap::new();
+        map.set_terrain_flags(1, 0x00);
+        for (x, z) in cells {
+            map.get_cell_mut(tile(x, z)).terrain_type = 1;
+        }
+        map
+    }
+
+    #[test]
+    fn open_ground_paths_are_octile_optimal() {
+        let map = RegionMap::new();
+        let straight = astar(&map, tile(10, 10), tile(20, 10), false);
+        assert_eq!(straight.length(), Some(10.0));
+        let diagonal = astar(&map, tile(10, 10), tile(15, 20), false);
+        let expected = 5.0 * std::f32::consts::SQRT_2 + 5.0;
+        assert!((diagonal.length().unwrap() - expected).abs() < 1e-4);
+        let path = diagonal.path.unwrap();
+        assert_eq!(path.first().map(|p| (p.x, p.z)), Some((10, 10)));
+        assert_eq!(path.last().map(|p| (p.x, p.z)), Some((15, 20)));
+    }
+
+    #[test]
+    fn detours_around_walls_without_cutting_corners() {
+        // Vertical wall at x = 20 from z = 5 to z = 25
+        let map = wall_map((5..=25).map(|z| (20, z)));
+        let result = astar(&map, tile(18, 15), tile(22, 15), false);
+        let path = result.path.expect("path around the wall");
+        assert!(path.iter().all(|p| p.x != 20 || !(5..=25).contains(&p.z)));
+        for w in path.windows(2) {
+            if w[0].x != w[1].x && w[0].z != w[1].z {
+                assert!(passable(&map, w[1].x, w[0].z) && passable(&map, w[0].x, w[1].z));
+            }
+        }
+        assert!(result.visited > path.len());
+
+        // Fully enclosed goal
+        let ring = wall_map((40..=44).flat_map(|x| [(x, 40), (x, 44)]).chain((41..=43).flat_map(|z| [(40, z), (44, z)])));
+        assert!(astar(&ring, tile(10, 10), tile(42, 42), true).path.is_none());
+    }
+
+    #[test]
+    fn wrapping_crosses_the_map_edge() {
+        // Wall down the middle of the map: only the torus joins the halves
+        let mid = REGION_GRID_SIZE as i32 / 2;
+        let map = wall_map((0..REGION_GRID_SIZE as i32).map(|z| (mid, z)));
+        assert!(astar(&map, tile(2, 30), tile(125, 30), false).path.is_none());
+        let wrapped = astar(&map, tile(2, 30), tile(125, 30), true);
+        assert_eq!(wrapped.length(), Some(5.0));
+        assert_eq!(cell_delta(2, 125, true), -5);
+        assert_eq!(cell_delta(2, 125, false), 123);
+    }
+}
@@ -112,6 +112,22 @@ pub const MAX_WALKABILITY_SEARCH: usize = 32;
 /// Maximum entries in the used-targets cache (prevents loop revisiting)
 pub const MAX_USED_TARGETS: usize = 16;
 
+// === Terrain classes (low nibble of RegionMapCell::terrain_type) ===
+// Assigned at level load by UnitCoordinator::populate_water.
+
+/// Open water — unwalkable for persons, sailable for boats
+pub const TERRAIN_CLASS_WATER: u8 = 1;
+/// Building footprint — unwalkable
+pub const TERRAIN_CLASS_BUILDING: u8 = 2;
+/// Shore buffer eroded one cell inland from water — unwalkable
+pub const TERRAIN_CLASS_SHORE: u8 = 3;
+
+// === Water transport ===
+
+/// Max distance (cells, Chebyshev) between a landing tile and the water
+/// tile a boat docks at. Spans the one-cell shore buffer.
+pub const LANDING_REACH: i32 = 2;
+
 // === Waypoint stepping (ProcessRouteMovement @ 0x4d8e60) ===
 
 /// Distance threshold for waypoint arrival (normal paths, world units)
@@ -0,0 +1,318 @@
+// Pathfinder cross-validation — the dual-arm wall follower (pathfinder.rs)
+// against the optimal A* reference (astar.rs) on random start/goal pairs.
+//
+// Per batch this reports how often the wall follower misses a reachable
+// goal, how much longer its paths are than the optimum, and how many cells
+// each search touches. Pairs are drawn with the game LCG so a run is fully
+// reproducible; a saved JSON report can serve as the baseline that later
+// runs must match exactly (see src/bin/pathfind_check.rs).
+//
+// The reference search wraps around the torus like the game world; the
+// wall follower treats the map edges as walls. Failures whose goal is only
+// reachable across an edge are flagged `needs_wrap`.
+//
+// `cross_validate_with` measures the enhanced route provider (jps.rs) the
+// same way; its "wall follower" figures are then the JPS ones.
+
+use serde_json::{json, Value};
+
+use crate::engine::state::rng::GameRng;
+use super::astar::astar;
+use super::constants::REGION_GRID_SIZE;
+use super::jps::pathfind_jps_debug;
+use super::pathfinder::{pathfind_debug, PathNode, PathfindResult};
+use super::region::RegionMap;
+use super::route::PathfindingMode;
+use super::types::{TileCoord, Waypoint};
+
+/// A reachable goal the wall follower did not find.
+#[derive(Debug, Clone)]
+pub struct FailureCase {
+    pub start: TileCoord,
+    pub goal: TileCoord,
+    /// Optimal path length in cells.
+    pub optimal: f32,
+    /// Only reachable across a map edge.
+    pub needs_wrap: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RatioSummary {
+    pub min: f32,
+    pub mean: f32,
+    pub median: f32,
+    pub p95: f32,
+    pub max: f32,
+}
+
+#[derive(Debug, Default)]
+pub struct CrossValReport {
+    pub pairs: usize,
+    /// Pairs A* connects.
+    pub reachable: usize,
+    /// Reachable pairs the wall follower also connects.
+    pub found: usize,
+    /// Pairs the wall follower "connects" although A* cannot — always a bug.
+    pub false_paths: usize,
+    pub failures: Vec<FailureCase>,
+    /// Wall-follower length / optimal length for every pair both found.
+    pub length_ratios: Vec<f32>,
+    pub wall_visited: u64,
+    pub astar_visited: u64,
+}
+
+impl CrossValReport {
+    /// Share of reachable pairs the wall follower failed on.
+    pub fn failure_rate(&self) -> f32 {
+        self.failures.len() as f32 / self.reachable.max(1) as f32
+    }
+
+    pub fn ratio_summary(&self) -> Option<RatioSummary> {
+        if self.length_ratios.is_empty() {
+            return None;
+        }
+        let mut sorted = self.length_ratios.clone();
+        sorted.sort_by(f32::total_cmp);
+        let at = |q: f32| sorted[((sorted.len() - 1) as f32 * q).round() as usize];
+        Some(RatioSummary {
+            min: sorted[0],
+            mean: sorted.iter().sum::<f32>() / sorted.len() as f32,
+            median: at(0.5),
+            p95: at(0.95),
+            max: sorted[sorted.len() - 1],
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        let cell = |t: &TileCoord| json!([t.x >> 1, t.z >> 1]);
+        let per_pair = |n: u64| n as f64 / self.pairs.max(1) as f64;
+        let ratio = self.ratio_summary().map_or(Value::Null, |r| json!({
+            "min": r.min, "mean": r.mean, "median": r.median, "p95": r.p95, "max": r.max,
+        }));
+        json!({
+            "pairs": self.pairs,
+            "reachable": self.reachable,
+            "found": self.found,
+            "false_paths": self.false_paths,
+            "failure_rate": self.failure_rate(),
+            "length_ratio": ratio,
+            "visited": {
+                "wall_follower": per_pair(self.wall_visited),
+                "astar": per_pair(self.astar_visited),
+            },
+            "failures": self.failures.iter().map(|f| json!({
+                "start": cell(&f.start),
+                "goal": cell(&f.goal),
+                "optimal": f.optimal,
+                "needs_wrap": f.needs_wrap,
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Length in cells of a wall-follower route: straight lines from the start
+/// through each waypoint (never across a map edge).
+pub fn route_length(start: TileCoord, waypoints: &[Waypoint]) -> f32 {
+    let mut prev = PathNode::from_tile(start);
+    let mut length = 0.0;
+    for wp in waypoints {
+        let node = PathNode::from_tile(TileCoord::new(wp.tile_x, wp.tile_z));
+        let dx = (node.x - prev.x) as f32;
+        let dz = (node.z - prev.z) as f32;
+        length += (dx * dx + dz * dz).sqrt();
+        prev = node;
+    }
+    length
+}
+
+/// `count` distinct-cell start/goal pairs on walkable cells, drawn with the
+/// game LCG from `seed`. Empty if the map has fewer than two walkable cells.
+pub fn random_pairs(region_map: &RegionMap, count: usize, seed: u32) -> Vec<(TileCoord, TileCoord)> {
+    let n = REGION_GRID_SIZE as i32;
+    let walkable: Vec<TileCoord> = (0..n * n)
+        .map(|i| PathNode::new(i % n, i / n).to_tile())
+        .filter(|&t| region_map.is_walkable(t))
+        .collect();
+    if walkable.len() < 2 {
+        return Vec::new();
+    }
+    let mut rng = GameRng::new(seed);
+    let mut pick = || walkable[rng.next() as usize % walkable.len()];
+    let mut pairs = Vec::with_capacity(count);
+    while pairs.len() < count {
+        let (start, goal) = (pick(), pick());
+        if start != goal {
+            pairs.push((start, goal));
+        }
+    }
+    pairs
+}
+
+/// Run both pathfinders on every pair.
+pub fn cross_validate(region_map: &RegionMap, pairs: &[(TileCoord, TileCoord)]) -> CrossValReport {
+    cross_validate_with(PathfindingMode::Original, region_map, pairs)
+}
+
+/// Run the given route provider's search and A* on every pair.
+pub fn cross_validate_with(
+    mode: PathfindingMode,
+    region_map: &RegionMap,
+    pairs: &[(TileCoord, TileCoord)],
+) -> CrossValReport {
+    let mut report = CrossValReport { pairs: pairs.len(), ..Default::default() };
+    for &(start, goal) in pairs {
+        let (result, visited) = match mode {
+            PathfindingMode::Original => {
+                let debug = pathfind_debug(region_map, start, goal);
+                (debug.result, debug.visited.count())
+            }
+            PathfindingMode::Enhanced => pathfind_jps_debug(region_map, start, goal),
+        };
+        let reference = astar(region_map, start, goal, true);
+        report.wall_visited += visited as u64;
+        report.astar_visited += reference.visited as u64;
+
+        match (&result, reference.length()) {
+            (PathfindResult::Found(waypoints), Some(optimal)) => {
+                report.reachable += 1;
+                report.found += 1;
+                if optimal > 0.0 {
+                    report.length_ratios.push(route_length(start, waypoints) / optimal);
+                }
+            }
+            (PathfindResult::NotFound, Some(optimal)) => {
+                report.reachable += 1;
+                let needs_wrap = astar(region_map, start, goal, false).path.is_none();
+                report.failures.push(FailureCase { start, goal, optimal, needs_wrap });
+            }
+            (PathfindResult::Found(_), None) => report.false_paths += 1,
+            (PathfindResult::NotFound, None) => {}
+        }
+    }
+    report
+}
+
+fn diff_values(path: &str, actual: Option<&Value>, baseline: &Value, out: &mut Vec<String>) {
+    match (actual, baseline) {
+        (Some(Value::Object(a)), Value::Object(b)) => {
+            for (key, value) in b {
+                diff_values(&format!("{}.{}", path, key), a.get(key), value, out);
+            }
+        }
+        (Some(Value::Array(a)), Value::Array(b)) if a.len() == b.len() => {
+            for (i, (x, y)) in a.iter().zip(b).enumerate() {
+                diff_values(&format!("{}[{}]", path, i), Some(x), y, out);
+            }
+        }
+        (Some(Value::Array(a)), Value::Array(b)) => {
+            out.push(format!("{}: {} entries, baseline has {}", path, a.len(), b.len()));
+        }
+        (Some(Value::Number(a)), Value::Number(b)) => {
+            let (x, y) = (a.as_f64().unwrap_or(0.0), b.as_f64().unwrap_or(0.0));
+            if (x - y).abs() > 1e-4 {
+                out.push(format!("{}: {} (baseline {})", path, a, b));
+            }
+        }
+        (Some(a), b) if a == b => {}
+        (a, b) => out.push(format!("{}: {} (baseline {})",
+            path, a.map_or("missing".to_string(), Value::to_string), b)),
+    }
+}
+
+/// Differences between a report and a saved baseline, one message each.
+/// Fields missing from the baseline are ignored.
+pub fn compare_to_baseline(actual: &Value, baseline: &Value) -> Vec<String> {
+    let mut out = Vec::new();
+    diff_values("", Some(actual), baseline, &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(x: i32, z: i32) -> TileCoord {
+        PathNode::new(x, z).to_tile()
+    }
+
+    /// Wall across the whole map at x = 64 with a gap at z = 100..=102.
+    fn gapped_wall() -> RegionMap {
+        let mut map = RegionMap::new();
+        map.set_terrain_flags(1, 0x00);
+        for z in (0..REGION_GRID_SIZE as i32).filter(|z| !(100..=102).contains(z)) {
+            map.get_cell_mut(tile(64, z)).terrain_type = 1;
+        }
+        map
+    }
+
+    #[test]
+    fn open_ground_matches_the_optimum() {
+        // Open field in the middle of the map, where wrapping never helps
+        let mut map = RegionMap::new();
+        map.set_terrain_flags(1, 0x00);
+        for i in 0..(REGION_GRID_SIZE * REGION_GRID_SIZE) as i32 {
+            let (x, z) = (i % REGION_GRID_SIZE as i32, i / REGION_GRID_SIZE as i32);
+            if !(32..96).contains(&x) || !(32..96).contains(&z) {
+                map.get_cell_mut(tile(x, z)).terrain_type = 1;
+            }
+        }
+        let pairs = random_pairs(&map, 50, 7);
+        assert_eq!(pairs, random_pairs(&map, 50, 7));
+
+        let report = cross_validate(&map, &pairs);
+        assert_eq!(report.reachable, 50);
+        assert_eq!(report.found, 50);
+        assert_eq!(report.false_paths, 0);
+        assert!(report.failures.is_empty());
+        let ratio = report.ratio_summary().unwrap();
+        // Straight segments may beat the octile optimum by up to ~8%
+        assert!(ratio.min > 0.9 && ratio.max < 1.1, "{:?}", ratio);
+    }
+
+    #[test]
+    fn detours_cost_length_and_are_reported() {
+        let map = gapped_wall();
+        let pairs = [(tile(60, 20), tile(68, 20)), (tile(60, 101), tile(68, 101))];
+        let report = cross_validate(&map, &pairs);
+        assert_eq!(report.reachable, 2);
+        assert_eq!(report.found + report.failures.len(), 2);
+        assert!(report.astar_visited > 0 && report.wall_visited > 0);
+        for failure in &report.failures {
+            assert!(failure.optimal > 8.0);
+        }
+        if let Some(ratio) = report.ratio_summary() {
+            assert!(ratio.min >= 0.9);
+        }
+        assert_eq!(report.to_json()["pairs"], 2);
+    }
+
+    #[test]
+    fn baseline_comparison_flags_changed_fields() {
+        let map = gapped_wall();
+        let pairs = random_pairs(&map, 20, 3);
+        let json = cross_validate(&map, &pairs).to_json();
+        assert!(compare_to_baseline(&json, &json).is_empty());
+
+        let mut changed = json.clone();
+        changed["found"] = json!(json["found"].as_u64().unwrap() + 1);
+        changed["visited"]["astar"] = json!(0.5);
+        let diffs = compare_to_baseline(&json, &changed);
+        assert_eq!(diffs.len(), 2, "{:?}", diffs);
+        assert!(diffs[0].contains(".found") || diffs[1].contains(".found"));
+
+        let waypoints = [Waypoint { tile_x: 30, tile_z: 10, flags: 0, _pad: 0 }];
+        assert_eq!(route_length(tile(10, 5), &waypoints), 5.0);
+    }
+
+    #[test]
+    fn enhanced_mode_finds_every_reachable_goal() {
+        let map = gapped_wall();
+        let pairs = random_pairs(&map, 40, 5);
+        let report = cross_validate_with(PathfindingMode::Enhanced, &map, &pairs);
+        assert_eq!(report.reachable, 40);
+        assert_eq!(report.found, 40, "{:?}", report.failures);
+        assert_eq!(report.false_paths, 0);
+        let ratio = report.ratio_summary().unwrap();
+        assert!(ratio.min >= 0.9, "{:?}", ratio);
+    }
+}
@@ -0,0 +1,240 @@
+// Formation groups — Object_UpdateMovement @ 0x4ed510.
+//
+// A group move is led by one unit that runs the normal route (state_goto);
+// up to MAX_FOLLOWERS followers hold slots relative to it instead of
+// pathfinding themselves. The leader stores the group on its own object:
+//
+//   +0x68  follower_count
+//   +0x6A  follower_ids[12]
+//   +0x82  formation_offsets[12 × 6]
+//
+// and each follower carries FORMATION_FLAG_BIT in formation_flags.
+//
+// Only the leader fields above come from the original object layout. How
+// the original fills the 6-byte offset records and spaces its slots is not
+// decoded yet, so the record layout, FORMATION_ROW_WIDTH,
+// FORMATION_OFFSET_UNIT and FORMATION_SLOT_SPACING below are this crate's
+// own choices, not Object_UpdateMovement behaviour.
+//
+// Slots form rows of FORMATION_ROW_WIDTH behind the leader, filled from
+// the middle outwards. Every follower slot gets a small jitter from the
+// formation RNG so a group never lines up on an exact grid.
+
+use super::constants::*;
+use super::math::{formation_rng_next, move_point_by_angle};
+use super::types::{PersonMovement, WorldCoord};
+
+/// Bytes per follower in `formation_offsets` (12 × 6 at +0x82).
+pub const FORMATION_OFFSET_SIZE: usize = 6;
+
+/// Slots per row, leader in the middle of the front row (this crate's own).
+pub const FORMATION_ROW_WIDTH: usize = 5;
+
+/// World units per jitter step (this crate's own).
+pub const FORMATION_OFFSET_UNIT: i32 = 0x20; // 32
+
+/// Units per group (the leader and its followers).
+pub const FORMATION_GROUP_SIZE: usize = MAX_FOLLOWERS + 1;
+
+/// Rows taken by a full group.
+pub const FORMATION_GROUP_ROWS: usize = FORMATION_GROUP_SIZE.div_ceil(FORMATION_ROW_WIDTH);
+
+/// Distance between neighbouring slots (world units, this crate's own;
+/// roughly one map cell).
+pub const FORMATION_SLOT_SPACING: i32 = 0x240; // 576
+
+/// One follower's slot relative to its leader, in world units.
+/// Record layout (this crate's own, unverified against the original):
+/// lateral (i16) | back (i16) | slot (u8) | row (u8).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FormationOffset {
+    /// Sideways from the leader (positive = heading + 0x200).
+    pub lateral: i16,
+    /// Behind the leader (positive = back).
+    pub back: i16,
+    /// Slot index within the group (0 = leader).
+    pub slot: u8,
+    pub row: u8,
+}
+
+impl FormationOffset {
+    fn read(bytes: &[u8]) -> Self {
+        Self {
+            lateral: i16::from_le_bytes([bytes[0], bytes[1]]),
+            back: i16::from_le_bytes([bytes[2], bytes[3]]),
+            slot: bytes[4],
+            row: bytes[5],
+        }
+    }
+
+    fn write(&self, bytes: &mut [u8]) {
+        bytes[0..2].copy_from_slice(&self.lateral.to_le_bytes());
+        bytes[2..4].copy_from_slice(&self.back.to_le_bytes());
+        bytes[4] = self.slot;
+        bytes[5] = self.row;
+    }
+}
+
+/// Un-jittered offset of a slot in the group lattice. Slot 0 is the leader
+/// (front row, middle); columns fill middle, left, right, far left, ...
+pub fn slot_offset(slot: usize) -> FormationOffset {
+    let row = slot / FORMATION_ROW_WIDTH;
+    let n = (slot % FORMATION_ROW_WIDTH) as i32;
+    let column = if n % 2 == 0 { n / 2 } else { -(n + 1) / 2 };
+    FormationOffset {
+        lateral: (column * FORMATION_SLOT_SPACING) as i16,
+        back: (row as i32 * FORMATION_SLOT_SPACING) as i16,
+        slot: slot as u8,
+        row: row as u8,
+    }
+}
+
+/// Offset of a follower slot with up to one offset step of jitter per axis.
+/// Advances the formation RNG twice (original state at 0x885710).
+pub fn jittered_slot_offset(slot: usize, rng_state: &mut u32) -> FormationOffset {
+    let mut jitter = || {
+        *rng_state = formation_rng_next(*rng_state);
+        ((*rng_state >> 16) % 3) as i32 - 1
+    };
+    let mut offset = slot_offset(slot);
+    offset.lateral += (jitter() * FORMATION_OFFSET_UNIT) as i16;
+    offset.back += (jitter() * FORMATION_OFFSET_UNIT) as i16;
+    offset
+}
+
+/// World position of a slot for a leader at `origin` facing `heading`.
+pub fn slot_position(origin: WorldCoord, heading: u16, offset: &FormationOffset) -> WorldCoord {
+    let mut pos = origin;
+    move_point_by_angle(&mut pos, heading.wrapping_add(0x400) & 0x7FF, offset.back);
+    move_point_by_angle(&mut pos, heading.wrapping_add(0x200) & 0x7FF, offset.lateral);
+    pos
+}
+
+/// Distance behind the group target at which group `group` is re-formed,
+/// so successive groups of a large selection line up one behind another.
+/// Groups beyond the i16 range all re-form at the furthest offset.
+pub fn group_back_offset(group: usize) -> FormationOffset {
+    let row = group.saturating_mul(FORMATION_GROUP_ROWS).min(i16::MAX as usize) as i32;
+    FormationOffset {
+        back: (row * FORMATION_SLOT_SPACING).min(i16::MAX as i32) as i16,
+        row: row.min(u8::MAX as i32) as u8,
+        ..Default::default()
+    }
+}
+
+/// Stored offset of follower `k` of a leader.
+pub fn follower_offset(leader: &PersonMovement, k: usize) -> FormationOffset {
+    let at = k * FORMATION_OFFSET_SIZE;
+    FormationOffset::read(&leader.formation_offsets[at..at + FORMATION_OFFSET_SIZE])
+}
+
+/// Append a follower to a leader's group. Returns false if the group is full.
+pub fn add_follower(leader: &mut PersonMovement, follower_id: u16, offset: FormationOffset) -> bool {
+    let k = leader.follower_count as usize;
+    if k >= MAX_FOLLOWERS {
+        return false;
+    }
+    leader.follower_ids[k] = follower_id;
+    let at = k * FORMATION_OFFSET_SIZE;
+    offset.write(&mut leader.formation_offsets[at..at + FORMATION_OFFSET_SIZE]);
+    leader.follower_count += 1;
+    true
+}
+
+/// Remove follower `k`, moving later followers down one place.
+pub fn remove_follower(leader: &mut PersonMovement, k: usize) {
+    let count = leader.follower_count as usize;
+    if k >= count {
+        return;
+    }
+    leader.follower_ids.copy_within(k + 1..count, k);
+    leader.formation_offsets.copy_within(
+        (k + 1) * FORMATION_OFFSET_SIZE..count * FORMATION_OFFSET_SIZE,
+        k * FORMATION_OFFSET_SIZE,
+    );
+    leader.follower_count -= 1;
+    leader.follower_ids[count - 1] = 0;
+    let last = (count - 1) * FORMATION_OFFSET_SIZE;
+    leader.formation_offsets[last..last + FORMATION_OFFSET_SIZE].fill(0);
+}
+
+/// Drop all followers of a leader.
+pub fn clear_followers(leader: &mut PersonMovement) {
+    leader.follower_count = 0;
+    leader.follower_ids = [0; MAX_FOLLOWERS];
+    leader.formation_offsets = [0; MAX_FOLLOWERS * FORMATION_OFFSET_SIZE];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn group_back_offset_saturates() {
+        let rows_per_group = FORMATION_GROUP_ROWS as i32 * FORMATION_SLOT_SPACING;
+        let last = (i16::MAX as i32 / rows_per_group) as usize;
+        assert_eq!(group_back_offset(1).back as i32, rows_per_group);
+        assert_eq!(group_back_offset(last).back as i32, last as i32 * rows_per_group);
+        assert_eq!(group_back_offset(last + 1).back, i16::MAX);
+        assert_eq!(group_back_offset(usize::MAX).back, i16::MAX);
+        assert_eq!(group_back_offset(usize::MAX).row, u8::MAX);
+    }
+
+    #[test]
+    fn slots_fill_rows_from_the_middle() {
+        assert_eq!(slot_offset(0), FormationOffset { lateral: 0, back: 0, slot: 0, row: 0 });
+        assert_eq!(slot_offset(1).lateral as i32, -FORMATION_SLOT_SPACING);
+        assert_eq!(slot_offset(2).lateral as i32, FORMATION_SLOT_SPACING);
+        assert_eq!(slot_offset(5).back as i32, FORMATION_SLOT_SPACING);
+        assert_eq!(slot_offset(5).row, 1);
+
+        let mut seen = std::collections::HashSet::new();
+        for slot in 0..FORMATION_GROUP_SIZE {
+            let o = slot_offset(slot);
+            assert!(seen.insert((o.lateral, o.back)), "slot {} overlaps", slot);
+        }
+        assert_eq!(FORMATION_GROUP_ROWS, 3);
+    }
+
+    #[test]
+    fn jitter_is_deterministic_and_stays_within_one_step() {
+        let (mut a, mut b) = (0u32, 0u32);
+        for slot in 1..FORMATION_GROUP_SIZE {
+            let oa = jittered_slot_offset(slot, &mut a);
+            assert_eq!(oa, jittered_slot_offset(slot, &mut b));
+            let base = slot_offset(slot);
+            assert!((oa.lateral - base.lateral).abs() as i32 <= FORMATION_OFFSET_UNIT);
+            assert!((oa.back - base.back).abs() as i32 <= FORMATION_OFFSET_UNIT);
+        }
+        assert_eq!(a, (0..2 * MAX_FOLLOWERS).fold(0, |s, _| formation_rng_next(s)));
+    }
+
+    #[test]
+    fn followers_pack_into_leader_and_compact_on_removal() {
+        let mut leader = PersonMovement::default();
+        for id in 0..MAX_FOLLOWERS as u16 {
+            assert!(add_follower(&mut leader, 100 + id, slot_offset(id as usize + 1)));
+        }
+        assert!(!add_follower(&mut leader, 999, slot_offset(1)));
+
+        remove_follower(&mut leader, 3);
+        assert_eq!(leader.follower_count as usize, MAX_FOLLOWERS - 1);
+        assert_eq!(leader.follower_ids[3], 104);
+        assert_eq!(follower_offset(&leader, 3), slot_offset(5));
+        assert_eq!(leader.follower_ids[MAX_FOLLOWERS - 1], 0);
+
+        // Heading 0 moves along +z; the back slot lies on the other side
+        let origin = WorldCoord::new(0x4000, 0x4000);
+        let ahead = {
+            let mut p = origin;
+            move_point_by_angle(&mut p, 0, 0x100);
+            p
+        };
+        let behind = slot_position(origin, 0, &slot_offset(5));
+        assert_eq!(behind.x, origin.x);
+        assert_eq!((behind.z - origin.z).signum(), -(ahead.z - origin.z).signum());
+
+        clear_followers(&mut leader);
+        assert_eq!(leader.follower_count, 0);
+    }
+}
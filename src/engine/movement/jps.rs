@@ -0,0 +1,347 @@
+// Jump point search — route provider for the enhanced pathfinding mode.
+// Not part of the original game (see route.rs, PathfindingMode).
+//
+// Optimal 8-connected search over the RegionMap with the same passability
+// and corner rule as astar.rs, but straight and diagonal runs are skipped
+// in one "jump" so only cells where the route can turn enter the open list.
+// Unlike the dual-arm wall follower it never gives up on a reachable goal,
+// which matters on spiral coastlines where wall following runs in circles.
+//
+// Units switch to the next waypoint up to WAYPOINT_ARRIVAL_THRESHOLD (just
+// over a cell) early, so a route hugging a coast would cut its corners
+// through water. The route provider therefore first searches with one cell
+// of clearance from anything unwalkable and only falls back to the bare
+// grid for narrow passages. The jump points are LOS-smoothed on the same
+// grid and stored as ordinary segment waypoints, so routes are cached and
+// shared through the SegmentPool exactly like original routes. Map edges
+// are walls (PathNode::on_map), as for the wall follower.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use super::astar::{heuristic, DIAGONAL_COST, STRAIGHT_COST};
+use super::constants::{PATHFIND_MAX_SEGMENT_WAYPOINTS, REGION_GRID_SIZE};
+use super::pathfinder::{PathNode, PathfindResult};
+use super::region::RegionMap;
+use super::types::{TileCoord, Waypoint};
+
+pub struct JpsResult {
+    /// Jump points from start to goal inclusive, or None if unreachable.
+    /// Consecutive points lie on one straight or 45° line.
+    pub jump_points: Option<Vec<PathNode>>,
+    /// Jump points expanded (closed) during the search.
+    pub visited: usize,
+}
+
+impl JpsResult {
+    /// Every cell along the route, start and goal included.
+    pub fn cells(&self) -> Option<Vec<PathNode>> {
+        let points = self.jump_points.as_ref()?;
+        let mut cells = vec![points[0]];
+        for w in points.windows(2) {
+            let (dx, dz) = ((w[1].x - w[0].x).signum(), (w[1].z - w[0].z).signum());
+            let mut at = w[0];
+            while (at.x, at.z) != (w[1].x, w[1].z) {
+                at = PathNode::new(at.x + dx, at.z + dz);
+                cells.push(at);
+            }
+        }
+        Some(cells)
+    }
+}
+
+/// Passability of every cell for one search.
+struct Grid {
+    open: Vec<bool>,
+}
+
+impl Grid {
+    /// Walkable cells; with `clearance`, only those whose eight neighbours
+    /// are walkable too (plus the start and goal themselves).
+    fn new(region_map: &RegionMap, clearance: bool, start: PathNode, goal: PathNode) -> Self {
+        let n = REGION_GRID_SIZE as i32;
+        let walkable = |x: i32, z: i32| {
+            let node = PathNode::new(x, z);
+            node.on_map() && region_map.is_walkable(node.to_tile())
+        };
+        let open = (0..n * n).map(|i| {
+            let (x, z) = (i % n, i / n);
+            if !clearance || (x, z) == (start.x, start.z) || (x, z) == (goal.x, goal.z) {
+                return walkable(x, z);
+            }
+            (-1..=1).all(|dz| (-1..=1).all(|dx| walkable(x + dx, z + dz)))
+        }).collect();
+        Self { open }
+    }
+
+    fn open(&self, x: i32, z: i32) -> bool {
+        let n = REGION_GRID_SIZE as i32;
+        (0..n).contains(&x) && (0..n).contains(&z) && self.open[(z * n + x) as usize]
+    }
+
+    /// Can a unit step from (x, z) by (dx, dz)? Diagonals may not cut a corner.
+    fn can_step(&self, x: i32, z: i32, dx: i32, dz: i32) -> bool {
+        self.open(x + dx, z + dz)
+            && (dx == 0 || dz == 0 || (self.open(x + dx, z) && self.open(x, z + dz)))
+    }
+
+    /// Follow direction (dx, dz) from (x, z) until the goal, a cell with a
+    /// forced neighbour (a wall alongside ends), or a dead end (None).
+    fn jump(&self, mut x: i32, mut z: i32, dx: i32, dz: i32, goal: (i32, i32)) -> Option<(i32, i32)> {
+        loop {
+            if !self.can_step(x, z, dx, dz) {
+                return None;
+            }
+            x += dx;
+            z += dz;
+            if (x, z) == goal {
+                return Some((x, z));
+            }
+            let forced = if dx != 0 && dz != 0 {
+                self.jump(x, z, dx, 0, goal).is_some() || self.jump(x, z, 0, dz, goal).is_some()
+            } else if dx != 0 {
+                (self.open(x, z + 1) && !self.open(x - dx, z + 1))
+                    || (self.open(x, z - 1) && !self.open(x - dx, z - 1))
+            } else {
+                (self.open(x + 1, z) && !self.open(x + 1, z - dz))
+                    || (self.open(x - 1, z) && !self.open(x - 1, z - dz))
+            };
+            if forced {
+                return Some((x, z));
+            }
+        }
+    }
+
+    /// Bresenham line with the corner rule, as the wall follower's
+    /// line_of_sight but on this grid.
+    fn line_of_sight(&self, from: PathNode, to: PathNode) -> bool {
+        let (mut x, mut z) = (from.x, from.z);
+        let (dx, dz) = ((to.x - from.x).abs(), (to.z - from.z).abs());
+        let (sx, sz) = ((to.x - from.x).signum(), (to.z - from.z).signum());
+        let mut err = dx - dz;
+        loop {
+            if !self.open(x, z) {
+                return false;
+            }
+            if (x, z) == (to.x, to.z) {
+                return true;
+            }
+            let e2 = 2 * err;
+            let (step_x, step_z) = (e2 > -dz, e2 < dx);
+            if step_x && step_z && (!self.open(x + sx, z) || !self.open(x, z + sz)) {
+                return false;
+            }
+            if step_x {
+                err -= dz;
+                x += sx;
+            }
+            if step_z {
+                err += dx;
+                z += sz;
+            }
+        }
+    }
+
+    /// Drop every point the previous kept point can see past.
+    fn smooth(&self, points: &[PathNode]) -> Vec<PathNode> {
+        let mut kept = vec![points[0]];
+        let mut i = 0;
+        while i + 1 < points.len() {
+            let j = (i + 2..points.len()).rev()
+                .find(|&j| self.line_of_sight(points[i], points[j]))
+                .unwrap_or(i + 1);
+            kept.push(points[j]);
+            i = j;
+        }
+        kept
+    }
+
+    fn search(&self, start: PathNode, goal: PathNode) -> JpsResult {
+        let n = REGION_GRID_SIZE as i32;
+        if !self.open(start.x, start.z) || !self.open(goal.x, goal.z) {
+            return JpsResult { jump_points: None, visited: 0 };
+        }
+
+        let index = |x: i32, z: i32| (z * n + x) as usize;
+        let mut cost = vec![u32::MAX; (n * n) as usize];
+        let mut parent = vec![usize::MAX; (n * n) as usize];
+        let mut closed = vec![false; (n * n) as usize];
+        let mut open_list = BinaryHeap::new();
+        let mut visited = 0;
+        let target = (goal.x, goal.z);
+
+        cost[index(start.x, start.z)] = 0;
+        open_list.push(Reverse((heuristic((start.x, start.z), target, false), 0u32, start.x, start.z)));
+
+        while let Some(Reverse((_, g, x, z))) = open_list.pop() {
+            let here = index(x, z);
+            if closed[here] { continue; }
+            closed[here] = true;
+            visited += 1;
+
+            if (x, z) == target {
+                let mut points = vec![PathNode::new(x, z)];
+                let mut at = here;
+                while parent[at] != usize::MAX {
+                    at = parent[at];
+                    points.push(PathNode::new(at as i32 % n, at as i32 / n));
+                }
+                points.reverse();
+                return JpsResult { jump_points: Some(points), visited };
+            }
+
+            let (dx, dz) = match parent[here] {
+                usize::MAX => (0, 0),
+                p => ((x - p as i32 % n).signum(), (z - p as i32 / n).signum()),
+            };
+            for (sx, sz) in directions(dx, dz) {
+                let Some((jx, jz)) = self.jump(x, z, sx, sz, target) else { continue };
+                let next = index(jx, jz);
+                let steps = (jx - x).abs().max((jz - z).abs()) as u32;
+                let g_next = g + steps * if sx != 0 && sz != 0 { DIAGONAL_COST } else { STRAIGHT_COST };
+                if closed[next] || g_next >= cost[next] { continue; }
+                cost[next] = g_next;
+                parent[next] = here;
+                let f = g_next + heuristic((jx, jz), target, false);
+                open_list.push(Reverse((f, g_next, jx, jz)));
+            }
+        }
+        JpsResult { jump_points: None, visited }
+    }
+}
+
+/// Directions worth searching from a jump point reached moving (dx, dz);
+/// every direction for the start cell.
+fn directions(dx: i32, dz: i32) -> Vec<(i32, i32)> {
+    match (dx, dz) {
+        (0, 0) => vec![(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)],
+        (dx, 0) => vec![(dx, 0), (0, 1), (0, -1), (dx, 1), (dx, -1)],
+        (0, dz) => vec![(0, dz), (1, 0), (-1, 0), (1, dz), (-1, dz)],
+        (dx, dz) => vec![(dx, 0), (0, dz), (dx, dz)],
+    }
+}
+
+/// Find the shortest path between two tiles (no clearance, so the length
+/// is directly comparable with `astar`).
+pub fn jps(region_map: &RegionMap, start: TileCoord, goal: TileCoord) -> JpsResult {
+    let (start, goal) = (PathNode::from_tile(start), PathNode::from_tile(goal));
+    Grid::new(region_map, false, start, goal).search(start, goal)
+}
+
+/// Route provider entry point with the same contract as `pathfind`:
+/// waypoint tiles after the start, empty when start and goal share a cell.
+/// Routes with more turns than a segment holds keep the first
+/// PATHFIND_MAX_SEGMENT_WAYPOINTS; the unit re-routes from the last one.
+pub fn pathfind_jps(region_map: &RegionMap, start: TileCoord, goal: TileCoord) -> PathfindResult {
+    pathfind_jps_debug(region_map, start, goal).0
+}
+
+/// `pathfind_jps` plus the number of jump points expanded over all passes.
+pub fn pathfind_jps_debug(region_map: &RegionMap, start: TileCoord, goal: TileCoord) -> (PathfindResult, usize) {
+    let (start, goal) = (PathNode::from_tile(start), PathNode::from_tile(goal));
+    if start == goal {
+        return (PathfindResult::Found(vec![]), 0);
+    }
+    let mut visited = 0;
+    for clearance in [true, false] {
+        let grid = Grid::new(region_map, clearance, start, goal);
+        let result = grid.search(start, goal);
+        visited += result.visited;
+        if let Some(points) = result.jump_points {
+            let waypoints = grid.smooth(&points).iter().skip(1)
+                .take(PATHFIND_MAX_SEGMENT_WAYPOINTS)
+                .map(|node| {
+                    let tile = node.to_tile();
+                    Waypoint { tile_x: tile.x, tile_z: tile.z, flags: 0, _pad: 0 }
+                })
+                .collect();
+            return (PathfindResult::Found(waypoints), visited);
+        }
+    }
+    (PathfindResult::NotFound, visited)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::movement::astar::astar;
+    use crate::engine::state::rng::GameRng;
+
+    fn tile(x: i32, z: i32) -> TileCoord {
+        PathNode::new(x, z).to_tile()
+    }
+
+    fn wall_map(cells: impl Iterator<Item = (i32, i32)>) -> RegionMap {
+        let mut map = RegionMap::new();
+        map.set_terrain_flags(1, 0x00);
+        for (x, z) in cells {
+            map.get_cell_mut(tile(x, z)).terrain_type = 1;
+        }
+        map
+    }
+
+    fn length(cells: &[PathNode]) -> f32 {
+        cells.windows(2).map(|w| {
+            if w[0].x != w[1].x && w[0].z != w[1].z { std::f32::consts::SQRT_2 } else { 1.0 }
+        }).sum()
+    }
+
+    #[test]
+    fn matches_astar_length_on_random_obstacles() {
+        let mut rng = GameRng::new(11);
+        for _ in 0..4 {
+            let walls: Vec<(i32, i32)> = (0..3000)
+                .map(|_| ((rng.next() % 48) as i32, (rng.next() % 48) as i32))
+                .collect();
+            let map = wall_map(walls.into_iter());
+            for _ in 0..40 {
+                let mut cell = || tile((rng.next() % 48) as i32, (rng.next() % 48) as i32);
+                let (start, goal) = (cell(), cell());
+                let reference = astar(&map, start, goal, false);
+                let cells = jps(&map, start, goal).cells();
+                assert_eq!(cells.is_some(), reference.path.is_some());
+                if let (Some(cells), Some(optimal)) = (cells, reference.length()) {
+                    assert!((length(&cells) - optimal).abs() < 1e-3, "{} vs {}", length(&cells), optimal);
+                    let grid = Grid::new(&map, false, cells[0], cells[cells.len() - 1]);
+                    for w in cells.windows(2) {
+                        assert!(grid.can_step(w[0].x, w[0].z, w[1].x - w[0].x, w[1].z - w[0].z));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn open_ground_is_one_straight_waypoint() {
+        let map = RegionMap::new();
+        let result = jps(&map, tile(10, 10), tile(40, 25));
+        assert!(result.visited <= 4, "visited {}", result.visited);
+        assert_eq!(pathfind_jps(&map, tile(10, 10), tile(40, 25)), PathfindResult::Found(vec![
+            Waypoint { tile_x: 80, tile_z: 50, flags: 0, _pad: 0 },
+        ]));
+        assert_eq!(pathfind_jps(&map, tile(5, 5), tile(5, 5)), PathfindResult::Found(vec![]));
+    }
+
+    #[test]
+    fn routes_keep_clear_of_walls_unless_the_gap_is_narrow() {
+        // Wall along z = 30 from x = 0 to 40: the route rounds its end a
+        // cell away, never on a cell touching it
+        let map = wall_map((0..=40).map(|x| (x, 30)));
+        let PathfindResult::Found(waypoints) = pathfind_jps(&map, tile(20, 25), tile(20, 35)) else {
+            panic!("no route around the wall");
+        };
+        let grid = Grid::new(&map, true, PathNode::new(20, 25), PathNode::new(20, 35));
+        for wp in &waypoints {
+            let node = PathNode::from_tile(TileCoord::new(wp.tile_x, wp.tile_z));
+            assert!(grid.open(node.x, node.z), "{:?} touches the wall", node);
+        }
+
+        // Enclosure whose only way in is a one-cell gap
+        let ring = (40..=44).flat_map(|i| [(i, 40), (i, 44), (40, i), (44, i)]);
+        let closed = wall_map(ring.clone());
+        assert!(jps(&closed, tile(10, 10), tile(42, 42)).jump_points.is_none());
+        assert_eq!(pathfind_jps(&closed, tile(10, 10), tile(42, 42)), PathfindResult::NotFound);
+        let gapped = wall_map(ring.filter(|&c| c != (42, 40)));
+        assert!(matches!(pathfind_jps(&gapped, tile(10, 10), tile(42, 42)), PathfindResult::Found(_)));
+    }
+}
@@ -17,6 +17,9 @@
 //   Tier 2: Segment Pool (400 slots) — reuse cached path segments
 //   Tier 3: Failure Cache (8 entries) — skip known-impossible routes
 //   Tier 4: Dual-arm wall-following pathfinder (Bug2 variant)
+//
+// PathfindingMode::Enhanced (not in the original) swaps tier 4 for jump
+// point search and tightens tier 1 to a line-of-sight check.
 
 pub mod constants;
 pub mod tables;
@@ -27,12 +30,25 @@ pub mod segment;
 pub mod route;
 pub mod waypoint;
 pub mod pathfinder;
+pub mod transport;
+pub mod formation;
+pub mod astar;
+pub mod crossval;
+pub mod jps;
 
 // Re-export primary API
 pub use types::{WorldCoord, TileCoord, Waypoint, PersonMovement, UsedTargetsCache};
 pub use region::RegionMap;
 pub use segment::{SegmentPool, FailureCache};
-pub use route::{state_goto, route_table_lookup, adjust_target_for_walkability, RouteResult};
+pub use route::{
+    state_goto, state_goto_with, route_table_lookup, route_table_lookup_with,
+    adjust_target_for_walkability, PathfindingMode, RouteResult,
+};
 pub use waypoint::{process_route_movement, WaypointResult};
 pub use math::{move_point_by_angle, angle_difference, rotation_direction, distance, atan2, formation_rng_next};
 pub use pathfinder::{pathfind, pathfind_debug, PathfindResult, PathfindDebug, PathNode, VisitedBitmap};
+pub use transport::{IslandMap, Landing, WaterCrossing, plan_water_crossing, sail_path, compress_route};
+pub use formation::{FormationOffset, slot_position};
+pub use astar::{astar, AstarResult};
+pub use crossval::{cross_validate, random_pairs, CrossValReport};
+pub use jps::{jps, pathfind_jps, pathfind_jps_debug, JpsResult};
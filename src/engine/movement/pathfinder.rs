@@ -91,6 +91,11 @@ impl VisitedBitmap {
         (self.bits[idx >> 3] >> (idx & 7)) & 1 != 0
     }
 
+    /// Number of visited cells.
+    pub fn count(&self) -> usize {
+        self.bits.iter().map(|b| b.count_ones() as usize).sum()
+    }
+
     fn mark(&mut self, x: i32, z: i32) {
         let idx = (z as usize) * REGION_GRID_SIZE + (x as usize);
         self.bits[idx >> 3] |= 1 << (idx & 7);
@@ -678,7 +683,7 @@ fn build_path_result(
 /// Check if there's a clear line of sight between two cells.
 /// Walks a Bresenham line and checks every cell for passability.
 /// Original: part of path optimizer at 0x45e500
-fn line_of_sight(region_map: &RegionMap, from: PathNode, to: PathNode) -> bool {
+pub fn line_of_sight(region_map: &RegionMap, from: PathNode, to: PathNode) -> bool {
     let mut x = from.x;
     let mut z = from.z;
     let dx = (to.x - from.x).abs();
@@ -11,6 +11,7 @@ use super::types::{RegionMapCell, TileCoord, WorldCoord};
 
 /// The 128×128 region map grid.
 /// Populated at level load time from the level data.
+#[derive(Clone)]
 pub struct RegionMap {
     cells: Vec<RegionMapCell>,
     /// Terrain flags table (binary: 0x5A3038).
@@ -7,12 +7,65 @@
 // Tier 4: A* pathfinder — compute new path (expensive, deferred)
 //
 // Returns a segment index (0 = direct walk, >0 = follow segment waypoints).
+//
+// Tier 1 and tier 4 depend on the PathfindingMode. The original mode is the
+// binary's behaviour. The enhanced mode (not in the original) walks
+// directly only with a clear line of sight and searches with JPS (jps.rs);
+// its routes go through the same segment pool and failure cache.
 
 use super::constants::*;
+use super::jps::pathfind_jps;
+use super::pathfinder::{line_of_sight, pathfind, PathNode, PathfindResult};
 use super::region::{spiral_neighbors, RegionMap};
 use super::segment::{FailureCache, SegmentPool};
 use super::types::{PersonMovement, TileCoord, UsedTargetsCache, WorldCoord};
 
+/// Route provider used for unit moves, chosen per game.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PathfindingMode {
+    /// Region check plus dual-arm wall follower, as in popTB.exe.
+    #[default]
+    Original,
+    /// Line-of-sight check plus jump point search. Finds every reachable
+    /// goal, including around spiral coastlines that defeat the wall follower.
+    Enhanced,
+}
+
+impl PathfindingMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            PathfindingMode::Original => "original",
+            PathfindingMode::Enhanced => "enhanced",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "original" => Some(PathfindingMode::Original),
+            "enhanced" => Some(PathfindingMode::Enhanced),
+            _ => None,
+        }
+    }
+
+    /// Tier 1: may a unit in the same region walk straight to the target?
+    pub fn walks_directly(self, region_map: &RegionMap, from: TileCoord, to: TileCoord) -> bool {
+        match self {
+            PathfindingMode::Original => true,
+            PathfindingMode::Enhanced => {
+                line_of_sight(region_map, PathNode::from_tile(from), PathNode::from_tile(to))
+            }
+        }
+    }
+
+    /// Tier 4: search for a route between two tiles.
+    pub fn find_path(self, region_map: &RegionMap, from: TileCoord, to: TileCoord) -> PathfindResult {
+        match self {
+            PathfindingMode::Original => pathfind(region_map, from, to),
+            PathfindingMode::Enhanced => pathfind_jps(region_map, from, to),
+        }
+    }
+}
+
 /// Result of a route lookup.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RouteResult {
@@ -93,6 +146,20 @@ pub fn route_table_lookup(
     failure_cache: &FailureCache,
     person: &mut PersonMovement,
     target: WorldCoord,
+) -> RouteResult {
+    route_table_lookup_with(PathfindingMode::Original, region_map, segment_pool, failure_cache, person, target)
+}
+
+/// `route_table_lookup` with the given route provider. In enhanced mode a
+/// same-region target without line of sight is routed like any other, and
+/// walked to directly only if no route is found.
+pub fn route_table_lookup_with(
+    mode: PathfindingMode,
+    region_map: &RegionMap,
+    segment_pool: &mut SegmentPool,
+    failure_cache: &FailureCache,
+    person: &mut PersonMovement,
+    target: WorldCoord,
 ) -> RouteResult {
     // Step 1: Store target at unit+0x4F
     person.target_pos = target;
@@ -105,12 +172,10 @@ pub fn route_table_lookup(
     let src_region = region_map.region_at(src_tile);
     let dst_region = region_map.region_at(dst_tile);
 
-    if src_region == dst_region {
+    let same_region = src_region == dst_region;
+    if same_region && mode.walks_directly(region_map, src_tile, dst_tile) {
         // Tier 1 hit: same region, direct walk
-        person.next_waypoint = target;
-        person.segment_index = 0;
-        person.waypoint_idx = 0;
-        return RouteResult::DirectWalk;
+        return direct_walk(person, target);
     }
 
     // Step 5: Different region — search segment pool (Tier 2)
@@ -134,6 +199,9 @@ pub fn route_table_lookup(
     // Step 6: Check failure cache (Tier 3)
     if failure_cache.is_failed(src_tile, dst_tile) {
         // Recently failed — don't bother with A*
+        if same_region {
+            return direct_walk(person, target);
+        }
         person.segment_index = 0;
         person.waypoint_idx = 0;
         return RouteResult::NoRoute;
@@ -144,8 +212,8 @@ pub fn route_table_lookup(
     let src_tile = person.position.to_tile();
     let dst_tile = target.to_tile();
 
-    match super::pathfinder::pathfind(region_map, src_tile, dst_tile) {
-        super::pathfinder::PathfindResult::Found(waypoints) if !waypoints.is_empty() => {
+    match mode.find_path(region_map, src_tile, dst_tile) {
+        PathfindResult::Found(waypoints) if !waypoints.is_empty() => {
             // Allocate a new segment and populate it
             if let Some(seg_idx) = segment_pool.allocate() {
                 segment_pool.add_ref(seg_idx);
@@ -178,11 +246,22 @@ pub fn route_table_lookup(
 
     // Record failure in cache for future lookups
     // (failure_cache is immutable here — caller would need to handle this)
+    if same_region {
+        return direct_walk(person, target);
+    }
     person.segment_index = 0;
     person.waypoint_idx = 0;
     RouteResult::NoRoute
 }
 
+/// Tier 1 result: walk straight at the target with no segment.
+fn direct_walk(person: &mut PersonMovement, target: WorldCoord) -> RouteResult {
+    person.next_waypoint = target;
+    person.segment_index = 0;
+    person.waypoint_idx = 0;
+    RouteResult::DirectWalk
+}
+
 /// STATE_GOTO dispatcher — thin wrapper around route lookup.
 /// Original: FUN_004d7e20 (76 bytes)
 ///
@@ -195,13 +274,27 @@ pub fn state_goto(
     person: &mut PersonMovement,
     target: WorldCoord,
     used_targets: &mut UsedTargetsCache,
+) -> RouteResult {
+    state_goto_with(PathfindingMode::Original, region_map, segment_pool, failure_cache, person, target, used_targets)
+}
+
+/// `state_goto` with the given route provider.
+pub fn state_goto_with(
+    mode: PathfindingMode,
+    region_map: &RegionMap,
+    segment_pool: &mut SegmentPool,
+    failure_cache: &FailureCache,
+    person: &mut PersonMovement,
+    target: WorldCoord,
+    used_targets: &mut UsedTargetsCache,
 ) -> RouteResult {
     // Adjust target for walkability
     let mut adjusted_target = target;
     adjust_target_for_walkability(region_map, person, &mut adjusted_target, used_targets);
 
     // Route lookup (4-tier cache)
-    let result = route_table_lookup(
+    let result = route_table_lookup_with(
+        mode,
         region_map,
         segment_pool,
         failure_cache,
@@ -393,4 +486,87 @@ mod tests {
         assert_ne!(target2, target_tile.to_world());
         assert_ne!(target1, target2);
     }
+
+    fn cell(x: i32, z: i32) -> TileCoord {
+        PathNode::new(x, z).to_tile()
+    }
+
+    /// Square spiral coastline wound outward from cell (64, 64).
+    fn spiral_map() -> RegionMap {
+        let mut map = RegionMap::new();
+        map.set_terrain_flags(1, 0x00);
+        let (mut x, mut z, mut len) = (64, 64, 2);
+        for leg in 0..12 {
+            let (dx, dz) = [(1, 0), (0, 1), (-1, 0), (0, -1)][leg % 4];
+            for _ in 0..len {
+                map.get_cell_mut(cell(x, z)).terrain_type = 1;
+                x += dx;
+                z += dz;
+            }
+            if leg % 2 == 1 {
+                len += 3;
+            }
+        }
+        map
+    }
+
+    #[test]
+    fn enhanced_mode_routes_out_of_a_spiral() {
+        let mut map = spiral_map();
+        let (src, dst) = (cell(65, 65), cell(10, 10));
+        map.set_cell_region(dst, 2);
+        assert_eq!(PathfindingMode::default(), PathfindingMode::Original);
+
+        let mut pool = SegmentPool::new();
+        let cache = FailureCache::new();
+        let mut person = PersonMovement { position: src.to_world(), ..Default::default() };
+        let result = route_table_lookup(&map, &mut pool, &cache, &mut person, dst.to_world());
+        assert_eq!(result, RouteResult::NoRoute);
+
+        let result = route_table_lookup_with(PathfindingMode::Enhanced, &map, &mut pool, &cache, &mut person, dst.to_world());
+        let RouteResult::Segment(idx) = result else { panic!("expected a segment, got {:?}", result) };
+        let seg = pool.get(idx).unwrap();
+        assert!(seg.waypoint_count > 4);
+        let mut prev = PathNode::from_tile(src);
+        for wp in &seg.waypoints[..seg.waypoint_count as usize] {
+            let next = PathNode::from_tile(TileCoord::new(wp.tile_x, wp.tile_z));
+            assert!(line_of_sight(&map, prev, next), "{:?} -> {:?} crosses the coast", prev, next);
+            prev = next;
+        }
+        assert_eq!(prev.to_tile(), dst);
+    }
+
+    #[test]
+    fn enhanced_routes_are_shared_through_the_segment_pool() {
+        let map = spiral_map();
+        let (mut pool, cache) = (SegmentPool::new(), FailureCache::new());
+        let (src, dst) = (cell(65, 65), cell(10, 10));
+        let mut a = PersonMovement { position: src.to_world(), ..Default::default() };
+        let mut b = a.clone();
+        let mode = PathfindingMode::Enhanced;
+        let first = route_table_lookup_with(mode, &map, &mut pool, &cache, &mut a, dst.to_world());
+        let second = route_table_lookup_with(mode, &map, &mut pool, &cache, &mut b, dst.to_world());
+        assert!(matches!(first, RouteResult::Segment(_)));
+        assert_eq!(first, second);
+        assert_eq!(pool.get(a.segment_index).unwrap().ref_count, 2);
+        assert_eq!(pool.active_count, 1);
+    }
+
+    #[test]
+    fn enhanced_mode_walks_directly_only_with_line_of_sight() {
+        let map = spiral_map();
+        let (mut pool, cache) = (SegmentPool::new(), FailureCache::new());
+        let mode = PathfindingMode::Enhanced;
+        let mut person = PersonMovement { position: cell(10, 10).to_world(), ..Default::default() };
+        let target = cell(30, 20).to_world();
+        assert_eq!(route_table_lookup_with(mode, &map, &mut pool, &cache, &mut person, target),
+            RouteResult::DirectWalk);
+
+        // Same region, but the coast is in the way
+        person.position = cell(65, 65).to_world();
+        assert_eq!(route_table_lookup(&map, &mut pool, &cache, &mut person, target), RouteResult::DirectWalk);
+        assert!(matches!(route_table_lookup_with(mode, &map, &mut pool, &cache, &mut person, target),
+            RouteResult::Segment(_)));
+        assert_eq!(PathfindingMode::from_name(mode.name()), Some(mode));
+    }
 }
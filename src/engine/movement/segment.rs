@@ -12,6 +12,7 @@ use super::types::{FailureCacheEntry, RouteSegment, TileCoord, WorldCoord};
 
 /// The route segment pool: circular buffer of MAX_SEGMENTS slots.
 /// Globals at 0x93DD44-0x93DD70 in the binary.
+#[derive(Clone)]
 pub struct SegmentPool {
     /// The segment slots
     pub segments: Vec<RouteSegment>,
@@ -115,6 +116,7 @@ impl Default for SegmentPool {
 /// Failure cache: remembers recently-failed A* searches.
 /// Binary: 8 entries × 10 bytes at 0x93E171.
 /// Prevents repeating expensive pathfinding for known-impossible routes.
+#[derive(Clone)]
 pub struct FailureCache {
     entries: [FailureCacheEntry; FAILURE_CACHE_SIZE],
 }
@@ -6,24 +6,24 @@ use std::sync::LazyLock;
 
 /// Cosine table: 2048 entries, i32, 16.16 fixed-point.
 /// Binary location: 0x5ACEA0
-/// cos[i] = round(cos(i * 2*PI / 2048) * 65536)
+/// cos[i] = trunc(cos(i * 2*PI / 2048) * 65536), matching the Frida captures
 pub static COS_TABLE: LazyLock<[i32; 2048]> = LazyLock::new(|| {
     let mut table = [0i32; 2048];
     for (i, entry) in table.iter_mut().enumerate() {
         let angle = (i as f64) * std::f64::consts::TAU / 2048.0;
-        *entry = (angle.cos() * 65536.0).round() as i32;
+        *entry = (angle.cos() * 65536.0) as i32;
     }
     table
 });
 
 /// Sine table: 2048 entries, i32, 16.16 fixed-point.
 /// Binary location: 0x5AC6A0
-/// sin[i] = round(sin(i * 2*PI / 2048) * 65536)
+/// sin[i] = trunc(sin(i * 2*PI / 2048) * 65536), matching the Frida captures
 pub static SIN_TABLE: LazyLock<[i32; 2048]> = LazyLock::new(|| {
     let mut table = [0i32; 2048];
     for (i, entry) in table.iter_mut().enumerate() {
         let angle = (i as f64) * std::f64::consts::TAU / 2048.0;
-        *entry = (angle.sin() * 65536.0).round() as i32;
+        *entry = (angle.sin() * 65536.0) as i32;
     }
     table
 });
@@ -31,12 +31,14 @@ pub static SIN_TABLE: LazyLock<[i32; 2048]> = LazyLock::new(|| {
 /// Atan lookup table: 256 entries, u16 angles.
 /// Binary location: 0x5641B4
 /// Used by the 8-octant atan2 for ratio → angle mapping.
+/// atan[i] = trunc(atan(i / 256) * 2048 / 2PI); rounding misses 21 of the
+/// 49 Math_Atan2 captures.
 pub static ATAN_TABLE: LazyLock<[u16; 256]> = LazyLock::new(|| {
     let mut table = [0u16; 256];
     for (i, entry) in table.iter_mut().enumerate() {
         let ratio = (i as f64) / 256.0;
         let angle = ratio.atan();
-        *entry = (angle * 2048.0 / std::f64::consts::TAU).round() as u16;
+        *entry = (angle * 2048.0 / std::f64::consts::TAU) as u16;
     }
     table
 });
@@ -82,7 +84,8 @@ mod tests {
             let sum = c * c + s * s;
             let expected = 0x1_0000_0000i64;
             let error = (sum - expected).abs();
-            assert!(error < 0x20000, "angle {i}: sum={sum:#x}, error={error}");
+            // Truncated entries are up to 1 short each: error < 2(|c| + |s|)
+            assert!(error < 0x30000, "angle {i}: sum={sum:#x}, error={error}");
         }
     }
 }
@@ -0,0 +1,460 @@
+// Water transport — multi-leg routing between disconnected land masses.
+//
+// RouteTableLookup only plans over walkable land: a target on another island
+// either snaps back to the near shore (AdjustTargetForWalkability) or fails in
+// the pathfinder. This layer sits on top of it. Walkable land is labelled into
+// islands, and when source and target are on different islands the trip is
+// planned as:
+//
+//   walk to shore → board boat → sail → disembark → walk to target
+//
+// The walking legs still go through state_goto; only the sailing leg is
+// routed here (breadth-first over water cells).
+
+use std::collections::VecDeque;
+
+use super::constants::*;
+use super::region::{spiral_neighbors, RegionMap};
+use super::types::{TileCoord, WorldCoord};
+
+/// Island label for cells that are not walkable land.
+pub const NO_ISLAND: u16 = 0;
+
+/// Connected components of walkable land (4-connected, toroidal).
+/// Islands are numbered from 1; water, shore and buildings are NO_ISLAND.
+#[derive(Clone)]
+pub struct IslandMap {
+    labels: Vec<u16>,
+    count: u16,
+}
+
+impl IslandMap {
+    /// Create an empty map (no land labelled).
+    pub fn new() -> Self {
+        Self {
+            labels: vec![NO_ISLAND; REGION_GRID_SIZE * REGION_GRID_SIZE],
+            count: 0,
+        }
+    }
+
+    /// Flood-fill walkable tiles of the region map into islands.
+    pub fn build(region_map: &RegionMap) -> Self {
+        let mut map = Self::new();
+        let mut queue = VecDeque::new();
+
+        for tx in (0..=254u8).step_by(2) {
+            for tz in (0..=254u8).step_by(2) {
+                let seed = TileCoord::new(tx, tz);
+                if map.labels[seed.cell_index()] != NO_ISLAND || !region_map.is_walkable(seed) {
+                    continue;
+                }
+                map.count += 1;
+                let label = map.count;
+                map.labels[seed.cell_index()] = label;
+                queue.push_back(seed);
+
+                while let Some(tile) = queue.pop_front() {
+                    for next in tile_neighbors(tile) {
+                        let idx = next.cell_index();
+                        if map.labels[idx] == NO_ISLAND && region_map.is_walkable(next) {
+                            map.labels[idx] = label;
+                            queue.push_back(next);
+                        }
+                    }
+                }
+            }
+        }
+        map
+    }
+
+    /// Island label at a tile (NO_ISLAND if not walkable land).
+    pub fn island_at(&self, tile: TileCoord) -> u16 {
+        self.labels[tile.cell_index()]
+    }
+
+    /// Island label at a world position.
+    pub fn island_at_world(&self, pos: WorldCoord) -> u16 {
+        self.island_at(pos.to_tile())
+    }
+
+    /// Number of islands found.
+    pub fn island_count(&self) -> u16 {
+        self.count
+    }
+
+    /// Island the target belongs to. Off-land targets (water, shore) resolve
+    /// to the nearest labelled tile using the same spiral as
+    /// AdjustTargetForWalkability.
+    pub fn island_near(&self, tile: TileCoord) -> u16 {
+        let label = self.island_at(tile);
+        if label != NO_ISLAND {
+            return label;
+        }
+        for (dx, dz) in spiral_neighbors(MAX_WALKABILITY_SEARCH) {
+            let candidate = offset_tile(tile, dx as i32, dz as i32);
+            let label = self.island_at(candidate);
+            if label != NO_ISLAND {
+                return label;
+            }
+        }
+        NO_ISLAND
+    }
+}
+
+impl Default for IslandMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Check if a boat can sail over a tile (open water).
+pub fn is_sailable(region_map: &RegionMap, tile: TileCoord) -> bool {
+    region_map.terrain_class(tile) == TERRAIN_CLASS_WATER
+}
+
+/// A shore crossing point: the land tile a person stands on, and the
+/// water tile the boat docks at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Landing {
+    pub land: TileCoord,
+    pub water: TileCoord,
+}
+
+/// A planned trip between two islands.
+#[derive(Debug, Clone)]
+pub struct WaterCrossing {
+    /// Where passengers board on the source island.
+    pub embark: Landing,
+    /// Where passengers are put ashore on the target island.
+    pub disembark: Landing,
+    /// Sailing waypoints from embark.water to disembark.water (corners only).
+    pub sail_route: Vec<WorldCoord>,
+    /// Island the trip ends on.
+    pub target_island: u16,
+}
+
+/// Plan a water crossing from `src` to `dst`.
+///
+/// Returns None when both positions are on the same island (the normal
+/// route_table_lookup applies), when either is not near land, or when no
+/// water path joins the two islands.
+pub fn plan_water_crossing(
+    region_map: &RegionMap,
+    islands: &IslandMap,
+    src: WorldCoord,
+    dst: WorldCoord,
+) -> Option<WaterCrossing> {
+    let src_island = islands.island_near(src.to_tile());
+    let dst_island = islands.island_near(dst.to_tile());
+    if src_island == NO_ISLAND || dst_island == NO_ISLAND || src_island == dst_island {
+        return None;
+    }
+
+    let start = nearest_island_tile(islands, src.to_tile(), src_island)?;
+    let embark = find_embark_landing(region_map, islands, start)?;
+    let (path, disembark) = sail_to_island(region_map, islands, embark.water, dst_island)?;
+
+    Some(WaterCrossing {
+        embark,
+        disembark,
+        sail_route: compress_route(&path),
+        target_island: dst_island,
+    })
+}
+
+/// Breadth-first search over the source island for the closest land tile
+/// within LANDING_REACH of open water.
+pub fn find_embark_landing(
+    region_map: &RegionMap,
+    islands: &IslandMap,
+    from: TileCoord,
+) -> Option<Landing> {
+    let island = islands.island_at(from);
+    if island == NO_ISLAND {
+        return None;
+    }
+
+    let mut visited = vec![false; REGION_GRID_SIZE * REGION_GRID_SIZE];
+    let mut queue = VecDeque::new();
+    visited[from.cell_index()] = true;
+    queue.push_back(from);
+
+    while let Some(tile) = queue.pop_front() {
+        if let Some(water) = nearby_tile(tile, |t| is_sailable(region_map, t)) {
+            return Some(Landing { land: tile, water });
+        }
+        for next in tile_neighbors(tile) {
+            let idx = next.cell_index();
+            if !visited[idx] && islands.island_at(next) == island {
+                visited[idx] = true;
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+/// Breadth-first search over water from `from` to the first water tile
+/// within LANDING_REACH of `island`. Returns the tile path (inclusive)
+/// and the landing found.
+pub fn sail_to_island(
+    region_map: &RegionMap,
+    islands: &IslandMap,
+    from: TileCoord,
+    island: u16,
+) -> Option<(Vec<TileCoord>, Landing)> {
+    let mut landing = None;
+    let path = sail_search(region_map, from, |t| {
+        match nearby_tile(t, |l| islands.island_at(l) == island) {
+            Some(land) => {
+                landing = Some(Landing { land, water: t });
+                true
+            }
+            None => false,
+        }
+    })?;
+    Some((path, landing?))
+}
+
+/// Breadth-first search over water between two tiles.
+/// Returns the tile path (inclusive), or None if they are not connected.
+pub fn sail_path(region_map: &RegionMap, from: TileCoord, to: TileCoord) -> Option<Vec<TileCoord>> {
+    sail_search(region_map, from, |t| t == to)
+}
+
+/// Reduce a tile path to world waypoints at direction changes plus the
+/// final tile.
+pub fn compress_route(path: &[TileCoord]) -> Vec<WorldCoord> {
+    let mut route = Vec::new();
+    for i in 1..path.len() {
+        let is_last = i + 1 == path.len();
+        if is_last || step(path[i - 1], path[i]) != step(path[i], path[i + 1]) {
+            route.push(path[i].to_world());
+        }
+    }
+    route
+}
+
+// --- Internal helpers ---
+
+fn sail_search(
+    region_map: &RegionMap,
+    from: TileCoord,
+    mut is_goal: impl FnMut(TileCoord) -> bool,
+) -> Option<Vec<TileCoord>> {
+    let n = REGION_GRID_SIZE * REGION_GRID_SIZE;
+    let mut prev: Vec<Option<TileCoord>> = vec![None; n];
+    let mut visited = vec![false; n];
+    let mut queue = VecDeque::new();
+    visited[from.cell_index()] = true;
+    queue.push_back(from);
+
+    while let Some(tile) = queue.pop_front() {
+        if is_goal(tile) {
+            let mut path = vec![tile];
+            let mut cur = tile;
+            while let Some(p) = prev[cur.cell_index()] {
+                path.push(p);
+                cur = p;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for next in tile_neighbors(tile) {
+            let idx = next.cell_index();
+            if !visited[idx] && is_sailable(region_map, next) {
+                visited[idx] = true;
+                prev[idx] = Some(tile);
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+/// Closest tile (by spiral) labelled `island`, starting at `tile` itself.
+fn nearest_island_tile(islands: &IslandMap, tile: TileCoord, island: u16) -> Option<TileCoord> {
+    if islands.island_at(tile) == island {
+        return Some(tile);
+    }
+    spiral_neighbors(MAX_WALKABILITY_SEARCH)
+        .into_iter()
+        .map(|(dx, dz)| offset_tile(tile, dx as i32, dz as i32))
+        .find(|&t| islands.island_at(t) == island)
+}
+
+/// First tile within LANDING_REACH (nearest ring first) matching `pred`.
+fn nearby_tile(center: TileCoord, pred: impl Fn(TileCoord) -> bool) -> Option<TileCoord> {
+    for ring in 1..=LANDING_REACH {
+        for dx in -ring..=ring {
+            for dz in -ring..=ring {
+                if dx.abs() != ring && dz.abs() != ring {
+                    continue;
+                }
+                let t = offset_tile(center, dx, dz);
+                if pred(t) {
+                    return Some(t);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Offset a tile by whole cells, wrapping around the toroidal map.
+fn offset_tile(tile: TileCoord, dx: i32, dz: i32) -> TileCoord {
+    TileCoord::new(
+        (tile.x as i32 + dx * 2) as u8 & 0xFE,
+        (tile.z as i32 + dz * 2) as u8 & 0xFE,
+    )
+}
+
+/// The four cardinal neighbours of a tile (S, E, N, W — DIRECTION_DX/DZ order).
+fn tile_neighbors(tile: TileCoord) -> [TileCoord; NUM_DIRECTIONS] {
+    let mut out = [tile; NUM_DIRECTIONS];
+    for (d, n) in out.iter_mut().enumerate() {
+        *n = offset_tile(tile, DIRECTION_DX[d], DIRECTION_DZ[d]);
+    }
+    out
+}
+
+fn step(a: TileCoord, b: TileCoord) -> (u8, u8) {
+    (b.x.wrapping_sub(a.x), b.z.wrapping_sub(a.z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two land strips separated by a channel of water, with one-cell shore
+    /// buffers on both banks. Columns are tile x (cell = x/2).
+    ///   cells x 0..=19  : west island
+    ///   cell  x 20      : shore
+    ///   cells x 21..=29 : water
+    ///   cell  x 30      : shore
+    ///   cells x 31..=50 : east island
+    ///   cells x 51..    : water (so the islands don't join around the torus)
+    fn channel_map() -> RegionMap {
+        let mut map = RegionMap::new();
+        map.set_terrain_flags(TERRAIN_CLASS_WATER, 0x00);
+        map.set_terrain_flags(TERRAIN_CLASS_SHORE, 0x00);
+        for cx in 0..128u8 {
+            let class = match cx {
+                0..=19 | 31..=50 => 0,
+                20 | 30 | 51 | 127 => TERRAIN_CLASS_SHORE,
+                _ => TERRAIN_CLASS_WATER,
+            };
+            for cz in 0..128u8 {
+                map.get_cell_mut(TileCoord::new(cx * 2, cz * 2)).terrain_type = class;
+            }
+        }
+        map
+    }
+
+    #[test]
+    fn islands_labelled_separately() {
+        let map = channel_map();
+        let islands = IslandMap::build(&map);
+        assert_eq!(islands.island_count(), 2);
+
+        let west = islands.island_at(TileCoord::new(10, 40));
+        let east = islands.island_at(TileCoord::new(80, 40));
+        assert_ne!(west, NO_ISLAND);
+        assert_ne!(east, NO_ISLAND);
+        assert_ne!(west, east);
+        assert_eq!(islands.island_at(TileCoord::new(50, 40)), NO_ISLAND); // water
+    }
+
+    #[test]
+    fn single_landmass_is_one_island() {
+        let islands = IslandMap::build(&RegionMap::new());
+        assert_eq!(islands.island_count(), 1);
+    }
+
+    #[test]
+    fn island_near_resolves_water_target() {
+        let map = channel_map();
+        let islands = IslandMap::build(&map);
+        // Cell x=29 is water next to the east shore buffer
+        let east = islands.island_at(TileCoord::new(80, 40));
+        assert_eq!(islands.island_near(TileCoord::new(58, 40)), east);
+    }
+
+    #[test]
+    fn same_island_has_no_crossing() {
+        let map = channel_map();
+        let islands = IslandMap::build(&map);
+        let a = TileCoord::new(4, 40).to_world();
+        let b = TileCoord::new(30, 90).to_world();
+        assert!(plan_water_crossing(&map, &islands, a, b).is_none());
+    }
+
+    #[test]
+    fn crossing_plans_both_landings() {
+        let map = channel_map();
+        let islands = IslandMap::build(&map);
+        let src = TileCoord::new(10, 40).to_world();
+        let dst = TileCoord::new(90, 40).to_world();
+
+        let crossing = plan_water_crossing(&map, &islands, src, dst).expect("crossing");
+        let west = islands.island_at(src.to_tile());
+        let east = islands.island_at(dst.to_tile());
+
+        assert_eq!(islands.island_at(crossing.embark.land), west);
+        assert_eq!(islands.island_at(crossing.disembark.land), east);
+        assert!(is_sailable(&map, crossing.embark.water));
+        assert!(is_sailable(&map, crossing.disembark.water));
+        assert_eq!(crossing.target_island, east);
+        // Straight channel crossing compresses to a single waypoint
+        assert_eq!(crossing.sail_route.last(), Some(&crossing.disembark.water.to_world()));
+    }
+
+    #[test]
+    fn no_crossing_without_connecting_water() {
+        let mut map = channel_map();
+        map.set_terrain_flags(TERRAIN_CLASS_BUILDING, 0x00);
+        let src = TileCoord::new(10, 40).to_world();
+        let dst = TileCoord::new(90, 40).to_world();
+
+        // Dam the channel at cell x=25. The islands are still joined by the
+        // outer sea (cells 52..=126), which wraps around to the west island.
+        for cz in 0..128u8 {
+            map.get_cell_mut(TileCoord::new(25 * 2, cz * 2)).terrain_type = TERRAIN_CLASS_BUILDING;
+        }
+        let islands = IslandMap::build(&map);
+        assert!(plan_water_crossing(&map, &islands, src, dst).is_some());
+
+        // Fill in the outer sea as well — no water path remains.
+        for cx in 52..127u8 {
+            for cz in 0..128u8 {
+                map.get_cell_mut(TileCoord::new(cx * 2, cz * 2)).terrain_type = TERRAIN_CLASS_BUILDING;
+            }
+        }
+        let islands = IslandMap::build(&map);
+        assert!(plan_water_crossing(&map, &islands, src, dst).is_none());
+    }
+
+    #[test]
+    fn sail_path_stays_on_water() {
+        let map = channel_map();
+        let from = TileCoord::new(44, 10);
+        let to = TileCoord::new(56, 100);
+        let path = sail_path(&map, from, to).expect("path");
+        assert_eq!(path.first(), Some(&from));
+        assert_eq!(path.last(), Some(&to));
+        assert!(path.iter().all(|&t| is_sailable(&map, t)));
+    }
+
+    #[test]
+    fn compress_route_keeps_corners() {
+        let path = [
+            TileCoord::new(0, 0),
+            TileCoord::new(2, 0),
+            TileCoord::new(4, 0),
+            TileCoord::new(4, 2),
+            TileCoord::new(4, 4),
+        ];
+        let route = compress_route(&path);
+        assert_eq!(route, vec![TileCoord::new(4, 0).to_world(), TileCoord::new(4, 4).to_world()]);
+    }
+}
@@ -0,0 +1,916 @@
+// Lockstep session — every peer runs tick N only once it holds every
+// player's commands for tick N, then applies them in player order.
+//
+// Commands the local player issues are scheduled `input_delay` ticks ahead,
+// so on a healthy connection the batches for a tick have arrived by the
+// time it is due and nobody waits. Ticks before the first scheduled batch
+// carry no commands.
+//
+// Each run tick is acknowledged (0x07); a batch is kept and resent until
+// every peer has acknowledged running its tick. While a tick is blocked the
+// session heartbeats with its last acknowledgement, resends those batches
+// and its pause state, so lost datagrams only cost time.
+//
+// A leaving player announces the first tick it has no batch for (0x0A).
+// Every peer still applies its batches before that tick, so they agree on
+// what it did last, and stops waiting for it from then on. The leaver keeps
+// resending the announcement and its unacknowledged batches until every
+// peer has run that tick (finish_leaving).
+//
+// Every `sync_interval` ticks the peers also trade state checksums (0x06,
+// see sync.rs); a mismatch is recorded as a DesyncReport. With rebuild on,
+// a peer that has drifted from the host (the lowest active player) asks for
+// the host's input history (0x10/0x11), stalls until it has all of it, and
+// then replays it from a freshly loaded level. This is not the original's
+// resync (Net_RequestResync @ 0x004a8450), which sends the host's state:
+// no units, RNGs or tribes cross the network here. It only recovers a peer
+// whose state went wrong outside the simulation, e.g. one that started the
+// level from different state; a desync the simulation itself causes, such
+// as a nondeterministic bug, comes back after the rebuild.
+//
+// That only holds if every peer starts each level from the same state and
+// plays by the same rules. The host's unit RNG seed for level starts and its
+// GameRules go to every player that announces itself (0x12), and nobody runs
+// tick 0 before they have the seed; a player whose rules differ refuses to
+// start. Loading a level with the seed and rewinding the world
+// (GameWorld::rewind) is the reset a rebuild starts from, the same one the
+// game starts from.
+
+use std::collections::BTreeMap;
+
+use crate::engine::command::GameCommand;
+use crate::engine::state::tick::GameRules;
+use crate::engine::state::traits::NetworkTick;
+
+use super::packet::{history_packets, Packet, PlayerAction};
+use super::sync::{DesyncReport, StateChecksums, DEFAULT_SYNC_INTERVAL};
+use super::transport::Transport;
+
+/// Ticks between issuing a command and running it.
+pub const DEFAULT_INPUT_DELAY: u32 = 2;
+
+/// Blocked polls between heartbeats/resends.
+const RESEND_INTERVAL: u32 = 8;
+
+/// Sync ticks whose checksums are kept for late arrivals.
+const SYNC_WINDOW: u32 = 8;
+
+/// Level seed a host starts with: the unit RNG's initial seed
+/// (UnitCoordinator::new).
+pub const DEFAULT_LEVEL_SEED: u32 = 0x1234;
+
+/// Progress of a rebuild from the host's history.
+enum Rebuild {
+    None,
+    /// Asked the host; collecting the parts of its reply.
+    Requested { upto: Option<u32>, parts: BTreeMap<u16, Vec<(u32, PlayerAction)>>, total: u16 },
+    /// History complete; waiting for the caller to reload and replay.
+    Ready { history: Vec<(u32, PlayerAction)>, until: u32 },
+    /// Replaying: ticks are released from the history, not the network.
+    Running { history: Vec<(u32, PlayerAction)>, tick: u32, until: u32 },
+}
+
+pub struct Lockstep<T: Transport> {
+    transport: T,
+    /// Local player (= tribe index).
+    player: u8,
+    players: u8,
+    input_delay: u32,
+    /// Next tick to run.
+    tick: u32,
+    /// Next tick to schedule a local batch for.
+    scheduled: u32,
+    /// Local commands waiting for the next batch.
+    outgoing: Vec<GameCommand>,
+    /// Local batches some peer hasn't acknowledged yet.
+    unacked: BTreeMap<u32, Vec<GameCommand>>,
+    /// Batches for ticks not yet run, per player.
+    batches: BTreeMap<u32, Vec<Option<Vec<GameCommand>>>>,
+    /// Last tick each player acknowledged running.
+    acked: Vec<Option<u32>>,
+    /// Per player, once it has left: the first tick it has no batch for.
+    left: Vec<Option<u32>>,
+    paused: Vec<bool>,
+    /// Actions of the tick just released, until the tick loop takes them.
+    ready: Vec<PlayerAction>,
+    blocked_polls: u32,
+    sync_interval: u32,
+    /// Our checksums per sync tick, and each player's as they arrive.
+    local_sums: BTreeMap<u32, StateChecksums>,
+    remote_sums: Vec<BTreeMap<u32, StateChecksums>>,
+    /// Last sync tick each player agreed with us on.
+    last_good: Vec<Option<u32>>,
+    /// Players currently known to disagree with us.
+    out_of_sync: Vec<bool>,
+    desyncs: Vec<DesyncReport>,
+    /// Every action released so far, with its tick.
+    history: Vec<(u32, PlayerAction)>,
+    rebuild_enabled: bool,
+    rebuild: Rebuild,
+    /// Seed we'd start levels from as host.
+    own_seed: u32,
+    /// Unit RNG seed levels start from: ours as host, the host's once it
+    /// has arrived otherwise.
+    level_seed: Option<u32>,
+    /// Rules we play with; the host's have to match.
+    rules: GameRules,
+    /// Why we won't play with the host, once its seed came with other rules.
+    refused: Option<String>,
+}
+
+impl<T: Transport> Lockstep<T> {
+    pub fn new(transport: T, player: u8, players: u8) -> Self {
+        assert!(player < players, "player {} of {}", player, players);
+        let n = players as usize;
+        Self {
+            transport,
+            player,
+            players,
+            input_delay: DEFAULT_INPUT_DELAY,
+            tick: 0,
+            scheduled: DEFAULT_INPUT_DELAY,
+            outgoing: Vec::new(),
+            unacked: BTreeMap::new(),
+            batches: BTreeMap::new(),
+            acked: vec![None; n],
+            left: vec![None; n],
+            paused: vec![false; n],
+            ready: Vec::new(),
+            blocked_polls: 0,
+            sync_interval: DEFAULT_SYNC_INTERVAL,
+            local_sums: BTreeMap::new(),
+            remote_sums: vec![BTreeMap::new(); n],
+            last_good: vec![None; n],
+            out_of_sync: vec![false; n],
+            desyncs: Vec::new(),
+            history: Vec::new(),
+            rebuild_enabled: false,
+            rebuild: Rebuild::None,
+            own_seed: DEFAULT_LEVEL_SEED,
+            level_seed: (player == 0).then_some(DEFAULT_LEVEL_SEED),
+            rules: GameRules::default(),
+            refused: None,
+        }
+    }
+
+    /// Change the input delay. Every peer must use the same one; only
+    /// allowed before the first tick.
+    pub fn with_input_delay(mut self, ticks: u32) -> Self {
+        assert!(self.tick == 0 && self.unacked.is_empty(), "input delay set after start");
+        self.input_delay = ticks;
+        self.scheduled = ticks;
+        self
+    }
+
+    /// Change how often checksums are traded; 0 turns it off. Every peer
+    /// must use the same interval.
+    pub fn with_sync_interval(mut self, ticks: u32) -> Self {
+        self.sync_interval = ticks;
+        self
+    }
+
+    /// Seed the host starts levels from; the other players take the host's
+    /// and ignore theirs. Only allowed before the first tick.
+    pub fn with_level_seed(mut self, seed: u32) -> Self {
+        assert!(self.tick == 0, "level seed set after start");
+        self.own_seed = seed;
+        if self.player == self.host() {
+            self.level_seed = Some(seed);
+        }
+        self
+    }
+
+    /// Rules we play with. Every peer must use the host's: a player whose
+    /// rules differ refuses the host's seed (see poll_level_seed). Only
+    /// allowed before the first tick.
+    pub fn with_rules(mut self, rules: GameRules) -> Self {
+        assert!(self.tick == 0, "rules set after start");
+        self.rules = rules;
+        self
+    }
+
+    /// Ask the host for its history when we drift from it.
+    pub fn with_rebuild(mut self, enabled: bool) -> Self {
+        self.rebuild_enabled = enabled;
+        self
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    pub fn player(&self) -> u8 {
+        self.player
+    }
+
+    pub fn players(&self) -> u8 {
+        self.players
+    }
+
+    /// Next tick to run.
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    /// Queue a local command for the next batch. Returns false (and drops
+    /// it) for commands that don't affect the simulation.
+    pub fn queue(&mut self, command: GameCommand) -> bool {
+        if !command.is_networked() {
+            return false;
+        }
+        self.outgoing.push(command);
+        true
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused[self.player as usize] = paused;
+        self.send(&Packet::Pause { player: self.player, paused });
+    }
+
+    /// Some player has paused the game.
+    pub fn is_paused(&self) -> bool {
+        self.paused.iter().zip(&self.left).any(|(&p, gone)| p && gone.is_none())
+    }
+
+    /// Tell the peers we're going: they apply the batches we have already
+    /// scheduled and stop waiting for us after them. Nothing is scheduled
+    /// from now on. Call finish_leaving until it returns true to make sure
+    /// they heard.
+    pub fn leave(&mut self) {
+        let until = *self.left[self.player as usize].get_or_insert(self.scheduled);
+        self.send(&Packet::Leave { player: self.player, until });
+    }
+
+    /// After leave: read acknowledgements and resend what the peers are
+    /// missing. True once every remaining peer has run past our last batch.
+    pub fn finish_leaving(&mut self) -> bool {
+        let Some(until) = self.left[self.player as usize] else { return false };
+        self.receive();
+        let done = self.active_players()
+            .all(|p| self.acked[p as usize].is_some_and(|tick| tick >= until));
+        if !done {
+            if self.blocked_polls.is_multiple_of(RESEND_INTERVAL) {
+                self.heartbeat();
+            }
+            self.blocked_polls += 1;
+        }
+        done
+    }
+
+    /// Players that haven't left; the local player too until it leaves.
+    pub fn active_players(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..self.players).filter(|&p| self.left[p as usize].is_none())
+    }
+
+    /// Lowest active player: the one whose state a rebuild replays.
+    pub fn host(&self) -> u8 {
+        self.active_players().next().unwrap_or(self.player)
+    }
+
+    /// Unit RNG seed every peer loads levels with (UnitCoordinator::load_level),
+    /// None until the host's has arrived.
+    pub fn level_seed(&self) -> Option<u32> {
+        self.level_seed
+    }
+
+    /// Rules every peer plays with (the host's, once its seed is taken).
+    pub fn rules(&self) -> GameRules {
+        self.rules
+    }
+
+    /// Read what has arrived and, until the host's level seed is among it,
+    /// keep asking for it. Poll this before loading the first level. Errors
+    /// once the host turns out to play with other rules.
+    pub fn poll_level_seed(&mut self) -> Result<Option<u32>, String> {
+        self.receive();
+        if let Some(reason) = &self.refused {
+            return Err(reason.clone());
+        }
+        if self.level_seed.is_none() {
+            if self.blocked_polls.is_multiple_of(RESEND_INTERVAL) {
+                self.heartbeat();
+            }
+            self.blocked_polls += 1;
+        }
+        Ok(self.level_seed)
+    }
+
+    /// Every desync seen so far, oldest first.
+    pub fn desync_reports(&self) -> &[DesyncReport] {
+        &self.desyncs
+    }
+
+    /// The host's history has arrived: reload the level, rewind the world
+    /// and call `begin_rebuild`.
+    pub fn rebuild_ready(&self) -> bool {
+        matches!(self.rebuild, Rebuild::Ready { .. })
+    }
+
+    /// Start replaying the host's history. Returns the number of ticks to
+    /// run back to back from tick 0; during them the session releases the
+    /// history's actions instead of waiting for the network.
+    pub fn begin_rebuild(&mut self) -> u32 {
+        let Rebuild::Ready { history, until } = std::mem::replace(&mut self.rebuild, Rebuild::None) else {
+            panic!("begin_rebuild without a complete history");
+        };
+        log::info!("[net] rebuilding {} ticks from the host's history ({} actions)", until, history.len());
+        self.rebuild = Rebuild::Running { history, tick: 0, until };
+        until
+    }
+
+    /// Read everything that has arrived.
+    pub fn receive(&mut self) {
+        while let Some(bytes) = self.transport.recv() {
+            match Packet::decode(&bytes) {
+                Ok(packet) => self.handle(packet),
+                Err(e) => log::debug!("[net] dropping packet: {}", e),
+            }
+        }
+    }
+
+    fn handle(&mut self, packet: Packet) {
+        let player = packet.player();
+        if player >= self.players || player == self.player {
+            log::debug!("[net] packet from unexpected player {}", player);
+            return;
+        }
+        let p = player as usize;
+        match packet {
+            Packet::Actions { tick, commands, .. } => {
+                if tick < self.tick.max(self.input_delay) {
+                    return; // resend of a batch already run
+                }
+                let slot = &mut self.batches.entry(tick).or_insert_with(|| vec![None; self.players as usize])[p];
+                if slot.is_none() {
+                    *slot = Some(commands);
+                }
+            }
+            Packet::TickAck { tick, .. } => {
+                if self.acked[p].is_none_or(|t| tick > t) {
+                    self.acked[p] = Some(tick);
+                    self.prune_acknowledged();
+                }
+            }
+            Packet::Leave { until, .. } => {
+                if self.left[p].is_none() {
+                    log::info!("[net] player {} left after tick {}", player, until.saturating_sub(1));
+                    self.left[p] = Some(until);
+                    self.prune_acknowledged();
+                }
+            }
+            Packet::Pause { paused, .. } => self.paused[p] = paused,
+            Packet::StateSync { checksums, .. } => {
+                let oldest = self.tick.saturating_sub(SYNC_WINDOW * self.sync_interval);
+                if checksums.tick >= oldest {
+                    self.remote_sums[p].insert(checksums.tick, checksums);
+                    self.compare(player, checksums.tick);
+                }
+            }
+            Packet::LevelSeed { seed, rules, .. } => {
+                if player == self.host() && self.level_seed.is_none() {
+                    if rules != self.rules {
+                        if self.refused.is_none() {
+                            log::warn!("[net] player {} plays with {:?}, we with {:?}", player, rules, self.rules);
+                            self.refused = Some(format!("host plays with {:?}, we with {:?}", rules, self.rules));
+                        }
+                        return;
+                    }
+                    log::info!("[net] level seed {:#x} from player {}", seed, player);
+                    self.level_seed = Some(seed);
+                } else if self.player == self.host() {
+                    // Another player announcing itself: the host's seed wins,
+                    // and a player with other rules refuses it
+                    if rules != self.rules {
+                        log::warn!("[net] player {} plays with {:?}, we with {:?}", player, rules, self.rules);
+                    }
+                    let seed = self.level_seed.unwrap_or(self.own_seed);
+                    self.send(&Packet::LevelSeed { player: self.player, seed, rules: self.rules });
+                }
+            }
+            Packet::HistoryRequest { .. } => {
+                if self.host() != self.player {
+                    return;
+                }
+                log::info!("[net] sending player {} our history up to tick {}", player, self.tick);
+                match history_packets(self.player, self.tick, &self.history) {
+                    Ok(packets) => packets.iter().for_each(|packet| self.send(packet)),
+                    Err(e) => log::warn!("[net] history not sent: {}", e),
+                }
+            }
+            Packet::History { upto, part, parts, actions, .. } => {
+                if player != self.host() {
+                    return;
+                }
+                let Rebuild::Requested { upto: expected, parts: received, total } = &mut self.rebuild else { return };
+                // Parts of two answers to a resent request don't mix
+                if expected.is_some_and(|t| t != upto) {
+                    return;
+                }
+                *expected = Some(upto);
+                *total = parts;
+                received.insert(part, actions);
+                if received.len() == parts as usize {
+                    self.finish_history();
+                }
+            }
+        }
+    }
+
+    /// Compare our checksums for `tick` with `player`'s, once both exist.
+    fn compare(&mut self, player: u8, tick: u32) {
+        let p = player as usize;
+        let (Some(ours), Some(theirs)) = (self.local_sums.get(&tick), self.remote_sums[p].get(&tick)) else { return };
+        let categories = ours.differences(theirs);
+        self.remote_sums[p].remove(&tick);
+        if categories.is_empty() {
+            self.last_good[p] = self.last_good[p].max(Some(tick));
+            self.out_of_sync[p] = false;
+            return;
+        }
+        if self.out_of_sync[p] {
+            return;
+        }
+        let report = DesyncReport { tick, last_good_tick: self.last_good[p], player, categories };
+        log::warn!("[net] {}", report);
+        self.desyncs.push(report);
+        self.out_of_sync[p] = true;
+
+        if self.rebuild_enabled && player == self.host() && matches!(self.rebuild, Rebuild::None) {
+            log::info!("[net] asking player {} for its history", player);
+            self.rebuild = Rebuild::Requested { upto: None, parts: BTreeMap::new(), total: 0 };
+            self.send(&Packet::HistoryRequest { player: self.player });
+        }
+    }
+
+    /// Put the host's history together: its actions for the ticks both of
+    /// us have run, ours for any we ran past its end.
+    fn finish_history(&mut self) {
+        let Rebuild::Requested { upto: Some(upto), parts, .. } = std::mem::replace(&mut self.rebuild, Rebuild::None) else {
+            return;
+        };
+        let until = self.tick;
+        let mut history: Vec<_> = parts.into_values().flatten()
+            .filter(|&(tick, _)| tick < until.min(upto))
+            .collect();
+        history.extend(self.history.iter().filter(|&&(tick, _)| tick >= upto && tick < until).cloned());
+        self.rebuild = Rebuild::Ready { history, until };
+    }
+
+    /// Release the next tick of the history being replayed.
+    fn rebuild_step(&mut self) -> bool {
+        let Rebuild::Running { history, tick, until } = &mut self.rebuild else { return false };
+        if *tick >= *until {
+            return false;
+        }
+        let t = *tick;
+        self.ready.extend(history.iter().filter(|&&(at, _)| at == t).map(|(_, action)| action.clone()));
+        *tick += 1;
+        if *tick == *until {
+            let until = *until;
+            let Rebuild::Running { history, .. } = std::mem::replace(&mut self.rebuild, Rebuild::None) else { unreachable!() };
+            self.history = history;
+            self.out_of_sync.fill(false);
+            self.local_sums.retain(|&t, _| t > until);
+            self.remote_sums.iter_mut().for_each(|sums| sums.retain(|&t, _| t > until));
+            log::info!("[net] rebuilt up to tick {}", until);
+        }
+        true
+    }
+
+    /// Drop local batches every remaining peer has run.
+    fn prune_acknowledged(&mut self) {
+        let oldest = self.active_players().filter(|&p| p != self.player)
+            .map(|p| self.acked[p as usize])
+            .min();
+        match oldest {
+            // Nobody left to send to
+            None => self.unacked.clear(),
+            Some(None) => {}
+            Some(Some(tick)) => self.unacked.retain(|&t, _| t > tick),
+        }
+    }
+
+    fn send(&mut self, packet: &Packet) {
+        match packet.encode() {
+            Ok(bytes) => self.transport.broadcast(&bytes),
+            Err(e) => log::warn!("[net] not sent: {}", e),
+        }
+    }
+
+    /// Schedule the local batch for `tick + input_delay`, once. Nothing
+    /// after leaving.
+    fn schedule_local_batch(&mut self) {
+        if self.left[self.player as usize].is_some() {
+            return;
+        }
+        while self.scheduled <= self.tick + self.input_delay {
+            let tick = self.scheduled;
+            // Whatever doesn't fit one batch goes in the next
+            let count = self.outgoing.len().min(u8::MAX as usize);
+            let commands: Vec<_> = self.outgoing.drain(..count).collect();
+            self.send(&Packet::Actions { player: self.player, tick, commands: commands.clone() });
+            self.batches.entry(tick).or_insert_with(|| vec![None; self.players as usize])
+                [self.player as usize] = Some(commands.clone());
+            self.unacked.insert(tick, commands);
+            self.scheduled += 1;
+        }
+    }
+
+    /// Every player's batch for the next tick, in player order. Players
+    /// that left count up to their last batch.
+    fn take_ready_batch(&mut self) -> Option<Vec<PlayerAction>> {
+        if self.tick < self.input_delay {
+            return Some(Vec::new());
+        }
+        let batch = self.batches.get(&self.tick)?;
+        let mut actions = Vec::new();
+        let tick = self.tick;
+        for player in (0..self.players).filter(|&p| self.left[p as usize].is_none_or(|until| tick < until)) {
+            for command in batch[player as usize].as_ref()? {
+                actions.push(PlayerAction { player, command: command.clone() });
+            }
+        }
+        self.batches.remove(&self.tick);
+        Some(actions)
+    }
+
+    fn heartbeat(&mut self) {
+        if let Some(last) = self.tick.checked_sub(1) {
+            self.send(&Packet::TickAck { player: self.player, tick: last });
+        }
+        let resend: Vec<_> = self.unacked.iter().map(|(&tick, commands)| (tick, commands.clone())).collect();
+        for (tick, commands) in resend {
+            self.send(&Packet::Actions { player: self.player, tick, commands });
+        }
+        let paused = self.paused[self.player as usize];
+        self.send(&Packet::Pause { player: self.player, paused });
+        if let Some(until) = self.left[self.player as usize] {
+            self.send(&Packet::Leave { player: self.player, until });
+        }
+        if self.level_seed.is_none() {
+            self.send(&Packet::LevelSeed { player: self.player, seed: self.own_seed, rules: self.rules });
+        }
+        if matches!(self.rebuild, Rebuild::Requested { .. }) {
+            self.send(&Packet::HistoryRequest { player: self.player });
+        }
+    }
+
+    /// Try to release the next tick. Returns false while blocked on another
+    /// player's commands, on a pause, on a rebuild or, before tick 0, on the
+    /// host's level seed.
+    pub fn advance(&mut self) -> bool {
+        if matches!(self.rebuild, Rebuild::Running { .. }) {
+            return self.rebuild_step();
+        }
+        self.receive();
+        let rebuilding = !matches!(self.rebuild, Rebuild::None);
+        let released = if self.is_paused() || rebuilding || self.level_seed.is_none() { None } else {
+            self.schedule_local_batch();
+            self.take_ready_batch()
+        };
+        let Some(actions) = released else {
+            self.blocked_polls += 1;
+            if self.blocked_polls.is_multiple_of(RESEND_INTERVAL) {
+                self.heartbeat();
+            }
+            return false;
+        };
+        self.history.extend(actions.iter().map(|action| (self.tick, action.clone())));
+        self.ready.extend(actions);
+        self.send(&Packet::TickAck { player: self.player, tick: self.tick });
+        self.tick += 1;
+        self.blocked_polls = 0;
+        true
+    }
+
+    /// Actions of the released tick(s), in the order they must be applied.
+    pub fn take_ready(&mut self) -> Vec<PlayerAction> {
+        std::mem::take(&mut self.ready)
+    }
+}
+
+/// Dropping a session tells the peers we left.
+impl<T: Transport> Drop for Lockstep<T> {
+    fn drop(&mut self) {
+        self.leave();
+    }
+}
+
+/// Multiplayer half of Tick_ProcessNetworkMessages (0x004a76b0): sends our
+/// tick packets and holds the tick until every player's have arrived.
+impl<T: Transport> NetworkTick for Lockstep<T> {
+    fn tick_process_network(&mut self) -> bool {
+        self.advance()
+    }
+
+    fn take_actions(&mut self) -> Vec<PlayerAction> {
+        self.take_ready()
+    }
+
+    fn net_paused(&self) -> bool {
+        self.is_paused()
+    }
+
+    fn wants_checksums(&self, tick: u32) -> bool {
+        self.sync_interval > 0 && tick > 0 && tick.is_multiple_of(self.sync_interval)
+            && !matches!(self.rebuild, Rebuild::Running { .. })
+    }
+
+    fn submit_checksums(&mut self, sums: StateChecksums) {
+        self.send(&Packet::StateSync { player: self.player, checksums: sums });
+        self.local_sums.insert(sums.tick, sums);
+        let oldest = sums.tick.saturating_sub(SYNC_WINDOW * self.sync_interval);
+        self.local_sums.retain(|&t, _| t >= oldest);
+        self.remote_sums.iter_mut().for_each(|remote| remote.retain(|&t, _| t >= oldest));
+        let local = self.player;
+        for player in (0..self.players).filter(|&p| p != local) {
+            self.compare(player, sums.tick);
+        }
+    }
+
+    fn desynced(&self) -> bool {
+        self.active_players().any(|p| self.out_of_sync[p as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::units::{ModelType, UnitRaw};
+    use crate::engine::movement::{PathfindingMode, WorldCoord};
+    use crate::engine::net::sync::SyncCategory;
+    use crate::engine::net::transport::{loopback, UdpTransport};
+    use crate::engine::state::state_machine::GameState;
+    use crate::engine::state::tick::{GameWorld, ManualTimeSource, TickSubsystems};
+    use crate::engine::state::traits::{NoOp, ObjectTick};
+    use crate::engine::trace::capture_frame;
+    use crate::engine::units::person_state::PersonState;
+    use crate::engine::units::UnitCoordinator;
+    use std::time::{Duration, Instant};
+
+    fn sessions(count: u8) -> Vec<Lockstep<UdpTransport>> {
+        loopback(count as usize).unwrap().into_iter().enumerate()
+            .map(|(i, t)| Lockstep::new(t, i as u8, count))
+            .collect()
+    }
+
+    /// Advance until `done` holds, polling every session in turn like
+    /// separate machines would.
+    fn pump<T: Transport>(peers: &mut [Lockstep<T>], mut done: impl FnMut(&mut [Lockstep<T>]) -> bool) {
+        let start = Instant::now();
+        while !done(peers) {
+            assert!(start.elapsed() < Duration::from_secs(10), "peers stuck at {:?}",
+                peers.iter().map(|p| p.tick()).collect::<Vec<_>>());
+            std::thread::sleep(Duration::from_micros(200));
+        }
+    }
+
+    #[test]
+    fn every_peer_applies_the_same_commands_on_the_same_tick() {
+        let mut peers = sessions(3);
+        peers[2].queue(GameCommand::SelectUnit(4));
+        peers[0].queue(GameCommand::OrderMove { x: 100.0, z: 200.0 });
+        assert!(!peers[1].queue(GameCommand::ToggleHud));
+
+        let mut log: Vec<Vec<(u32, u8, String)>> = vec![Vec::new(); 3];
+        pump(&mut peers, |peers| {
+            for (i, peer) in peers.iter_mut().enumerate() {
+                if peer.tick() < 10 && peer.advance() {
+                    let tick = peer.tick() - 1;
+                    log[i].extend(peer.take_ready().into_iter().map(|a| (tick, a.player, format!("{:?}", a.command))));
+                }
+            }
+            peers.iter().all(|p| p.tick() == 10)
+        });
+
+        // Issued before tick 0 → scheduled for tick 0 + input delay, player 0 first
+        let expected = vec![
+            (DEFAULT_INPUT_DELAY, 0, "OrderMove { x: 100.0, z: 200.0 }".to_string()),
+            (DEFAULT_INPUT_DELAY, 2, "SelectUnit(4)".to_string()),
+        ];
+        assert!(log.iter().all(|l| *l == expected), "{:?}", log);
+    }
+
+    #[test]
+    fn waits_for_a_silent_peer_then_catches_up() {
+        let mut peers = sessions(2);
+        // Only player 0 runs: the delay ticks need nobody, then it blocks
+        for _ in 0..DEFAULT_INPUT_DELAY {
+            assert!(peers[0].advance());
+        }
+        for _ in 0..50 {
+            assert!(!peers[0].advance());
+        }
+        assert_eq!(peers[0].tick(), DEFAULT_INPUT_DELAY);
+
+        // Player 1 shows up: both get going again
+        pump(&mut peers, |peers| {
+            for peer in peers.iter_mut() {
+                if peer.tick() < 8 {
+                    peer.advance();
+                }
+            }
+            peers.iter().all(|p| p.tick() == 8)
+        });
+
+        // Player 1 leaves: player 0 no longer waits for it
+        peers[1].leave();
+        pump(&mut peers[..1], |peers| peers[0].advance() && peers[0].tick() > 20);
+    }
+
+    #[test]
+    fn leave_is_resent_until_acknowledged() {
+        let mut peers = sessions(2);
+        peers[1].queue(GameCommand::SelectUnit(4));
+        // Player 1 gets the seed and schedules its first batches, then leaves
+        pump(&mut peers, |peers| {
+            peers[0].receive();
+            peers[1].advance()
+        });
+        peers[1].leave();
+        assert!(!peers[1].finish_leaving());
+        // Everything player 1 sent so far is lost
+        while peers[0].transport.recv().is_some() {}
+
+        let until = DEFAULT_INPUT_DELAY + 1;
+        let mut log = Vec::new();
+        pump(&mut peers, |peers| {
+            if peers[0].advance() {
+                let tick = peers[0].tick() - 1;
+                log.extend(peers[0].take_ready().into_iter().map(|a| (tick, a.player)));
+            }
+            let done = peers[1].finish_leaving();
+            done && peers[0].tick() > until + 10
+        });
+        // Its last batches still count; nothing after them is waited for
+        assert_eq!(log, vec![(DEFAULT_INPUT_DELAY, 1)]);
+    }
+
+    struct Peer {
+        world: GameWorld,
+        coord: UnitCoordinator,
+        net: Lockstep<UdpTransport>,
+        time: ManualTimeSource,
+    }
+
+    /// Load a level of two braves for tribe 0 and two for tribe 1 the way
+    /// the game does, into a coordinator that may have played before.
+    fn load_squads(coord: &mut UnitCoordinator, seed: u32) {
+        let raws: Vec<_> = [0x2100u16, 0x2300, 0x6100, 0x6300].into_iter().enumerate()
+            .map(|(i, x)| UnitRaw::for_test(ModelType::Person, 2, (i / 2) as u8, x, 0x2100, &[]))
+            .collect();
+        coord.load_level(seed, &raws, &[[100u16; 128]; 128], 128);
+    }
+
+    /// Peers proposing different level seeds; like the game, each waits for
+    /// the host's before loading the level.
+    fn multiplayer_peers(sessions: Vec<Lockstep<UdpTransport>>) -> Vec<Peer> {
+        let mut nets: Vec<_> = sessions.into_iter()
+            .map(|net| { let seed = 0x100 + net.player() as u32; net.with_level_seed(seed) })
+            .collect();
+        pump(&mut nets, |nets| nets.iter_mut().map(|net| net.poll_level_seed().unwrap()).filter(Option::is_none).count() == 0);
+        nets.into_iter().map(|net| {
+            let mut world = GameWorld::new(20);
+            world.state = GameState::InGame;
+            world.flags.set_multiplayer(true);
+            world.player_tribe = net.player();
+            let mut coord = UnitCoordinator::new();
+            load_squads(&mut coord, net.level_seed().unwrap());
+            Peer { world, coord, net, time: ManualTimeSource::new() }
+        }).collect()
+    }
+
+    /// One render frame; a peer whose input history has arrived resets
+    /// the way the game does (rewind, reload its coordinator with the
+    /// level seed) and replays it first.
+    fn frame(peer: &mut Peer) -> u32 {
+        let (mut a, mut c, mut e, mut f, mut g, mut h, mut i, mut j, mut k) =
+            (NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp);
+        if peer.net.rebuild_ready() {
+            peer.world.rewind();
+            load_squads(&mut peer.coord, peer.net.level_seed().unwrap());
+            let ticks = peer.net.begin_rebuild();
+            let mut subs = TickSubsystems {
+                terrain: &mut a, objects: &mut peer.coord, water: &mut c,
+                network: &mut peer.net, actions: &mut e, game_time: &mut f,
+                single_player: &mut g, tutorial: &mut h, ai: &mut i,
+                population: &mut j, mana: &mut k,
+            };
+            assert_eq!(peer.world.fast_forward(ticks, &mut subs), ticks);
+        }
+        let mut subs = TickSubsystems {
+            terrain: &mut a, objects: &mut peer.coord, water: &mut c,
+            network: &mut peer.net, actions: &mut e, game_time: &mut f,
+            single_player: &mut g, tutorial: &mut h, ai: &mut i,
+            population: &mut j, mana: &mut k,
+        };
+        peer.time.advance(50);
+        peer.world.simulation_tick(&peer.time, &mut subs)
+    }
+
+    fn run_to(peers: &mut [Peer], tick: u32) {
+        let start = Instant::now();
+        while peers.iter().any(|p| p.world.game_tick < tick) {
+            assert!(start.elapsed() < Duration::from_secs(10), "peers stuck at {:?}",
+                peers.iter().map(|p| p.world.game_tick).collect::<Vec<_>>());
+            for peer in peers.iter_mut() {
+                if peer.world.game_tick < tick {
+                    frame(peer);
+                }
+            }
+            std::thread::sleep(Duration::from_micros(200));
+        }
+    }
+
+    /// Both players order their own units.
+    fn queue_orders(peers: &mut [Peer]) {
+        peers[0].net.queue(GameCommand::SelectMultiple(vec![0, 1, 2]));
+        peers[0].net.queue(GameCommand::OrderMove { x: 0x3100 as f32, z: 0x2900 as f32 });
+        peers[1].net.queue(GameCommand::SelectMultiple(vec![3]));
+        peers[1].net.queue(GameCommand::OrderMove { x: 0x6100 as f32, z: 0x3900 as f32 });
+    }
+
+    #[test]
+    fn refuses_a_host_with_other_rules() {
+        let mut nets = sessions(2);
+        let guest = nets.pop().unwrap().with_rules(GameRules { pathfinding: PathfindingMode::Enhanced, avoidance: false });
+        nets.push(guest);
+        let start = Instant::now();
+        let reason = loop {
+            assert!(start.elapsed() < Duration::from_secs(10), "guest never heard from the host");
+            assert!(nets[0].poll_level_seed().unwrap().is_some());
+            match nets[1].poll_level_seed() {
+                Ok(seed) => assert_eq!(seed, None, "took the seed of a host with other rules"),
+                Err(reason) => break reason,
+            }
+            std::thread::sleep(Duration::from_micros(200));
+        };
+        assert!(reason.contains("Enhanced"), "{}", reason);
+        assert!(!nets[1].advance());
+    }
+
+    #[test]
+    fn multiplayer_worlds_stay_identical() {
+        let mut peers = multiplayer_peers(sessions(2));
+        // Player 1 proposed its own seed, but levels start from the host's
+        assert!(peers.iter().all(|p| p.net.level_seed() == Some(0x100)));
+        assert_eq!(peers[1].coord.rng.seed(), peers[0].coord.rng.seed());
+
+        // Player 0 also tries to move player 1's unit 2
+        queue_orders(&mut peers);
+
+        // Player 1 is slow to start: player 0 stalls rather than runs ahead
+        for _ in 0..10 {
+            frame(&mut peers[0]);
+        }
+        assert_eq!(peers[0].world.game_tick, DEFAULT_INPUT_DELAY);
+        assert!(peers[0].world.flags.is_net_waiting());
+
+        run_to(&mut peers, 100);
+        let states: Vec<_> = peers.iter().map(|p| format!("{:?}", capture_frame(&p.coord, 100))).collect();
+        assert_eq!(states[0], states[1]);
+        let units = &peers[0].coord.units;
+        assert!(units[0].movement.position != WorldCoord::new(0x2100, 0x2100), "player 0's order not applied");
+        assert!(units[3].movement.position != WorldCoord::new(0x6300, 0x2100), "player 1's order not applied");
+        // Unit 2 belongs to player 1's tribe: player 0's order skipped it
+        assert_eq!(units[2].state, PersonState::Idle);
+    }
+
+    #[test]
+    fn detects_a_desync_and_rebuilds_from_the_host() {
+        let nets = sessions(2).into_iter()
+            .map(|net| net.with_sync_interval(8).with_rebuild(true))
+            .collect();
+        let mut peers = multiplayer_peers(nets);
+        queue_orders(&mut peers);
+        run_to(&mut peers, 20);
+        assert!(peers.iter().all(|p| p.net.desync_reports().is_empty()));
+
+        // Something outside the inputs moves player 1's copy of the idle
+        // unit 2, which nothing would move back, and its tribe data
+        let nudged = &mut peers[1].coord.units[2].movement.position;
+        nudged.x = nudged.x.wrapping_add(0x40);
+        peers[1].world.tribes.tribes[1].mana += 500;
+        run_to(&mut peers, 100);
+
+        for (peer, other) in [(0, 1), (1, 0)] {
+            let reports = peers[peer].net.desync_reports();
+            assert_eq!(reports.len(), 1, "{:?}", reports);
+            assert_eq!(reports[0].player, other);
+            assert_eq!(reports[0].categories, vec![SyncCategory::Players, SyncCategory::PlayerThings]);
+            assert!(reports[0].tick > 20 && reports[0].last_good_tick.is_some_and(|t| t < 20), "{}", reports[0]);
+        }
+        // Player 1 reset to the level start, replayed the host's inputs
+        // and is back in step: units, both RNGs and tribes
+        assert!(peers.iter().all(|p| !p.world.flags.is_desynced()));
+        let states: Vec<_> = peers.iter().map(|p| format!("{:?}", capture_frame(&p.coord, 100))).collect();
+        assert_eq!(states[0], states[1]);
+        let sums: Vec<_> = peers.iter().map(|p| {
+            let mut sums = StateChecksums::new(100);
+            p.coord.add_checksums(&mut sums);
+            sums
+        }).collect();
+        assert!(sums[0].differences(&sums[1]).is_empty(), "{:?}", sums[0].differences(&sums[1]));
+        assert_eq!(format!("{:?}", peers[1].world.tribes), format!("{:?}", peers[0].world.tribes));
+    }
+}
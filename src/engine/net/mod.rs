@@ -0,0 +1,53 @@
+// Multiplayer — deterministic lockstep over UDP.
+//
+// Peers exchange only their players' commands, one batch per tick, and
+// every peer runs the same ticks on the same commands (the original's
+// model, see docs/specs/level_save_network.md). Lockstep plugs into the
+// tick loop as its NetworkTick; GameWorld runs the multiplayer branch when
+// FLAG_MULTIPLAYER is set.
+
+pub mod packet;
+pub mod transport;
+pub mod lockstep;
+pub mod sync;
+
+pub use packet::{Packet, PlayerAction};
+pub use transport::{Transport, UdpTransport, loopback};
+pub use lockstep::{Lockstep, DEFAULT_INPUT_DELAY, DEFAULT_LEVEL_SEED};
+pub use sync::{StateChecksums, SyncCategory, DesyncReport, DEFAULT_SYNC_INTERVAL};
+
+use std::net::SocketAddr;
+
+/// Who we are in a multiplayer game: every peer gets the same address list
+/// (one per player, in player order) and its own index into it.
+#[derive(Debug, Clone)]
+pub struct NetConfig {
+    pub player: u8,
+    pub peers: Vec<SocketAddr>,
+    /// Rebuild from the host's input history after a desync.
+    pub rebuild: bool,
+    /// Each player's team, in player order: players on the same team are
+    /// allied (TribeArray::set_teams). Empty for every player for themselves.
+    pub teams: Vec<u8>,
+}
+
+impl NetConfig {
+    /// Bind our own address and start a session with the others.
+    pub fn connect(&self) -> Result<Lockstep<UdpTransport>, String> {
+        let players = u8::try_from(self.peers.len()).ok()
+            .filter(|&n| (2..=crate::engine::state::constants::MAX_TRIBES as u8).contains(&n))
+            .ok_or_else(|| format!("{} players: need 2 to 4", self.peers.len()))?;
+        if !self.teams.is_empty() && self.teams.len() != self.peers.len() {
+            return Err(format!("{} teams for {} players", self.teams.len(), players));
+        }
+        let own = *self.peers.get(self.player as usize)
+            .ok_or_else(|| format!("player {} of {}", self.player, players))?;
+        let mut transport = UdpTransport::bind(own)?;
+        for (i, &addr) in self.peers.iter().enumerate() {
+            if i != self.player as usize {
+                transport.add_peer(addr);
+            }
+        }
+        Ok(Lockstep::new(transport, self.player, players).with_rebuild(self.rebuild))
+    }
+}
@@ -0,0 +1,396 @@
+// Lockstep wire format.
+//
+// Only inputs cross the network: each player sends the commands it issued
+// for a tick and every peer runs the same ticks on the same inputs. Four
+// messages reuse the type number of the original's message with the same
+// purpose (docs/specs/level_save_network.md, "Network Protocol"), not its
+// layout; the original spreads RLE-compressed actions over 0x01-0x05:
+//
+//   0x01  actions    [type][player][tick u32][count u8] then `count` commands
+//   0x06  state sync [type][player][tick u32][checksum u32 * 6]
+//   0x07  tick ack   [type][player][tick u32] — also the heartbeat sent
+//                    while waiting for other players
+//   0x0A  leave      [type][player][until u32] — the first tick the leaving
+//                    player has no batch for
+//
+// The rest are ours, numbered past the original's:
+//
+//   0x10  history request [type][player]
+//   0x11  input history   [type][player][upto u32][part u16][parts u16]
+//                         [count u16] then `count` x [tick u32][player u8][command]
+//   0x12  level seed      [type][player][seed u32][rules u8]
+//   0x13  pause           [type][player][paused u8]
+//
+// The rules byte carries the GameRules every peer must share: bit 0 =
+// enhanced pathfinding, bit 1 = collision avoidance.
+//
+// The original's action packet carries a u16 sequence and u16 game tick;
+// ours keys batches by a u32 tick alone, which also serves as the sequence.
+// All integers are little-endian.
+//
+// Commands inside an actions packet are [tag] + payload:
+//
+//   0x01  SelectUnit      [id u16]
+//   0x02  SelectMultiple  [count u16][id u16]*
+//   0x03  ClearSelection
+//   0x04  OrderMove       [x f32][z f32]
+
+use crate::engine::command::GameCommand;
+use crate::engine::movement::PathfindingMode;
+use crate::engine::state::tick::GameRules;
+
+use super::sync::StateChecksums;
+
+pub const MSG_ACTIONS: u8 = 0x01;
+pub const MSG_STATE_SYNC: u8 = 0x06;
+pub const MSG_TICK_ACK: u8 = 0x07;
+pub const MSG_LEAVE: u8 = 0x0A;
+pub const MSG_HISTORY_REQUEST: u8 = 0x10;
+pub const MSG_HISTORY: u8 = 0x11;
+pub const MSG_LEVEL_SEED: u8 = 0x12;
+pub const MSG_PAUSE: u8 = 0x13;
+
+const RULE_ENHANCED_PATHFINDING: u8 = 0x01;
+const RULE_AVOIDANCE: u8 = 0x02;
+
+const CMD_SELECT_UNIT: u8 = 0x01;
+const CMD_SELECT_MULTIPLE: u8 = 0x02;
+const CMD_CLEAR_SELECTION: u8 = 0x03;
+const CMD_ORDER_MOVE: u8 = 0x04;
+
+/// Largest datagram we send or accept.
+pub const MAX_PACKET: usize = 1400;
+
+/// A command issued by one player, as applied by every peer.
+#[derive(Debug, Clone)]
+pub struct PlayerAction {
+    /// Issuing player (= tribe index).
+    pub player: u8,
+    pub command: GameCommand,
+}
+
+#[derive(Debug, Clone)]
+pub enum Packet {
+    /// Commands `player` issued for `tick` (possibly none).
+    Actions { player: u8, tick: u32, commands: Vec<GameCommand> },
+    /// `player` has run every tick up to and including `tick`.
+    TickAck { player: u8, tick: u32 },
+    /// `player` left the game; its batches cover the ticks before `until`.
+    Leave { player: u8, until: u32 },
+    /// `player` paused or resumed.
+    Pause { player: u8, paused: bool },
+    /// `player`'s state checksums after `checksums.tick` ticks.
+    StateSync { player: u8, checksums: StateChecksums },
+    /// `player` wants the host's input history to rebuild its state.
+    HistoryRequest { player: u8 },
+    /// Part of the host's input history: every action run before tick `upto`.
+    History { player: u8, upto: u32, part: u16, parts: u16, actions: Vec<(u32, PlayerAction)> },
+    /// The seed `player` would start levels from and the rules it plays
+    /// with. Players without the host's seed send theirs to announce
+    /// themselves; the host answers with its own, which every level of the
+    /// session starts from.
+    LevelSeed { player: u8, seed: u32, rules: GameRules },
+}
+
+impl Packet {
+    pub fn player(&self) -> u8 {
+        match self {
+            Packet::Actions { player, .. } | Packet::TickAck { player, .. }
+            | Packet::Leave { player, .. } | Packet::Pause { player, .. }
+            | Packet::StateSync { player, .. } | Packet::HistoryRequest { player }
+            | Packet::History { player, .. } | Packet::LevelSeed { player, .. } => *player,
+        }
+    }
+
+    /// Errors on local-only commands and on batches too large for one datagram.
+    pub fn encode(&self) -> Result<Vec<u8>, String> {
+        let mut out = Vec::new();
+        match self {
+            Packet::Actions { player, tick, commands } => {
+                if commands.len() > u8::MAX as usize {
+                    return Err(format!("{} commands in one tick", commands.len()));
+                }
+                out.extend([MSG_ACTIONS, *player]);
+                out.extend(tick.to_le_bytes());
+                out.push(commands.len() as u8);
+                for command in commands {
+                    encode_command(command, &mut out)?;
+                }
+            }
+            Packet::TickAck { player, tick } => {
+                out.extend([MSG_TICK_ACK, *player]);
+                out.extend(tick.to_le_bytes());
+            }
+            Packet::Leave { player, until } => {
+                out.extend([MSG_LEAVE, *player]);
+                out.extend(until.to_le_bytes());
+            }
+            Packet::Pause { player, paused } => out.extend([MSG_PAUSE, *player, *paused as u8]),
+            Packet::StateSync { player, checksums } => {
+                out.extend([MSG_STATE_SYNC, *player]);
+                out.extend(checksums.tick.to_le_bytes());
+                for sum in checksums.sums {
+                    out.extend(sum.to_le_bytes());
+                }
+            }
+            Packet::HistoryRequest { player } => out.extend([MSG_HISTORY_REQUEST, *player]),
+            Packet::History { player, upto, part, parts, actions } => {
+                let count = u16::try_from(actions.len()).map_err(|_| format!("{} actions in one part", actions.len()))?;
+                out.extend([MSG_HISTORY, *player]);
+                out.extend(upto.to_le_bytes());
+                out.extend(part.to_le_bytes());
+                out.extend(parts.to_le_bytes());
+                out.extend(count.to_le_bytes());
+                for (tick, action) in actions {
+                    out.extend(tick.to_le_bytes());
+                    out.push(action.player);
+                    encode_command(&action.command, &mut out)?;
+                }
+            }
+            Packet::LevelSeed { player, seed, rules } => {
+                out.extend([MSG_LEVEL_SEED, *player]);
+                out.extend(seed.to_le_bytes());
+                out.push(encode_rules(rules));
+            }
+        }
+        if out.len() > MAX_PACKET {
+            return Err(format!("packet of {} bytes exceeds {}", out.len(), MAX_PACKET));
+        }
+        Ok(out)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Packet, String> {
+        let mut r = Reader { bytes, pos: 0 };
+        let kind = r.u8()?;
+        let player = r.u8()?;
+        let packet = match kind {
+            MSG_ACTIONS => {
+                let tick = r.u32()?;
+                let count = r.u8()?;
+                let commands = (0..count).map(|_| decode_command(&mut r)).collect::<Result<_, _>>()?;
+                Packet::Actions { player, tick, commands }
+            }
+            MSG_TICK_ACK => Packet::TickAck { player, tick: r.u32()? },
+            MSG_LEAVE => Packet::Leave { player, until: r.u32()? },
+            MSG_PAUSE => Packet::Pause { player, paused: r.u8()? != 0 },
+            MSG_STATE_SYNC => {
+                let mut checksums = StateChecksums::new(r.u32()?);
+                for sum in &mut checksums.sums {
+                    *sum = r.u32()?;
+                }
+                Packet::StateSync { player, checksums }
+            }
+            MSG_HISTORY_REQUEST => Packet::HistoryRequest { player },
+            MSG_HISTORY => {
+                let (upto, part, parts, count) = (r.u32()?, r.u16()?, r.u16()?, r.u16()?);
+                let actions = (0..count).map(|_| {
+                    let tick = r.u32()?;
+                    let player = r.u8()?;
+                    Ok((tick, PlayerAction { player, command: decode_command(&mut r)? }))
+                }).collect::<Result<_, String>>()?;
+                Packet::History { player, upto, part, parts, actions }
+            }
+            MSG_LEVEL_SEED => Packet::LevelSeed { player, seed: r.u32()?, rules: decode_rules(r.u8()?)? },
+            _ => return Err(format!("unknown message type 0x{:02X}", kind)),
+        };
+        if r.pos != bytes.len() {
+            return Err(format!("{} trailing bytes", bytes.len() - r.pos));
+        }
+        Ok(packet)
+    }
+}
+
+/// The host's input history as History packets, each within
+/// MAX_PACKET.
+pub fn history_packets(player: u8, upto: u32, actions: &[(u32, PlayerAction)]) -> Result<Vec<Packet>, String> {
+    const HEADER: usize = 12;
+    let mut parts: Vec<Vec<(u32, PlayerAction)>> = vec![Vec::new()];
+    let mut size = HEADER;
+    for entry in actions {
+        let mut bytes = Vec::new();
+        encode_command(&entry.1.command, &mut bytes)?;
+        let entry_size = 5 + bytes.len();
+        if HEADER + entry_size > MAX_PACKET {
+            return Err(format!("{:?} doesn't fit a packet", entry.1.command));
+        }
+        if size + entry_size > MAX_PACKET {
+            parts.push(Vec::new());
+            size = HEADER;
+        }
+        size += entry_size;
+        parts.last_mut().unwrap().push(entry.clone());
+    }
+    let count = u16::try_from(parts.len()).map_err(|_| "input history too long".to_string())?;
+    Ok(parts.into_iter().enumerate()
+        .map(|(i, actions)| Packet::History { player, upto, part: i as u16, parts: count, actions })
+        .collect())
+}
+
+fn encode_rules(rules: &GameRules) -> u8 {
+    let mut bits = 0;
+    if rules.pathfinding == PathfindingMode::Enhanced {
+        bits |= RULE_ENHANCED_PATHFINDING;
+    }
+    if rules.avoidance {
+        bits |= RULE_AVOIDANCE;
+    }
+    bits
+}
+
+fn decode_rules(bits: u8) -> Result<GameRules, String> {
+    if bits & !(RULE_ENHANCED_PATHFINDING | RULE_AVOIDANCE) != 0 {
+        return Err(format!("unknown rules 0x{:02X}", bits));
+    }
+    Ok(GameRules {
+        pathfinding: if bits & RULE_ENHANCED_PATHFINDING != 0 { PathfindingMode::Enhanced } else { PathfindingMode::Original },
+        avoidance: bits & RULE_AVOIDANCE != 0,
+    })
+}
+
+fn unit_id(id: usize) -> Result<[u8; 2], String> {
+    u16::try_from(id).map(u16::to_le_bytes).map_err(|_| format!("unit id {} out of range", id))
+}
+
+fn encode_command(command: &GameCommand, out: &mut Vec<u8>) -> Result<(), String> {
+    match command {
+        GameCommand::SelectUnit(id) => {
+            out.push(CMD_SELECT_UNIT);
+            out.extend(unit_id(*id)?);
+        }
+        GameCommand::SelectMultiple(ids) => {
+            let count = u16::try_from(ids.len()).map_err(|_| format!("{} units selected", ids.len()))?;
+            out.push(CMD_SELECT_MULTIPLE);
+            out.extend(count.to_le_bytes());
+            for &id in ids {
+                out.extend(unit_id(id)?);
+            }
+        }
+        GameCommand::ClearSelection => out.push(CMD_CLEAR_SELECTION),
+        GameCommand::OrderMove { x, z } => {
+            out.push(CMD_ORDER_MOVE);
+            out.extend(x.to_le_bytes());
+            out.extend(z.to_le_bytes());
+        }
+        other => return Err(format!("{:?} is not a networked command", other)),
+    }
+    Ok(())
+}
+
+fn decode_command(r: &mut Reader) -> Result<GameCommand, String> {
+    let tag = r.u8()?;
+    Ok(match tag {
+        CMD_SELECT_UNIT => GameCommand::SelectUnit(r.u16()? as usize),
+        CMD_SELECT_MULTIPLE => {
+            let count = r.u16()?;
+            GameCommand::SelectMultiple((0..count).map(|_| r.u16().map(|id| id as usize)).collect::<Result<_, _>>()?)
+        }
+        CMD_CLEAR_SELECTION => GameCommand::ClearSelection,
+        CMD_ORDER_MOVE => GameCommand::OrderMove { x: r.f32()?, z: r.f32()? },
+        _ => return Err(format!("unknown command tag 0x{:02X}", tag)),
+    })
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let end = self.pos + N;
+        let slice = self.bytes.get(self.pos..end).ok_or("truncated packet")?;
+        self.pos = end;
+        Ok(slice.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        self.take().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    fn f32(&mut self) -> Result<f32, String> {
+        self.take().map(f32::from_le_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actions_round_trip() {
+        let packet = Packet::Actions {
+            player: 2,
+            tick: 0x01020304,
+            commands: vec![
+                GameCommand::SelectMultiple(vec![3, 700, 65535]),
+                GameCommand::OrderMove { x: 1234.5, z: -77.0 },
+                GameCommand::SelectUnit(9),
+                GameCommand::ClearSelection,
+            ],
+        };
+        let bytes = packet.encode().unwrap();
+        assert_eq!(&bytes[..7], &[MSG_ACTIONS, 2, 4, 3, 2, 1, 4]);
+        let Packet::Actions { player, tick, commands } = Packet::decode(&bytes).unwrap() else { panic!() };
+        assert_eq!((player, tick), (2, 0x01020304));
+        assert_eq!(format!("{:?}", commands), format!("{:?}", match packet {
+            Packet::Actions { commands, .. } => commands,
+            _ => unreachable!(),
+        }));
+    }
+
+    #[test]
+    fn control_messages_round_trip() {
+        for packet in [
+            Packet::TickAck { player: 1, tick: 99 },
+            Packet::Leave { player: 3, until: 120 },
+            Packet::Pause { player: 0, paused: true },
+            Packet::StateSync { player: 2, checksums: StateChecksums { tick: 32, sums: [1, 2, 3, 4, 5, 0xFFFF_FFFF] } },
+            Packet::HistoryRequest { player: 1 },
+            Packet::LevelSeed { player: 0, seed: 0xDEAD_BEEF, rules: GameRules::default() },
+            Packet::LevelSeed { player: 3, seed: 7, rules: GameRules { pathfinding: PathfindingMode::Enhanced, avoidance: true } },
+        ] {
+            let bytes = packet.encode().unwrap();
+            assert_eq!(format!("{:?}", Packet::decode(&bytes).unwrap()), format!("{:?}", packet));
+        }
+    }
+
+    #[test]
+    fn history_splits_into_packets_that_fit() {
+        let order = |tick: u32| (tick, PlayerAction { player: (tick % 4) as u8, command: GameCommand::SelectMultiple((0..100).collect()) });
+        let history: Vec<_> = (0..20).map(order).collect();
+        let packets = history_packets(0, 20, &history).unwrap();
+        assert!(packets.len() > 1);
+        let mut rebuilt = Vec::new();
+        for (i, packet) in packets.iter().enumerate() {
+            let bytes = packet.encode().unwrap();
+            assert!(bytes.len() <= MAX_PACKET);
+            let Packet::History { upto, part, parts, actions, .. } = Packet::decode(&bytes).unwrap() else { panic!() };
+            assert_eq!((upto, part as usize, parts as usize), (20, i, packets.len()));
+            rebuilt.extend(actions);
+        }
+        assert_eq!(format!("{:?}", rebuilt), format!("{:?}", history));
+        // An empty history is still one (empty) part
+        assert_eq!(history_packets(0, 5, &[]).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn rejects_local_commands_and_bad_input() {
+        let camera = Packet::Actions { player: 0, tick: 0, commands: vec![GameCommand::ResetCamera] };
+        assert!(camera.encode().is_err());
+        let big = Packet::Actions { player: 0, tick: 0, commands: vec![GameCommand::SelectUnit(70_000)] };
+        assert!(big.encode().is_err());
+
+        let bytes = Packet::TickAck { player: 1, tick: 5 }.encode().unwrap();
+        assert!(Packet::decode(&bytes[..4]).is_err());
+        assert!(Packet::decode(&[bytes.as_slice(), &[0]].concat()).is_err());
+        assert!(Packet::decode(&[0x42, 0]).is_err());
+    }
+}
@@ -0,0 +1,148 @@
+// State checksums for desync detection.
+//
+// Every few ticks each peer folds its simulation state into one checksum
+// per category and sends them (0x06). Categories follow the original's
+// sync log (Network_WriteSyncLog @ 0x004e5ad0), so a mismatch says which
+// part of the state went wrong, not just that something did:
+//
+//   0  random seed        state machine and formation RNGs
+//   1  players            tribes, alliances, pillars, stone head worship
+//   2  counts             persons alive, vehicles
+//   3  player things      persons owned by a tribe
+//   4  wild things        unowned persons
+//   5  vehicles           boats and balloons, passengers included
+//
+// The original's creature and map segment categories have no counterpart
+// here yet: creatures aren't simulated and the map doesn't change in play.
+// Checksums are FNV-1a, so identical states give identical sums on every
+// platform.
+
+use std::fmt;
+
+use crate::engine::movement::WorldCoord;
+
+/// Ticks between checksum exchanges.
+pub const DEFAULT_SYNC_INTERVAL: u32 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncCategory {
+    RandomSeed,
+    Players,
+    Counts,
+    PlayerThings,
+    WildThings,
+    Vehicles,
+}
+
+impl SyncCategory {
+    pub const COUNT: usize = 6;
+
+    pub const ALL: [SyncCategory; Self::COUNT] = [
+        SyncCategory::RandomSeed, SyncCategory::Players, SyncCategory::Counts,
+        SyncCategory::PlayerThings, SyncCategory::WildThings, SyncCategory::Vehicles,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SyncCategory::RandomSeed => "random seed",
+            SyncCategory::Players => "players",
+            SyncCategory::Counts => "counts",
+            SyncCategory::PlayerThings => "player things",
+            SyncCategory::WildThings => "wild things",
+            SyncCategory::Vehicles => "vehicles",
+        }
+    }
+}
+
+const FNV_OFFSET: u32 = 0x811C_9DC5;
+const FNV_PRIME: u32 = 0x0100_0193;
+
+/// One peer's checksums after running `tick` ticks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateChecksums {
+    pub tick: u32,
+    pub sums: [u32; SyncCategory::COUNT],
+}
+
+impl StateChecksums {
+    pub fn new(tick: u32) -> Self {
+        Self { tick, sums: [FNV_OFFSET; SyncCategory::COUNT] }
+    }
+
+    pub fn mix_bytes(&mut self, category: SyncCategory, bytes: &[u8]) {
+        let sum = &mut self.sums[category as usize];
+        for &b in bytes {
+            *sum = (*sum ^ b as u32).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    pub fn mix(&mut self, category: SyncCategory, value: u32) {
+        self.mix_bytes(category, &value.to_le_bytes());
+    }
+
+    pub fn mix_coord(&mut self, category: SyncCategory, coord: WorldCoord) {
+        self.mix(category, (coord.x as u16 as u32) | (coord.z as u16 as u32) << 16);
+    }
+
+    /// Categories whose checksums differ from `other`'s.
+    pub fn differences(&self, other: &StateChecksums) -> Vec<SyncCategory> {
+        SyncCategory::ALL.into_iter().filter(|&c| self.sums[c as usize] != other.sums[c as usize]).collect()
+    }
+}
+
+/// A peer's checksums disagreed with ours.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DesyncReport {
+    /// First sync tick that disagreed.
+    pub tick: u32,
+    /// Latest earlier sync tick on which this peer agreed with us.
+    pub last_good_tick: Option<u32>,
+    pub player: u8,
+    pub categories: Vec<SyncCategory>,
+}
+
+impl fmt::Display for DesyncReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<_> = self.categories.iter().map(|c| c.name()).collect();
+        write!(f, "desync with player {} at tick {}: {} differ", self.player, self.tick, names.join(", "))?;
+        match self.last_good_tick {
+            Some(t) => write!(f, " (in sync at tick {})", t),
+            None => write!(f, " (never compared in sync)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_depend_on_content_and_order() {
+        let mut a = StateChecksums::new(16);
+        a.mix(SyncCategory::PlayerThings, 1);
+        a.mix(SyncCategory::PlayerThings, 2);
+        let mut b = StateChecksums::new(16);
+        b.mix(SyncCategory::PlayerThings, 2);
+        b.mix(SyncCategory::PlayerThings, 1);
+        assert_eq!(a.differences(&b), vec![SyncCategory::PlayerThings]);
+
+        let mut c = StateChecksums::new(16);
+        c.mix(SyncCategory::PlayerThings, 1);
+        c.mix(SyncCategory::PlayerThings, 2);
+        assert!(a.differences(&c).is_empty());
+        // FNV-1a of the empty input is the offset basis
+        assert_eq!(StateChecksums::new(0).sums[SyncCategory::Vehicles as usize], 0x811C_9DC5);
+    }
+
+    #[test]
+    fn report_names_the_tick_player_and_categories() {
+        let report = DesyncReport {
+            tick: 48,
+            last_good_tick: Some(32),
+            player: 1,
+            categories: vec![SyncCategory::RandomSeed, SyncCategory::PlayerThings],
+        };
+        assert_eq!(report.to_string(),
+            "desync with player 1 at tick 48: random seed, player things differ (in sync at tick 32)");
+    }
+}
@@ -0,0 +1,121 @@
+// Datagram transport for the lockstep layer.
+//
+// Delivery is unreliable and unordered (UDP): the lockstep session resends
+// what hasn't been acknowledged, so a transport only has to move bytes.
+
+use std::io::ErrorKind;
+use std::net::{SocketAddr, UdpSocket};
+
+use super::packet::MAX_PACKET;
+
+pub trait Transport {
+    /// Send one datagram to every peer.
+    fn broadcast(&mut self, bytes: &[u8]);
+    /// Next datagram received, if any. Never blocks.
+    fn recv(&mut self) -> Option<Vec<u8>>;
+}
+
+/// Non-blocking UDP socket with a fixed peer list.
+pub struct UdpTransport {
+    socket: UdpSocket,
+    peers: Vec<SocketAddr>,
+}
+
+impl UdpTransport {
+    pub fn bind(addr: SocketAddr) -> Result<Self, String> {
+        let socket = UdpSocket::bind(addr).map_err(|e| format!("bind {}: {}", addr, e))?;
+        socket.set_nonblocking(true).map_err(|e| e.to_string())?;
+        Ok(Self { socket, peers: Vec::new() })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.socket.local_addr().expect("bound socket has an address")
+    }
+
+    pub fn add_peer(&mut self, addr: SocketAddr) {
+        if !self.peers.contains(&addr) {
+            self.peers.push(addr);
+        }
+    }
+}
+
+impl Transport for UdpTransport {
+    fn broadcast(&mut self, bytes: &[u8]) {
+        for peer in &self.peers {
+            // A peer that isn't up yet (or has gone) just misses the
+            // datagram; the resend covers the first case
+            if let Err(e) = self.socket.send_to(bytes, peer) {
+                log::debug!("[net] send to {}: {}", peer, e);
+            }
+        }
+    }
+
+    fn recv(&mut self) -> Option<Vec<u8>> {
+        let mut buf = [0u8; MAX_PACKET];
+        loop {
+            match self.socket.recv_from(&mut buf) {
+                Ok((len, from)) if self.peers.contains(&from) => return Some(buf[..len].to_vec()),
+                Ok((_, from)) => log::debug!("[net] ignoring datagram from {}", from),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return None,
+                // ICMP port unreachable from an earlier send surfaces here
+                Err(e) if e.kind() == ErrorKind::ConnectionRefused
+                    || e.kind() == ErrorKind::ConnectionReset => continue,
+                Err(e) => {
+                    log::warn!("[net] receive: {}", e);
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// `count` transports on 127.0.0.1, each peered with all the others — for
+/// running several players in one process.
+pub fn loopback(count: usize) -> Result<Vec<UdpTransport>, String> {
+    let any: SocketAddr = ([127, 0, 0, 1], 0).into();
+    let mut transports = (0..count).map(|_| UdpTransport::bind(any)).collect::<Result<Vec<_>, _>>()?;
+    let addrs: Vec<SocketAddr> = transports.iter().map(UdpTransport::local_addr).collect();
+    for (i, transport) in transports.iter_mut().enumerate() {
+        for (j, &addr) in addrs.iter().enumerate() {
+            if i != j {
+                transport.add_peer(addr);
+            }
+        }
+    }
+    Ok(transports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn recv_within(transport: &mut UdpTransport, timeout: Duration) -> Option<Vec<u8>> {
+        let start = Instant::now();
+        while start.elapsed() < timeout {
+            if let Some(bytes) = transport.recv() {
+                return Some(bytes);
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        None
+    }
+
+    #[test]
+    fn loopback_broadcast_reaches_every_peer() {
+        let mut peers = loopback(3).unwrap();
+        peers[0].broadcast(b"hello");
+        for peer in &mut peers[1..] {
+            assert_eq!(recv_within(peer, Duration::from_secs(2)).as_deref(), Some(&b"hello"[..]));
+        }
+        assert!(peers[0].recv().is_none());
+    }
+
+    #[test]
+    fn ignores_strangers() {
+        let mut peers = loopback(1).unwrap();
+        let stranger = UdpSocket::bind("127.0.0.1:0").unwrap();
+        stranger.send_to(b"spam", peers[0].local_addr()).unwrap();
+        assert!(recv_within(&mut peers[0], Duration::from_millis(100)).is_none());
+    }
+}
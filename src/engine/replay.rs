@@ -0,0 +1,465 @@
+// Replays — a game stored as its level, the unit RNG seed and the commands
+// that changed the simulation, stamped with the tick they were applied on.
+// The simulation is deterministic, so playing the commands back through
+// apply_command on the same level and seed reproduces the game.
+//
+// A replay is JSON Lines, like a trace (trace.rs). The first line is a
+// header, every later line one command:
+//
+//   {"format": "pop3-replay", "version": 1, "level": 1, "rng_seed": 4660, "ticks": 1200,
+//    "pathfinding": "original", "avoidance": false}
+//   {"tick": 0, "select_multiple": [3, 4]}
+//   {"tick": 0, "order_move": [9984.0, 8448.0]}
+//   {"tick": 35, "select_unit": 7}
+//   {"tick": 40, "clear_selection": true}
+//
+// Header `rng_seed` is the unit RNG seed before the level's persons are
+// created and `ticks` the length of the recording. `pathfinding` and
+// `avoidance` are the GameRules the game was played with; playback switches
+// to them. The formation RNG needs no entry: loading a level always resets
+// it. A command's `tick` is the
+// number of ticks that had run when it was applied: playback applies it
+// right before running the next one, so `ticks` always runs past the last
+// command's. Only commands that affect the simulation
+// (GameCommand::is_networked) are recorded; camera and view changes stay
+// with whoever watches.
+//
+// Seeking re-simulates: from a snapshot of the state at some earlier tick,
+// taken every `snapshot_interval` ticks during playback, or from tick 0.
+
+use std::collections::BTreeMap;
+
+use serde_json::{json, Value};
+
+use crate::engine::command::GameCommand;
+use crate::engine::movement::PathfindingMode;
+use crate::engine::state::flags::GameFlags;
+use crate::engine::state::tick::{GameRules, GameWorld};
+use crate::engine::state::tribe::TribeArray;
+use crate::engine::units::UnitCoordinator;
+
+pub const REPLAY_FORMAT: &str = "pop3-replay";
+pub const REPLAY_VERSION: u64 = 1;
+
+/// Ticks between playback snapshots.
+pub const DEFAULT_SNAPSHOT_INTERVAL: u32 = 500;
+
+/// Ticks run per call while seeking, so a long seek doesn't freeze the
+/// window.
+const SEEK_CHUNK: u32 = 200;
+
+/// Ticks run per call at most when playback falls behind the clock.
+const MAX_CATCHUP_TICKS: u32 = 5;
+
+/******************************************************************************/
+
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub level: u8,
+    pub rng_seed: u32,
+    pub rules: GameRules,
+    /// Ticks the recording covers.
+    pub ticks: u32,
+    /// In tick order.
+    pub commands: Vec<(u32, GameCommand)>,
+}
+
+fn uint(value: &Value, key: &str, what: &str) -> Result<u64, String> {
+    value.get(key).ok_or_else(|| format!("{}: missing \"{}\"", what, key))?
+        .as_u64().ok_or_else(|| format!("{}: \"{}\" is not an unsigned integer", what, key))
+}
+
+fn command_to_json(tick: u32, command: &GameCommand) -> Option<Value> {
+    let mut line = json!({ "tick": tick });
+    match command {
+        GameCommand::SelectUnit(id) => line["select_unit"] = json!(id),
+        GameCommand::SelectMultiple(ids) => line["select_multiple"] = json!(ids),
+        GameCommand::ClearSelection => line["clear_selection"] = json!(true),
+        GameCommand::OrderMove { x, z } => line["order_move"] = json!([x, z]),
+        _ => return None,
+    }
+    Some(line)
+}
+
+fn command_from_json(value: &Value, what: &str) -> Result<GameCommand, String> {
+    let id = |v: &Value| v.as_u64().map(|id| id as usize).ok_or_else(|| format!("{}: bad unit id {}", what, v));
+    if let Some(v) = value.get("select_unit") {
+        return Ok(GameCommand::SelectUnit(id(v)?));
+    }
+    if let Some(v) = value.get("select_multiple") {
+        let ids = v.as_array().ok_or_else(|| format!("{}: \"select_multiple\" is not a list", what))?;
+        return Ok(GameCommand::SelectMultiple(ids.iter().map(id).collect::<Result<_, _>>()?));
+    }
+    if value.get("clear_selection").is_some() {
+        return Ok(GameCommand::ClearSelection);
+    }
+    if let Some(v) = value.get("order_move") {
+        return match v.as_array().map(Vec::as_slice) {
+            Some([x, z]) => match (x.as_f64(), z.as_f64()) {
+                (Some(x), Some(z)) => Ok(GameCommand::OrderMove { x: x as f32, z: z as f32 }),
+                _ => Err(format!("{}: \"order_move\" coordinates are not numbers", what)),
+            },
+            _ => Err(format!("{}: \"order_move\" is not an [x, z] pair", what)),
+        };
+    }
+    Err(format!("{}: no command", what))
+}
+
+impl Replay {
+    pub fn new(level: u8, rng_seed: u32, rules: GameRules) -> Self {
+        Self { level, rng_seed, rules, ticks: 0, commands: Vec::new() }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty());
+        let (_, header) = lines.next().ok_or("empty replay")?;
+        let header: Value = serde_json::from_str(header).map_err(|e| format!("line 1: {}", e))?;
+        if header.get("format").and_then(Value::as_str) != Some(REPLAY_FORMAT) {
+            return Err(format!("line 1: not a {} header", REPLAY_FORMAT));
+        }
+        let version = uint(&header, "version", "header")?;
+        if version != REPLAY_VERSION {
+            return Err(format!("unsupported replay version {} (expected {})", version, REPLAY_VERSION));
+        }
+        let pathfinding = header.get("pathfinding").and_then(Value::as_str)
+            .ok_or("header: missing \"pathfinding\"")?;
+        let rules = GameRules {
+            pathfinding: PathfindingMode::from_name(pathfinding)
+                .ok_or_else(|| format!("header: unknown pathfinding \"{}\"", pathfinding))?,
+            avoidance: header.get("avoidance").and_then(Value::as_bool)
+                .ok_or("header: missing \"avoidance\"")?,
+        };
+        let mut replay = Replay::new(uint(&header, "level", "header")? as u8, uint(&header, "rng_seed", "header")? as u32, rules);
+        replay.ticks = uint(&header, "ticks", "header")? as u32;
+        for (n, line) in lines {
+            let what = format!("line {}", n);
+            let value: Value = serde_json::from_str(line).map_err(|e| format!("{}: {}", what, e))?;
+            let tick = uint(&value, "tick", &what)? as u32;
+            if replay.commands.last().is_some_and(|&(last, _)| last > tick) {
+                return Err(format!("{}: tick {} is before the previous command", what, tick));
+            }
+            if tick >= replay.ticks {
+                return Err(format!("{}: tick {} is past the end of the replay ({} ticks)", what, tick, replay.ticks));
+            }
+            replay.commands.push((tick, command_from_json(&value, &what)?));
+        }
+        Ok(replay)
+    }
+
+    pub fn to_jsonl(&self) -> String {
+        let header = json!({
+            "format": REPLAY_FORMAT, "version": REPLAY_VERSION,
+            "level": self.level, "rng_seed": self.rng_seed, "ticks": self.ticks,
+            "pathfinding": self.rules.pathfinding.name(), "avoidance": self.rules.avoidance,
+        });
+        std::iter::once(header)
+            .chain(self.commands.iter().filter_map(|(tick, command)| command_to_json(*tick, command)))
+            .map(|line| line.to_string() + "\n")
+            .collect()
+    }
+
+    /// Commands to apply before running the tick after `tick`, in order.
+    pub fn commands_at(&self, tick: u32) -> impl Iterator<Item = &GameCommand> {
+        let start = self.commands.partition_point(|&(t, _)| t < tick);
+        self.commands[start..].iter().take_while(move |&&(t, _)| t == tick).map(|(_, command)| command)
+    }
+}
+
+/// Collects a replay while the game is played.
+pub struct ReplayRecorder {
+    replay: Replay,
+    start_tick: u32,
+}
+
+impl ReplayRecorder {
+    /// `rng_seed` is the unit RNG seed the level was loaded with, `rules`
+    /// the game's and `start_tick` the world's game tick at that point; the
+    /// replay counts ticks from there.
+    pub fn new(level: u8, rng_seed: u32, rules: GameRules, start_tick: u32) -> Self {
+        Self { replay: Replay::new(level, rng_seed, rules), start_tick }
+    }
+
+    /// Note a command applied at game tick `tick`. Returns false (and skips
+    /// it) for commands that don't affect the simulation.
+    pub fn record(&mut self, tick: u32, command: &GameCommand) -> bool {
+        if !command.is_networked() {
+            return false;
+        }
+        self.replay.commands.push((tick - self.start_tick, command.clone()));
+        true
+    }
+
+    /// The replay up to game tick `tick`, one tick longer if a command
+    /// came in after the last tick ran, so playback still applies it.
+    pub fn finish(&self, tick: u32) -> Replay {
+        let commands_end = self.replay.commands.last().map_or(0, |&(last, _)| last + 1);
+        Replay { ticks: (tick - self.start_tick).max(commands_end), ..self.replay.clone() }
+    }
+}
+
+/******************************************************************************/
+
+/// Simulation state at the start of a tick: the world's counters, flags and
+/// tribes, and every object.
+#[derive(Clone)]
+pub struct SimSnapshot {
+    pub game_tick: u32,
+    pub tick_counter: u32,
+    pub flags: GameFlags,
+    pub tribes: TribeArray,
+    pub coord: UnitCoordinator,
+}
+
+impl SimSnapshot {
+    pub fn capture(world: &GameWorld, coord: &UnitCoordinator) -> Self {
+        Self {
+            game_tick: world.game_tick,
+            tick_counter: world.tick_counter,
+            flags: world.flags,
+            tribes: world.tribes.clone(),
+            coord: coord.clone(),
+        }
+    }
+
+    pub fn restore(&self, world: &mut GameWorld, coord: &mut UnitCoordinator) {
+        world.game_tick = self.game_tick;
+        world.tick_counter = self.tick_counter;
+        world.flags = self.flags;
+        world.tribes = self.tribes.clone();
+        *coord = self.coord.clone();
+    }
+}
+
+/// Playback position and pacing. `S` is whatever the player needs to jump
+/// back to a tick — a SimSnapshot plus any view state.
+pub struct ReplayPlayback<S> {
+    replay: Replay,
+    snapshot_interval: u32,
+    snapshots: BTreeMap<u32, S>,
+    /// Tick being sought; runs ahead of the clock until reached.
+    seek_target: Option<u32>,
+    /// Clock time the last tick was due at.
+    last_tick_time: Option<u64>,
+}
+
+impl<S: Clone> ReplayPlayback<S> {
+    /// `snapshot_interval` 0 keeps only the tick 0 snapshot, so every seek
+    /// back re-simulates from the start.
+    pub fn new(replay: Replay, snapshot_interval: u32) -> Self {
+        Self { replay, snapshot_interval, snapshots: BTreeMap::new(), seek_target: None, last_tick_time: None }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn is_finished(&self, tick: u32) -> bool {
+        tick >= self.replay.ticks
+    }
+
+    pub fn is_seeking(&self) -> bool {
+        self.seek_target.is_some()
+    }
+
+    /// A snapshot should be stored before running the tick after `tick`.
+    pub fn wants_snapshot(&self, tick: u32) -> bool {
+        let due = tick == 0 || (self.snapshot_interval > 0 && tick.is_multiple_of(self.snapshot_interval));
+        due && !self.snapshots.contains_key(&tick)
+    }
+
+    pub fn store_snapshot(&mut self, tick: u32, snapshot: S) {
+        self.snapshots.insert(tick, snapshot);
+    }
+
+    /// Start seeking from `current` to `target` (clamped to the replay).
+    /// Returns the snapshot to restore first, if there is one between where
+    /// we are and the target or the target lies behind us.
+    pub fn seek(&mut self, current: u32, target: u32) -> Option<(u32, S)> {
+        let target = target.min(self.replay.ticks);
+        self.seek_target = Some(target);
+        let (&tick, snapshot) = self.snapshots.range(..=target).next_back()?;
+        (tick > current || target < current).then(|| (tick, snapshot.clone()))
+    }
+
+    /// Ticks to run now, at `current`, with the clock at `now_ms`: toward
+    /// the seek target if seeking, else as many as `speed` ticks per second
+    /// allow. Nothing while not `running` (paused) or at the end.
+    pub fn due_ticks(&mut self, current: u32, now_ms: u64, speed: u32, running: bool) -> u32 {
+        if let Some(target) = self.seek_target {
+            if current < target {
+                return (target - current).min(SEEK_CHUNK);
+            }
+            self.seek_target = None;
+            self.last_tick_time = Some(now_ms);
+        }
+        if !running || self.is_finished(current) {
+            self.last_tick_time = Some(now_ms);
+            return 0;
+        }
+        let interval = 1000 / speed.max(1) as u64;
+        let last = self.last_tick_time.get_or_insert(now_ms);
+        let due = (now_ms.saturating_sub(*last) / interval) as u32;
+        let ticks = due.min(MAX_CATCHUP_TICKS).min(self.replay.ticks - current);
+        // Fell too far behind: drop the backlog rather than race through it
+        *last = if due > MAX_CATCHUP_TICKS { now_ms } else { *last + ticks as u64 * interval };
+        ticks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::movement::WorldCoord;
+    use crate::engine::state::state_machine::GameState;
+    use crate::engine::state::tick::TickSubsystems;
+    use crate::engine::state::traits::NoOp;
+    use crate::engine::trace::capture_frame;
+    use crate::engine::units::Unit;
+
+    #[test]
+    fn replay_round_trips_and_keeps_only_simulation_commands() {
+        let rules = GameRules { pathfinding: PathfindingMode::Enhanced, avoidance: true };
+        let mut recorder = ReplayRecorder::new(3, 0x1234, rules, 500);
+        // Recording started at game tick 500, when the level was loaded
+        assert!(recorder.record(500, &GameCommand::SelectMultiple(vec![3, 4])));
+        assert!(recorder.record(500, &GameCommand::OrderMove { x: 9984.0, z: 8448.5 }));
+        assert!(!recorder.record(510, &GameCommand::RotateCamera { delta_z: 5 }));
+        assert!(recorder.record(535, &GameCommand::SelectUnit(7)));
+        assert!(recorder.record(540, &GameCommand::ClearSelection));
+        let replay = recorder.finish(620);
+
+        let text = replay.to_jsonl();
+        assert_eq!(text.lines().next().unwrap(),
+            r#"{"avoidance":true,"format":"pop3-replay","level":3,"pathfinding":"enhanced","rng_seed":4660,"ticks":120,"version":1}"#);
+        let parsed = Replay::parse(&text).unwrap();
+        assert_eq!(format!("{:?}", parsed), format!("{:?}", replay));
+        assert_eq!(parsed.commands_at(0).count(), 2);
+        assert!(matches!(parsed.commands_at(35).collect::<Vec<_>>()[..], [GameCommand::SelectUnit(7)]));
+        assert_eq!(parsed.commands_at(36).count(), 0);
+
+        let past_end = text.replace(r#""ticks":120"#, r#""ticks":40"#);
+        assert!(Replay::parse(&past_end).unwrap_err().contains("past the end"));
+        let unknown_rules = text.replace(r#""pathfinding":"enhanced""#, r#""pathfinding":"magic""#);
+        assert!(Replay::parse(&unknown_rules).unwrap_err().contains("unknown pathfinding"));
+    }
+
+    #[test]
+    fn a_command_after_the_last_tick_still_plays() {
+        let mut recorder = ReplayRecorder::new(1, 0, GameRules::default(), 100);
+        recorder.record(160, &GameCommand::SelectUnit(2));
+        // Given after tick 160 had run, and the game quit before the next
+        let replay = recorder.finish(160);
+        assert_eq!(replay.ticks, 61);
+
+        let parsed = Replay::parse(&replay.to_jsonl()).unwrap();
+        assert_eq!(parsed.ticks, 61);
+        assert!(matches!(parsed.commands_at(60).collect::<Vec<_>>()[..], [GameCommand::SelectUnit(2)]));
+    }
+
+    #[test]
+    fn playback_follows_the_clock_and_seeks() {
+        let mut replay = Replay::new(1, 0, GameRules::default());
+        replay.ticks = 1000;
+        let mut playback: ReplayPlayback<u32> = ReplayPlayback::new(replay, 100);
+        // 20 ticks/s: one tick per 50 ms, nothing while paused
+        assert_eq!(playback.due_ticks(0, 1000, 20, true), 0);
+        assert_eq!(playback.due_ticks(0, 1120, 20, true), 2);
+        assert_eq!(playback.due_ticks(2, 1500, 20, false), 0);
+        assert_eq!(playback.due_ticks(2, 1550, 20, true), 1);
+
+        for tick in [0, 100, 200] {
+            assert!(playback.wants_snapshot(tick));
+            playback.store_snapshot(tick, tick);
+        }
+        assert!(!playback.wants_snapshot(150));
+        // Back: restore the latest snapshot before the target, then run on
+        assert_eq!(playback.seek(250, 150), Some((100, 100)));
+        assert_eq!(playback.due_ticks(100, 1600, 20, true), 50);
+        // Forward past a snapshot: jump to it; forward short of one: just run
+        assert_eq!(playback.seek(150, 260), Some((200, 200)));
+        assert_eq!(playback.seek(200, 260), None);
+        assert_eq!(playback.due_ticks(200, 1600, 20, false), 60);
+        assert_eq!(playback.due_ticks(260, 1600, 20, false), 0);
+        assert!(!playback.is_seeking());
+    }
+
+    fn person(id: usize, x: i16, z: i16) -> Unit {
+        let position = WorldCoord::new(x, z);
+        Unit { state_timer: 40, ..Unit::new_person(id, 2, 0, position) }
+    }
+
+    /// Apply a simulation command the way the app does in single player.
+    fn apply(coord: &mut UnitCoordinator, command: &GameCommand) {
+        match command {
+            GameCommand::SelectUnit(id) => coord.selection.select_single(*id),
+            GameCommand::SelectMultiple(ids) => coord.selection.select_multiple(ids.clone()),
+            GameCommand::ClearSelection => coord.selection.clear(),
+            GameCommand::OrderMove { x, z } => coord.order_move(WorldCoord::new(*x as i16, *z as i16)),
+            _ => {}
+        }
+    }
+
+    /// Play `replay` up to `ticks`, one tick at a time, snapshotting on the
+    /// way.
+    fn play(world: &mut GameWorld, coord: &mut UnitCoordinator, playback: &mut ReplayPlayback<SimSnapshot>, ticks: u32) {
+        while world.game_tick < ticks {
+            let tick = world.game_tick;
+            if playback.wants_snapshot(tick) {
+                playback.store_snapshot(tick, SimSnapshot::capture(world, coord));
+            }
+            let commands: Vec<_> = playback.replay().commands_at(tick).cloned().collect();
+            commands.iter().for_each(|command| apply(coord, command));
+            let (mut a, mut c, mut d, mut e, mut f, mut g, mut h, mut i, mut j, mut k) =
+                (NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp);
+            let mut subs = TickSubsystems {
+                terrain: &mut a, objects: coord, water: &mut c,
+                network: &mut d, actions: &mut e, game_time: &mut f,
+                single_player: &mut g, tutorial: &mut h, ai: &mut i,
+                population: &mut j, mana: &mut k,
+            };
+            assert_eq!(world.fast_forward(1, &mut subs), 1);
+        }
+    }
+
+    #[test]
+    fn playback_and_seeking_reproduce_the_recorded_game() {
+        let level = || {
+            let mut coord = UnitCoordinator::new();
+            for (i, x) in [0x2100i16, 0x2300, 0x2500].into_iter().enumerate() {
+                coord.units.push(person(i, x, 0x2100));
+            }
+            coord
+        };
+        let mut world = GameWorld::new(20);
+        world.state = GameState::InGame;
+        let mut recorder = ReplayRecorder::new(1, 0x1234, GameRules::default(), 0);
+        recorder.record(0, &GameCommand::SelectMultiple(vec![0, 1]));
+        recorder.record(0, &GameCommand::OrderMove { x: 0x3100 as f32, z: 0x2900 as f32 });
+        recorder.record(60, &GameCommand::SelectUnit(2));
+        recorder.record(60, &GameCommand::OrderMove { x: 0x2500 as f32, z: 0x3100 as f32 });
+        let replay = recorder.finish(200);
+
+        // The game as played: commands applied live between ticks
+        let mut live = level();
+        for tick in 0..200 {
+            replay.commands_at(tick).for_each(|command| apply(&mut live, command));
+            live.tick();
+        }
+        let expected = format!("{:?}", capture_frame(&live, 200));
+
+        let mut coord = level();
+        let mut playback = ReplayPlayback::new(replay, 50);
+        play(&mut world, &mut coord, &mut playback, 200);
+        assert_eq!(format!("{:?}", capture_frame(&coord, 200)), expected);
+
+        // Back to tick 80 from the tick 50 snapshot, then on to the end
+        let (tick, snapshot) = playback.seek(200, 80).unwrap();
+        assert_eq!(tick, 50);
+        snapshot.restore(&mut world, &mut coord);
+        assert_eq!(world.game_tick, 50);
+        play(&mut world, &mut coord, &mut playback, 200);
+        assert_eq!(format!("{:?}", capture_frame(&coord, 200)), expected);
+    }
+}
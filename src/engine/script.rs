@@ -0,0 +1,690 @@
+// Script language for --script replay and QA scenarios.
+//
+// A script is one statement per line; blank lines and `#` comments are
+// skipped. ScriptRunner walks the statements and hands the app one resolved
+// ScriptStep at a time: a GameCommand (by key name or by snake_case variant
+// name), an app action (click, screenshot, dumps), a wait measured in
+// simulation ticks, or an assertion checked against a ScriptView.
+//
+//   set n 3                 variables are numbers, read back as $n or ${n}
+//   set n $n * 2 + 1        expressions evaluate left to right (+ - * /)
+//   repeat $n ... end       repeat a block
+//   for id 0 9 ... end      loop a variable over an inclusive range
+//   W / key F8              key replay through translate_key
+//   order_move 120 -40      any GameCommand variant, arguments in order
+//   wait 0.5 / wait_ticks 4 seconds of simulation ticks, or exact ticks
+//   assert unit 3 state Fighting
+//   assert tribe 0 population >= 10
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use winit::keyboard::KeyCode;
+
+use crate::engine::command::{translate_key, GameCommand};
+use crate::engine::state::tribe::TribeArray;
+use crate::engine::units::{Unit, UnitId};
+use crate::render::hud::HudTab;
+
+/******************************************************************************/
+
+/// One resolved script step.
+#[derive(Debug, Clone)]
+pub enum ScriptStep {
+    /// A game command; `label` names it in the camera log ("KeyW" for keys,
+    /// the script word otherwise).
+    Command { cmd: GameCommand, label: String },
+    /// Run until this many simulation ticks have passed.
+    WaitTicks(u32),
+    /// Run for this many seconds of simulation time at the current speed.
+    WaitSeconds(f32),
+    Click { x: f32, y: f32 },
+    RightClick { x: f32, y: f32 },
+    Screenshot(Option<String>),
+    DumpUnits,
+    DumpBuildings,
+    Echo(String),
+    Assert(Assertion),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CmpOp {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "==" | "=" => Some(CmpOp::Eq),
+            "!=" => Some(CmpOp::Ne),
+            "<" => Some(CmpOp::Lt),
+            "<=" => Some(CmpOp::Le),
+            ">" => Some(CmpOp::Gt),
+            ">=" => Some(CmpOp::Ge),
+            _ => None,
+        }
+    }
+
+    fn holds(self, a: f64, b: f64) -> bool {
+        match self {
+            CmpOp::Eq => a == b,
+            CmpOp::Ne => a != b,
+            CmpOp::Lt => a < b,
+            CmpOp::Le => a <= b,
+            CmpOp::Gt => a > b,
+            CmpOp::Ge => a >= b,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            CmpOp::Eq => "==",
+            CmpOp::Ne => "!=",
+            CmpOp::Lt => "<",
+            CmpOp::Le => "<=",
+            CmpOp::Gt => ">",
+            CmpOp::Ge => ">=",
+        }
+    }
+}
+
+/// A numeric value an assertion can read from the game.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quantity {
+    UnitHealth(UnitId),
+    UnitTribe(UnitId),
+    UnitSubtype(UnitId),
+    UnitCellX(UnitId),
+    UnitCellY(UnitId),
+    /// Live units of the tribe (the count the HUD shows).
+    TribePopulation(u8),
+    TribeMana(u8),
+    Selected,
+    AngleX,
+    AngleZ,
+    Zoom,
+    Tick,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Assertion {
+    /// Unit is in the named PersonState (matched case-insensitively).
+    UnitState { id: UnitId, state: String },
+    UnitAlive { id: UnitId, alive: bool },
+    Compare { lhs: Quantity, op: CmpOp, rhs: f64 },
+}
+
+/// Game state visible to assertions.
+pub struct ScriptView<'a> {
+    pub units: &'a [Unit],
+    pub selected: &'a [UnitId],
+    pub tribes: &'a TribeArray,
+    pub angle_x: i16,
+    pub angle_z: i16,
+    pub zoom: f32,
+    pub game_tick: u32,
+}
+
+impl ScriptView<'_> {
+    fn unit(&self, id: UnitId) -> Result<&Unit, String> {
+        self.units.iter().find(|u| u.id == id).ok_or_else(|| format!("no unit {}", id))
+    }
+
+    fn tribe(&self, tribe: u8) -> Result<usize, String> {
+        let t = tribe as usize;
+        if t < self.tribes.tribes.len() { Ok(t) } else { Err(format!("no tribe {}", tribe)) }
+    }
+
+    fn read(&self, q: Quantity) -> Result<f64, String> {
+        Ok(match q {
+            Quantity::UnitHealth(id) => self.unit(id)?.health as f64,
+            Quantity::UnitTribe(id) => self.unit(id)?.tribe_index as f64,
+            Quantity::UnitSubtype(id) => self.unit(id)?.subtype as f64,
+            Quantity::UnitCellX(id) => self.unit(id)?.cell_x as f64,
+            Quantity::UnitCellY(id) => self.unit(id)?.cell_y as f64,
+            Quantity::TribePopulation(tribe) => {
+                self.tribe(tribe)?;
+                self.units.iter().filter(|u| u.alive && u.tribe_index == tribe).count() as f64
+            }
+            Quantity::TribeMana(tribe) => self.tribes.tribes[self.tribe(tribe)?].mana as f64,
+            Quantity::Selected => self.selected.len() as f64,
+            Quantity::AngleX => self.angle_x as f64,
+            Quantity::AngleZ => self.angle_z as f64,
+            Quantity::Zoom => self.zoom as f64,
+            Quantity::Tick => self.game_tick as f64,
+        })
+    }
+}
+
+impl Assertion {
+    /// Ok when the assertion holds, otherwise what was found instead.
+    pub fn check(&self, view: &ScriptView) -> Result<(), String> {
+        match self {
+            Assertion::UnitState { id, state } => {
+                let actual = format!("{:?}", view.unit(*id)?.state);
+                if actual.eq_ignore_ascii_case(state) {
+                    Ok(())
+                } else {
+                    Err(format!("unit {} state is {}, expected {}", id, actual, state))
+                }
+            }
+            Assertion::UnitAlive { id, alive } => {
+                let actual = view.unit(*id)?.alive;
+                if actual == *alive {
+                    Ok(())
+                } else {
+                    Err(format!("unit {} is {}", id, if actual { "alive" } else { "dead" }))
+                }
+            }
+            Assertion::Compare { lhs, op, rhs } => {
+                let value = view.read(*lhs)?;
+                if op.holds(value, *rhs) {
+                    Ok(())
+                } else {
+                    Err(format!("{:?} is {}, expected {} {}", lhs, value, op.symbol(), rhs))
+                }
+            }
+        }
+    }
+}
+
+/******************************************************************************/
+// Parsing
+
+#[derive(Debug)]
+enum Stmt {
+    Set { line: usize, name: String, expr: Vec<String> },
+    Repeat { line: usize, count: String, body: Rc<Vec<Stmt>> },
+    For { line: usize, var: String, from: String, to: String, body: Rc<Vec<Stmt>> },
+    Step { line: usize, words: Vec<String> },
+}
+
+fn parse_block(lines: &mut impl Iterator<Item = (usize, Vec<String>)>, open: Option<usize>) -> Result<Vec<Stmt>, String> {
+    let mut block = Vec::new();
+    while let Some((line, words)) = lines.next() {
+        match words[0].as_str() {
+            "end" if words.len() == 1 => {
+                return match open {
+                    Some(_) => Ok(block),
+                    None => Err(format!("line {}: `end` without `repeat` or `for`", line)),
+                };
+            }
+            "repeat" => {
+                if words.len() != 2 {
+                    return Err(format!("line {}: usage: repeat COUNT", line));
+                }
+                let body = Rc::new(parse_block(lines, Some(line))?);
+                block.push(Stmt::Repeat { line, count: words[1].clone(), body });
+            }
+            "for" => {
+                if words.len() != 4 {
+                    return Err(format!("line {}: usage: for NAME FROM TO", line));
+                }
+                let body = Rc::new(parse_block(lines, Some(line))?);
+                block.push(Stmt::For { line, var: words[1].clone(), from: words[2].clone(), to: words[3].clone(), body });
+            }
+            "set" => {
+                if words.len() < 3 {
+                    return Err(format!("line {}: usage: set NAME EXPR", line));
+                }
+                block.push(Stmt::Set { line, name: words[1].clone(), expr: words[2..].to_vec() });
+            }
+            _ => block.push(Stmt::Step { line, words }),
+        }
+    }
+    match open {
+        Some(line) => Err(format!("line {}: block is missing `end`", line)),
+        None => Ok(block),
+    }
+}
+
+/******************************************************************************/
+// Execution
+
+enum FrameKind {
+    Block,
+    Repeat { remaining: u32 },
+    For { var: String, next: i64, to: i64 },
+}
+
+struct Frame {
+    body: Rc<Vec<Stmt>>,
+    pc: usize,
+    kind: FrameKind,
+}
+
+pub struct ScriptRunner {
+    stack: Vec<Frame>,
+    vars: HashMap<String, f64>,
+    line: usize,
+}
+
+impl ScriptRunner {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().enumerate()
+            .map(|(i, l)| (i + 1, l.split_whitespace().map(str::to_string).collect::<Vec<_>>()))
+            .filter(|(_, words)| !words.is_empty() && !words[0].starts_with('#'));
+        let program = parse_block(&mut lines, None)?;
+        Ok(Self {
+            stack: vec![Frame { body: Rc::new(program), pc: 0, kind: FrameKind::Block }],
+            vars: HashMap::new(),
+            line: 0,
+        })
+    }
+
+    /// Script line of the last step returned.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Next step of the script, None once it has finished. Errors carry the
+    /// script line; the runner moves past the failing statement.
+    pub fn next_step(&mut self) -> Option<Result<ScriptStep, String>> {
+        loop {
+            let frame = self.stack.last_mut()?;
+            if frame.pc >= frame.body.len() {
+                match &mut frame.kind {
+                    FrameKind::Block => { self.stack.pop(); }
+                    FrameKind::Repeat { remaining } if *remaining > 1 => {
+                        *remaining -= 1;
+                        frame.pc = 0;
+                    }
+                    FrameKind::For { var, next, to } if *next <= *to => {
+                        self.vars.insert(var.clone(), *next as f64);
+                        *next += 1;
+                        frame.pc = 0;
+                    }
+                    _ => { self.stack.pop(); }
+                }
+                continue;
+            }
+            let body = frame.body.clone();
+            let pc = frame.pc;
+            frame.pc += 1;
+            match &body[pc] {
+                Stmt::Set { line, name, expr } => {
+                    match self.eval(expr) {
+                        Ok(value) => { self.vars.insert(name.clone(), value); }
+                        Err(e) => return Some(Err(format!("line {}: {}", line, e))),
+                    }
+                }
+                Stmt::Repeat { line, count, body } => {
+                    let count = match self.number(count) {
+                        Ok(n) if n >= 0.0 => n as u32,
+                        Ok(n) => return Some(Err(format!("line {}: negative repeat count {}", line, n))),
+                        Err(e) => return Some(Err(format!("line {}: {}", line, e))),
+                    };
+                    if count > 0 {
+                        self.stack.push(Frame { body: body.clone(), pc: 0, kind: FrameKind::Repeat { remaining: count } });
+                    }
+                }
+                Stmt::For { line, var, from, to, body } => {
+                    let (from, to) = match (self.number(from), self.number(to)) {
+                        (Ok(a), Ok(b)) => (a as i64, b as i64),
+                        (Err(e), _) | (_, Err(e)) => return Some(Err(format!("line {}: {}", line, e))),
+                    };
+                    if from <= to {
+                        self.vars.insert(var.clone(), from as f64);
+                        let kind = FrameKind::For { var: var.clone(), next: from + 1, to };
+                        self.stack.push(Frame { body: body.clone(), pc: 0, kind });
+                    }
+                }
+                Stmt::Step { line, words } => {
+                    self.line = *line;
+                    let step = self.substitute(words).and_then(|words| parse_step(&words));
+                    return Some(step.map_err(|e| format!("line {}: {}", line, e)));
+                }
+            }
+        }
+    }
+
+    fn substitute(&self, words: &[String]) -> Result<Vec<String>, String> {
+        words.iter().map(|w| {
+            match w.strip_prefix('$') {
+                Some(name) => {
+                    let name = name.strip_prefix('{').and_then(|n| n.strip_suffix('}')).unwrap_or(name);
+                    let value = self.vars.get(name).ok_or_else(|| format!("undefined variable ${}", name))?;
+                    Ok(format_number(*value))
+                }
+                None => Ok(w.clone()),
+            }
+        }).collect()
+    }
+
+    fn number(&self, word: &str) -> Result<f64, String> {
+        let word = &self.substitute(&[word.to_string()])?[0];
+        word.parse().map_err(|_| format!("expected a number, got {:?}", word))
+    }
+
+    fn eval(&self, expr: &[String]) -> Result<f64, String> {
+        let mut value = self.number(&expr[0])?;
+        let mut rest = expr[1..].chunks(2);
+        for pair in &mut rest {
+            let [op, operand] = pair else {
+                return Err(format!("expression ends with operator {:?}", pair[0]));
+            };
+            let rhs = self.number(operand)?;
+            value = match op.as_str() {
+                "+" => value + rhs,
+                "-" => value - rhs,
+                "*" => value * rhs,
+                "/" if rhs == 0.0 => return Err("division by zero".to_string()),
+                "/" => value / rhs,
+                other => return Err(format!("unknown operator {:?}", other)),
+            };
+        }
+        Ok(value)
+    }
+}
+
+fn format_number(v: f64) -> String {
+    if v.fract() == 0.0 && v.abs() < 1e15 { format!("{}", v as i64) } else { format!("{}", v) }
+}
+
+/******************************************************************************/
+// Step resolution
+
+/// Script key names: `A`-`Z`, `F1`-`F12`, and winit names such as `Space`,
+/// `Escape`, `ArrowUp`, `BracketLeft`, `Equal` or `KeyW`.
+pub fn key_code(name: &str) -> Option<KeyCode> {
+    let letter = |c: char| -> Option<KeyCode> {
+        const LETTERS: [KeyCode; 26] = [
+            KeyCode::KeyA, KeyCode::KeyB, KeyCode::KeyC, KeyCode::KeyD, KeyCode::KeyE, KeyCode::KeyF,
+            KeyCode::KeyG, KeyCode::KeyH, KeyCode::KeyI, KeyCode::KeyJ, KeyCode::KeyK, KeyCode::KeyL,
+            KeyCode::KeyM, KeyCode::KeyN, KeyCode::KeyO, KeyCode::KeyP, KeyCode::KeyQ, KeyCode::KeyR,
+            KeyCode::KeyS, KeyCode::KeyT, KeyCode::KeyU, KeyCode::KeyV, KeyCode::KeyW, KeyCode::KeyX,
+            KeyCode::KeyY, KeyCode::KeyZ,
+        ];
+        c.is_ascii_uppercase().then(|| LETTERS[(c as u8 - b'A') as usize])
+    };
+    const FKEYS: [KeyCode; 12] = [
+        KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+        KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    ];
+    let name = name.strip_prefix("Key").filter(|n| n.len() == 1).unwrap_or(name);
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return letter(c);
+    }
+    if let Some(n) = name.strip_prefix('F').and_then(|n| n.parse::<usize>().ok()) {
+        return FKEYS.get(n.wrapping_sub(1)).copied();
+    }
+    match name {
+        "Space" => Some(KeyCode::Space),
+        "Escape" => Some(KeyCode::Escape),
+        "ArrowUp" => Some(KeyCode::ArrowUp),
+        "ArrowDown" => Some(KeyCode::ArrowDown),
+        "ArrowLeft" => Some(KeyCode::ArrowLeft),
+        "ArrowRight" => Some(KeyCode::ArrowRight),
+        "BracketLeft" => Some(KeyCode::BracketLeft),
+        "BracketRight" => Some(KeyCode::BracketRight),
+        "Equal" => Some(KeyCode::Equal),
+        "Minus" => Some(KeyCode::Minus),
+        _ => None,
+    }
+}
+
+fn arg<T: std::str::FromStr>(words: &[String], i: usize) -> Result<T, String> {
+    let word = words.get(i).ok_or_else(|| format!("{}: missing argument {}", words[0], i))?;
+    word.parse().map_err(|_| format!("{}: bad argument {:?}", words[0], word))
+}
+
+fn expect_args(words: &[String], n: usize) -> Result<(), String> {
+    if words.len() == n + 1 {
+        Ok(())
+    } else {
+        Err(format!("{}: expected {} argument(s), got {}", words[0], n, words.len() - 1))
+    }
+}
+
+/// GameCommand named by its snake_case variant name, if `words[0]` is one.
+fn parse_command(words: &[String]) -> Option<Result<GameCommand, String>> {
+    let args = |n: usize| expect_args(words, n);
+    let cmd = |n: usize, c: GameCommand| Some(args(n).map(|_| c));
+    let parsed = |r: Result<GameCommand, String>| Some(r);
+    match words[0].as_str() {
+        "rotate_camera" => parsed(args(1).and_then(|_| Ok(GameCommand::RotateCamera { delta_z: arg(words, 1)? }))),
+        "tilt_camera" => parsed(args(1).and_then(|_| Ok(GameCommand::TiltCamera { delta_x: arg(words, 1)? }))),
+        "pan_screen" => parsed(args(2).and_then(|_| {
+            Ok(GameCommand::PanScreen { forward: arg(words, 1)?, right: arg(words, 2)? })
+        })),
+        "pan_terrain" => parsed(args(2).and_then(|_| Ok(GameCommand::PanTerrain { dx: arg(words, 1)?, dy: arg(words, 2)? }))),
+        "reset_camera" => cmd(0, GameCommand::ResetCamera),
+        "top_down_view" => cmd(0, GameCommand::TopDownView),
+        "center_on_shaman" => cmd(0, GameCommand::CenterOnShaman),
+        "zoom" | "set_zoom" => parsed(args(1).and_then(|_| Ok(GameCommand::SetZoom(arg(words, 1)?)))),
+        "toggle_curvature" => cmd(0, GameCommand::ToggleCurvature),
+        "adjust_curvature" => parsed(args(1).and_then(|_| Ok(GameCommand::AdjustCurvature { factor: arg(words, 1)? }))),
+        "next_level" => cmd(0, GameCommand::NextLevel),
+        "prev_level" => cmd(0, GameCommand::PrevLevel),
+        "next_shader" => cmd(0, GameCommand::NextShader),
+        "prev_shader" => cmd(0, GameCommand::PrevShader),
+        "toggle_objects" => cmd(0, GameCommand::ToggleObjects),
+        "toggle_shadows" => cmd(0, GameCommand::ToggleShadows),
+        "toggle_markers" => cmd(0, GameCommand::ToggleMarkers),
+        "adjust_sunlight" => parsed(args(2).and_then(|_| {
+            Ok(GameCommand::AdjustSunlight { dx: arg(words, 1)?, dy: arg(words, 2)? })
+        })),
+        "adjust_sprite_offset" => parsed(args(1).and_then(|_| Ok(GameCommand::AdjustSpriteOffset { delta: arg(words, 1)? }))),
+        "adjust_sprite_scale" => parsed(args(1).and_then(|_| Ok(GameCommand::AdjustSpriteScale { delta: arg(words, 1)? }))),
+        "select_unit" => parsed(args(1).and_then(|_| Ok(GameCommand::SelectUnit(arg(words, 1)?)))),
+        "select_multiple" => parsed((1..words.len()).map(|i| arg(words, i)).collect::<Result<Vec<_>, _>>()
+            .map(GameCommand::SelectMultiple)),
+        "clear_selection" => cmd(0, GameCommand::ClearSelection),
+        "order_move" => parsed(args(2).and_then(|_| Ok(GameCommand::OrderMove { x: arg(words, 1)?, z: arg(words, 2)? }))),
+        "toggle_simulation" => cmd(0, GameCommand::ToggleSimulation),
+        "increase_game_speed" => cmd(0, GameCommand::IncreaseGameSpeed),
+        "decrease_game_speed" => cmd(0, GameCommand::DecreaseGameSpeed),
+        "seek_replay" => parsed(args(1).and_then(|_| Ok(GameCommand::SeekReplay { ticks: arg(words, 1)? }))),
+        "set_hud_tab" => parsed(args(1).and_then(|_| match words[1].to_ascii_lowercase().as_str() {
+            "spells" => Ok(GameCommand::SetHudTab(HudTab::Spells)),
+            "buildings" => Ok(GameCommand::SetHudTab(HudTab::Buildings)),
+            "units" => Ok(GameCommand::SetHudTab(HudTab::Units)),
+            other => Err(format!("set_hud_tab: unknown tab {:?}", other)),
+        })),
+        "toggle_hud" => cmd(0, GameCommand::ToggleHud),
+        "toggle_compass" => cmd(0, GameCommand::ToggleCompass),
+        "toggle_walkability" => cmd(0, GameCommand::ToggleWalkability),
+        "quit" => cmd(0, GameCommand::Quit),
+        _ => None,
+    }
+}
+
+fn parse_assertion(words: &[String]) -> Result<Assertion, String> {
+    let usage = "usage: assert unit ID state NAME | assert unit ID alive|dead | assert unit ID FIELD OP VALUE \
+                 | assert tribe N population|mana OP VALUE | assert selected|angle_x|angle_z|zoom|tick OP VALUE";
+    let compare = |lhs: Quantity, rest: &[String]| -> Result<Assertion, String> {
+        match rest {
+            [op, value] => Ok(Assertion::Compare {
+                lhs,
+                op: CmpOp::parse(op).ok_or_else(|| format!("assert: unknown comparison {:?}", op))?,
+                rhs: value.parse().map_err(|_| format!("assert: bad value {:?}", value))?,
+            }),
+            _ => Err(usage.to_string()),
+        }
+    };
+    let words: Vec<&str> = words[1..].iter().map(String::as_str).collect();
+    match words.as_slice() {
+        ["unit", id, rest @ ..] => {
+            let id: UnitId = id.parse().map_err(|_| format!("assert: bad unit id {:?}", id))?;
+            let rest: Vec<String> = rest.iter().map(|s| s.to_string()).collect();
+            match rest.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+                ["state", state] => Ok(Assertion::UnitState { id, state: state.to_string() }),
+                ["alive"] => Ok(Assertion::UnitAlive { id, alive: true }),
+                ["dead"] => Ok(Assertion::UnitAlive { id, alive: false }),
+                ["health", ..] => compare(Quantity::UnitHealth(id), &rest[1..]),
+                ["tribe", ..] => compare(Quantity::UnitTribe(id), &rest[1..]),
+                ["subtype", ..] => compare(Quantity::UnitSubtype(id), &rest[1..]),
+                ["cell_x", ..] => compare(Quantity::UnitCellX(id), &rest[1..]),
+                ["cell_y", ..] => compare(Quantity::UnitCellY(id), &rest[1..]),
+                _ => Err(usage.to_string()),
+            }
+        }
+        ["tribe", tribe, field, rest @ ..] => {
+            let tribe: u8 = tribe.parse().map_err(|_| format!("assert: bad tribe {:?}", tribe))?;
+            let rest: Vec<String> = rest.iter().map(|s| s.to_string()).collect();
+            match *field {
+                "population" => compare(Quantity::TribePopulation(tribe), &rest),
+                "mana" => compare(Quantity::TribeMana(tribe), &rest),
+                _ => Err(usage.to_string()),
+            }
+        }
+        [field, rest @ ..] => {
+            let rest: Vec<String> = rest.iter().map(|s| s.to_string()).collect();
+            match *field {
+                "selected" => compare(Quantity::Selected, &rest),
+                "angle_x" => compare(Quantity::AngleX, &rest),
+                "angle_z" => compare(Quantity::AngleZ, &rest),
+                "zoom" => compare(Quantity::Zoom, &rest),
+                "tick" => compare(Quantity::Tick, &rest),
+                _ => Err(usage.to_string()),
+            }
+        }
+        [] => Err(usage.to_string()),
+    }
+}
+
+/// Resolve a statement (variables already substituted) into a step.
+fn parse_step(words: &[String]) -> Result<ScriptStep, String> {
+    if let Some(cmd) = parse_command(words) {
+        return cmd.map(|cmd| ScriptStep::Command { cmd, label: words[0].clone() });
+    }
+    match words[0].as_str() {
+        "wait" => {
+            expect_args(words, 1)?;
+            Ok(ScriptStep::WaitSeconds(arg(words, 1)?))
+        }
+        "wait_ticks" => {
+            expect_args(words, 1)?;
+            Ok(ScriptStep::WaitTicks(arg(words, 1)?))
+        }
+        "click" => {
+            expect_args(words, 2)?;
+            Ok(ScriptStep::Click { x: arg(words, 1)?, y: arg(words, 2)? })
+        }
+        "rightclick" => {
+            expect_args(words, 2)?;
+            Ok(ScriptStep::RightClick { x: arg(words, 1)?, y: arg(words, 2)? })
+        }
+        "screenshot" => match words.len() {
+            1 => Ok(ScriptStep::Screenshot(None)),
+            2 => Ok(ScriptStep::Screenshot(Some(words[1].clone()))),
+            _ => Err("usage: screenshot [PATH]".to_string()),
+        },
+        "dump_units" => expect_args(words, 0).map(|_| ScriptStep::DumpUnits),
+        "dump_buildings" => expect_args(words, 0).map(|_| ScriptStep::DumpBuildings),
+        "echo" => Ok(ScriptStep::Echo(words[1..].join(" "))),
+        "assert" => parse_assertion(words).map(ScriptStep::Assert),
+        "key" => {
+            expect_args(words, 1)?;
+            key_step(&words[1])
+        }
+        name if words.len() == 1 => key_step(name),
+        other => Err(format!("unknown command {:?}", other)),
+    }
+}
+
+fn key_step(name: &str) -> Result<ScriptStep, String> {
+    let key = key_code(name).ok_or_else(|| format!("unknown key or command {:?}", name))?;
+    let cmd = translate_key(key).ok_or_else(|| format!("key {:?} has no command", name))?;
+    Ok(ScriptStep::Command { cmd, label: format!("{:?}", key) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::movement::WorldCoord;
+    use crate::engine::units::person_state::PersonState;
+
+    fn steps(text: &str) -> Vec<Result<ScriptStep, String>> {
+        let mut runner = ScriptRunner::parse(text).unwrap();
+        std::iter::from_fn(|| runner.next_step()).collect()
+    }
+
+    fn labels(text: &str) -> Vec<String> {
+        steps(text).into_iter().map(|s| match s.unwrap() {
+            ScriptStep::Command { cmd, .. } => format!("{:?}", cmd),
+            other => format!("{:?}", other),
+        }).collect()
+    }
+
+    #[test]
+    fn loops_and_variables_expand_in_order() {
+        let script = "
+            # comment
+            set n 2
+            repeat $n
+              Q
+            end
+            for id 3 4
+              select_unit $id
+              set last ${id} * 10
+            end
+            order_move $last -5
+            repeat 0
+              E
+            end";
+        assert_eq!(labels(script), vec![
+            "RotateCamera { delta_z: -5 }",
+            "RotateCamera { delta_z: -5 }",
+            "SelectUnit(3)",
+            "SelectUnit(4)",
+            "OrderMove { x: 40.0, z: -5.0 }",
+        ]);
+        assert!(ScriptRunner::parse("repeat 2\nQ").is_err());
+        assert!(ScriptRunner::parse("end").is_err());
+    }
+
+    #[test]
+    fn commands_keys_and_errors_resolve() {
+        let all = steps("W\nkey F8\nselect_multiple 1 2 5\nset_hud_tab units\nwait 0.5\nwait_ticks 3\n\
+                         screenshot\nclick 10 20\nbogus 1\nzoom x\nprint $nope\nQ");
+        let ok: Vec<String> = all.iter().filter_map(|s| s.as_ref().ok()).map(|s| format!("{:?}", s)).collect();
+        assert_eq!(ok[0], "Command { cmd: PanScreen { forward: 1.0, right: 0.0 }, label: \"KeyW\" }");
+        assert_eq!(ok[1], "Command { cmd: ToggleWalkability, label: \"F8\" }");
+        assert!(ok[2].contains("SelectMultiple([1, 2, 5])"));
+        assert!(ok[3].contains("SetHudTab(Units)"));
+        assert_eq!(&ok[4..7], ["WaitSeconds(0.5)", "WaitTicks(3)", "Screenshot(None)"]);
+        assert_eq!(ok[7], "Click { x: 10.0, y: 20.0 }");
+        let errors: Vec<&String> = all.iter().filter_map(|s| s.as_ref().err()).collect();
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].starts_with("line 9:"));
+        assert!(errors[2].contains("undefined variable $nope"));
+        // The runner carries on after an error
+        assert!(matches!(all.last(), Some(Ok(ScriptStep::Command { .. }))));
+    }
+
+    fn make_unit(id: usize, tribe: u8, state: PersonState) -> Unit {
+        Unit { state, cell_x: 1.0, cell_y: 2.0, ..Unit::new_person(id, 2, tribe, WorldCoord::new(0, 0)) }
+    }
+
+    #[test]
+    fn assertions_check_the_view() {
+        let units = vec![make_unit(3, 0, PersonState::Fighting), make_unit(4, 0, PersonState::Idle),
+                         make_unit(5, 1, PersonState::Idle)];
+        let tribes = TribeArray::new();
+        let view = ScriptView {
+            units: &units, selected: &[3], tribes: &tribes,
+            angle_x: -55, angle_z: 0, zoom: 1.0, game_tick: 40,
+        };
+        let check = |line: &str| match steps(line).remove(0).unwrap() {
+            ScriptStep::Assert(a) => a.check(&view),
+            other => panic!("not an assertion: {:?}", other),
+        };
+        assert!(check("assert unit 3 state Fighting").is_ok());
+        assert!(check("assert unit 3 state fighting").is_ok());
+        assert!(check("assert unit 4 state Fighting").unwrap_err().contains("Idle"));
+        assert!(check("assert tribe 0 population >= 2").is_ok());
+        assert!(check("assert tribe 1 population >= 2").is_err());
+        assert!(check("assert unit 9 alive").unwrap_err().contains("no unit 9"));
+        assert!(check("assert selected == 1").is_ok());
+        assert!(check("assert tick > 40").is_err());
+        assert!(check("assert angle_x == -55").is_ok());
+        assert!(steps("assert tribe 0 population about 3").remove(0).is_err());
+    }
+}
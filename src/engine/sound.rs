@@ -0,0 +1,43 @@
+// Sound events — what the simulation asks to be heard.
+//
+// The engine has no audio dependency: UnitCoordinator queues SoundEvents
+// and the app hands them to the audio mixer, which maps them to sound ids
+// and positions them relative to the camera.
+
+use crate::engine::movement::WorldCoord;
+
+/// Gameplay occurrences that make a sound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameSound {
+    /// A melee blow lands.
+    CombatStrike,
+    /// A follower dies.
+    UnitDeath,
+    /// A shaman dies.
+    ShamanDeath,
+    /// A shaman reappears at the reincarnation pillar.
+    ShamanReincarnated,
+    /// A stone head grants its discovery.
+    Discovery,
+    /// A unit falls into the water.
+    Splash,
+    // Spell casts and building completion get sounds once spells and
+    // construction exist; nothing raises those events yet.
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SoundEvent {
+    pub sound: GameSound,
+    /// Where the sound comes from; None for 2D (UI/global) sounds.
+    pub position: Option<WorldCoord>,
+}
+
+impl SoundEvent {
+    pub fn at(sound: GameSound, position: WorldCoord) -> Self {
+        Self { sound, position: Some(position) }
+    }
+
+    pub fn global(sound: GameSound) -> Self {
+        Self { sound, position: None }
+    }
+}
@@ -56,6 +56,10 @@ pub const FLAG_NET_WAITING: u32 = 0x20;
 /// Network game is paused.
 pub const FLAG_NET_PAUSED: u32 = 0x40;
 
+/// Peers' state checksums disagree.
+/// Original: set by Net_ValidateChecksum (0x004a8120)
+pub const FLAG_DESYNC: u32 = 0x1000;
+
 /// Victory or defeat state is active (used to skip AI updates).
 pub const FLAG_VICTORY_DEFEAT: u32 = 0x800000;
 
@@ -86,6 +90,22 @@ pub const REINCARNATION_TIMER_MAX: i32 = 0x60;
 /// Original: 0x10
 pub const REINCARNATION_TIMER_INCREMENT: i32 = 0x10;
 
+// --- Shaman Death ---
+
+/// Percent of a tribe's mana lost when its shaman dies.
+/// Named after SHAMEN_DEAD_MANA_%_LOST in constant.dat.
+///
+/// Unverified: 0x005a35e8 holds the name string, not the value; the value
+/// here is this crate's own until it is read from constant.dat.
+pub const SHAMAN_DEAD_MANA_PCT_LOST: u32 = 10;
+
+/// Percent of the victim tribe's mana gained by the tribe that killed the shaman.
+/// Named after SHAMEN_DEAD_MANA_%_GAIN in constant.dat.
+///
+/// Unverified: 0x005a3607 holds the name string, not the value; the value
+/// here is this crate's own until it is read from constant.dat.
+pub const SHAMAN_DEAD_MANA_PCT_GAIN: u32 = 10;
+
 // --- Tribe Structure Offsets ---
 // These document the original struct layout at g_TribeArray (0x00885760).
 // Each tribe is 0xC65 (3173) bytes.
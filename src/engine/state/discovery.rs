@@ -0,0 +1,121 @@
+// Discovery grants — spells, building types and one-shot abilities a tribe
+// unlocks by worshipping stone heads.
+//
+// The worship side (stone heads, progress) lives in units::stone_head;
+// this module only applies a completed discovery to the tribe.
+
+use super::tribe::TribeData;
+
+/// Discovery types (general object data, discovery type byte).
+pub const DISCOVERY_TYPE_SPELL: u8 = 1;
+pub const DISCOVERY_TYPE_BUILDING: u8 = 2;
+pub const DISCOVERY_TYPE_ABILITY: u8 = 3;
+
+/// Spell/building ids a discovery bitfield can hold. The original keeps
+/// g_DiscoveredSpells (0x00885800) and g_DiscoveredBuildings (0x00885808)
+/// 8 bytes apart, so 64 is its limit too; there are 22 spells (0x01-0x16,
+/// docs/specs/spells.md).
+pub const DISCOVERY_MAX_ID: u8 = 64;
+
+/// The bit for `id` in a discovery bitfield, or None if it's out of range.
+pub fn discovery_bit(id: u8) -> Option<u64> {
+    1u64.checked_shl(id as u32)
+}
+
+/// What a stone head grants once worship completes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Discovery {
+    /// Permanently unlocks a spell (spell model index).
+    Spell(u8),
+    /// Permanently unlocks a building type (building subtype).
+    Building(u8),
+    /// Grants a single use of a spell/ability (spell model index).
+    Ability(u8),
+}
+
+impl Discovery {
+    /// Decode a (type, model) pair as stored in level data.
+    /// Returns None for type 0 (no discovery), unknown types, or a model
+    /// past DISCOVERY_MAX_ID.
+    pub fn from_raw(kind: u8, model: u8) -> Option<Self> {
+        if model >= DISCOVERY_MAX_ID {
+            return None;
+        }
+        match kind {
+            DISCOVERY_TYPE_SPELL => Some(Self::Spell(model)),
+            DISCOVERY_TYPE_BUILDING => Some(Self::Building(model)),
+            DISCOVERY_TYPE_ABILITY => Some(Self::Ability(model)),
+            _ => None,
+        }
+    }
+}
+
+/// Unlock a discovery for `tribe`.
+/// Original: Discovery_Grant @ 0x004bee20 — sets the bit in
+/// g_DiscoveredSpells (0x00885800) or g_DiscoveredBuildings (0x00885808).
+///
+/// Returns false if the spell/building was already known. One-shot
+/// abilities always stack.
+pub fn discovery_grant(tribe: &mut TribeData, discovery: Discovery) -> bool {
+    let granted = match discovery {
+        Discovery::Spell(id) => {
+            let Some(bit) = discovery_bit(id) else { return false };
+            let new = tribe.discovered_spells & bit == 0;
+            tribe.discovered_spells |= bit;
+            new
+        }
+        Discovery::Building(id) => {
+            let Some(bit) = discovery_bit(id) else { return false };
+            let new = tribe.discovered_buildings & bit == 0;
+            tribe.discovered_buildings |= bit;
+            new
+        }
+        Discovery::Ability(id) => {
+            tribe.one_shot_abilities.push(id);
+            true
+        }
+    };
+    log::info!("[discovery] tribe {} granted {:?} (new={})", tribe.index, discovery, granted);
+    granted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_raw_decodes_types() {
+        assert_eq!(Discovery::from_raw(1, 5), Some(Discovery::Spell(5)));
+        assert_eq!(Discovery::from_raw(2, 3), Some(Discovery::Building(3)));
+        assert_eq!(Discovery::from_raw(3, 9), Some(Discovery::Ability(9)));
+        assert_eq!(Discovery::from_raw(0, 9), None);
+        assert_eq!(Discovery::from_raw(7, 9), None);
+    }
+
+    #[test]
+    fn grant_spell_and_building_sets_bits_once() {
+        let mut t = TribeData::new(1);
+        assert!(discovery_grant(&mut t, Discovery::Spell(4)));
+        assert!(t.has_spell(4));
+        assert!(!discovery_grant(&mut t, Discovery::Spell(4)));
+
+        assert!(discovery_grant(&mut t, Discovery::Building(7)));
+        assert!(t.has_building(7));
+        assert!(!t.has_building(6));
+
+        // Past the bitfield: no wrapping onto a low id
+        assert!(!discovery_grant(&mut t, Discovery::Spell(69)));
+        assert!(!t.has_spell(69) && !t.has_spell(5));
+        assert_eq!(Discovery::from_raw(1, 64), None);
+    }
+
+    #[test]
+    fn one_shot_abilities_stack_and_are_consumed() {
+        let mut t = TribeData::new(0);
+        discovery_grant(&mut t, Discovery::Ability(12));
+        discovery_grant(&mut t, Discovery::Ability(12));
+        assert!(t.take_ability(12));
+        assert!(t.take_ability(12));
+        assert!(!t.take_ability(12));
+    }
+}
@@ -36,6 +36,10 @@ impl GameFlags {
         self.0 & FLAG_NET_PAUSED != 0
     }
 
+    pub fn is_desynced(&self) -> bool {
+        self.0 & FLAG_DESYNC != 0
+    }
+
     /// Victory or defeat state is active.
     /// When set, AI updates are skipped in the tick loop.
     pub fn is_victory_defeat(&self) -> bool {
@@ -68,6 +72,32 @@ impl GameFlags {
         }
     }
 
+    /// Blocked waiting for other players' commands for the next tick.
+    pub fn set_net_waiting(&mut self, v: bool) {
+        if v {
+            self.0 |= FLAG_NET_WAITING;
+        } else {
+            self.0 &= !FLAG_NET_WAITING;
+        }
+    }
+
+    /// Some player in a multiplayer game has paused.
+    pub fn set_net_paused(&mut self, v: bool) {
+        if v {
+            self.0 |= FLAG_NET_PAUSED;
+        } else {
+            self.0 &= !FLAG_NET_PAUSED;
+        }
+    }
+
+    pub fn set_desynced(&mut self, v: bool) {
+        if v {
+            self.0 |= FLAG_DESYNC;
+        } else {
+            self.0 &= !FLAG_DESYNC;
+        }
+    }
+
     /// Set the player-won flag. Also sets victory/defeat active.
     /// Original: _DAT_00884bf9 = (_DAT_00884bf9 & 0xf9ffffff) | 0x2000000
     pub fn set_won(&mut self) {
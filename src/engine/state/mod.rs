@@ -1,4 +1,5 @@
 pub mod constants;
+pub mod discovery;
 pub mod flags;
 pub mod rng;
 pub mod state_machine;
@@ -1,5 +1,9 @@
+use std::cell::Cell;
 use std::time::Instant;
 
+use crate::engine::movement::PathfindingMode;
+use crate::engine::net::sync::StateChecksums;
+
 use super::constants::*;
 use super::flags::GameFlags;
 use super::rng::GameRng;
@@ -34,6 +38,38 @@ impl TimeSource for StdTimeSource {
     }
 }
 
+/// Manually advanced time source for headless runs (scripted tests), so the
+/// simulation steps deterministically regardless of wall-clock speed.
+#[derive(Default)]
+pub struct ManualTimeSource {
+    ms: Cell<u64>,
+}
+
+impl ManualTimeSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, ms: u64) {
+        self.ms.set(self.ms.get() + ms);
+    }
+}
+
+impl TimeSource for ManualTimeSource {
+    fn now_ms(&self) -> u64 {
+        self.ms.get()
+    }
+}
+
+/// Game options that change what the simulation does with the same inputs.
+/// Neither is in the original, so the defaults are its behaviour; every
+/// peer of a multiplayer game and every replay of a game needs the same.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GameRules {
+    pub pathfinding: PathfindingMode,
+    pub avoidance: bool,
+}
+
 /// Central game world state. Owns all simulation data.
 ///
 /// This struct is the single owner of the game's simulation state.
@@ -79,6 +115,14 @@ pub struct GameWorld {
     /// Values 2 or 3 trigger tutorial tick instead of single-player tick.
     pub tutorial_mode: u8,
 
+    /// Route provider for unit moves. Not in the original, which always
+    /// uses the wall follower (PathfindingMode::Original).
+    pub pathfinding: PathfindingMode,
+
+    /// Local collision avoidance between units (units/avoidance.rs). Not in
+    /// the original, where units walk through each other; off by default.
+    pub unit_avoidance: bool,
+
     /// Milliseconds per tick = 1000 / game_speed.
     /// Original: g_TickIntervalMs at 0x0059ac70
     tick_interval_ms: u64,
@@ -102,6 +146,8 @@ impl GameWorld {
             ai_update_mult: 0,
             player_tribe: 0,
             tutorial_mode: 0,
+            pathfinding: PathfindingMode::Original,
+            unit_avoidance: false,
             tick_interval_ms: (TICK_BASE_MS as u64) / (speed as u64),
             last_tick_time: 0,
         }
@@ -153,6 +199,10 @@ impl GameWorld {
             return 0;
         }
 
+        if self.flags.is_multiplayer() {
+            return self.multiplayer_tick_loop(now, subs);
+        }
+
         // Single-player tick loop
         // Original: 0x004bb941 branch (when not multiplayer)
         let mut ticks_run: u32 = 0;
@@ -166,6 +216,30 @@ impl GameWorld {
         ticks_run
     }
 
+    /// Multiplayer tick loop: same tick order, but the network step holds
+    /// each tick until every player's commands for it have arrived. While
+    /// held, FLAG_NET_WAITING (or FLAG_NET_PAUSED) is set and the clock
+    /// doesn't run on, so a stall costs time instead of piling up ticks.
+    fn multiplayer_tick_loop(&mut self, now: u64, subs: &mut TickSubsystems) -> u32 {
+        let mut ticks_run: u32 = 0;
+
+        while now > self.last_tick_time && ticks_run < MAX_CATCHUP_TICKS as u32 {
+            if !self.run_one_tick(subs) {
+                let paused = subs.network.net_paused();
+                self.flags.set_net_paused(paused);
+                self.flags.set_net_waiting(!paused);
+                self.last_tick_time = self.last_tick_time.max(now.saturating_sub(self.tick_interval_ms));
+                break;
+            }
+            self.flags.set_net_paused(false);
+            self.flags.set_net_waiting(false);
+            self.last_tick_time += self.tick_interval_ms;
+            ticks_run += 1;
+        }
+
+        ticks_run
+    }
+
     /// Execute a single simulation tick.
     /// Faithfully reproduces the call order from Game_SimulationTick (0x004bb5a0).
     ///
@@ -181,14 +255,20 @@ impl GameWorld {
     ///    b. AI_UpdateAllTribes (skip if FLAG_VICTORY_DEFEAT)
     ///    c. Tick_UpdatePopulation
     ///    d. Tick_UpdateMana
-    fn run_one_tick(&mut self, subs: &mut TickSubsystems) {
+    ///
+    /// Returns false if the network step aborted the tick.
+    fn run_one_tick(&mut self, subs: &mut TickSubsystems) -> bool {
         // 1. Tick_ProcessNetworkMessages (0x004a76b0)
         let proceed = subs.network.tick_process_network();
         if !proceed {
-            return;
+            return false;
         }
 
         // 2. Tick_ProcessPendingActions (0x004a6f60)
+        // Commands players issued for this tick go first, in player order
+        for action in subs.network.take_actions() {
+            subs.objects.apply_action(&action);
+        }
         subs.actions.tick_process_actions();
 
         // 3. Tick_UpdateGameTime (0x004a7ac0)
@@ -198,7 +278,16 @@ impl GameWorld {
         subs.terrain.tick_update_terrain();
 
         // 5. Tick_UpdateObjects (0x004a7550)
+        subs.objects.set_alliances(&self.tribes.alliances);
         subs.objects.tick_update_objects();
+        // Discoveries, shaman deaths, respawns and the persons left, so
+        // every peer's tribes change on the same tick
+        for event in subs.objects.take_tribe_events() {
+            self.tribes.apply_event(event);
+        }
+        if let Some(populations) = subs.objects.populations() {
+            self.tribes.set_populations(populations);
+        }
 
         // 6. Tick_UpdateWater (0x0048bf10)
         subs.water.tick_update_water();
@@ -245,6 +334,48 @@ impl GameWorld {
             &mut self.tribes,
             self.player_tribe,
         );
+
+        // Net_ValidateChecksum (0x004a8120): trade state checksums with
+        // the other players
+        if subs.network.wants_checksums(self.game_tick) {
+            let mut sums = StateChecksums::new(self.game_tick);
+            subs.objects.add_checksums(&mut sums);
+            self.tribes.add_checksums(&mut sums);
+            subs.network.submit_checksums(sums);
+        }
+        self.flags.set_desynced(subs.network.desynced());
+        true
+    }
+
+    /// Run `ticks` ticks back to back, regardless of the clock — for
+    /// rebuilding state from an input history. Stops early if the network
+    /// step aborts a tick; returns the number run.
+    pub fn fast_forward(&mut self, ticks: u32, subs: &mut TickSubsystems) -> u32 {
+        for run in 0..ticks {
+            if !self.run_one_tick(subs) {
+                return run;
+            }
+        }
+        ticks
+    }
+
+    pub fn rules(&self) -> GameRules {
+        GameRules { pathfinding: self.pathfinding, avoidance: self.unit_avoidance }
+    }
+
+    pub fn set_rules(&mut self, rules: GameRules) {
+        self.pathfinding = rules.pathfinding;
+        self.unit_avoidance = rules.avoidance;
+    }
+
+    /// Back to tick 0 of the level, keeping the clock and settings. Tribes
+    /// start over (alliances stay); the caller reloads the level's objects.
+    pub fn rewind(&mut self) {
+        self.game_tick = 0;
+        self.tick_counter = 0;
+        self.tribes.reset();
+        self.flags.clear_victory_defeat();
+        self.flags.set_desynced(false);
     }
 }
 
@@ -267,6 +398,7 @@ pub struct TickSubsystems<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::engine::state::tribe::TribeEvent;
     use std::cell::RefCell;
     use std::rc::Rc;
 
@@ -395,6 +527,25 @@ mod tests {
         assert_eq!(ticks, MAX_CATCHUP_TICKS as u32);
     }
 
+    #[test]
+    fn test_tribe_events_apply_within_the_tick() {
+        struct Events(Vec<TribeEvent>);
+        impl ObjectTick for Events {
+            fn tick_update_objects(&mut self) {}
+            fn take_tribe_events(&mut self) -> Vec<TribeEvent> {
+                std::mem::take(&mut self.0)
+            }
+        }
+
+        let mut world = GameWorld::new(10);
+        world.state = GameState::InGame;
+        let mut objects = Events(vec![TribeEvent::ReincarnationSite { tribe: 2, standing: true }]);
+        let (mut a, _, mut c, mut d, mut e, mut f, mut g, mut h, mut i, mut j, mut k) = noop_subs();
+        let mut subs = make_subs!(&mut a, &mut objects, &mut c, &mut d, &mut e, &mut f, &mut g, &mut h, &mut i, &mut j, &mut k);
+        assert_eq!(world.fast_forward(1, &mut subs), 1);
+        assert!(world.tribes.tribes[2].reincarnation_site);
+    }
+
     #[test]
     fn test_tutorial_mode_uses_tutorial_tick() {
         let mut world = GameWorld::new(10);
@@ -434,4 +585,29 @@ mod tests {
         assert!(!calls.contains(&"single_player"));
         assert!(!calls.contains(&"ai"));
     }
+
+    /// Network step that lets ticks through only while `open` is set.
+    struct Gate { open: bool }
+    impl NetworkTick for Gate { fn tick_process_network(&mut self) -> bool { self.open } }
+
+    #[test]
+    fn test_multiplayer_holds_ticks_while_waiting() {
+        let mut world = GameWorld::new(10);
+        world.state = GameState::InGame;
+        world.flags.set_multiplayer(true);
+        let (mut closed, mut open) = (Gate { open: false }, Gate { open: true });
+        let (mut a, mut b, mut c, _, mut e, mut f, mut g, mut h, mut i, mut j, mut k) = noop_subs();
+        let mut subs = make_subs!(&mut a, &mut b, &mut c, &mut closed, &mut e, &mut f, &mut g, &mut h, &mut i, &mut j, &mut k);
+
+        world.simulation_tick(&MockTime { ms: 1 }, &mut subs);
+        assert!(world.flags.is_net_waiting());
+        // Blocked for a long time: nothing runs, and no backlog builds up
+        assert_eq!(world.simulation_tick(&MockTime { ms: 5_000 }, &mut subs), 0);
+        assert_eq!(world.game_tick, 0);
+
+        subs.network = &mut open;
+        assert_eq!(world.simulation_tick(&MockTime { ms: 5_000 }, &mut subs), 1);
+        assert!(!world.flags.is_net_waiting());
+        assert_eq!(world.simulation_tick(&MockTime { ms: 5_100 }, &mut subs), 1);
+    }
 }
@@ -1,3 +1,9 @@
+use crate::engine::net::PlayerAction;
+use crate::engine::net::sync::StateChecksums;
+
+use super::constants::MAX_TRIBES;
+use super::tribe::{AllianceMatrix, TribeEvent};
+
 /// Trait definitions for subsystem dependencies called by the tick loop.
 ///
 /// Each trait corresponds to one of the Tick_Update* calls in
@@ -15,6 +21,29 @@ pub trait TerrainTick {
 /// Original: Tick_UpdateObjects at 0x004a7550
 pub trait ObjectTick {
     fn tick_update_objects(&mut self);
+
+    /// Carry out a player's command received through the network, during
+    /// Tick_ProcessPendingActions.
+    fn apply_action(&mut self, _action: &PlayerAction) {}
+
+    /// Fold the object state into the desync checksums.
+    fn add_checksums(&self, _sums: &mut StateChecksums) {}
+
+    /// The tribes' current alliances, before objects update: allies don't
+    /// fight each other.
+    fn set_alliances(&mut self, _alliances: &AllianceMatrix) {}
+
+    /// Tribe consequences of the update just run, applied to the tribes
+    /// before the tick goes on.
+    fn take_tribe_events(&mut self) -> Vec<TribeEvent> {
+        Vec::new()
+    }
+
+    /// Persons alive per tribe after the update, for the victory check.
+    /// None leaves the tribes' populations as they are.
+    fn populations(&self) -> Option<[u32; MAX_TRIBES]> {
+        None
+    }
 }
 
 /// Water simulation and effects.
@@ -29,6 +58,29 @@ pub trait NetworkTick {
     /// Process incoming network messages. Returns true if the game should
     /// continue processing this tick, false to abort (e.g. waiting for sync).
     fn tick_process_network(&mut self) -> bool;
+
+    /// Player commands released for the tick just let through, in the
+    /// order every peer applies them.
+    fn take_actions(&mut self) -> Vec<PlayerAction> {
+        Vec::new()
+    }
+
+    /// The last abort was a multiplayer pause rather than a wait.
+    fn net_paused(&self) -> bool {
+        false
+    }
+
+    /// State checksums are to be exchanged after `tick` ticks.
+    fn wants_checksums(&self, _tick: u32) -> bool {
+        false
+    }
+
+    fn submit_checksums(&mut self, _sums: StateChecksums) {}
+
+    /// Some peer's checksums disagree with ours.
+    fn desynced(&self) -> bool {
+        false
+    }
 }
 
 /// Pending player action queue processing.
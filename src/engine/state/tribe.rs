@@ -1,4 +1,6 @@
 use super::constants::*;
+use super::discovery::{Discovery, discovery_bit, discovery_grant};
+use crate::engine::net::sync::{StateChecksums, SyncCategory};
 
 /// Per-tribe game data.
 ///
@@ -11,7 +13,8 @@ pub struct TribeData {
     /// Tribe index (0=Blue, 1=Red, 2=Yellow, 3=Green).
     pub index: u8,
 
-    /// Whether this tribe is active in the current level.
+    /// Whether this tribe is active in the current level: it had persons
+    /// when the level loaded (TribeArray::start_level).
     /// Original: tribe struct offset +0xC20
     pub active: bool,
 
@@ -28,8 +31,29 @@ pub struct TribeData {
     /// Bit 0: victory celebration triggered.
     pub victory_flags: u32,
 
-    /// Current population count (persons alive for this tribe).
+    /// Current population count (persons alive for this tribe), recounted
+    /// every tick after objects update.
     pub population: u32,
+
+    /// Spells unlocked by discovery, one bit per spell model index.
+    /// Original: g_DiscoveredSpells (0x00885800)
+    pub discovered_spells: u64,
+
+    /// Building types unlocked by discovery, one bit per building subtype.
+    /// Original: g_DiscoveredBuildings (0x00885808)
+    pub discovered_buildings: u64,
+
+    /// Single-use spells granted by discovery, consumed on cast.
+    pub one_shot_abilities: Vec<u8>,
+
+    /// Current mana.
+    pub mana: u32,
+
+    /// Whether the tribe's reincarnation pillar is standing.
+    pub reincarnation_site: bool,
+
+    /// Shaman is dead and queued to respawn at the pillar.
+    pub shaman_respawning: bool,
 }
 
 impl TribeData {
@@ -40,6 +64,12 @@ impl TribeData {
             reincarnation_timer: 0,
             victory_flags: 0,
             population: 0,
+            discovered_spells: 0,
+            discovered_buildings: 0,
+            one_shot_abilities: Vec::new(),
+            mana: 0,
+            reincarnation_site: false,
+            shaman_respawning: false,
         }
     }
 
@@ -47,13 +77,70 @@ impl TribeData {
     pub fn is_eliminated(&self) -> bool {
         self.population == 0 && self.reincarnation_timer >= REINCARNATION_TIMER_MAX
     }
+
+    /// A dead shaman will come back: the tribe isn't out yet even with no
+    /// population left.
+    pub fn can_reincarnate(&self) -> bool {
+        self.shaman_respawning && self.reincarnation_site
+    }
+
+    pub fn has_spell(&self, spell: u8) -> bool {
+        discovery_bit(spell).is_some_and(|bit| self.discovered_spells & bit != 0)
+    }
+
+    pub fn has_building(&self, building: u8) -> bool {
+        discovery_bit(building).is_some_and(|bit| self.discovered_buildings & bit != 0)
+    }
+
+    /// Use up one charge of a one-shot ability. Returns false if none left.
+    pub fn take_ability(&mut self, spell: u8) -> bool {
+        match self.one_shot_abilities.iter().position(|&s| s == spell) {
+            Some(i) => {
+                self.one_shot_abilities.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forget all discoveries (new level).
+    pub fn clear_discoveries(&mut self) {
+        self.discovered_spells = 0;
+        self.discovered_buildings = 0;
+        self.one_shot_abilities.clear();
+    }
+}
+
+/// Tribe-level consequences of object events, raised by the
+/// UnitCoordinator and applied to the TribeArray within the tick, right
+/// after objects update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TribeEvent {
+    /// Worship at a stone head completed.
+    Discovered { tribe: u8, discovery: Discovery },
+    /// The tribe's shaman died; `killer` is the tribe that struck the
+    /// final blow, `respawn` whether the pillar will bring it back.
+    ShamanKilled { tribe: u8, killer: Option<u8>, respawn: bool },
+    /// The shaman reappeared at the pillar.
+    ShamanReincarnated { tribe: u8 },
+    /// The tribe's reincarnation pillar was placed or destroyed.
+    ReincarnationSite { tribe: u8, standing: bool },
 }
 
+/// Which tribes are allied with which: `[a][b]` is set when tribe `a`
+/// counts tribe `b` as an ally. A tribe is never listed as its own ally.
+/// Original: alliance matrix at DAT_00948e4e
+pub type AllianceMatrix = [[bool; MAX_TRIBES]; MAX_TRIBES];
+
 /// Array of all tribes. Always exactly MAX_TRIBES (4).
 /// Original: g_TribeArray at 0x00885760
 #[derive(Debug, Clone)]
 pub struct TribeArray {
     pub tribes: [TribeData; MAX_TRIBES],
+
+    /// Alliances between tribes (multiplayer teams). Kept symmetric by
+    /// set_allied and set_teams.
+    pub alliances: AllianceMatrix,
 }
 
 impl TribeArray {
@@ -65,6 +152,99 @@ impl TribeArray {
                 TribeData::new(2),
                 TribeData::new(3),
             ],
+            alliances: [[false; MAX_TRIBES]; MAX_TRIBES],
+        }
+    }
+
+    /// Back to the start of a level: every tribe's data as new. Alliances
+    /// are the session's teams and stay.
+    pub fn reset(&mut self) {
+        for (i, tribe) in self.tribes.iter_mut().enumerate() {
+            *tribe = TribeData::new(i as u8);
+        }
+    }
+
+    /// A level loaded with `populations` persons per tribe: every tribe's
+    /// data as new, and those with persons on the level in play.
+    pub fn start_level(&mut self, populations: [u32; MAX_TRIBES]) {
+        self.reset();
+        for (tribe, population) in self.tribes.iter_mut().zip(populations) {
+            tribe.active = population > 0;
+            tribe.population = population;
+        }
+    }
+
+    pub fn set_populations(&mut self, populations: [u32; MAX_TRIBES]) {
+        for (tribe, population) in self.tribes.iter_mut().zip(populations) {
+            tribe.population = population;
+        }
+    }
+
+    /// Tribes on the same side: the same tribe, or allied both ways.
+    pub fn are_allied(&self, a: u8, b: u8) -> bool {
+        let (a, b) = (a as usize, b as usize);
+        a == b || (a < MAX_TRIBES && b < MAX_TRIBES && self.alliances[a][b] && self.alliances[b][a])
+    }
+
+    /// Make or break an alliance between two tribes, in both directions.
+    pub fn set_allied(&mut self, a: u8, b: u8, allied: bool) {
+        let (a, b) = (a as usize, b as usize);
+        if a == b || a >= MAX_TRIBES || b >= MAX_TRIBES {
+            return;
+        }
+        self.alliances[a][b] = allied;
+        self.alliances[b][a] = allied;
+    }
+
+    /// Split the tribes into teams: `teams[i]` is tribe i's team, and tribes
+    /// with the same team are allied. Tribes past the end of `teams` are
+    /// on their own. An empty list means every tribe for itself.
+    pub fn set_teams(&mut self, teams: &[u8]) {
+        self.alliances = [[false; MAX_TRIBES]; MAX_TRIBES];
+        for (a, &ta) in teams.iter().enumerate().take(MAX_TRIBES) {
+            for (b, &tb) in teams.iter().enumerate().take(MAX_TRIBES) {
+                self.alliances[a][b] = a != b && ta == tb;
+            }
+        }
+    }
+
+    /// Apply a tribe event raised by the object layer.
+    pub fn apply_event(&mut self, event: TribeEvent) {
+        match event {
+            TribeEvent::Discovered { tribe, discovery } => {
+                if let Some(t) = self.tribes.get_mut(tribe as usize) {
+                    discovery_grant(t, discovery);
+                }
+            }
+            TribeEvent::ShamanKilled { tribe, killer, respawn } => {
+                let Some(victim) = self.tribes.get_mut(tribe as usize) else { return };
+                victim.shaman_respawning = respawn;
+                // Nothing accumulates mana yet (the mana tick is a no-op),
+                // so this moves whatever a level or test put there
+                let lost = victim.mana * SHAMAN_DEAD_MANA_PCT_LOST / 100;
+                let gained = victim.mana * SHAMAN_DEAD_MANA_PCT_GAIN / 100;
+                victim.mana -= lost;
+                if let Some(k) = killer.filter(|&k| k != tribe) {
+                    if let Some(t) = self.tribes.get_mut(k as usize) {
+                        t.mana += gained;
+                    }
+                }
+                log::info!("[reinc] tribe {} shaman killed by {:?}: -{} mana, respawn={}",
+                    tribe, killer, lost, respawn);
+            }
+            TribeEvent::ShamanReincarnated { tribe } => {
+                if let Some(t) = self.tribes.get_mut(tribe as usize) {
+                    t.shaman_respawning = false;
+                }
+            }
+            TribeEvent::ReincarnationSite { tribe, standing } => {
+                if let Some(t) = self.tribes.get_mut(tribe as usize) {
+                    t.reincarnation_site = standing;
+                    if !standing {
+                        t.shaman_respawning = false;
+                    }
+                }
+            }
         }
     }
 
@@ -74,6 +254,22 @@ impl TribeArray {
             .filter(|t| t.active && !t.is_eliminated())
             .count()
     }
+
+    /// Fold every tribe's data into the desync checksums.
+    pub fn add_checksums(&self, sums: &mut StateChecksums) {
+        for tribe in &self.tribes {
+            sums.mix(SyncCategory::Players, tribe.active as u32 | (tribe.reincarnation_site as u32) << 1
+                | (tribe.shaman_respawning as u32) << 2 | tribe.victory_flags << 8);
+            sums.mix(SyncCategory::Players, tribe.reincarnation_timer as u32);
+            sums.mix(SyncCategory::Players, tribe.population);
+            sums.mix(SyncCategory::Players, tribe.mana);
+            for bits in [tribe.discovered_spells, tribe.discovered_buildings] {
+                sums.mix(SyncCategory::Players, bits as u32);
+                sums.mix(SyncCategory::Players, (bits >> 32) as u32);
+            }
+            sums.mix_bytes(SyncCategory::Players, &tribe.one_shot_abilities);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -116,4 +312,46 @@ mod tests {
         arr.tribes[3].active = false;
         assert_eq!(arr.alive_count(), 2);
     }
+
+    #[test]
+    fn test_teams_ally_both_ways() {
+        let mut arr = TribeArray::new();
+        assert!(arr.are_allied(2, 2));
+        assert!(!arr.are_allied(0, 1));
+
+        arr.set_teams(&[0, 1, 0, 1]);
+        assert!(arr.are_allied(0, 2) && arr.are_allied(2, 0));
+        assert!(arr.are_allied(1, 3));
+        assert!(!arr.are_allied(0, 1) && !arr.are_allied(2, 3));
+
+        // A one-sided alliance doesn't count
+        arr.alliances[0][1] = true;
+        assert!(!arr.are_allied(0, 1));
+        arr.set_allied(0, 2, false);
+        assert!(!arr.are_allied(2, 0));
+
+        arr.set_teams(&[]);
+        assert!(!arr.are_allied(1, 3));
+    }
+
+    #[test]
+    fn test_shaman_killed_moves_mana() {
+        let mut arr = TribeArray::new();
+        arr.tribes[0].mana = 1000;
+        arr.tribes[1].mana = 50;
+        arr.apply_event(TribeEvent::ShamanKilled { tribe: 0, killer: Some(1), respawn: true });
+        assert_eq!(arr.tribes[0].mana, 1000 - 1000 * SHAMAN_DEAD_MANA_PCT_LOST / 100);
+        assert_eq!(arr.tribes[1].mana, 50 + 1000 * SHAMAN_DEAD_MANA_PCT_GAIN / 100);
+        assert!(arr.tribes[0].shaman_respawning);
+    }
+
+    #[test]
+    fn test_destroyed_pillar_stops_reincarnation() {
+        let mut arr = TribeArray::new();
+        arr.apply_event(TribeEvent::ReincarnationSite { tribe: 2, standing: true });
+        arr.apply_event(TribeEvent::ShamanKilled { tribe: 2, killer: None, respawn: true });
+        assert!(arr.tribes[2].can_reincarnate());
+        arr.apply_event(TribeEvent::ReincarnationSite { tribe: 2, standing: false });
+        assert!(!arr.tribes[2].can_reincarnate());
+    }
 }
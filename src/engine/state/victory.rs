@@ -54,6 +54,9 @@ pub fn check_victory_conditions(
 ///
 /// Defeat: player tribe population == 0 (timer starts on first zero-pop check)
 /// Victory: all enemy tribes eliminated
+///
+/// A tribe whose shaman is waiting to reincarnate at a standing pillar
+/// is not out yet, even with no population left.
 fn check_singleplayer_victory(
     flags: &mut GameFlags,
     tribes: &mut TribeArray,
@@ -65,7 +68,9 @@ fn check_singleplayer_victory(
     // Original checks population via tribe offset that maps to the linked list head
     let player_pop = tribes.tribes[player].population;
 
-    if player_pop == 0 {
+    if player_pop == 0 && tribes.tribes[player].can_reincarnate() {
+        tribes.tribes[player].reincarnation_timer = 0;
+    } else if player_pop == 0 {
         // Start reincarnation timer if not already started
         if tribes.tribes[player].reincarnation_timer == 0 {
             tribes.tribes[player].reincarnation_timer = 1;
@@ -82,7 +87,7 @@ fn check_singleplayer_victory(
     // Check if all enemy tribes are eliminated
     let all_enemies_dead = tribes.tribes.iter().enumerate()
         .filter(|(i, t)| *i != player && t.active)
-        .all(|(_, t)| t.population == 0);
+        .all(|(_, t)| t.population == 0 && !t.can_reincarnate());
 
     if all_enemies_dead {
         // Original: sets victory flag, transitions units to celebrate state (0x29)
@@ -93,55 +98,42 @@ fn check_singleplayer_victory(
 /// Multiplayer victory/defeat logic.
 /// Original: branch at 0x00423d19 (when multiplayer flag is set)
 ///
-/// Each tribe with zero population gets its timer started.
-/// When timer reaches max, tribe is defeated.
-/// Last tribe standing (or allied group) wins.
+/// Each tribe with zero population gets its timer started and is out once
+/// it maxes. Allied tribes play as a team (TribeArray::alliances):
+/// - Defeat: no tribe on the player's side is still standing.
+/// - Victory: every tribe still standing is mutually allied and on the
+///   player's side — last tribe, or allied group, standing. A player whose
+///   own tribe is out but whose allies win shares the victory.
 fn check_multiplayer_victory(
     flags: &mut GameFlags,
     tribes: &mut TribeArray,
     player_tribe: u8,
 ) {
-    let player = player_tribe as usize;
+    // Tribes still standing: timer not started (has population, or a
+    // shaman about to reincarnate). Tribes with a running timer are in
+    // their elimination countdown or already out.
+    let standing: Vec<u8> = tribes.tribes.iter()
+        .filter(|t| t.active && t.reincarnation_timer == 0)
+        .filter(|t| t.population > 0 || t.can_reincarnate())
+        .map(|t| t.index)
+        .collect();
 
-    // Count alive tribes and detect eliminations
-    let mut alive_count: u32 = 0;
-    let mut last_alive: i32 = -1;
-
-    for (i, tribe) in tribes.tribes.iter().enumerate() {
-        if !tribe.active {
-            continue;
-        }
-
-        if tribe.reincarnation_timer == 0 {
-            // Tribe is alive (timer not started = has population)
-            if tribe.population > 0 {
-                alive_count += 1;
-                last_alive = i as i32;
-            } else {
-                // Start elimination timer
-                // (handled by the reincarnation timer update above)
-            }
-        }
-        // If timer > 0, tribe is in elimination countdown or already eliminated
-    }
-
-    // If 0 or 1 tribes remain, determine winner
-    if alive_count < 2 {
-        if last_alive >= 0 {
-            // One tribe remains — they win
-            if last_alive == player as i32 {
-                flags.set_won();
-            } else {
-                flags.set_lost();
-            }
-        }
-        // If alive_count == 0, something went wrong — no action
+    // Nobody left at all — nothing to decide
+    if standing.is_empty() {
+        return;
     }
 
-    // TODO: Alliance victory check.
-    // Original uses alliance matrix at DAT_00948e4e to check if all
-    // remaining tribes are mutually allied. Requires alliance data
-    // from the save-network subsystem.
+    // Original: alliance check against DAT_00948e4e — the game is over for
+    // us once our side is gone, or once only one side remains
+    if !standing.iter().any(|&t| tribes.are_allied(player_tribe, t)) {
+        flags.set_lost();
+        return;
+    }
+    let one_side = standing.iter()
+        .all(|&a| standing.iter().all(|&b| tribes.are_allied(a, b)));
+    if one_side {
+        flags.set_won();
+    }
 }
 
 #[cfg(test)]
@@ -203,6 +195,36 @@ mod tests {
         assert!(!flags.has_won());
     }
 
+    #[test]
+    fn test_sp_no_defeat_while_shaman_reincarnates() {
+        let mut flags = GameFlags::new();
+        let mut tribes = setup_tribes([0, 10, 0, 0], [true, true, false, false]);
+        tribes.tribes[0].reincarnation_site = true;
+        tribes.tribes[0].shaman_respawning = true;
+        check_victory_conditions(0x20, &mut flags, &mut tribes, 0);
+        assert!(!flags.has_lost());
+        assert_eq!(tribes.tribes[0].reincarnation_timer, 0);
+
+        // Pillar destroyed — the shaman can't come back, countdown starts
+        tribes.tribes[0].reincarnation_site = false;
+        check_victory_conditions(0x20, &mut flags, &mut tribes, 0);
+        assert_eq!(tribes.tribes[0].reincarnation_timer, 1);
+    }
+
+    #[test]
+    fn test_sp_no_victory_while_enemy_shaman_reincarnates() {
+        let mut flags = GameFlags::new();
+        let mut tribes = setup_tribes([10, 0, 0, 0], [true, true, false, false]);
+        tribes.tribes[1].reincarnation_site = true;
+        tribes.tribes[1].shaman_respawning = true;
+        check_victory_conditions(0x20, &mut flags, &mut tribes, 0);
+        assert!(!flags.has_won());
+
+        tribes.tribes[1].reincarnation_site = false;
+        check_victory_conditions(0x30, &mut flags, &mut tribes, 0);
+        assert!(flags.has_won());
+    }
+
     #[test]
     fn test_mp_last_tribe_standing() {
         let mut flags = GameFlags::from_raw(super::super::constants::FLAG_MULTIPLAYER);
@@ -214,6 +236,46 @@ mod tests {
         assert!(flags.has_won());
     }
 
+    #[test]
+    fn test_mp_team_wins_together() {
+        // 2v2: tribes 0+2 against 1+3; tribe 0 is already out
+        let mut flags = GameFlags::from_raw(super::super::constants::FLAG_MULTIPLAYER);
+        let mut tribes = setup_tribes([0, 10, 10, 0], [true, true, true, true]);
+        tribes.set_teams(&[0, 1, 0, 1]);
+        tribes.tribes[0].reincarnation_timer = REINCARNATION_TIMER_MAX;
+        tribes.tribes[3].reincarnation_timer = REINCARNATION_TIMER_MAX;
+        check_victory_conditions(0x20, &mut flags, &mut tribes, 0);
+        // Our ally still fights on
+        assert!(!flags.has_won() && !flags.has_lost());
+
+        tribes.tribes[1].population = 0;
+        tribes.tribes[1].reincarnation_timer = REINCARNATION_TIMER_MAX;
+        check_victory_conditions(0x30, &mut flags, &mut tribes, 0);
+        assert!(flags.has_won());
+
+        // The other team saw it as a defeat
+        let mut flags = GameFlags::from_raw(super::super::constants::FLAG_MULTIPLAYER);
+        check_victory_conditions(0x30, &mut flags, &mut tribes, 3);
+        assert!(flags.has_lost());
+    }
+
+    #[test]
+    fn test_mp_defeat_when_side_is_out() {
+        // Three sides: 0 alone, 1+2 allied, 3 alone. Once tribe 0 is out it
+        // has lost, though the others play on.
+        let mut flags = GameFlags::from_raw(super::super::constants::FLAG_MULTIPLAYER);
+        let mut tribes = setup_tribes([0, 10, 10, 10], [true, true, true, true]);
+        tribes.set_teams(&[0, 1, 1, 2]);
+        tribes.tribes[0].reincarnation_timer = REINCARNATION_TIMER_MAX;
+        check_victory_conditions(0x20, &mut flags, &mut tribes, 0);
+        assert!(flags.has_lost());
+
+        // Allies standing with a third tribe still around: not over yet
+        let mut flags = GameFlags::from_raw(super::super::constants::FLAG_MULTIPLAYER);
+        check_victory_conditions(0x20, &mut flags, &mut tribes, 1);
+        assert!(!flags.has_won() && !flags.has_lost());
+    }
+
     #[test]
     fn test_reincarnation_timer_increments() {
         let mut flags = GameFlags::new();
@@ -0,0 +1,502 @@
+// Recorded unit traces — replay a capture of the original game on the
+// UnitCoordinator and find where the two simulations part ways.
+//
+// A trace is JSON Lines. The first line is a header, every later line one
+// frame of simulation state:
+//
+//   {"format": "pop3-trace", "version": 1, "level": 1, "rng_seed": 4660}
+//   {"tick": 0, "rng_seed": 4660,
+//    "units": [{"id": 17, "x": 8448, "z": 8448, "state": 1, "health": 1400}],
+//    "orders": [{"units": [17], "move": [9984, 8448]}]}
+//
+// Header `rng_seed` is g_RandomSeed before the level's persons are created.
+// Frame `tick` counts simulation ticks since the level started; tick 0 is
+// the loaded level, before any tick ran, and must come first. Frames may
+// skip ticks. A frame's units, state byte (+0x2C) and seed are the state at
+// the end of that tick; its orders were issued during it and take effect
+// from the next tick. `state`, `health` and frame `rng_seed` are optional.
+//
+// Coordinates are world units, 0..0xFFFF (negative values wrap the same
+// way). Unit ids are the capture's own object numbers. They are matched to our
+// units by position in the tick 0 frame, so the capture side never needs
+// to know how the level loader numbers persons.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde_json::{json, Value};
+
+use crate::engine::movement::WorldCoord;
+use crate::engine::units::{UnitCoordinator, UnitId};
+
+pub const TRACE_FORMAT: &str = "pop3-trace";
+pub const TRACE_VERSION: u64 = 1;
+
+/******************************************************************************/
+
+/// One unit's state in a frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceUnit {
+    pub id: u32,
+    pub position: WorldCoord,
+    /// PersonState byte (offset 0x2C).
+    pub state: Option<u8>,
+    pub health: Option<u16>,
+}
+
+/// A move order: select `units`, then right-click `target`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceOrder {
+    pub units: Vec<u32>,
+    pub target: WorldCoord,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceFrame {
+    pub tick: u32,
+    pub rng_seed: Option<u32>,
+    pub units: Vec<TraceUnit>,
+    pub orders: Vec<TraceOrder>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    pub level: u8,
+    pub rng_seed: u32,
+    pub frames: Vec<TraceFrame>,
+}
+
+fn field<'a>(value: &'a Value, key: &str, what: &str) -> Result<&'a Value, String> {
+    value.get(key).ok_or_else(|| format!("{}: missing \"{}\"", what, key))
+}
+
+fn uint(value: &Value, key: &str, what: &str) -> Result<u64, String> {
+    field(value, key, what)?.as_u64().ok_or_else(|| format!("{}: \"{}\" is not an unsigned integer", what, key))
+}
+
+fn coord(x: &Value, z: &Value, what: &str) -> Result<WorldCoord, String> {
+    match (x.as_i64(), z.as_i64()) {
+        (Some(x), Some(z)) => Ok(WorldCoord::new(x as i16, z as i16)),
+        _ => Err(format!("{}: coordinates are not integers", what)),
+    }
+}
+
+impl TraceUnit {
+    fn from_json(value: &Value, what: &str) -> Result<Self, String> {
+        let id = uint(value, "id", what)? as u32;
+        let what = format!("{} unit {}", what, id);
+        Ok(Self {
+            id,
+            position: coord(field(value, "x", &what)?, field(value, "z", &what)?, &what)?,
+            state: value.get("state").and_then(Value::as_u64).map(|s| s as u8),
+            health: value.get("health").and_then(Value::as_u64).map(|h| h as u16),
+        })
+    }
+
+    fn to_json(&self) -> Value {
+        let mut unit = json!({ "id": self.id, "x": self.position.x as u16, "z": self.position.z as u16 });
+        if let Some(state) = self.state {
+            unit["state"] = json!(state);
+        }
+        if let Some(health) = self.health {
+            unit["health"] = json!(health);
+        }
+        unit
+    }
+}
+
+impl TraceOrder {
+    fn from_json(value: &Value, what: &str) -> Result<Self, String> {
+        let units = field(value, "units", what)?.as_array()
+            .ok_or_else(|| format!("{}: \"units\" is not a list", what))?
+            .iter()
+            .map(|id| id.as_u64().map(|id| id as u32).ok_or_else(|| format!("{}: bad unit id {}", what, id)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let target = match field(value, "move", what)?.as_array().map(Vec::as_slice) {
+            Some([x, z]) => coord(x, z, what)?,
+            _ => return Err(format!("{}: \"move\" is not an [x, z] pair", what)),
+        };
+        Ok(Self { units, target })
+    }
+
+    fn to_json(&self) -> Value {
+        json!({ "units": self.units, "move": [self.target.x as u16, self.target.z as u16] })
+    }
+}
+
+impl TraceFrame {
+    fn from_json(value: &Value, line: usize) -> Result<Self, String> {
+        let what = format!("line {}", line);
+        let tick = uint(value, "tick", &what)? as u32;
+        let list = |key: &str| value.get(key).and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+        Ok(Self {
+            tick,
+            rng_seed: value.get("rng_seed").and_then(Value::as_u64).map(|s| s as u32),
+            units: list("units").iter().map(|u| TraceUnit::from_json(u, &what)).collect::<Result<_, _>>()?,
+            orders: list("orders").iter().map(|o| TraceOrder::from_json(o, &what)).collect::<Result<_, _>>()?,
+        })
+    }
+
+    fn to_json(&self) -> Value {
+        let mut frame = json!({
+            "tick": self.tick,
+            "units": self.units.iter().map(TraceUnit::to_json).collect::<Vec<_>>(),
+        });
+        if let Some(seed) = self.rng_seed {
+            frame["rng_seed"] = json!(seed);
+        }
+        if !self.orders.is_empty() {
+            frame["orders"] = json!(self.orders.iter().map(TraceOrder::to_json).collect::<Vec<_>>());
+        }
+        frame
+    }
+}
+
+impl Trace {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty());
+        let (_, header) = lines.next().ok_or("empty trace")?;
+        let header: Value = serde_json::from_str(header).map_err(|e| format!("line 1: {}", e))?;
+        if header.get("format").and_then(Value::as_str) != Some(TRACE_FORMAT) {
+            return Err(format!("line 1: not a {} header", TRACE_FORMAT));
+        }
+        let version = uint(&header, "version", "header")?;
+        if version != TRACE_VERSION {
+            return Err(format!("unsupported trace version {} (expected {})", version, TRACE_VERSION));
+        }
+        let mut trace = Trace {
+            level: uint(&header, "level", "header")? as u8,
+            rng_seed: uint(&header, "rng_seed", "header")? as u32,
+            frames: Vec::new(),
+        };
+        for (n, line) in lines {
+            let value: Value = serde_json::from_str(line).map_err(|e| format!("line {}: {}", n, e))?;
+            let frame = TraceFrame::from_json(&value, n)?;
+            if trace.frames.last().is_some_and(|last| last.tick >= frame.tick) {
+                return Err(format!("line {}: tick {} is not after the previous frame", n, frame.tick));
+            }
+            trace.frames.push(frame);
+        }
+        match trace.frames.first() {
+            Some(frame) if frame.tick == 0 => Ok(trace),
+            Some(frame) => Err(format!("first frame is tick {}, expected tick 0", frame.tick)),
+            None => Err("trace has no frames".to_string()),
+        }
+    }
+
+    pub fn to_jsonl(&self) -> String {
+        let header = json!({
+            "format": TRACE_FORMAT, "version": TRACE_VERSION,
+            "level": self.level, "rng_seed": self.rng_seed,
+        });
+        std::iter::once(header)
+            .chain(self.frames.iter().map(TraceFrame::to_json))
+            .map(|line| line.to_string() + "\n")
+            .collect()
+    }
+}
+
+/******************************************************************************/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceField {
+    /// A captured unit with no counterpart in our simulation.
+    Unit,
+    Position,
+    State,
+    Health,
+    Rng,
+}
+
+impl TraceField {
+    pub const ALL: [TraceField; 5] =
+        [TraceField::Unit, TraceField::Position, TraceField::State, TraceField::Health, TraceField::Rng];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TraceField::Unit => "unit",
+            TraceField::Position => "position",
+            TraceField::State => "state",
+            TraceField::Health => "health",
+            TraceField::Rng => "rng",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.name() == name)
+    }
+}
+
+/// Which fields to compare, and how far apart positions may be.
+#[derive(Debug, Clone, Default)]
+pub struct CompareOptions {
+    pub ignore: Vec<TraceField>,
+    /// Allowed per-axis position difference in world units.
+    pub tolerance: u16,
+}
+
+impl CompareOptions {
+    fn checks(&self, field: TraceField) -> bool {
+        !self.ignore.contains(&field)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub tick: u32,
+    /// Capture unit id; None for frame-level fields.
+    pub unit: Option<u32>,
+    pub field: TraceField,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "tick {}", self.tick)?;
+        if let Some(unit) = self.unit {
+            write!(f, " unit {}", unit)?;
+        }
+        write!(f, ": {} expected {}, got {}", self.field.name(), self.expected, self.actual)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReplayReport {
+    /// Frames that matched before the first divergence (or all of them).
+    pub frames_matched: usize,
+    /// Last tick that matched.
+    pub last_good_tick: Option<u32>,
+    /// Every difference in the first diverging frame; empty if none.
+    pub divergences: Vec<Divergence>,
+}
+
+impl ReplayReport {
+    pub fn first_divergence(&self) -> Option<&Divergence> {
+        self.divergences.first()
+    }
+}
+
+/// Map capture unit ids to our units by their tick 0 positions.
+pub fn match_units(coord: &UnitCoordinator, frame: &TraceFrame) -> Result<HashMap<u32, UnitId>, String> {
+    let mut ids = HashMap::new();
+    let mut taken = vec![false; coord.units.len()];
+    for unit in &frame.units {
+        let ours = coord.units.iter().position(|u| !taken[u.id] && u.movement.position == unit.position)
+            .ok_or_else(|| format!("capture unit {} at ({}, {}) has no unit of ours at that position",
+                unit.id, unit.position.x, unit.position.z))?;
+        taken[ours] = true;
+        if ids.insert(unit.id, ours).is_some() {
+            return Err(format!("capture unit {} is listed twice at tick {}", unit.id, frame.tick));
+        }
+    }
+    Ok(ids)
+}
+
+fn axis_error(a: i16, b: i16) -> u16 {
+    a.wrapping_sub(b).unsigned_abs()
+}
+
+/// Differences between our simulation and one captured frame.
+pub fn compare_frame(
+    coord: &UnitCoordinator,
+    frame: &TraceFrame,
+    ids: &HashMap<u32, UnitId>,
+    options: &CompareOptions,
+) -> Vec<Divergence> {
+    let mut out = Vec::new();
+    let mut diverge = |unit, field, expected: String, actual: String| {
+        out.push(Divergence { tick: frame.tick, unit, field, expected, actual });
+    };
+    if let (Some(seed), true) = (frame.rng_seed, options.checks(TraceField::Rng)) {
+        if coord.rng.seed() != seed {
+            diverge(None, TraceField::Rng, format!("{:#010x}", seed), format!("{:#010x}", coord.rng.seed()));
+        }
+    }
+    for captured in &frame.units {
+        let Some(ours) = ids.get(&captured.id).and_then(|&i| coord.units.get(i)) else {
+            if options.checks(TraceField::Unit) {
+                diverge(Some(captured.id), TraceField::Unit, "a unit".to_string(), "none".to_string());
+            }
+            continue;
+        };
+        let (p, q) = (captured.position, ours.movement.position);
+        if options.checks(TraceField::Position)
+            && (axis_error(p.x, q.x) > options.tolerance || axis_error(p.z, q.z) > options.tolerance)
+        {
+            diverge(Some(captured.id), TraceField::Position,
+                format!("({}, {})", p.x as u16, p.z as u16), format!("({}, {})", q.x as u16, q.z as u16));
+        }
+        if let (Some(state), true) = (captured.state, options.checks(TraceField::State)) {
+            if ours.state as u8 != state {
+                diverge(Some(captured.id), TraceField::State,
+                    format!("{:#04x}", state), format!("{:#04x} ({:?})", ours.state as u8, ours.state));
+            }
+        }
+        if let (Some(health), true) = (captured.health, options.checks(TraceField::Health)) {
+            if ours.health != health {
+                diverge(Some(captured.id), TraceField::Health, health.to_string(), ours.health.to_string());
+            }
+        }
+    }
+    out
+}
+
+/// Issue a captured order to our units.
+pub fn apply_order(coord: &mut UnitCoordinator, order: &TraceOrder, ids: &HashMap<u32, UnitId>) -> Result<(), String> {
+    let units = order.units.iter()
+        .map(|id| ids.get(id).copied().ok_or_else(|| format!("order for unknown capture unit {}", id)))
+        .collect::<Result<Vec<_>, _>>()?;
+    coord.selection.select_multiple(units);
+    coord.order_move(order.target);
+    Ok(())
+}
+
+/// Replay `trace` on a coordinator that has just loaded the trace's level
+/// (with `rng` seeded from the header) and stop at the first frame that
+/// differs. Errors are problems with the trace itself.
+pub fn replay(coord: &mut UnitCoordinator, trace: &Trace, options: &CompareOptions) -> Result<ReplayReport, String> {
+    let first = trace.frames.first().ok_or("trace has no frames")?;
+    let ids = match_units(coord, first)?;
+    let mut report = ReplayReport::default();
+    let mut tick = 0;
+    for frame in &trace.frames {
+        while tick < frame.tick {
+            coord.tick();
+            tick += 1;
+        }
+        report.divergences = compare_frame(coord, frame, &ids, options);
+        if !report.divergences.is_empty() {
+            return Ok(report);
+        }
+        report.frames_matched += 1;
+        report.last_good_tick = Some(frame.tick);
+        for order in &frame.orders {
+            apply_order(coord, order, &ids)?;
+        }
+    }
+    Ok(report)
+}
+
+/// Snapshot every unit of ours; ids are our unit ids.
+pub fn capture_frame(coord: &UnitCoordinator, tick: u32) -> TraceFrame {
+    TraceFrame {
+        tick,
+        rng_seed: Some(coord.rng.seed()),
+        units: coord.units.iter().map(|u| TraceUnit {
+            id: u.id as u32,
+            position: u.movement.position,
+            state: Some(u.state as u8),
+            health: Some(u.health),
+        }).collect(),
+        orders: Vec::new(),
+    }
+}
+
+/// Record our own simulation for `ticks` ticks in trace form, issuing
+/// `orders` (ids are our unit ids) at the end of their tick. `rng_seed` is
+/// the seed the level was loaded with.
+pub fn record(coord: &mut UnitCoordinator, level: u8, rng_seed: u32, ticks: u32, orders: &[(u32, TraceOrder)]) -> Trace {
+    let ids: HashMap<u32, UnitId> = coord.units.iter().map(|u| (u.id as u32, u.id)).collect();
+    let mut frames = Vec::with_capacity(ticks as usize + 1);
+    for tick in 0..=ticks {
+        if tick > 0 {
+            coord.tick();
+        }
+        let mut frame = capture_frame(coord, tick);
+        for (_, order) in orders.iter().filter(|(t, _)| *t == tick) {
+            // Orders naming units we don't have are dropped, as in the game
+            if apply_order(coord, order, &ids).is_ok() {
+                frame.orders.push(order.clone());
+            }
+        }
+        frames.push(frame);
+    }
+    Trace { level, rng_seed, frames }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::units::Unit;
+
+    fn coordinator() -> UnitCoordinator {
+        let mut coord = UnitCoordinator::new();
+        for (i, x) in [0x2100i16, 0x2300, 0x2500].into_iter().enumerate() {
+            let position = WorldCoord::new(x, 0x2100);
+            coord.units.push(Unit { state_timer: 40, ..Unit::new_person(i, 2, 0, position) });
+        }
+        coord
+    }
+
+    fn recording() -> Trace {
+        let mut coord = coordinator();
+        let order = TraceOrder { units: vec![0, 2], target: WorldCoord::new(0x3100, 0x2900) };
+        let seed = coord.rng.seed();
+        record(&mut coord, 1, seed, 60, &[(5, order)])
+    }
+
+    #[test]
+    fn traces_round_trip_through_json_lines() {
+        let trace = recording();
+        let text = trace.to_jsonl();
+        assert!(text.starts_with("{\"format\":\"pop3-trace\""));
+        assert_eq!(text.lines().count(), 62);
+        assert_eq!(Trace::parse(&text), Ok(trace));
+
+        assert!(Trace::parse("").is_err());
+        let header = "{\"format\":\"pop3-trace\",\"version\":1,\"level\":1,\"rng_seed\":0}\n";
+        assert!(Trace::parse(&format!("{}{{\"tick\":3}}\n", header)).unwrap_err().contains("expected tick 0"));
+        assert!(Trace::parse(&format!("{}{{\"tick\":0}}\n{{\"tick\":0}}\n", header)).unwrap_err().contains("line 3"));
+        assert!(Trace::parse(&format!("{}{{\"tick\":0,\"units\":[{{\"id\":1}}]}}\n", header))
+            .unwrap_err().contains("missing \"x\""));
+    }
+
+    #[test]
+    fn replaying_our_own_recording_matches_every_frame() {
+        let trace = recording();
+        assert!(trace.frames[60].units[0].position != trace.frames[0].units[0].position, "unit 0 never moved");
+
+        // Capture ids need not be ours: renumber them
+        let mut renumbered = trace.clone();
+        for frame in &mut renumbered.frames {
+            frame.units.iter_mut().for_each(|u| u.id += 100);
+            frame.orders.iter_mut().for_each(|o| o.units.iter_mut().for_each(|id| *id += 100));
+        }
+        // ...and keep only every tenth frame
+        renumbered.frames.retain(|f| f.tick % 10 == 0 || !f.orders.is_empty());
+
+        let report = replay(&mut coordinator(), &renumbered, &CompareOptions::default()).unwrap();
+        assert!(report.divergences.is_empty(), "{:?}", report.first_divergence());
+        assert_eq!(report.last_good_tick, Some(60));
+        assert_eq!(report.frames_matched, renumbered.frames.len());
+    }
+
+    #[test]
+    fn reports_the_first_diverging_tick_and_field() {
+        let mut trace = recording();
+        let moved = trace.frames[30].units[2].position;
+        trace.frames[30].units[2].position = WorldCoord::new(moved.x + 3, moved.z);
+        trace.frames[40].units[1].health = Some(1);
+
+        let report = replay(&mut coordinator(), &trace, &CompareOptions::default()).unwrap();
+        assert_eq!(report.last_good_tick, Some(29));
+        let first = report.first_divergence().unwrap();
+        assert_eq!((first.tick, first.unit, first.field), (30, Some(2), TraceField::Position));
+        assert!(first.to_string().starts_with("tick 30 unit 2: position expected"));
+
+        // Within tolerance, the health edit is the next difference
+        let options = CompareOptions { tolerance: 3, ..Default::default() };
+        let report = replay(&mut coordinator(), &trace, &options).unwrap();
+        let first = report.first_divergence().unwrap();
+        assert_eq!((first.tick, first.unit, first.field), (40, Some(1), TraceField::Health));
+        let options = CompareOptions { tolerance: 3, ignore: vec![TraceField::Health] };
+        assert!(replay(&mut coordinator(), &trace, &options).unwrap().divergences.is_empty());
+
+        // An unknown capture unit at tick 0 is a trace error
+        trace.frames[0].units[0].position = WorldCoord::new(0x100, 0x100);
+        assert!(replay(&mut coordinator(), &trace, &CompareOptions::default()).unwrap_err().contains("no unit of ours"));
+    }
+}
@@ -0,0 +1,131 @@
+// Local collision avoidance — keeps walking units from passing through
+// each other.
+//
+// Routes are planned per unit and only destination tiles are de-duplicated
+// (UsedTargetsCache), so two units on crossing paths would otherwise overlap.
+// UnitCoordinator layers two rules on top of the per-tick movement:
+//
+//   Blocking:   a step that ends within BLOCK_RADIUS of another unit and
+//               brings the mover closer to it is refused. The mover sets
+//               FLAG1_BLOCKED and tries a sidestep (45°, then 90°, always
+//               turning the same way first so head-on pairs pass each
+//               other), or waits. After BLOCK_GIVE_UP_TICKS it squeezes
+//               past anyway so a jam can never last forever.
+//
+//   Separation: units closer than SEPARATION_RADIUS push apart, each taking
+//               half the overlap, capped at SEPARATION_MAX_PUSH per tick.
+//
+// Neighbours come from the UnitGrid, so both checks only look at nearby cells.
+//
+// Neither rule is in the original, where units walk through each other. Both
+// only run when GameWorld::unit_avoidance is set (`--avoidance`).
+
+use crate::engine::movement::{distance, move_point_by_angle, WorldCoord};
+use super::coords::toroidal_delta;
+use super::unit::UnitId;
+
+/// A step may not end closer than this to another unit (world units).
+pub const BLOCK_RADIUS: i32 = 0x60;
+/// Units closer than this are pushed apart (world units).
+pub const SEPARATION_RADIUS: i32 = 0x80;
+/// Largest separation push per axis per tick (world units).
+pub const SEPARATION_MAX_PUSH: i32 = 0x10;
+/// Blocked ticks before a unit squeezes past regardless.
+pub const BLOCK_GIVE_UP_TICKS: u8 = 24;
+/// Facing offsets tried for a sidestep, in order.
+pub const SIDESTEP_ANGLES: [u16; 4] = [0x100, 0x700, 0x200, 0x600];
+/// Neighbour search margin covering one step of the fastest mover.
+pub const AVOIDANCE_SEARCH_MARGIN: i32 = 0x80;
+
+/// Would stepping from `from` to `to` crowd the unit standing at `other`?
+/// Steps that keep or widen the gap are always allowed, so units can walk
+/// away from a neighbour they already overlap.
+pub fn step_blocked_by(from: &WorldCoord, to: &WorldCoord, other: &WorldCoord) -> bool {
+    let gap = distance(to, other);
+    gap < BLOCK_RADIUS && gap < distance(from, other)
+}
+
+/// Sidestep position: one step from `from` at `facing` turned by `turn`.
+pub fn sidestep(from: WorldCoord, facing: u16, turn: u16, speed: u16) -> WorldCoord {
+    let mut pos = from;
+    move_point_by_angle(&mut pos, facing.wrapping_add(turn) & 0x7FF, speed as i16);
+    pos
+}
+
+/// Push on unit `id` at `pos` away from unit `other_id` at `other`.
+/// Units on the exact same spot are split along a direction derived from
+/// the pair's ids, in opposite directions.
+pub fn separation_push(pos: &WorldCoord, other: &WorldCoord, id: UnitId, other_id: UnitId) -> (i32, i32) {
+    let dist = distance(other, pos);
+    if dist >= SEPARATION_RADIUS {
+        return (0, 0);
+    }
+    if dist == 0 {
+        let lo = id.min(other_id) as u32;
+        let mut angle = (lo.wrapping_mul(0x25B) & 0x7FF) as u16;
+        if id > other_id {
+            angle ^= 0x400;
+        }
+        let mut p = WorldCoord::new(0, 0);
+        move_point_by_angle(&mut p, angle, (SEPARATION_MAX_PUSH / 2) as i16);
+        return (p.x as i32, p.z as i32);
+    }
+    let overlap = (SEPARATION_RADIUS - dist) / 2;
+    let dx = toroidal_delta(other.x, pos.x);
+    let dz = toroidal_delta(other.z, pos.z);
+    (dx * overlap / dist, dz * overlap / dist)
+}
+
+/// Clamp a summed push to SEPARATION_MAX_PUSH per axis.
+pub fn clamp_push((x, z): (i32, i32)) -> (i16, i16) {
+    (
+        x.clamp(-SEPARATION_MAX_PUSH, SEPARATION_MAX_PUSH) as i16,
+        z.clamp(-SEPARATION_MAX_PUSH, SEPARATION_MAX_PUSH) as i16,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_toward_a_close_unit_are_blocked() {
+        let other = WorldCoord::new(1000, 1000);
+        let from = WorldCoord::new(1000, 1140);
+        assert!(step_blocked_by(&from, &WorldCoord::new(1000, 1080), &other));
+        // Still outside the radius
+        assert!(!step_blocked_by(&from, &WorldCoord::new(1000, 1100), &other));
+        // Walking away from an overlapping unit is fine
+        assert!(!step_blocked_by(&WorldCoord::new(1000, 1010), &WorldCoord::new(1000, 1050), &other));
+    }
+
+    #[test]
+    fn separation_splits_overlap_and_coincident_units() {
+        let a = WorldCoord::new(1000, 1000);
+        let b = WorldCoord::new(1000, 1040);
+        let (x, z) = separation_push(&a, &b, 0, 1);
+        assert_eq!(x, 0);
+        assert_eq!(z, -(SEPARATION_RADIUS - 40) / 2);
+        assert_eq!(separation_push(&b, &a, 1, 0), (0, (SEPARATION_RADIUS - 40) / 2));
+        assert_eq!(separation_push(&a, &WorldCoord::new(1000, 1200), 0, 1), (0, 0));
+
+        let (ax, az) = separation_push(&a, &a, 3, 7);
+        let (bx, bz) = separation_push(&a, &a, 7, 3);
+        assert!(ax != 0 || az != 0);
+        assert!((ax + bx).abs() <= 1 && (az + bz).abs() <= 1, "coincident pushes should oppose");
+    }
+
+    #[test]
+    fn pushes_are_clamped_and_sidesteps_turn_the_facing() {
+        assert_eq!(clamp_push((100, -100)), (SEPARATION_MAX_PUSH as i16, -SEPARATION_MAX_PUSH as i16));
+        assert_eq!(clamp_push((3, -4)), (3, -4));
+
+        let from = WorldCoord::new(2000, 2000);
+        let ahead = sidestep(from, 0, 0, 0x30);
+        let one_side = sidestep(from, 0, 0x200, 0x30);
+        let other_side = sidestep(from, 0, 0x600, 0x30);
+        assert_eq!(distance(&from, &ahead), 0x30);
+        assert_eq!(toroidal_delta(from.x, one_side.x), -toroidal_delta(from.x, other_side.x));
+        assert_ne!(one_side, ahead);
+    }
+}
@@ -5,34 +5,83 @@
 
 use crate::engine::state::rng::GameRng;
 use crate::engine::state::traits::ObjectTick;
+use crate::engine::command::GameCommand;
+use crate::engine::net::PlayerAction;
+use crate::engine::net::sync::{StateChecksums, SyncCategory};
 use crate::engine::movement::{
     RegionMap, SegmentPool, FailureCache, UsedTargetsCache,
-    PersonMovement, WorldCoord, RouteResult,
-    state_goto, process_route_movement, move_point_by_angle,
-    atan2,
+    WorldCoord, RouteResult, IslandMap, PathfindingMode,
+    state_goto_with, route_table_lookup_with, process_route_movement, move_point_by_angle,
+    atan2, distance, plan_water_crossing, sail_path, compress_route, slot_position,
+};
+use crate::engine::movement::constants::{
+    ARRIVAL_THRESHOLD, TERRAIN_CLASS_WATER, TERRAIN_CLASS_BUILDING, TERRAIN_CLASS_SHORE,
+    FORMATION_FLAG_BIT, FORMATION_MAX_DISTANCE, FORMATION_MAX_DIST_SQ,
+    CATCHUP_SPEED_NUM, CATCHUP_SPEED_DEN, FLAG1_BLOCKED,
+};
+use crate::engine::movement::formation::{
+    self, FORMATION_GROUP_SIZE, group_back_offset, jittered_slot_offset,
 };
 use crate::data::units::{ModelType, UnitRaw};
-use super::unit::Unit;
+use crate::engine::state::tribe::{AllianceMatrix, TribeEvent};
+use crate::engine::sound::{GameSound, SoundEvent};
+use crate::engine::state::constants::MAX_TRIBES;
+use crate::data::constants::PERSON_SUBTYPE_SHAMAN;
+use super::unit::{Unit, UnitId};
+use super::vehicle::{Vehicle, VehicleState, Ferry, Rider, BOAT_MAX_PASSENGERS};
+use super::trigger::{Trigger, TriggerKind, TriggerEffect, LevelEvent, load_triggers};
+use super::reincarnation::{ReincarnationPillar, load_pillars};
+use super::effect::{EffectPool, EffectType, EffectId, BLOOD_TINT};
+use super::stone_head::{
+    StoneHead, StoneHeadId, discovery_init, WORSHIP_RANGE, STONE_HEAD_PICK_RANGE,
+};
 use super::person_state::{
     PersonState, person_type_defaults, enter_state, tick_state, TickResult,
     calculate_melee_damage, apply_damage,
     CombatPhase, SWING_READY_TICKS,
     COMBAT_DETECT_RANGE, COMBAT_MELEE_RANGE,
 };
-use super::animation::{AnimationState, select_animation, tick_animation};
+use super::animation::{select_animation, tick_animation};
 use super::selection::{SelectionState, DragState};
+use super::spatial::UnitGrid;
+use super::avoidance::{
+    step_blocked_by, sidestep, separation_push, clamp_push,
+    BLOCK_RADIUS, BLOCK_GIVE_UP_TICKS, SEPARATION_RADIUS, SIDESTEP_ANGLES, AVOIDANCE_SEARCH_MARGIN,
+};
 use super::coords::{world_to_render_pos, toroidal_delta, cell_to_world, cell_to_tile};
 
+#[derive(Clone)]
 pub struct UnitCoordinator {
     pub units: Vec<Unit>,
+    pub vehicles: Vec<Vehicle>,
+    pub stone_heads: Vec<StoneHead>,
+    pub pillars: Vec<ReincarnationPillar>,
+    pub triggers: Vec<Trigger>,
+    pub effects: EffectPool,
     pub selection: SelectionState,
     pub drag: DragState,
+    // What each player has selected as far as the simulation knows — set by
+    // networked commands, separate from the local UI selection
+    player_selections: [Vec<UnitId>; MAX_TRIBES],
 
     // Movement infrastructure
     region_map: RegionMap,
     segment_pool: SegmentPool,
     failure_cache: FailureCache,
     used_targets: UsedTargetsCache,
+    // Walkable land split into islands — drives water-crossing orders
+    islands: IslandMap,
+
+    // Tribe-level events (discoveries, shaman deaths) awaiting the TribeArray
+    events: Vec<TribeEvent>,
+    // Sounds raised this tick, drained by the app into the audio mixer
+    sounds: Vec<SoundEvent>,
+
+    // Trigger-linked objects not yet in play: persons/vehicles waiting to
+    // spawn (level index, raw) and static objects the renderer keeps hidden
+    dormant_units: Vec<(usize, UnitRaw)>,
+    hidden_objects: Vec<usize>,
+    level_events: Vec<LevelEvent>,
 
     landscape_size: f32,
 
@@ -42,118 +91,563 @@ pub struct UnitCoordinator {
 
     // State machine RNG (same LCG as original binary)
     pub rng: GameRng,
+
+    // Formation slot jitter RNG (original state at 0x885710)
+    formation_rng: u32,
+
+    // Per-cell unit lists, rebuilt at the start of each tick
+    grid: UnitGrid,
+
+    // Route provider, mirrored from GameWorld::pathfinding
+    pathfinding: PathfindingMode,
+
+    // Collision avoidance, mirrored from GameWorld::unit_avoidance
+    avoidance: bool,
+
+    // Tribe alliances, mirrored from TribeArray::alliances each tick
+    alliances: AllianceMatrix,
 }
 
 impl UnitCoordinator {
     pub fn new() -> Self {
         Self {
             units: Vec::new(),
+            vehicles: Vec::new(),
+            stone_heads: Vec::new(),
+            pillars: Vec::new(),
+            triggers: Vec::new(),
+            effects: EffectPool::new(),
             selection: SelectionState::new(),
             drag: DragState::None,
+            player_selections: Default::default(),
             region_map: RegionMap::new(),
             segment_pool: SegmentPool::new(),
             failure_cache: FailureCache::new(),
             used_targets: UsedTargetsCache::new(),
+            islands: IslandMap::new(),
+            events: Vec::new(),
+            sounds: Vec::new(),
+            dormant_units: Vec::new(),
+            hidden_objects: Vec::new(),
+            level_events: Vec::new(),
             landscape_size: 128.0,
             anim_frame_counts: Vec::new(),
             rng: GameRng::new(0x1234),
+            formation_rng: 0,
+            grid: UnitGrid::new(),
+            pathfinding: PathfindingMode::Original,
+            avoidance: false,
+            alliances: [[false; MAX_TRIBES]; MAX_TRIBES],
+        }
+    }
+
+    /// Units of these tribes leave each other alone: same tribe, or allied
+    /// both ways.
+    pub fn are_allied(&self, a: u8, b: u8) -> bool {
+        let (a, b) = (a as usize, b as usize);
+        a == b || (a < MAX_TRIBES && b < MAX_TRIBES && self.alliances[a][b] && self.alliances[b][a])
+    }
+
+    pub fn pathfinding_mode(&self) -> PathfindingMode {
+        self.pathfinding
+    }
+
+    pub fn avoidance(&self) -> bool {
+        self.avoidance
+    }
+
+    /// Turn local collision avoidance (blocking and separation, see
+    /// avoidance.rs) on or off. Units blocked when it is turned off are
+    /// released.
+    pub fn set_avoidance(&mut self, on: bool) {
+        if on != self.avoidance {
+            self.avoidance = on;
+            for unit in &mut self.units {
+                unit.movement.flags1 &= !FLAG1_BLOCKED;
+                unit.blocked_ticks = 0;
+            }
+        }
+    }
+
+    /// Select the route provider for move orders from now on. Routes already
+    /// in the segment pool were planned by the previous provider and are
+    /// dropped so the two never share segments.
+    pub fn set_pathfinding_mode(&mut self, mode: PathfindingMode) {
+        if mode != self.pathfinding {
+            self.pathfinding = mode;
+            self.segment_pool = SegmentPool::new();
+            for unit in &mut self.units {
+                unit.movement.segment_index = 0;
+                unit.movement.waypoint_idx = 0;
+                unit.movement.next_waypoint = unit.movement.target_pos;
+            }
         }
     }
 
-    /// Extract person units from level data into live units.
-    /// Non-person objects remain as static LevelObjects in main.rs.
-    pub fn load_level(&mut self, units_raw: &[UnitRaw], landscape_height: &[[u16; 128]; 128], landscape_size: usize) {
+    /// Extract person units and vehicles from level data into live objects.
+    /// Other objects remain as static LevelObjects in main.rs; objects linked
+    /// from a trigger are held back until it fires.
+    ///
+    /// `seed` is the unit RNG seed the persons are created with: a replay's
+    /// recorded seed, the session's agreed one in multiplayer, or whatever
+    /// the RNG holds when playing alone.
+    pub fn load_level(&mut self, seed: u32, units_raw: &[UnitRaw], landscape_height: &[[u16; 128]; 128], landscape_size: usize) {
+        self.rng.set_seed(seed);
         self.units.clear();
+        self.vehicles.clear();
+        self.effects.clear();
+        self.sounds.clear();
         self.selection.clear();
+        self.player_selections.iter_mut().for_each(Vec::clear);
+        // Each level starts the formation jitter afresh, so a replay or a
+        // resync that reloads the level sees the same sequence
+        self.formation_rng = 0;
+        self.load_terrain(landscape_height, landscape_size);
+
+        log::info!("[unit-ctrl] load_level: {} raw units, landscape_size={}", units_raw.len(), landscape_size);
+
+        // Objects linked from a trigger stay dormant until it fires
+        self.triggers = load_triggers(units_raw);
+        self.dormant_units.clear();
+        self.hidden_objects.clear();
+        self.level_events.clear();
+        let mut linked: Vec<usize> = self.triggers.iter().flat_map(|t| t.links.iter().copied()).collect();
+        linked.sort_unstable();
+        linked.dedup();
+        for &i in &linked {
+            let raw = match units_raw.get(i) {
+                Some(r) => r,
+                None => continue,
+            };
+            match raw.model_type() {
+                Some(ModelType::Person) => self.dormant_units.push((i, *raw)),
+                // The renderer draws vehicles from the live list once woken
+                Some(ModelType::Vehicle) => {
+                    self.dormant_units.push((i, *raw));
+                    self.hidden_objects.push(i);
+                }
+                Some(ModelType::General) if self.triggers.iter().any(|t| t.level_index == i) => {}
+                Some(_) => self.hidden_objects.push(i),
+                None => {}
+            }
+        }
+
+        for (i, raw) in units_raw.iter().enumerate() {
+            if raw.model_type() == Some(ModelType::Person) && linked.binary_search(&i).is_err() {
+                self.spawn_person_raw(raw);
+            }
+        }
+        log::info!("[unit-ctrl] loaded {} person units", self.units.len());
+
+        for (i, raw) in units_raw.iter().enumerate() {
+            if raw.model_type() == Some(ModelType::Vehicle) && linked.binary_search(&i).is_err() {
+                self.spawn_vehicle_raw(i, raw);
+            }
+        }
+        log::info!("[unit-ctrl] loaded {} vehicles, {} islands",
+            self.vehicles.len(), self.islands.island_count());
+
+        self.events.clear();
+        self.stone_heads = discovery_init(units_raw);
+        for head in &mut self.stone_heads {
+            let (cx, cy) = world_to_render_pos(&head.position, self.landscape_size);
+            head.cell_x = cx;
+            head.cell_y = cy;
+        }
+        log::info!("[unit-ctrl] loaded {} stone heads", self.stone_heads.len());
+
+        self.pillars = load_pillars(units_raw);
+        for pillar in &mut self.pillars {
+            let (cx, cy) = world_to_render_pos(&pillar.position, self.landscape_size);
+            pillar.cell_x = cx;
+            pillar.cell_y = cy;
+        }
+        for tribe in 0..MAX_TRIBES as u8 {
+            let standing = self.pillars.iter().any(|p| p.tribe_index == tribe);
+            self.events.push(TribeEvent::ReincarnationSite { tribe, standing });
+        }
+        log::info!("[unit-ctrl] loaded {} reincarnation pillars", self.pillars.len());
+        log::info!("[unit-ctrl] loaded {} triggers, {} dormant units, {} hidden objects",
+            self.triggers.len(), self.dormant_units.len(), self.hidden_objects.len());
+    }
+
+    /// Create a live person from its level object.
+    fn spawn_person_raw(&mut self, raw: &UnitRaw) {
+        if raw.loc_x() == 0 && raw.loc_y() == 0 {
+            return;
+        }
+
+        let position = WorldCoord::new(raw.loc_x() as i16, raw.loc_y() as i16);
+        let mut unit = Unit::new_person(self.units.len(), raw.subtype, raw.tribe_index(), position);
+        unit.movement.facing_angle = (raw.angle() & 0x7FF) as u16;
+        (unit.cell_x, unit.cell_y) = world_to_render_pos(&position, self.landscape_size);
+        self.units.push(unit);
+        // Initialize idle state with a random timer (matches Person_Init calling Person_SetState)
+        let idx = self.units.len() - 1;
+        enter_state(&mut self.units[idx], PersonState::Idle, &mut self.rng);
+        select_animation(&mut self.units[idx].anim, PersonState::Idle, raw.subtype, &self.anim_frame_counts, false);
+    }
+
+    /// Create a live vehicle from its level object.
+    fn spawn_vehicle_raw(&mut self, level_index: usize, raw: &UnitRaw) {
+        let pos = WorldCoord::new(raw.loc_x() as i16, raw.loc_y() as i16);
+        let mut vehicle = Vehicle::new(self.vehicles.len(), level_index, raw.subtype, raw.tribe_index(), pos);
+        vehicle.facing_angle = (raw.angle() & 0x7FF) as u16;
+        let (cx, cy) = world_to_render_pos(&pos, self.landscape_size);
+        vehicle.cell_x = cx;
+        vehicle.cell_y = cy;
+        self.vehicles.push(vehicle);
+    }
+
+    /// Reset movement infrastructure and rebuild walkability, water and
+    /// island data from the landscape heights.
+    fn load_terrain(&mut self, landscape_height: &[[u16; 128]; 128], landscape_size: usize) {
         self.landscape_size = landscape_size as f32;
 
         // Reset movement infrastructure
         self.segment_pool = SegmentPool::new();
         self.failure_cache = FailureCache::new();
-        self.region_map = RegionMap::new();
-
-        Self::populate_water(&mut self.region_map, landscape_height, landscape_size);
-        self.region_map.set_terrain_flags(2, 0x00); // terrain class 2 = building = unwalkable
+        self.used_targets = UsedTargetsCache::new();
+        self.region_map = Self::terrain_region_map(landscape_height, landscape_size);
+        self.islands = IslandMap::build(&self.region_map);
+    }
 
-        log::info!("[unit-ctrl] load_level: {} raw units, landscape_size={}", units_raw.len(), landscape_size);
+    /// Region map of a landscape as units see it: water and the shore buffer
+    /// around it unwalkable, no buildings placed yet.
+    pub fn terrain_region_map(landscape_height: &[[u16; 128]; 128], landscape_size: usize) -> RegionMap {
+        let mut region_map = RegionMap::new();
+        Self::populate_water(&mut region_map, landscape_height, landscape_size);
+        region_map.set_terrain_flags(TERRAIN_CLASS_BUILDING, 0x00); // building = unwalkable
+        region_map
+    }
 
-        for raw in units_raw {
-            if raw.model_type() != Some(ModelType::Person) {
+    /// Issue move orders to all selected units targeting `target_world`.
+    /// Transitions units into GoToPoint state and calls state_goto.
+    /// Targets on another island are reached by boat when one is available
+    /// (walk to shore → board → sail → disembark → walk).
+    /// Targeting an unclaimed stone head sends the units to worship it.
+    /// Units walking over land move as formation groups (see order_formation).
+    pub fn order_move(&mut self, target_world: WorldCoord) {
+        self.used_targets.clear();
+        let selected = self.selection.selected.clone();
+        let head = self.stone_head_at(target_world);
+        let mut walkers = Vec::new();
+        for unit_id in selected {
+            match self.units.get(unit_id) {
+                Some(unit) if unit.alive => {
+                    // Passengers can't be redirected mid-crossing
+                    if unit.state == PersonState::EnteringVehicle { continue; }
+                }
+                _ => continue,
+            }
+            self.cancel_ferry_booking(unit_id);
+            self.cancel_worship(unit_id);
+            self.leave_formation(unit_id);
+            if let Some(head_id) = head {
+                self.order_worship(unit_id, head_id);
                 continue;
             }
-            if raw.loc_x() == 0 && raw.loc_y() == 0 {
+            if self.book_water_crossing(unit_id, target_world) {
                 continue;
             }
+            walkers.push(unit_id);
+        }
+        self.order_formation(walkers, target_world);
+    }
 
-            let defaults = person_type_defaults(raw.subtype);
-            let mut movement = PersonMovement::default();
-            movement.position = WorldCoord::new(raw.loc_x() as i16, raw.loc_y() as i16);
-            movement.facing_angle = (raw.angle() & 0x7FF) as u16;
-            movement.unit_type = raw.subtype;
-            movement.speed = defaults.speed;
-
-            let home = movement.position;
-            let (cx, cy) = world_to_render_pos(&movement.position, self.landscape_size);
-            self.units.push(Unit {
-                id: self.units.len(),
-                model_type: ModelType::Person,
-                subtype: raw.subtype,
-                tribe_index: raw.tribe_index(),
-                movement,
-                cell_x: cx,
-                cell_y: cy,
-                state: PersonState::Idle,
-                prev_state: PersonState::Idle,
-                state_timer: 0,
-                state_counter: 0,
-                health: defaults.max_health,
-                max_health: defaults.max_health,
-                target_unit: None,
-                attacker_unit: None,
-                alive: true,
-                home_pos: home,
-                behavior_flags: 0,
-                wander_duration: 0,
-                wander_range: 0,
-                linked_obj_id: None,
-                bloodlust: false,
-                shielded: false,
-                anim: AnimationState::default(),
+    /// Split walking units into groups of up to MAX_FOLLOWERS + 1. The unit
+    /// of each group nearest the target leads and routes normally; the rest
+    /// follow in formation slots and re-form around the leader on arrival.
+    /// Groups line up one behind another, facing from the units to the target.
+    fn order_formation(&mut self, mut walkers: Vec<UnitId>, target: WorldCoord) {
+        if walkers.len() < 2 {
+            for unit_id in walkers {
+                self.goto(unit_id, target);
+            }
+            return;
+        }
+        walkers.sort_by_key(|&id| (distance(&self.units[id].movement.position, &target), id));
+        let heading = {
+            let n = walkers.len() as i32;
+            let (sx, sz) = walkers.iter().fold((0i32, 0i32), |(sx, sz), &id| {
+                let pos = self.units[id].movement.position;
+                (sx + toroidal_delta(target.x, pos.x), sz + toroidal_delta(target.z, pos.z))
             });
-            // Initialize idle state with a random timer (matches Person_Init calling Person_SetState)
-            let idx = self.units.len() - 1;
-            enter_state(&mut self.units[idx], PersonState::Idle, &mut self.rng);
-            select_animation(&mut self.units[idx].anim, PersonState::Idle, raw.subtype, &self.anim_frame_counts, false);
+            atan2(-sx / n, sz / n)
+        };
+
+        for (group, members) in walkers.chunks(FORMATION_GROUP_SIZE).enumerate() {
+            let leader = members[0];
+            let leader_target = slot_position(target, heading, &group_back_offset(group));
+            if self.goto(leader, leader_target) == RouteResult::NoRoute || members.len() == 1 {
+                for &unit_id in &members[1..] {
+                    self.goto(unit_id, leader_target);
+                }
+                continue;
+            }
+            let leader_pos = self.units[leader].movement.position;
+            for (slot, &unit_id) in members.iter().enumerate().skip(1) {
+                let offset = jittered_slot_offset(slot, &mut self.formation_rng);
+                let dest = slot_position(leader_target, heading, &offset);
+                let start = slot_position(leader_pos, heading, &offset);
+                let gap = distance(&self.units[unit_id].movement.position, &start) as i64;
+                if gap * gap > FORMATION_MAX_DIST_SQ as i64 {
+                    // Too far away to fall in behind the leader
+                    self.goto(unit_id, dest);
+                    continue;
+                }
+                formation::add_follower(&mut self.units[leader].movement, unit_id as u16, offset);
+                let speed = person_type_defaults(self.units[unit_id].subtype).speed;
+                let unit = &mut self.units[unit_id];
+                unit.state = PersonState::GoToPoint;
+                unit.target_unit = None;
+                unit.movement.speed = speed;
+                unit.movement.segment_index = 0;
+                unit.movement.target_pos = dest;
+                unit.movement.next_waypoint = dest;
+                unit.movement.formation_flags |= FORMATION_FLAG_BIT;
+                unit.movement.set_goto_flags();
+            }
+            log::info!("[move-order] unit {} leads {} followers to ({}, {})",
+                leader, self.units[leader].movement.follower_count, leader_target.x, leader_target.z);
         }
-        log::info!("[unit-ctrl] loaded {} person units", self.units.len());
     }
 
-    /// Issue move orders to all selected units targeting `target_world`.
-    /// Transitions units into GoToPoint state and calls state_goto.
-    pub fn order_move(&mut self, target_world: WorldCoord) {
-        self.used_targets.clear();
-        for &unit_id in &self.selection.selected {
-            if let Some(unit) = self.units.get_mut(unit_id) {
-                if !unit.alive { continue; }
-                let result = state_goto(
-                    &self.region_map,
-                    &mut self.segment_pool,
-                    &self.failure_cache,
-                    &mut unit.movement,
-                    target_world,
-                    &mut self.used_targets,
-                );
-                if result == RouteResult::NoRoute {
-                    unit.movement.flags1 &= !0x1000;
-                } else {
-                    unit.state = PersonState::GoToPoint;
-                    unit.target_unit = None; // Cancel combat
-                    // Restore subtype speed (enter_idle sets it to 0)
-                    unit.movement.speed = person_type_defaults(unit.subtype).speed;
+    /// Take a unit out of any formation: a follower leaves its leader's
+    /// group, a leader's followers stop where they are.
+    fn leave_formation(&mut self, unit_id: UnitId) {
+        let followers = self.units[unit_id].movement.follower_count as usize;
+        for k in 0..followers {
+            let id = self.units[unit_id].movement.follower_ids[k] as usize;
+            if let Some(follower) = self.units.get_mut(id) {
+                follower.movement.formation_flags &= !FORMATION_FLAG_BIT;
+                follower.movement.flags1 &= !0x1000;
+            }
+        }
+        formation::clear_followers(&mut self.units[unit_id].movement);
+
+        if !self.units[unit_id].movement.in_formation() { return; }
+        self.units[unit_id].movement.formation_flags &= !FORMATION_FLAG_BIT;
+        for leader in &mut self.units {
+            let count = leader.movement.follower_count as usize;
+            if let Some(k) = leader.movement.follower_ids[..count].iter().position(|&f| f as usize == unit_id) {
+                formation::remove_follower(&mut leader.movement, k);
+                break;
+            }
+        }
+    }
+
+    /// Nearest stone head that can still be worshipped, if `target` is on it.
+    fn stone_head_at(&self, target: WorldCoord) -> Option<StoneHeadId> {
+        self.stone_heads.iter()
+            .filter(|h| h.is_worshippable())
+            .map(|h| (h.id, h.distance_to(target)))
+            .filter(|&(_, dist)| dist <= STONE_HEAD_PICK_RANGE)
+            .min_by_key(|&(_, dist)| dist)
+            .map(|(id, _)| id)
+    }
+
+    /// Send a unit to worship at a stone head.
+    fn order_worship(&mut self, unit_id: UnitId, head_id: StoneHeadId) {
+        let head_pos = self.stone_heads[head_id].position;
+        self.stone_heads[head_id].worshippers.push(unit_id);
+        log::info!("[discovery] unit {} ordered to worship stone head {}", unit_id, head_id);
+        self.goto(unit_id, head_pos);
+    }
+
+    /// Remove a unit from any stone head it was ordered to worship.
+    fn cancel_worship(&mut self, unit_id: UnitId) {
+        for head in &mut self.stone_heads {
+            head.worshippers.retain(|&w| w != unit_id);
+        }
+    }
+
+    /// Tribe events raised since the last call, for TribeArray::apply_event.
+    pub fn take_tribe_events(&mut self) -> Vec<TribeEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Persons alive per tribe; dormant ones don't count until they wake.
+    pub fn tribe_populations(&self) -> [u32; MAX_TRIBES] {
+        let mut counts = [0; MAX_TRIBES];
+        for unit in self.units.iter().filter(|u| u.alive) {
+            if let Some(count) = counts.get_mut(unit.tribe_index as usize) {
+                *count += 1;
+            }
+        }
+        counts
+    }
+
+    /// Drain sounds raised since the last call.
+    pub fn take_sound_events(&mut self) -> Vec<SoundEvent> {
+        std::mem::take(&mut self.sounds)
+    }
+
+    /// Level indices of static objects kept hidden until a trigger reveals them.
+    pub fn hidden_objects(&self) -> &[usize] {
+        &self.hidden_objects
+    }
+
+    /// Level events (revealed objects, script actions) since the last call.
+    pub fn take_level_events(&mut self) -> Vec<LevelEvent> {
+        std::mem::take(&mut self.level_events)
+    }
+
+    /// Knock down a tribe's reincarnation pillar. Its shaman will no longer
+    /// respawn, including one already waiting to.
+    ///
+    /// Only tests call this so far: nothing in the simulation damages
+    /// scenery or buildings yet (no spells, no building combat). Whatever
+    /// brings a pillar down there should end up here, so the tribe loses
+    /// its reincarnation site (TribeEvent::ReincarnationSite).
+    pub fn destroy_pillar(&mut self, tribe: u8) {
+        if let Some(pillar) = self.pillars.iter_mut().find(|p| p.tribe_index == tribe && !p.destroyed) {
+            pillar.destroy();
+            let pos = pillar.position;
+            log::info!("[reinc] tribe {} pillar destroyed", tribe);
+            self.spawn_effect(EffectType::Explosion2, pos);
+            self.spawn_effect(EffectType::SmokeCloud, pos);
+            self.events.push(TribeEvent::ReincarnationSite { tribe, standing: false });
+        }
+    }
+
+    /// Create a visual effect; dropped silently when the effect pool is full.
+    pub fn spawn_effect(&mut self, kind: EffectType, position: WorldCoord) -> Option<EffectId> {
+        let id = self.effects.spawn(kind, position)?;
+        self.place_effect(id);
+        Some(id)
+    }
+
+    fn spawn_blood(&mut self, position: WorldCoord) {
+        if let Some(id) = self.effects.spawn_tinted(EffectType::Splash, position, BLOOD_TINT) {
+            self.place_effect(id);
+        }
+    }
+
+    fn place_effect(&mut self, id: EffectId) {
+        if let Some(e) = self.effects.effects.iter_mut().find(|e| e.id == id) {
+            let (cx, cy) = world_to_render_pos(&e.position, self.landscape_size);
+            e.cell_x = cx;
+            e.cell_y = cy;
+        }
+    }
+
+    /// Route a single unit to `target` via state_goto and enter GoToPoint.
+    fn goto(&mut self, unit_id: UnitId, target: WorldCoord) -> RouteResult {
+        let unit = &mut self.units[unit_id];
+        let result = state_goto_with(
+            self.pathfinding,
+            &self.region_map,
+            &mut self.segment_pool,
+            &self.failure_cache,
+            &mut unit.movement,
+            target,
+            &mut self.used_targets,
+        );
+        if result == RouteResult::NoRoute {
+            unit.movement.flags1 &= !0x1000;
+        } else {
+            unit.state = PersonState::GoToPoint;
+            unit.target_unit = None; // Cancel combat
+            // Restore subtype speed (enter_idle sets it to 0)
+            unit.movement.speed = person_type_defaults(unit.subtype).speed;
+        }
+        log::info!("[move-order] unit {} result={:?} state={:?} target=({}, {})",
+            unit_id, result, unit.state,
+            unit.movement.target_pos.x, unit.movement.target_pos.z);
+        result
+    }
+
+    /// A unit finished its route segment. Enhanced routes may have more
+    /// turns than one segment holds, so look up the rest of the way from
+    /// here; the original walks the final stretch directly.
+    fn continue_route(&mut self, unit_id: UnitId) {
+        if self.pathfinding != PathfindingMode::Enhanced {
+            return;
+        }
+        let unit = &mut self.units[unit_id];
+        let target = unit.movement.target_pos;
+        route_table_lookup_with(
+            self.pathfinding,
+            &self.region_map,
+            &mut self.segment_pool,
+            &self.failure_cache,
+            &mut unit.movement,
+            target,
+        );
+        unit.movement.movement_dest = unit.movement.next_waypoint;
+    }
+
+    /// Plan a boat trip for a unit whose target is on another island.
+    /// Joins a boat already loading at the same landing when it has room,
+    /// otherwise dispatches the nearest idle boat that can reach the landing.
+    /// Returns false (caller falls back to a plain move) if no trip is possible.
+    fn book_water_crossing(&mut self, unit_id: UnitId, target: WorldCoord) -> bool {
+        let (pos, tribe) = {
+            let unit = &self.units[unit_id];
+            (unit.movement.position, unit.tribe_index)
+        };
+        let crossing = match plan_water_crossing(&self.region_map, &self.islands, pos, target) {
+            Some(c) => c,
+            None => return false,
+        };
+
+        let mut boat_idx = self.vehicles.iter().position(|v| {
+            v.usable_by(tribe) && v.ferry.as_ref().is_some_and(|f| {
+                !f.departed
+                    && f.embark == crossing.embark
+                    && f.target_island == crossing.target_island
+                    && f.riders.len() < BOAT_MAX_PASSENGERS
+            })
+        });
+
+        if boat_idx.is_none() {
+            let mut best: Option<(usize, usize)> = None; // (vehicle index, path length)
+            let mut best_route = Vec::new();
+            for (i, v) in self.vehicles.iter().enumerate() {
+                if !v.is_available() || !v.usable_by(tribe) { continue; }
+                if let Some(path) = sail_path(&self.region_map, v.position.to_tile(), crossing.embark.water) {
+                    if best.is_none_or(|(_, len)| path.len() < len) {
+                        best = Some((i, path.len()));
+                        best_route = compress_route(&path);
+                    }
                 }
-                log::info!("[move-order] unit {} result={:?} state={:?} target=({}, {})",
-                    unit_id, result, unit.state,
-                    unit.movement.target_pos.x, unit.movement.target_pos.z);
+            }
+            let (i, _) = match best {
+                Some(b) => b,
+                None => return false,
+            };
+            let boat = &mut self.vehicles[i];
+            boat.sail(best_route);
+            boat.ferry = Some(Ferry {
+                embark: crossing.embark,
+                disembark: crossing.disembark,
+                crossing: crossing.sail_route.clone(),
+                target_island: crossing.target_island,
+                riders: Vec::new(),
+                departed: false,
+            });
+            boat_idx = Some(i);
+        }
+
+        let boat_idx = boat_idx.unwrap_or_default();
+        let boat_id = self.vehicles[boat_idx].id;
+        if let Some(ferry) = self.vehicles[boat_idx].ferry.as_mut() {
+            ferry.riders.push(Rider { unit: unit_id, target, aboard: false });
+        }
+        log::info!("[ferry] unit {} booked on boat {} to island {}",
+            unit_id, boat_id, crossing.target_island);
+
+        self.goto(unit_id, crossing.embark.land.to_world());
+        self.units[unit_id].linked_obj_id = Some(boat_id);
+        true
+    }
+
+    /// Drop a unit's pending (not yet boarded) ferry booking, if any.
+    fn cancel_ferry_booking(&mut self, unit_id: UnitId) {
+        if let Some(boat_id) = self.units[unit_id].linked_obj_id.take() {
+            if let Some(ferry) = self.vehicles.get_mut(boat_id).and_then(|v| v.ferry.as_mut()) {
+                ferry.riders.retain(|r| r.unit != unit_id);
             }
         }
     }
@@ -162,7 +656,11 @@ impl UnitCoordinator {
     pub fn tick(&mut self) {
         let unit_count = self.units.len();
 
+        // Phase 0: Index unit positions for neighbour queries
+        self.rebuild_grid();
+
         // Phase 1: State machine tick + movement for each unit
+        let mut killed: Vec<usize> = Vec::new();
         for i in 0..unit_count {
             let unit = &mut self.units[i];
             if !unit.alive { continue; }
@@ -171,6 +669,9 @@ impl UnitCoordinator {
             let result = tick_state(unit, &mut self.rng);
             if let TickResult::Transition(new_state) = result {
                 enter_state(unit, new_state, &mut self.rng);
+                if new_state == PersonState::Dead {
+                    killed.push(i);
+                }
             }
 
             // Select animation every tick (matches decomp — walk→idle override needs movement check)
@@ -179,27 +680,67 @@ impl UnitCoordinator {
             // Advance animation frame
             tick_animation(&mut unit.anim);
 
-            // Process movement for moving states
-            if unit.movement.is_moving() {
+            // Process movement for moving states (followers move in Phase 1a)
+            if unit.movement.is_moving() && !unit.movement.in_formation() {
+                let before = unit.movement.position;
+                let routed = unit.movement.segment_index != 0;
                 Self::advance_movement(&mut self.segment_pool, unit, self.landscape_size);
+                if routed && self.units[i].movement.segment_index == 0 {
+                    self.continue_route(i);
+                }
+                if self.avoidance {
+                    self.avoid_collisions(i, before);
+                }
             }
 
             // Update rendering cache
+            let unit = &mut self.units[i];
             let (cx, cy) = world_to_render_pos(&unit.movement.position, self.landscape_size);
             unit.cell_x = cx;
             unit.cell_y = cy;
         }
 
+        for i in killed {
+            self.on_unit_killed(i);
+        }
+
+        // Phase 1a: Formation followers keep their slots behind the leader
+        self.tick_formations();
+
+        // Phase 1b: Vehicles — sailing, ferry boarding and unloading
+        self.tick_vehicles();
+
+        // Phase 1c: Stone head worship
+        self.tick_stone_heads();
+
+        // Phase 1d: Shaman reincarnation
+        self.tick_pillars();
+
+        // Phase 1e: Level triggers
+        self.tick_triggers();
+
+        // Phase 1f: Visual effects (low-priority pool)
+        self.effects.tick();
+
+        // Phase 1g: Push apart units standing on top of each other
+        if self.avoidance {
+            self.separate_units();
+        }
+
         // Phase 2: Drowning detection
         for i in 0..unit_count {
             let unit = &self.units[i];
             if !unit.alive { continue; }
             if unit.state == PersonState::Drowning || unit.state == PersonState::Dead { continue; }
+            if unit.state == PersonState::EnteringVehicle { continue; } // aboard a boat
 
             let tile = unit.movement.position.to_tile();
             if !self.region_map.is_walkable(tile) {
                 let unit = &mut self.units[i];
                 enter_state(unit, PersonState::Drowning, &mut self.rng);
+                let pos = unit.movement.position;
+                self.spawn_effect(EffectType::Splash, pos);
+                self.sounds.push(SoundEvent::at(GameSound::Splash, pos));
             }
         }
 
@@ -210,6 +751,169 @@ impl UnitCoordinator {
         self.process_combat();
     }
 
+    fn rebuild_grid(&mut self) {
+        self.grid.clear(self.units.len());
+        for unit in self.units.iter().filter(|u| Self::is_obstacle(u)) {
+            self.grid.insert(unit.id, unit.movement.position);
+        }
+    }
+
+    /// Units that others have to walk around.
+    fn is_obstacle(unit: &Unit) -> bool {
+        unit.alive && unit.state != PersonState::Dead && unit.state != PersonState::EnteringVehicle
+    }
+
+    /// Check the step unit `i` just took from `before`. A step that crowds
+    /// another unit is replaced by a sidestep, or undone if no sidestep is
+    /// free, and the unit is flagged blocked (see avoidance.rs).
+    fn avoid_collisions(&mut self, i: usize, before: WorldCoord) {
+        let unit = &self.units[i];
+        let after = unit.movement.position;
+        if after == before || unit.state == PersonState::Fighting { return; }
+
+        let blockers: Vec<WorldCoord> = self.grid.near(after, BLOCK_RADIUS + AVOIDANCE_SEARCH_MARGIN)
+            .into_iter()
+            .filter(|&j| j != i && Some(j) != unit.target_unit && Self::is_obstacle(&self.units[j]))
+            .map(|j| self.units[j].movement.position)
+            .collect();
+        let blocked = |to: &WorldCoord| blockers.iter().any(|other| step_blocked_by(&before, to, other));
+        if !blocked(&after) || unit.blocked_ticks >= BLOCK_GIVE_UP_TICKS {
+            let unit = &mut self.units[i];
+            unit.movement.flags1 &= !FLAG1_BLOCKED;
+            unit.blocked_ticks = 0;
+            return;
+        }
+
+        let (facing, speed) = (unit.movement.facing_angle, unit.movement.speed);
+        let detour = SIDESTEP_ANGLES.iter()
+            .map(|&turn| sidestep(before, facing, turn, speed))
+            .find(|p| self.region_map.is_walkable(p.to_tile()) && !blocked(p));
+        let unit = &mut self.units[i];
+        unit.movement.flags1 |= FLAG1_BLOCKED;
+        unit.blocked_ticks += 1;
+        unit.movement.position = detour.unwrap_or(before);
+    }
+
+    /// Push apart units closer than SEPARATION_RADIUS. Fighters hold their
+    /// ground (melee range is inside the radius) but still push others.
+    fn separate_units(&mut self) {
+        let mut pushes = Vec::new();
+        for unit in &self.units {
+            if !Self::is_obstacle(unit) || unit.state == PersonState::Fighting
+                || unit.state == PersonState::Drowning
+            {
+                continue;
+            }
+            let pos = unit.movement.position;
+            let mut push = (0, 0);
+            self.grid.for_each_near(pos, SEPARATION_RADIUS + AVOIDANCE_SEARCH_MARGIN, |j| {
+                let other = &self.units[j];
+                if j == unit.id || !Self::is_obstacle(other) { return; }
+                let (x, z) = separation_push(&pos, &other.movement.position, unit.id, j);
+                push = (push.0 + x, push.1 + z);
+            });
+            if push != (0, 0) {
+                pushes.push((unit.id, clamp_push(push)));
+            }
+        }
+        for (i, (px, pz)) in pushes {
+            let unit = &mut self.units[i];
+            let pos = &unit.movement.position;
+            let pushed = WorldCoord::new(pos.x.wrapping_add(px), pos.z.wrapping_add(pz));
+            if !self.region_map.is_walkable(pushed.to_tile()) { continue; }
+            unit.movement.position = pushed;
+            let (cx, cy) = world_to_render_pos(&pushed, self.landscape_size);
+            unit.cell_x = cx;
+            unit.cell_y = cy;
+        }
+    }
+
+    /// Object_UpdateMovement @ 0x4ed510 — move every follower toward its slot
+    /// behind its leader, at catch-up speed when it has fallen behind.
+    /// Followers that stopped walking or lost touch drop out of the group;
+    /// once the leader stops, the group re-forms around it.
+    fn tick_formations(&mut self) {
+        for li in 0..self.units.len() {
+            if self.units[li].movement.follower_count == 0 { continue; }
+            let leader = &self.units[li];
+            if !leader.alive || leader.state != PersonState::GoToPoint || !leader.movement.is_moving() {
+                self.reform_at_leader(li);
+                continue;
+            }
+            let (origin, heading, speed) =
+                (leader.movement.position, leader.movement.facing_angle, leader.movement.speed as i32);
+
+            let mut k = 0;
+            while k < self.units[li].movement.follower_count as usize {
+                let fi = self.units[li].movement.follower_ids[k] as usize;
+                let offset = formation::follower_offset(&self.units[li].movement, k);
+                let slot = slot_position(origin, heading, &offset);
+                let follower = &self.units[fi];
+                let gap = distance(&follower.movement.position, &slot) as i64;
+                let following = follower.alive && follower.movement.in_formation()
+                    && follower.state == PersonState::GoToPoint;
+                if !following || gap * gap > FORMATION_MAX_DIST_SQ as i64 {
+                    formation::remove_follower(&mut self.units[li].movement, k);
+                    self.units[fi].movement.formation_flags &= !FORMATION_FLAG_BIT;
+                    if following {
+                        // Lost touch: find its own way to the slot
+                        self.goto(fi, slot);
+                    }
+                    continue;
+                }
+                let step = if gap > FORMATION_MAX_DISTANCE as i64 {
+                    speed * CATCHUP_SPEED_NUM / CATCHUP_SPEED_DEN
+                } else {
+                    speed
+                };
+                let unit = &mut self.units[fi];
+                unit.movement.target_pos = slot;
+                unit.movement.next_waypoint = slot;
+                unit.movement.set_goto_flags();
+                if gap <= step as i64 {
+                    unit.movement.position = slot;
+                    unit.movement.facing_angle = heading;
+                } else {
+                    let dx = toroidal_delta(unit.movement.position.x, slot.x);
+                    let dz = toroidal_delta(unit.movement.position.z, slot.z);
+                    unit.movement.facing_angle = atan2(dx, -dz);
+                    let mut next = unit.movement.position;
+                    move_point_by_angle(&mut next, unit.movement.facing_angle, step as i16);
+                    // Hold back rather than walk into water
+                    if self.region_map.is_walkable(next.to_tile()) {
+                        unit.movement.position = next;
+                    }
+                }
+                let (cx, cy) = world_to_render_pos(&unit.movement.position, self.landscape_size);
+                unit.cell_x = cx;
+                unit.cell_y = cy;
+                k += 1;
+            }
+        }
+    }
+
+    /// The leader stopped (arrived, died or was interrupted): send each
+    /// follower to its slot around the leader's final position and facing.
+    fn reform_at_leader(&mut self, li: usize) {
+        let leader = &self.units[li].movement;
+        let (origin, heading) = (leader.position, leader.facing_angle);
+        let slots: Vec<(usize, WorldCoord)> = (0..leader.follower_count as usize)
+            .map(|k| {
+                let offset = formation::follower_offset(leader, k);
+                (leader.follower_ids[k] as usize, slot_position(origin, heading, &offset))
+            })
+            .collect();
+        formation::clear_followers(&mut self.units[li].movement);
+        for (fi, slot) in slots {
+            let follower = &mut self.units[fi];
+            if !follower.movement.in_formation() { continue; }
+            follower.movement.formation_flags &= !FORMATION_FLAG_BIT;
+            if follower.alive && follower.state == PersonState::GoToPoint {
+                self.goto(fi, slot);
+            }
+        }
+    }
+
     /// Move a unit one step along its path (waypoint advancement + position update).
     fn advance_movement(segment_pool: &mut SegmentPool, unit: &mut Unit, _landscape_size: f32) {
         // Waypoint advancement for pathfind-routed movement
@@ -246,6 +950,328 @@ impl UnitCoordinator {
         );
     }
 
+    /// Advance all vehicles by one tick.
+    /// A ferry trip runs Moving (to the pickup landing) → Loading → Moving
+    /// (crossing) → Unloading → Idle.
+    fn tick_vehicles(&mut self) {
+        for vi in 0..self.vehicles.len() {
+            self.update_ferry_riders(vi);
+
+            match self.vehicles[vi].state {
+                VehicleState::Moving => {
+                    let vehicle = &mut self.vehicles[vi];
+                    if Self::advance_vehicle(vehicle) {
+                        vehicle.speed = 0;
+                        vehicle.state = match &vehicle.ferry {
+                            Some(f) if f.departed => VehicleState::Unloading,
+                            Some(_) => VehicleState::Loading,
+                            None => VehicleState::Idle,
+                        };
+                    }
+                }
+                VehicleState::Loading => self.load_ferry(vi),
+                VehicleState::Unloading => self.unload_ferry(vi),
+                _ => {}
+            }
+
+            // Passengers travel with the boat
+            let vehicle = &mut self.vehicles[vi];
+            let (cx, cy) = world_to_render_pos(&vehicle.position, self.landscape_size);
+            vehicle.cell_x = cx;
+            vehicle.cell_y = cy;
+            if let Some(ferry) = &vehicle.ferry {
+                for rider in ferry.riders.iter().filter(|r| r.aboard) {
+                    let unit = &mut self.units[rider.unit];
+                    unit.movement.position = vehicle.position;
+                    unit.cell_x = cx;
+                    unit.cell_y = cy;
+                }
+            }
+        }
+    }
+
+    /// Before departure: drop riders that died, were re-ordered, or stopped
+    /// somewhere other than the landing; riders that arrived wait for the boat.
+    /// A ferry left with no riders is cancelled.
+    fn update_ferry_riders(&mut self, vi: usize) {
+        let vehicle = &mut self.vehicles[vi];
+        let ferry = match vehicle.ferry.as_mut() {
+            Some(f) if !f.departed => f,
+            _ => return,
+        };
+        let landing = ferry.embark.land.to_world();
+        let units = &mut self.units;
+        let rng = &mut self.rng;
+
+        ferry.riders.retain(|rider| {
+            if rider.aboard { return true; }
+            let unit = &mut units[rider.unit];
+            if !unit.alive || unit.state == PersonState::Dead
+                || unit.linked_obj_id != Some(vehicle.id)
+            {
+                return false;
+            }
+            if unit.state == PersonState::Idle && !unit.movement.is_moving() {
+                let dx = toroidal_delta(unit.movement.position.x, landing.x);
+                let dz = toroidal_delta(unit.movement.position.z, landing.z);
+                if dx.abs() + dz.abs() > 0x400 {
+                    unit.linked_obj_id = None;
+                    return false;
+                }
+                enter_state(unit, PersonState::WaitingForBoat, rng);
+            }
+            true
+        });
+
+        if ferry.riders.is_empty() {
+            vehicle.ferry = None;
+            vehicle.route.clear();
+            vehicle.speed = 0;
+            vehicle.state = VehicleState::Idle;
+        }
+    }
+
+    /// Board riders waiting at the landing; set sail once everyone is aboard.
+    fn load_ferry(&mut self, vi: usize) {
+        let vehicle = &mut self.vehicles[vi];
+        let ferry = match vehicle.ferry.as_mut() {
+            Some(f) => f,
+            None => {
+                vehicle.state = VehicleState::Idle;
+                return;
+            }
+        };
+
+        for rider in ferry.riders.iter_mut().filter(|r| !r.aboard) {
+            let unit = &mut self.units[rider.unit];
+            if unit.state == PersonState::WaitingForBoat {
+                enter_state(unit, PersonState::EnteringVehicle, &mut self.rng);
+                unit.movement.position = vehicle.position;
+                rider.aboard = true;
+            }
+        }
+
+        if !ferry.riders.is_empty() && ferry.aboard_count() == ferry.riders.len() {
+            ferry.departed = true;
+            let route = ferry.crossing.clone();
+            log::info!("[ferry] boat {} departing with {} passengers", vehicle.id, ferry.riders.len());
+            vehicle.sail(route);
+        }
+    }
+
+    /// Put passengers ashore at the disembark landing and send each on to
+    /// its original target.
+    fn unload_ferry(&mut self, vi: usize) {
+        let vehicle = &mut self.vehicles[vi];
+        vehicle.state = VehicleState::Idle;
+        let ferry = match vehicle.ferry.take() {
+            Some(f) => f,
+            None => return,
+        };
+        let shore = ferry.disembark.land.to_world();
+        log::info!("[ferry] boat {} unloading {} passengers", vehicle.id, ferry.aboard_count());
+
+        self.used_targets.clear();
+        for rider in ferry.riders.iter().filter(|r| r.aboard) {
+            let unit = &mut self.units[rider.unit];
+            unit.linked_obj_id = None;
+            unit.movement.position = shore;
+            enter_state(unit, PersonState::Idle, &mut self.rng);
+            self.goto(rider.unit, rider.target);
+        }
+    }
+
+    /// Sail a vehicle one step along its route.
+    /// Returns true once the last waypoint is reached.
+    fn advance_vehicle(vehicle: &mut Vehicle) -> bool {
+        let next = match vehicle.route.first() {
+            Some(&wp) => wp,
+            None => return true,
+        };
+        let dx = toroidal_delta(vehicle.position.x, next.x);
+        let dz = toroidal_delta(vehicle.position.z, next.z);
+        if dx.abs() < ARRIVAL_THRESHOLD && dz.abs() < ARRIVAL_THRESHOLD {
+            vehicle.position = next;
+            vehicle.route.remove(0);
+            return vehicle.route.is_empty();
+        }
+        vehicle.facing_angle = atan2(dx, -dz);
+        move_point_by_angle(&mut vehicle.position, vehicle.facing_angle, vehicle.speed as i16);
+        false
+    }
+
+    /// Raise tribe-level consequences of a unit's death: a dead shaman
+    /// costs its tribe mana and is queued at the tribe's pillar.
+    fn on_unit_killed(&mut self, idx: usize) {
+        let unit = &self.units[idx];
+        let pos = unit.movement.position;
+        if unit.subtype != PERSON_SUBTYPE_SHAMAN {
+            self.spawn_effect(EffectType::Smoke, pos);
+            self.sounds.push(SoundEvent::at(GameSound::UnitDeath, pos));
+            return;
+        }
+        self.spawn_effect(EffectType::SpellBlast, pos);
+        self.sounds.push(SoundEvent::at(GameSound::ShamanDeath, pos));
+        let unit = &self.units[idx];
+        let tribe = unit.tribe_index;
+        let shaman = unit.id;
+        let killer = unit.attacker_unit
+            .and_then(|a| self.units.get(a))
+            .map(|a| a.tribe_index)
+            .filter(|&t| t != tribe);
+        let respawn = self.pillars.iter_mut()
+            .find(|p| p.tribe_index == tribe)
+            .is_some_and(|p| p.schedule(shaman));
+        log::info!("[reinc] tribe {} shaman {} died (killer={:?}, respawn={})",
+            tribe, shaman, killer, respawn);
+        self.events.push(TribeEvent::ShamanKilled { tribe, killer, respawn });
+    }
+
+    /// Count down pending respawns and bring due shamans back at their pillar.
+    fn tick_pillars(&mut self) {
+        for pi in 0..self.pillars.len() {
+            let shaman = match self.pillars[pi].tick() {
+                Some(id) => id,
+                None => continue,
+            };
+            let pillar = &self.pillars[pi];
+            let tribe = pillar.tribe_index;
+            let unit = &mut self.units[shaman];
+            unit.alive = true;
+            unit.health = unit.max_health;
+            unit.target_unit = None;
+            unit.attacker_unit = None;
+            unit.linked_obj_id = None;
+            unit.movement.position = pillar.position;
+            unit.movement.flags1 = 0;
+            enter_state(unit, PersonState::WaitingAtReincPillar, &mut self.rng);
+            let (cx, cy) = world_to_render_pos(&unit.movement.position, self.landscape_size);
+            unit.cell_x = cx;
+            unit.cell_y = cy;
+            let pos = unit.movement.position;
+            log::info!("[reinc] tribe {} shaman {} reincarnated", tribe, shaman);
+            self.spawn_effect(EffectType::BigSparkle, pos);
+            self.sounds.push(SoundEvent::at(GameSound::ShamanReincarnated, pos));
+            self.events.push(TribeEvent::ShamanReincarnated { tribe });
+        }
+    }
+
+    /// Fire triggers whose condition is met and apply their effects.
+    fn tick_triggers(&mut self) {
+        for ti in 0..self.triggers.len() {
+            let trigger = &self.triggers[ti];
+            if !trigger.active { continue; }
+            let in_range = trigger.kind == TriggerKind::Proximity
+                && self.units.iter().any(|u| {
+                    u.alive && u.state != PersonState::Dead
+                        && trigger.senses(u.tribe_index, u.movement.position)
+                });
+            if !self.triggers[ti].tick(in_range) { continue; }
+
+            let triggers = &self.triggers;
+            let effects = triggers[ti].effects(|l| triggers.iter().position(|t| t.level_index == l));
+            log::info!("[trigger] trigger {} fired ({} effects)", ti, effects.len());
+            for effect in effects {
+                self.apply_trigger_effect(effect);
+            }
+        }
+    }
+
+    fn apply_trigger_effect(&mut self, effect: TriggerEffect) {
+        match effect {
+            TriggerEffect::Wake(level_index) => {
+                if let Some(pos) = self.dormant_units.iter().position(|&(i, _)| i == level_index) {
+                    let (_, raw) = self.dormant_units.remove(pos);
+                    if raw.model_type() == Some(ModelType::Person) {
+                        self.spawn_person_raw(&raw);
+                    } else {
+                        self.spawn_vehicle_raw(level_index, &raw);
+                    }
+                }
+                if let Some(pos) = self.hidden_objects.iter().position(|&i| i == level_index) {
+                    self.hidden_objects.remove(pos);
+                    self.level_events.push(LevelEvent::Reveal(level_index));
+                }
+            }
+            TriggerEffect::Activate(id) => self.triggers[id].activate(),
+            TriggerEffect::Script { trigger, action } => {
+                self.level_events.push(LevelEvent::ScriptAction { trigger, action });
+            }
+        }
+    }
+
+    /// Sit arriving worshippers down at their stone head and accumulate
+    /// worship progress per tribe. Worshippers that died, were re-ordered
+    /// or stopped short of the head are dropped.
+    /// Original: Discovery_Check (0x004bed50), once per head per tick.
+    fn tick_stone_heads(&mut self) {
+        let mut claimed_effects: Vec<EffectId> = Vec::new();
+        for hi in 0..self.stone_heads.len() {
+            let head = &mut self.stone_heads[hi];
+            if head.worshippers.is_empty() { continue; }
+            let units = &mut self.units;
+            let rng = &mut self.rng;
+
+            if !head.is_worshippable() {
+                for &w in &head.worshippers {
+                    if units[w].state == PersonState::SitDown {
+                        enter_state(&mut units[w], PersonState::Idle, rng);
+                    }
+                }
+                head.worshippers.clear();
+                continue;
+            }
+
+            let head_pos = head.position;
+            head.worshippers.retain(|&w| {
+                let unit = &mut units[w];
+                if !unit.alive { return false; }
+                match unit.state {
+                    PersonState::SitDown | PersonState::GoToPoint => true,
+                    PersonState::Idle if !unit.movement.is_moving() => {
+                        let dx = toroidal_delta(unit.movement.position.x, head_pos.x);
+                        let dz = toroidal_delta(unit.movement.position.z, head_pos.z);
+                        if dx.abs() + dz.abs() > WORSHIP_RANGE {
+                            return false;
+                        }
+                        enter_state(unit, PersonState::SitDown, rng);
+                        unit.movement.facing_angle = atan2(dx, -dz);
+                        true
+                    }
+                    _ => false,
+                }
+            });
+
+            let mut counts = [0u32; 4];
+            for &w in &head.worshippers {
+                let unit = &units[w];
+                if unit.state == PersonState::SitDown && (unit.tribe_index as usize) < counts.len() {
+                    counts[unit.tribe_index as usize] += 1;
+                }
+            }
+
+            for (tribe, &count) in counts.iter().enumerate() {
+                if let Some(discovery) = head.worship(tribe as u8, count) {
+                    log::info!("[discovery] tribe {} completed stone head {}: {:?}",
+                        tribe, head.id, discovery);
+                    self.events.push(TribeEvent::Discovered { tribe: tribe as u8, discovery });
+                    for &w in &head.worshippers {
+                        enter_state(&mut units[w], PersonState::Idle, rng);
+                    }
+                    head.worshippers.clear();
+                    if let Some(id) = self.effects.spawn(EffectType::ShapeSparkle, head_pos) {
+                        claimed_effects.push(id);
+                    }
+                    self.sounds.push(SoundEvent::at(GameSound::Discovery, head_pos));
+                    break;
+                }
+            }
+        }
+        for id in claimed_effects {
+            self.place_effect(id);
+        }
+    }
+
     /// Detect nearby enemies and enter combat for idle/wandering units.
     fn detect_combat(&mut self) {
         // Collect (unit_index, target_index) pairs to avoid borrow issues
@@ -264,8 +1290,9 @@ impl UnitCoordinator {
                 if i == j { continue; }
                 let other = &self.units[j];
                 if !other.alive { continue; }
-                if other.tribe_index == unit.tribe_index { continue; } // Same tribe
+                if self.are_allied(other.tribe_index, unit.tribe_index) { continue; } // Same side
                 if other.state == PersonState::Dead { continue; }
+                if other.state == PersonState::EnteringVehicle { continue; } // out at sea
 
                 let dx = toroidal_delta(unit.movement.position.x, other.movement.position.x) as i32;
                 let dz = toroidal_delta(unit.movement.position.z, other.movement.position.z) as i32;
@@ -303,8 +1330,8 @@ impl UnitCoordinator {
     /// - SwingReady→Strike: pause then deal damage when in melee range
     /// - Lunge/Recovering: managed by tick_fighting in person_state.rs
     fn process_combat(&mut self) {
-        // Collect damage events: (target_index, damage, attacker_tribe)
-        let mut damage_events: Vec<(usize, u16, u8)> = Vec::new();
+        // Collect damage events: (target_index, damage, attacker_id)
+        let mut damage_events: Vec<(usize, u16, UnitId)> = Vec::new();
 
         for i in 0..self.units.len() {
             let unit = &self.units[i];
@@ -325,6 +1352,11 @@ impl UnitCoordinator {
             if !target.alive || target.health == 0 {
                 continue;
             }
+            if self.are_allied(unit.tribe_index, target.tribe_index) {
+                // Allied since the fight started — stand down
+                self.units[i].target_unit = None;
+                continue;
+            }
 
             let target_pos = target.movement.position;
             let dx = toroidal_delta(unit.movement.position.x, target_pos.x) as i32;
@@ -366,7 +1398,7 @@ impl UnitCoordinator {
                 CombatPhase::Strike => {
                     // tick_fighting sets Strike phase; we apply damage here
                     let damage = calculate_melee_damage(&self.units[i]);
-                    damage_events.push((target_idx, damage, self.units[i].tribe_index));
+                    damage_events.push((target_idx, damage, self.units[i].id));
                     // tick_fighting will advance to LungeBack on next tick
                 }
                 CombatPhase::SwingReady | CombatPhase::LungeBack
@@ -382,11 +1414,18 @@ impl UnitCoordinator {
         }
 
         // Apply damage
-        for (target_idx, damage, _attacker_tribe) in damage_events {
+        for (target_idx, damage, attacker) in damage_events {
             let target = &mut self.units[target_idx];
+            if target.state == PersonState::Dead { continue; }
             apply_damage(target, damage);
+            target.attacker_unit = Some(attacker);
+            let pos = target.movement.position;
+            self.spawn_blood(pos);
+            self.sounds.push(SoundEvent::at(GameSound::CombatStrike, pos));
+            let target = &mut self.units[target_idx];
             if target.health == 0 {
                 enter_state(target, PersonState::Dead, &mut self.rng);
+                self.on_unit_killed(target_idx);
             }
         }
 
@@ -409,8 +1448,8 @@ impl UnitCoordinator {
     /// routing between land (region 0) and water, forcing the pathfinder
     /// to engage and reject the unwalkable target.
     fn populate_water(region_map: &mut RegionMap, landscape_height: &[[u16; 128]; 128], size: usize) {
-        region_map.set_terrain_flags(1, 0x00); // terrain class 1 = water = unwalkable
-        region_map.set_terrain_flags(3, 0x00); // terrain class 3 = shore buffer = unwalkable
+        region_map.set_terrain_flags(TERRAIN_CLASS_WATER, 0x00); // water = unwalkable
+        region_map.set_terrain_flags(TERRAIN_CLASS_SHORE, 0x00); // shore buffer = unwalkable
         let ni = size as i32;
         let n = size;
 
@@ -428,7 +1467,7 @@ impl UnitCoordinator {
                     is_water[cell_y * n + cell_x] = true;
                     let tile = cell_to_tile(cell_x as i32, cell_y as i32, ni);
                     let cell = region_map.get_cell_mut(tile);
-                    cell.terrain_type = 1;
+                    cell.terrain_type = TERRAIN_CLASS_WATER;
                     region_map.set_cell_region(tile, 1); // water region
                 }
             }
@@ -450,7 +1489,7 @@ impl UnitCoordinator {
                 if adjacent_to_water {
                     let tile = cell_to_tile(cell_x as i32, cell_y as i32, ni);
                     let cell = region_map.get_cell_mut(tile);
-                    cell.terrain_type = 3; // shore buffer
+                    cell.terrain_type = TERRAIN_CLASS_SHORE;
                 }
             }
         }
@@ -471,11 +1510,93 @@ impl ObjectTick for UnitCoordinator {
     fn tick_update_objects(&mut self) {
         self.tick();
     }
+
+    fn set_alliances(&mut self, alliances: &AllianceMatrix) {
+        self.alliances = *alliances;
+    }
+
+    fn take_tribe_events(&mut self) -> Vec<TribeEvent> {
+        UnitCoordinator::take_tribe_events(self)
+    }
+
+    fn populations(&self) -> Option<[u32; MAX_TRIBES]> {
+        Some(self.tribe_populations())
+    }
+
+    /// Players may only select and order their own tribe's units; anything
+    /// else in a networked selection is dropped.
+    fn apply_action(&mut self, action: &PlayerAction) {
+        let player = action.player as usize;
+        if player >= MAX_TRIBES {
+            return;
+        }
+        let own = |units: &[Unit], id: &UnitId| units.get(*id).is_some_and(|u| u.tribe_index == action.player);
+        match &action.command {
+            GameCommand::SelectUnit(id) => {
+                self.player_selections[player] = [*id].into_iter().filter(|id| own(&self.units, id)).collect();
+            }
+            GameCommand::SelectMultiple(ids) => {
+                self.player_selections[player] = ids.iter().copied().filter(|id| own(&self.units, id)).collect();
+            }
+            GameCommand::ClearSelection => self.player_selections[player].clear(),
+            GameCommand::OrderMove { x, z } => {
+                // order_move works on `selection`: lend it the player's
+                let local = std::mem::replace(&mut self.selection.selected, self.player_selections[player].clone());
+                self.order_move(WorldCoord::new(*x as i16, *z as i16));
+                self.selection.selected = local;
+            }
+            _ => {}
+        }
+    }
+
+    fn add_checksums(&self, sums: &mut StateChecksums) {
+        sums.mix(SyncCategory::RandomSeed, self.rng.seed());
+        sums.mix(SyncCategory::RandomSeed, self.formation_rng);
+
+        let allied = self.alliances.iter().flatten()
+            .fold(0u32, |bits, &a| bits << 1 | a as u32);
+        sums.mix(SyncCategory::Players, allied);
+
+        for pillar in &self.pillars {
+            sums.mix(SyncCategory::Players, pillar.tribe_index as u32 | (pillar.destroyed as u32) << 8
+                | (pillar.respawn.is_some() as u32) << 9);
+        }
+        for head in &self.stone_heads {
+            head.progress.iter().for_each(|&p| sums.mix(SyncCategory::Players, p));
+            sums.mix(SyncCategory::Players, head.claimed_by.map_or(0xFF, u32::from));
+        }
+
+        let alive = self.units.iter().filter(|u| u.alive).count();
+        sums.mix(SyncCategory::Counts, alive as u32);
+        sums.mix(SyncCategory::Counts, self.vehicles.len() as u32);
+
+        for unit in self.units.iter().filter(|u| u.alive) {
+            let category = if (unit.tribe_index as usize) < MAX_TRIBES {
+                SyncCategory::PlayerThings
+            } else {
+                SyncCategory::WildThings
+            };
+            sums.mix(category, unit.id as u32);
+            sums.mix_coord(category, unit.movement.position);
+            sums.mix(category, unit.state as u32 | (unit.state_counter as u32) << 8 | (unit.state_timer as u32) << 16);
+            sums.mix(category, unit.health as u32 | (unit.movement.facing_angle as u32) << 16);
+            sums.mix(category, unit.target_unit.map_or(u32::MAX, |t| t as u32));
+        }
+
+        for vehicle in &self.vehicles {
+            sums.mix(SyncCategory::Vehicles, vehicle.id as u32 | (vehicle.tribe_index as u32) << 16);
+            sums.mix_coord(SyncCategory::Vehicles, vehicle.position);
+            sums.mix(SyncCategory::Vehicles, vehicle.state as u32 | (vehicle.facing_angle as u32) << 16);
+            sums.mix(SyncCategory::Vehicles, vehicle.ferry.as_ref().map_or(0, |f| f.riders.len() as u32 + 1));
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::engine::state::discovery::Discovery;
+    use crate::engine::units::avoidance::SEPARATION_MAX_PUSH;
 
     #[test]
     fn load_level_filters_persons() {
@@ -549,4 +1670,557 @@ mod tests {
         let far_land = cell_to_tile(8, 11, 128);
         assert!(map.is_walkable(far_land), "cell 2 away from water should be walkable");
     }
+
+    /// Two water bands (cell rows 40..=59 and 100..=119) split the torus
+    /// into a north and a south island.
+    fn two_island_heights() -> [[u16; 128]; 128] {
+        let mut height = [[100u16; 128]; 128];
+        for y in (40..=60).chain(100..=120) {
+            height[y] = [0u16; 128];
+        }
+        height
+    }
+
+    fn spawn_person(coord: &mut UnitCoordinator, pos: WorldCoord, tribe: u8) -> UnitId {
+        let id = coord.units.len();
+        coord.units.push(Unit::new_person(id, 2, tribe, pos));
+        id
+    }
+
+    /// Spawn `n` braves in rows of four from cell (30, 62).
+    fn spawn_group(coord: &mut UnitCoordinator, n: usize) -> Vec<UnitId> {
+        (0..n)
+            .map(|i| {
+                let pos = cell_to_tile(30 + (i % 4) as i32, 62 + (i / 4) as i32, 128).to_world();
+                spawn_person(coord, pos, 0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn group_move_has_one_leader_and_formation_followers() {
+        let mut coord = UnitCoordinator::new();
+        coord.load_terrain(&[[100u16; 128]; 128], 128);
+        let ids = spawn_group(&mut coord, 8);
+        coord.selection.select_multiple(ids.clone());
+        coord.order_move(cell_to_tile(90, 64, 128).to_world());
+
+        let leaders: Vec<UnitId> = ids.iter().copied()
+            .filter(|&id| coord.units[id].movement.follower_count > 0)
+            .collect();
+        assert_eq!(leaders.len(), 1);
+        let leader = &coord.units[leaders[0]].movement;
+        assert_eq!(leader.follower_count, 7);
+        assert!(!leader.in_formation());
+        for &f in &leader.follower_ids[..7] {
+            let follower = &coord.units[f as usize];
+            assert!(follower.movement.in_formation());
+            assert_eq!(follower.state, PersonState::GoToPoint);
+        }
+
+        // Re-ordering a follower takes it out of the group
+        let follower = leader.follower_ids[0] as usize;
+        coord.selection.select_single(follower);
+        coord.order_move(cell_to_tile(60, 20, 128).to_world());
+        assert!(!coord.units[follower].movement.in_formation());
+        assert_eq!(coord.units[leaders[0]].movement.follower_count, 6);
+    }
+
+    #[test]
+    fn group_move_reforms_on_distinct_tiles() {
+        let mut coord = UnitCoordinator::new();
+        coord.load_terrain(&[[100u16; 128]; 128], 128);
+        let ids = spawn_group(&mut coord, 20);
+        coord.selection.select_multiple(ids.clone());
+        coord.order_move(cell_to_tile(90, 64, 128).to_world());
+        let leaders = ids.iter().filter(|&&id| coord.units[id].movement.follower_count > 0).count();
+        assert_eq!(leaders, 2);
+
+        let following = |coord: &UnitCoordinator| ids.iter().filter(|&&id| coord.units[id].movement.in_formation()).count();
+        let joined = following(&coord);
+        for _ in 0..50 {
+            coord.tick();
+        }
+        assert!(joined > 0);
+        assert_eq!(following(&coord), joined, "followers dropped out on open ground");
+        for _ in 0..3000 {
+            coord.tick();
+            if ids.iter().all(|&id| coord.units[id].state == PersonState::Idle) { break; }
+        }
+        let mut tiles = std::collections::HashSet::new();
+        for &id in &ids {
+            let unit = &coord.units[id];
+            assert_eq!(unit.state, PersonState::Idle, "unit {} still walking", id);
+            assert!(!unit.movement.in_formation());
+            assert_eq!(unit.movement.follower_count, 0);
+            let tile = unit.movement.position.to_tile();
+            assert!(tiles.insert((tile.x, tile.z)), "unit {} shares tile {:?}", id, tile);
+        }
+    }
+
+    /// Land strip along cell rows 58..=66, water everywhere else.
+    fn land_bridge_heights() -> [[u16; 128]; 128] {
+        let mut height = [[0u16; 128]; 128];
+        for row in &mut height[58..=66] {
+            row.fill(100);
+        }
+        height
+    }
+
+    #[test]
+    fn head_on_units_on_a_bridge_pass_without_overlapping() {
+        let mut coord = UnitCoordinator::new();
+        coord.load_terrain(&land_bridge_heights(), 128);
+        coord.set_avoidance(true);
+        let west = cell_to_tile(30, 62, 128).to_world();
+        let east = cell_to_tile(50, 62, 128).to_world();
+        let a = spawn_person(&mut coord, west, 0);
+        let b = spawn_person(&mut coord, east, 0);
+        coord.selection.select_single(a);
+        coord.order_move(east);
+        coord.selection.select_single(b);
+        coord.order_move(west);
+
+        let mut closest = i32::MAX;
+        let mut blocked = false;
+        for _ in 0..1000 {
+            coord.tick();
+            let (pa, pb) = (coord.units[a].movement.position, coord.units[b].movement.position);
+            closest = closest.min(distance(&pa, &pb));
+            blocked |= coord.units[a].movement.is_blocked() || coord.units[b].movement.is_blocked();
+            if coord.units[a].state == PersonState::Idle && coord.units[b].state == PersonState::Idle { break; }
+        }
+        assert!(blocked, "units never noticed each other");
+        assert!(closest >= BLOCK_RADIUS, "units overlapped (closest {})", closest);
+        assert_eq!(coord.units[a].movement.position, east);
+        assert_eq!(coord.units[b].movement.position, west);
+        assert!(coord.units.iter().all(|u| u.alive && u.state == PersonState::Idle));
+    }
+
+    #[test]
+    fn avoidance_is_off_by_default() {
+        let mut coord = UnitCoordinator::new();
+        coord.load_terrain(&[[100u16; 128]; 128], 128);
+        let pos = cell_to_tile(40, 40, 128).to_world();
+        let ids: Vec<UnitId> = (0..3).map(|_| spawn_person(&mut coord, pos, 0)).collect();
+        for _ in 0..40 {
+            coord.tick();
+        }
+        assert!(ids.iter().all(|&i| coord.units[i].movement.position == pos));
+    }
+
+    #[test]
+    fn units_on_the_same_spot_separate() {
+        let mut coord = UnitCoordinator::new();
+        coord.load_terrain(&[[100u16; 128]; 128], 128);
+        coord.set_avoidance(true);
+        let pos = cell_to_tile(40, 40, 128).to_world();
+        let ids: Vec<UnitId> = (0..3).map(|_| spawn_person(&mut coord, pos, 0)).collect();
+        for _ in 0..40 {
+            coord.tick();
+        }
+        for (n, &i) in ids.iter().enumerate() {
+            for &j in &ids[n + 1..] {
+                let gap = distance(&coord.units[i].movement.position, &coord.units[j].movement.position);
+                assert!(gap >= SEPARATION_RADIUS - SEPARATION_MAX_PUSH, "units {} and {} only {} apart", i, j, gap);
+            }
+        }
+    }
+
+    #[test]
+    fn enhanced_pathfinding_leads_out_of_a_walled_bay() {
+        use crate::engine::movement::PathNode;
+        // Bay walled on three sides, open to the west; the target lies east
+        let mut coord = UnitCoordinator::new();
+        coord.load_terrain(&[[100u16; 128]; 128], 128);
+        let walls = (50..=70).flat_map(|i| [(i, 50), (i, 70), (70, i)]);
+        for (x, z) in walls {
+            coord.region_map_mut().get_cell_mut(PathNode::new(x, z).to_tile()).terrain_type = TERRAIN_CLASS_BUILDING;
+        }
+        coord.set_pathfinding_mode(PathfindingMode::Enhanced);
+        let start = PathNode::new(66, 60).to_tile().to_world();
+        let target = PathNode::new(80, 60).to_tile().to_world();
+        let id = spawn_person(&mut coord, start, 0);
+        coord.selection.select_single(id);
+        coord.order_move(target);
+        assert_ne!(coord.units[id].movement.segment_index, 0);
+
+        for _ in 0..2000 {
+            coord.tick();
+            let pos = coord.units[id].movement.position;
+            assert!(coord.region_map.is_walkable(pos.to_tile()), "walked onto the wall at {:?}", pos);
+            if coord.units[id].state == PersonState::Idle { break; }
+        }
+        assert_eq!(coord.units[id].movement.position, target);
+    }
+
+    #[test]
+    fn order_move_across_water_ferries_unit() {
+        let mut coord = UnitCoordinator::new();
+        coord.load_terrain(&two_island_heights(), 128);
+        assert_eq!(coord.islands.island_count(), 2);
+
+        let start = cell_to_tile(64, 70, 128).to_world();
+        let target = cell_to_tile(64, 20, 128).to_world();
+        let unit_id = spawn_person(&mut coord, start, 0);
+        let boat_pos = cell_to_tile(64, 50, 128).to_world();
+        coord.vehicles.push(Vehicle::new(0, 0, 1, 0xFF, boat_pos));
+
+        coord.selection.select_single(unit_id);
+        coord.order_move(target);
+        assert_eq!(coord.units[unit_id].linked_obj_id, Some(0));
+        assert_eq!(coord.vehicles[0].state, VehicleState::Moving);
+
+        let mut boarded = false;
+        for _ in 0..3000 {
+            coord.tick();
+            boarded |= coord.units[unit_id].state == PersonState::EnteringVehicle;
+            if boarded && coord.units[unit_id].state == PersonState::Idle { break; }
+        }
+
+        let unit = &coord.units[unit_id];
+        assert!(boarded, "unit never boarded the boat");
+        assert!(unit.alive);
+        assert_eq!(unit.linked_obj_id, None);
+        assert_eq!(unit.movement.position, target);
+        assert!(coord.vehicles[0].is_available());
+    }
+
+    #[test]
+    fn order_move_across_water_without_boat_walks_to_shore() {
+        let mut coord = UnitCoordinator::new();
+        coord.load_terrain(&two_island_heights(), 128);
+
+        let start = cell_to_tile(64, 70, 128).to_world();
+        let target = cell_to_tile(64, 20, 128).to_world();
+        let unit_id = spawn_person(&mut coord, start, 0);
+
+        coord.selection.select_single(unit_id);
+        coord.order_move(target);
+        assert_eq!(coord.units[unit_id].linked_obj_id, None);
+    }
+
+    #[test]
+    fn reorder_cancels_ferry_booking() {
+        let mut coord = UnitCoordinator::new();
+        coord.load_terrain(&two_island_heights(), 128);
+
+        let start = cell_to_tile(64, 70, 128).to_world();
+        let unit_id = spawn_person(&mut coord, start, 0);
+        coord.vehicles.push(Vehicle::new(0, 0, 1, 0xFF, cell_to_tile(64, 50, 128).to_world()));
+
+        coord.selection.select_single(unit_id);
+        coord.order_move(cell_to_tile(64, 20, 128).to_world());
+        assert!(coord.vehicles[0].ferry.is_some());
+
+        // New order on the same island — booking dropped, boat released
+        coord.order_move(cell_to_tile(70, 75, 128).to_world());
+        assert_eq!(coord.units[unit_id].linked_obj_id, None);
+        coord.tick();
+        assert!(coord.vehicles[0].is_available());
+    }
+
+    #[test]
+    fn worship_stone_head_grants_discovery() {
+        let mut coord = UnitCoordinator::new();
+        coord.load_terrain(&[[100u16; 128]; 128], 128);
+        let head_pos = cell_to_tile(64, 64, 128).to_world();
+        coord.stone_heads.push(StoneHead::new(0, head_pos, Some(Discovery::Spell(6))));
+
+        let ids: Vec<UnitId> = (0..4)
+            .map(|i| spawn_person(&mut coord, cell_to_tile(70 + i, 70, 128).to_world(), 1))
+            .collect();
+        coord.selection.select_multiple(ids.clone());
+        coord.order_move(head_pos);
+        assert_eq!(coord.stone_heads[0].worshippers.len(), 4);
+
+        let mut discoveries = Vec::new();
+        for _ in 0..2000 {
+            coord.tick();
+            discoveries.extend(coord.take_tribe_events());
+            if !discoveries.is_empty() { break; }
+        }
+
+        assert_eq!(discoveries, vec![TribeEvent::Discovered { tribe: 1, discovery: Discovery::Spell(6) }]);
+        assert_eq!(coord.stone_heads[0].claimed_by, Some(1));
+        assert!(coord.stone_heads[0].worshippers.is_empty());
+        assert!(ids.iter().all(|&id| coord.units[id].state == PersonState::Idle));
+    }
+
+    #[test]
+    fn reorder_stops_worship() {
+        let mut coord = UnitCoordinator::new();
+        coord.load_terrain(&[[100u16; 128]; 128], 128);
+        let head_pos = cell_to_tile(64, 64, 128).to_world();
+        coord.stone_heads.push(StoneHead::new(0, head_pos, Some(Discovery::Building(3))));
+        let unit_id = spawn_person(&mut coord, cell_to_tile(66, 64, 128).to_world(), 0);
+
+        coord.selection.select_single(unit_id);
+        coord.order_move(head_pos);
+        assert_eq!(coord.stone_heads[0].worshippers, vec![unit_id]);
+
+        coord.order_move(cell_to_tile(20, 20, 128).to_world());
+        assert!(coord.stone_heads[0].worshippers.is_empty());
+        assert_eq!(coord.stone_heads[0].progress, [0; 4]);
+    }
+
+    fn kill_shaman(coord: &mut UnitCoordinator, tribe: u8) -> UnitId {
+        let id = spawn_person(coord, cell_to_tile(30, 30, 128).to_world(), tribe);
+        coord.units[id].subtype = PERSON_SUBTYPE_SHAMAN;
+        enter_state(&mut coord.units[id], PersonState::Drowning, &mut coord.rng);
+        id
+    }
+
+    #[test]
+    fn dead_shaman_reincarnates_at_pillar() {
+        let mut coord = UnitCoordinator::new();
+        coord.load_terrain(&[[100u16; 128]; 128], 128);
+        let pillar_pos = cell_to_tile(80, 80, 128).to_world();
+        coord.pillars.push(ReincarnationPillar::new(2, pillar_pos));
+        let shaman = kill_shaman(&mut coord, 2);
+
+        let mut events = Vec::new();
+        for _ in 0..600 {
+            coord.tick();
+            events.extend(coord.take_tribe_events());
+            if events.contains(&TribeEvent::ShamanReincarnated { tribe: 2 }) { break; }
+        }
+
+        assert_eq!(events, vec![
+            TribeEvent::ShamanKilled { tribe: 2, killer: None, respawn: true },
+            TribeEvent::ShamanReincarnated { tribe: 2 },
+        ]);
+        let unit = &coord.units[shaman];
+        assert!(unit.alive);
+        assert_eq!(unit.health, unit.max_health);
+        assert_eq!(unit.state, PersonState::WaitingAtReincPillar);
+        assert_eq!(unit.movement.position, pillar_pos);
+    }
+
+    #[test]
+    fn combat_hits_and_deaths_spawn_effects() {
+        use super::super::effect::BLOOD_TINT;
+        let mut coord = UnitCoordinator::new();
+        coord.load_terrain(&[[100u16; 128]; 128], 128);
+        let a = spawn_person(&mut coord, cell_to_tile(40, 40, 128).to_world(), 0);
+        spawn_person(&mut coord, cell_to_tile(40, 41, 128).to_world(), 1);
+        coord.units[a].health = 1;
+
+        let mut blood = false;
+        let mut smoke = false;
+        for _ in 0..600 {
+            coord.tick();
+            blood |= coord.effects.effects.iter()
+                .any(|e| e.kind == EffectType::Splash && e.tint == Some(BLOOD_TINT));
+            smoke |= coord.effects.effects.iter().any(|e| e.kind == EffectType::Smoke);
+            if blood && smoke { break; }
+        }
+        assert!(blood, "no hit spray");
+        assert!(smoke, "no death smoke");
+    }
+
+    #[test]
+    fn allies_do_not_fight() {
+        let mut coord = UnitCoordinator::new();
+        coord.load_terrain(&[[100u16; 128]; 128], 128);
+        let a = spawn_person(&mut coord, cell_to_tile(40, 40, 128).to_world(), 0);
+        let b = spawn_person(&mut coord, cell_to_tile(40, 41, 128).to_world(), 2);
+        let mut tribes = crate::engine::state::tribe::TribeArray::new();
+        tribes.set_teams(&[0, 1, 0, 1]);
+        coord.set_alliances(&tribes.alliances);
+
+        for _ in 0..200 {
+            coord.tick();
+        }
+        assert!(coord.units.iter().all(|u| u.state != PersonState::Fighting && u.target_unit.is_none()));
+        assert!(coord.units.iter().all(|u| u.health == u.max_health));
+
+        // Alliance broken: they engage
+        tribes.set_allied(0, 2, false);
+        coord.set_alliances(&tribes.alliances);
+        for _ in 0..50 {
+            coord.tick();
+        }
+        assert!(coord.units[a].state == PersonState::Fighting || coord.units[b].state == PersonState::Fighting);
+    }
+
+    #[test]
+    fn last_tribe_standing_wins_through_the_tick() {
+        use crate::engine::state::state_machine::GameState;
+        use crate::engine::state::tick::{GameWorld, TickSubsystems};
+        use crate::engine::state::traits::NoOp;
+
+        let mut coord = UnitCoordinator::new();
+        coord.load_terrain(&[[100u16; 128]; 128], 128);
+        for dy in 0..3 {
+            spawn_person(&mut coord, cell_to_tile(40, 40 + dy, 128).to_world(), 0);
+        }
+        spawn_person(&mut coord, cell_to_tile(41, 41, 128).to_world(), 1);
+        let mut world = GameWorld::new(20);
+        world.state = GameState::InGame;
+        world.flags.set_multiplayer(true);
+        world.player_tribe = 0;
+        world.tribes.start_level(coord.tribe_populations());
+        assert_eq!(world.tribes.tribes.iter().map(|t| t.active).collect::<Vec<_>>(), [true, true, false, false]);
+
+        let (mut a, mut c, mut d, mut e, mut f, mut g, mut h, mut i, mut j, mut k) =
+            (NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp);
+        let mut subs = TickSubsystems {
+            terrain: &mut a, objects: &mut coord, water: &mut c,
+            network: &mut d, actions: &mut e, game_time: &mut f,
+            single_player: &mut g, tutorial: &mut h, ai: &mut i,
+            population: &mut j, mana: &mut k,
+        };
+        for _ in 0..2000 {
+            world.fast_forward(1, &mut subs);
+            if world.flags.is_victory_defeat() {
+                break;
+            }
+        }
+        // Three against one: tribe 1 is wiped out and tribe 0 wins
+        assert!(world.flags.has_won() && !world.flags.has_lost());
+        assert_eq!(world.tribes.tribes[1].population, 0);
+        assert!(world.tribes.tribes[0].population > 0);
+    }
+
+    #[test]
+    fn shaman_without_pillar_stays_dead() {
+        let mut coord = UnitCoordinator::new();
+        coord.load_terrain(&[[100u16; 128]; 128], 128);
+        coord.pillars.push(ReincarnationPillar::new(0, cell_to_tile(80, 80, 128).to_world()));
+        coord.destroy_pillar(0);
+        let shaman = kill_shaman(&mut coord, 0);
+
+        let mut events = Vec::new();
+        for _ in 0..600 {
+            coord.tick();
+            events.extend(coord.take_tribe_events());
+        }
+
+        assert_eq!(events, vec![
+            TribeEvent::ReincarnationSite { tribe: 0, standing: false },
+            TribeEvent::ShamanKilled { tribe: 0, killer: None, respawn: false },
+        ]);
+        assert!(!coord.units[shaman].alive);
+    }
+
+    fn raw_object(model: ModelType, subtype: u8, tribe: u8, pos: WorldCoord, fd: &[u8]) -> UnitRaw {
+        UnitRaw::for_test(model, subtype, tribe, pos.x as u16, pos.z as u16, fd)
+    }
+
+    #[test]
+    fn reloading_a_level_restarts_the_formation_rng() {
+        let raws: Vec<_> = (0..4)
+            .map(|i| raw_object(ModelType::Person, 2, 0, cell_to_tile(60 + i, 64, 128).to_world(), &[]))
+            .collect();
+        let play = |coord: &mut UnitCoordinator| {
+            coord.load_level(0x1234, &raws, &[[100u16; 128]; 128], 128);
+            coord.selection.select_multiple((0..4).collect());
+            coord.order_move(cell_to_tile(80, 70, 128).to_world());
+            for _ in 0..20 {
+                coord.tick();
+            }
+            let mut sums = StateChecksums::new(20);
+            coord.add_checksums(&mut sums);
+            sums
+        };
+
+        let mut coord = UnitCoordinator::new();
+        let first = play(&mut coord);
+        assert_ne!(coord.formation_rng, 0, "the group move drew no jitter");
+        assert_eq!(play(&mut coord), first);
+    }
+
+    /// Loads every level under `$POP3_BASE/levels` (skipped when unset) and
+    /// checks that trigger dormancy leaves all placed persons in play: the
+    /// trigger data layout is unverified, so it must not hide real units.
+    #[test]
+    fn real_levels_spawn_every_placed_person() {
+        let Some(base) = std::env::var_os("POP3_BASE").map(std::path::PathBuf::from) else {
+            println!("skipped: POP3_BASE not set");
+            return;
+        };
+        let mut checked = 0;
+        for level in 1..=255u8 {
+            if !crate::data::level::LevelPaths::dat_path(&base.join("levels"), level).exists() { continue; }
+            let level_res = crate::data::level::LevelRes::new(&base, level, None);
+            let placed = level_res.units.iter()
+                .filter(|u| u.model_type() == Some(ModelType::Person) && (u.loc_x() != 0 || u.loc_y() != 0))
+                .count();
+            let shores = level_res.landscape.make_shores();
+            let mut coord = UnitCoordinator::new();
+            coord.load_level(0x1234, &level_res.units, &shores.height, level_res.landscape.land_size());
+            assert_eq!(coord.units.len(), placed, "level {}: persons hidden by triggers", level);
+            checked += 1;
+        }
+        assert!(checked > 0, "no levels under {}", base.display());
+    }
+
+    #[test]
+    fn proximity_trigger_wakes_linked_objects() {
+        let trigger_pos = cell_to_tile(90, 64, 128).to_world();
+        // Proximity, radius 2 cells, fires once, script action 5, links 2 and 3
+        let mut fd = [0xFFu8; 28];
+        fd[..8].copy_from_slice(&[0, 2, 1, 0, 0, 0, 5, 0]);
+        fd[8..12].copy_from_slice(&[2, 0, 3, 0]);
+        let raws = vec![
+            raw_object(ModelType::Person, 2, 0, cell_to_tile(64, 64, 128).to_world(), &[]),
+            raw_object(ModelType::General, 6, 0, trigger_pos, &fd),
+            raw_object(ModelType::Person, 3, 1, cell_to_tile(100, 100, 128).to_world(), &[]),
+            raw_object(ModelType::Scenery, 1, 0xFF, cell_to_tile(95, 95, 128).to_world(), &[]),
+        ];
+
+        let mut coord = UnitCoordinator::new();
+        coord.load_level(0x1234, &raws, &[[100u16; 128]; 128], 128);
+        assert_eq!(coord.triggers.len(), 1);
+        assert_eq!(coord.units.len(), 1);
+        assert_eq!(coord.hidden_objects(), &[3]);
+
+        coord.selection.select_single(0);
+        coord.order_move(trigger_pos);
+        let mut level_events = Vec::new();
+        for _ in 0..1500 {
+            coord.tick();
+            level_events.extend(coord.take_level_events());
+            if !level_events.is_empty() { break; }
+        }
+
+        assert_eq!(level_events, vec![
+            LevelEvent::Reveal(3),
+            LevelEvent::ScriptAction { trigger: 0, action: 5 },
+        ]);
+        assert_eq!(coord.units.len(), 2);
+        assert_eq!(coord.units[1].tribe_index, 1);
+        assert!(coord.hidden_objects().is_empty());
+        assert!(!coord.triggers[0].active);
+    }
+
+    #[test]
+    fn timed_trigger_arms_chained_trigger() {
+        // Timed trigger (delay 10) activates an inactive timed trigger (delay 5)
+        // which then wakes a dormant person.
+        let mut first = [0xFFu8; 28];
+        first[..8].copy_from_slice(&[1, 0, 1, 0, 10, 0, 0, 0]);
+        first[8..10].copy_from_slice(&[1, 0]);
+        let mut second = [0xFFu8; 28];
+        second[..8].copy_from_slice(&[1, 0, 1, 1, 5, 0, 0, 0]);
+        second[8..10].copy_from_slice(&[2, 0]);
+        let pos = cell_to_tile(40, 40, 128).to_world();
+        let raws = vec![
+            raw_object(ModelType::General, 6, 0xFF, pos, &first),
+            raw_object(ModelType::General, 6, 0xFF, pos, &second),
+            raw_object(ModelType::Person, 2, 0, pos, &[]),
+        ];
+
+        let mut coord = UnitCoordinator::new();
+        coord.load_level(0x1234, &raws, &[[100u16; 128]; 128], 128);
+        assert!(coord.units.is_empty());
+        assert!(!coord.triggers[1].active);
+
+        for _ in 0..10 { coord.tick(); }
+        assert!(coord.triggers[1].active);
+        assert!(coord.units.is_empty());
+        for _ in 0..5 { coord.tick(); }
+        assert_eq!(coord.units.len(), 1);
+    }
 }
@@ -0,0 +1,316 @@
+// Visual effect objects (model type 7) — explosions, smoke, fire, sparkles,
+// debris and spell visuals with a fixed lifetime and a looping animation.
+//
+// Effects live in the low-priority object pool: spawning fails silently once
+// the pool is full. UnitCoordinator spawns them from gameplay events and ticks
+// them after the persons; the renderer draws them as billboards.
+//
+// Lifetime, animation and look are NOT the original's yet: they come from
+// the stand-ins in effect_placeholder.rs until the sprite sequences are
+// mapped.
+
+use crate::engine::movement::WorldCoord;
+use crate::engine::state::constants::LOW_PRIORITY_POOL;
+
+pub type EffectId = usize;
+
+/// Number of effect subtypes in the original (1..=93; 50 and 86 unused).
+pub const EFFECT_TYPE_COUNT: u8 = 93;
+
+/// Effect subtypes.
+/// Original: Effect_Init @ 0x004f0e20 dispatches on these.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum EffectType {
+    SimpleBlast            = 1,
+    SpriteCircles          = 2,
+    Smoke                  = 3,
+    LightningElement       = 4,
+    BurnCellObstacles      = 5,
+    FlattenLand            = 6,
+    MoveRsPillar           = 7,
+    PrepareRsLand          = 8,
+    SphereExplode1         = 9,
+    Fireball               = 10,
+    Firecloud              = 11,
+    GhostArmy              = 12,
+    Invisibility           = 13,
+    ExplodeBuildingPartial = 14,
+    Volcano                = 15,
+    Hypnotism              = 16,
+    LightningBolt          = 17,
+    Swamp                  = 18,
+    AngelOfDeath           = 19,
+    Whirlwind              = 20,
+    InsectPlague           = 21,
+    Firestorm              = 22,
+    Erosion                = 23,
+    LandBridge             = 24,
+    WrathOfGod             = 25,
+    Earthquake             = 26,
+    FlyThingummy           = 27,
+    SphereExplodeAndFire   = 28,
+    BigFire                = 29,
+    Lightning              = 30,
+    Flatten                = 31,
+    General                = 32,
+    ShapeSparkle           = 33,
+    LavaFlow               = 34,
+    VolcanoExplosions      = 35,
+    PurifyLand             = 36,
+    UnpurifyLand           = 37,
+    Explosion1             = 38,
+    Explosion2             = 39,
+    LavaSquare             = 40,
+    WhirlwindElement       = 41,
+    LightningStrand        = 42,
+    WhirlwindDust          = 43,
+    RaiseLand              = 44,
+    LowerLand              = 45,
+    Hill                   = 46,
+    Valley                 = 47,
+    PlaceTree              = 48,
+    Rise                   = 49,
+    ReinRockDebris         = 51,
+    ClearMapwho            = 52,
+    PlaceShaman            = 53,
+    PlaceWildMan           = 54,
+    BuildingSmoke          = 55,
+    MuchSimplerBlast       = 56,
+    TumblingBranch         = 57,
+    ConversionFlash        = 58,
+    HypnosisFlash          = 59,
+    Sparkle                = 60,
+    SmallSparkle           = 61,
+    Explosion3             = 62,
+    RockExplosion          = 63,
+    LavaGloop              = 64,
+    Splash                 = 65,
+    SmokeCloud             = 66,
+    SmokeCloudConstant     = 67,
+    Fireball2              = 68,
+    GroundShockwave        = 69,
+    Orbiter                = 70,
+    BigSparkle             = 71,
+    Meteor                 = 72,
+    ConvertWild            = 73,
+    BuildingSmokeFull      = 74,
+    BuildingSmokePart      = 75,
+    BuildingDamagedSmoke   = 76,
+    DeleteRsPillars        = 77,
+    SpellBlast             = 78,
+    FirestormSmoke         = 79,
+    PlayerDead             = 80,
+    RevealFogArea          = 81,
+    Shield                 = 82,
+    BoatHutRepair          = 83,
+    SwampReeds             = 84,
+    SwampMist              = 85,
+    Bloodlust              = 87,
+    Teleport               = 88,
+    AtlantisSet            = 89,
+    AtlantisInvoke         = 90,
+    StatueToAod            = 91,
+    FillSpellOneShots      = 92,
+    FireRollElement        = 93,
+}
+
+impl EffectType {
+    pub fn from_subtype(subtype: u8) -> Option<Self> {
+        use EffectType::*;
+        const ALL: [EffectType; 91] = [
+            SimpleBlast, SpriteCircles, Smoke, LightningElement, BurnCellObstacles,
+            FlattenLand, MoveRsPillar, PrepareRsLand, SphereExplode1, Fireball,
+            Firecloud, GhostArmy, Invisibility, ExplodeBuildingPartial, Volcano,
+            Hypnotism, LightningBolt, Swamp, AngelOfDeath, Whirlwind,
+            InsectPlague, Firestorm, Erosion, LandBridge, WrathOfGod,
+            Earthquake, FlyThingummy, SphereExplodeAndFire, BigFire, Lightning,
+            Flatten, General, ShapeSparkle, LavaFlow, VolcanoExplosions,
+            PurifyLand, UnpurifyLand, Explosion1, Explosion2, LavaSquare,
+            WhirlwindElement, LightningStrand, WhirlwindDust, RaiseLand, LowerLand,
+            Hill, Valley, PlaceTree, Rise, ReinRockDebris,
+            ClearMapwho, PlaceShaman, PlaceWildMan, BuildingSmoke, MuchSimplerBlast,
+            TumblingBranch, ConversionFlash, HypnosisFlash, Sparkle, SmallSparkle,
+            Explosion3, RockExplosion, LavaGloop, Splash, SmokeCloud,
+            SmokeCloudConstant, Fireball2, GroundShockwave, Orbiter, BigSparkle,
+            Meteor, ConvertWild, BuildingSmokeFull, BuildingSmokePart, BuildingDamagedSmoke,
+            DeleteRsPillars, SpellBlast, FirestormSmoke, PlayerDead, RevealFogArea,
+            Shield, BoatHutRepair, SwampReeds, SwampMist, Bloodlust,
+            Teleport, AtlantisSet, AtlantisInvoke, StatueToAod, FillSpellOneShots,
+            FireRollElement,
+        ];
+        ALL.iter().copied().find(|e| *e as u8 == subtype)
+    }
+}
+
+/// Tint for hit spray. The effect table has no blood subtype, so combat hits
+/// spawn a Splash drawn in red.
+pub const BLOOD_TINT: [u8; 3] = [170, 10, 10];
+
+#[derive(Clone)]
+pub struct Effect {
+    pub id: EffectId,
+    pub kind: EffectType,
+    pub position: WorldCoord,
+    /// Height above the ground (world units).
+    pub height: i16,
+    pub age: u16,
+    /// Colour override (blood).
+    pub tint: Option<[u8; 3]>,
+    // Rendering cache — cell-space position.
+    pub cell_x: f32,
+    pub cell_y: f32,
+}
+
+impl Effect {
+    /// Ticks the effect lives for (1 for logic-only effects).
+    pub fn lifetime(&self) -> u16 {
+        self.kind.placeholder_visual().map_or(1, |v| v.lifetime)
+    }
+
+    /// Current animation frame.
+    pub fn frame_index(&self) -> u8 {
+        match self.kind.placeholder_visual() {
+            Some(v) => ((self.age / v.frame_ticks.max(1) as u16) % v.frames.max(1) as u16) as u8,
+            None => 0,
+        }
+    }
+
+    /// Fraction of the lifetime elapsed, 0.0..=1.0.
+    pub fn progress(&self) -> f32 {
+        self.age as f32 / self.lifetime() as f32
+    }
+
+    pub fn color(&self) -> Option<[u8; 3]> {
+        let visual = self.kind.placeholder_visual()?;
+        Some(self.tint.unwrap_or(visual.color))
+    }
+
+    /// Advance one tick. Returns false once the effect has expired.
+    pub fn tick(&mut self) -> bool {
+        self.age += 1;
+        if let Some(v) = self.kind.placeholder_visual() {
+            self.height = self.height.saturating_add(v.rise);
+        }
+        self.age < self.lifetime()
+    }
+}
+
+/// Live effects, capped at the low-priority pool size.
+#[derive(Clone)]
+pub struct EffectPool {
+    pub effects: Vec<Effect>,
+    next_id: EffectId,
+}
+
+impl EffectPool {
+    pub fn new() -> Self {
+        Self { effects: Vec::new(), next_id: 0 }
+    }
+
+    pub fn clear(&mut self) {
+        self.effects.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.effects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    /// Create an effect at `position`. Returns None when the pool is full.
+    /// Original: Effect_Init @ 0x004f0e20 (via Object_Create, low-priority pool).
+    pub fn spawn(&mut self, kind: EffectType, position: WorldCoord) -> Option<EffectId> {
+        if self.effects.len() >= LOW_PRIORITY_POOL as usize {
+            return None;
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.effects.push(Effect {
+            id,
+            kind,
+            position,
+            height: 0,
+            age: 0,
+            tint: None,
+            cell_x: 0.0,
+            cell_y: 0.0,
+        });
+        Some(id)
+    }
+
+    /// Spawn a tinted effect (e.g. blood).
+    pub fn spawn_tinted(&mut self, kind: EffectType, position: WorldCoord, tint: [u8; 3]) -> Option<EffectId> {
+        let id = self.spawn(kind, position)?;
+        if let Some(e) = self.effects.last_mut() {
+            e.tint = Some(tint);
+        }
+        Some(id)
+    }
+
+    /// Age all effects and drop expired ones.
+    pub fn tick(&mut self) {
+        self.effects.retain_mut(|e| e.tick());
+    }
+}
+
+impl Default for EffectPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::units::effect_placeholder::{BLAST, SMOKE};
+
+    #[test]
+    fn subtype_table_covers_original_types() {
+        let named = (1..=EFFECT_TYPE_COUNT).filter_map(EffectType::from_subtype).count();
+        assert_eq!(named, 91);
+        assert_eq!(EffectType::from_subtype(50), None);
+        assert_eq!(EffectType::from_subtype(86), None);
+        assert_eq!(EffectType::from_subtype(93), Some(EffectType::FireRollElement));
+        for st in 1..=EFFECT_TYPE_COUNT {
+            if let Some(e) = EffectType::from_subtype(st) {
+                assert_eq!(e as u8, st);
+            }
+        }
+    }
+
+    #[test]
+    fn effect_expires_after_lifetime() {
+        let mut pool = EffectPool::new();
+        pool.spawn(EffectType::Explosion1, WorldCoord::new(0, 0));
+        let lifetime = BLAST.lifetime;
+        for _ in 0..lifetime - 1 {
+            pool.tick();
+        }
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool.effects[0].frame_index(), ((lifetime - 1) / 3 % 8) as u8);
+        pool.tick();
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn smoke_rises_and_logic_effects_vanish() {
+        let mut pool = EffectPool::new();
+        pool.spawn(EffectType::Smoke, WorldCoord::new(0, 0));
+        pool.spawn(EffectType::RaiseLand, WorldCoord::new(0, 0));
+        pool.tick();
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool.effects[0].height, SMOKE.rise);
+    }
+
+    #[test]
+    fn pool_capped_at_low_priority_size() {
+        let mut pool = EffectPool::new();
+        for _ in 0..LOW_PRIORITY_POOL {
+            assert!(pool.spawn(EffectType::Sparkle, WorldCoord::new(0, 0)).is_some());
+        }
+        assert_eq!(pool.spawn(EffectType::Sparkle, WorldCoord::new(0, 0)), None);
+    }
+}
@@ -0,0 +1,85 @@
+// Placeholder effect visuals — stand-in lifetime, animation and colour for
+// each effect subtype, until the original's sprite sequences are mapped.
+//
+// The original draws effects as sprite sequences
+// (Animation_RenderFrameSequence @ 0x004e7190, animation table at 0x5a7d84),
+// but which sequence each subtype uses isn't in docs/specs yet. Nothing in
+// this file comes from the binary: replace it, don't tune it.
+
+use super::effect::EffectType;
+
+/// Stand-in look and lifetime of an effect, until the original's sprite
+/// sequences are mapped. None of these numbers come from the binary.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaceholderVisual {
+    /// Ticks until the effect is removed.
+    pub lifetime: u16,
+    /// Animation frames, looped over the lifetime.
+    pub frames: u8,
+    /// Ticks per animation frame.
+    pub frame_ticks: u8,
+    /// Height gained per tick (world units), e.g. rising smoke.
+    pub rise: i16,
+    /// Billboard size in cells at spawn.
+    pub size: f32,
+    /// Size multiplier reached at the end of the lifetime.
+    pub grow: f32,
+    pub color: [u8; 3],
+}
+
+pub(crate) const BLAST: PlaceholderVisual = PlaceholderVisual {
+    lifetime: 24, frames: 8, frame_ticks: 3, rise: 0, size: 0.8, grow: 1.6, color: [255, 160, 40],
+};
+pub(crate) const SMOKE: PlaceholderVisual = PlaceholderVisual {
+    lifetime: 64, frames: 8, frame_ticks: 8, rise: 4, size: 0.5, grow: 2.0, color: [120, 120, 120],
+};
+pub(crate) const FIRE: PlaceholderVisual = PlaceholderVisual {
+    lifetime: 48, frames: 8, frame_ticks: 6, rise: 1, size: 0.6, grow: 1.0, color: [240, 90, 20],
+};
+pub(crate) const SPARKLE: PlaceholderVisual = PlaceholderVisual {
+    lifetime: 32, frames: 8, frame_ticks: 4, rise: 2, size: 0.4, grow: 0.5, color: [255, 240, 160],
+};
+pub(crate) const LIGHTNING: PlaceholderVisual = PlaceholderVisual {
+    lifetime: 12, frames: 4, frame_ticks: 3, rise: 0, size: 1.2, grow: 1.0, color: [200, 220, 255],
+};
+pub(crate) const DEBRIS: PlaceholderVisual = PlaceholderVisual {
+    lifetime: 24, frames: 6, frame_ticks: 4, rise: 0, size: 0.3, grow: 1.0, color: [130, 90, 50],
+};
+pub(crate) const SPELL_AREA: PlaceholderVisual = PlaceholderVisual {
+    lifetime: 128, frames: 8, frame_ticks: 8, rise: 0, size: 1.0, grow: 1.0, color: [170, 80, 220],
+};
+
+impl EffectType {
+    /// Placeholder visual of the effect; None for effects that only drive
+    /// game logic (terrain edits, placements, bookkeeping) and draw nothing
+    /// themselves.
+    pub fn placeholder_visual(self) -> Option<PlaceholderVisual> {
+        use EffectType::*;
+        let v = match self {
+            SimpleBlast | SphereExplode1 | ExplodeBuildingPartial | SphereExplodeAndFire
+            | VolcanoExplosions | Explosion1 | Explosion2 | Explosion3 | RockExplosion
+            | MuchSimplerBlast | GroundShockwave | SpellBlast => BLAST,
+            Smoke | SmokeCloud | SmokeCloudConstant | BuildingSmoke | BuildingSmokeFull
+            | BuildingSmokePart | BuildingDamagedSmoke | FirestormSmoke | WhirlwindDust
+            | SwampMist => SMOKE,
+            BurnCellObstacles | Fireball | Firecloud | Volcano | Firestorm | BigFire
+            | LavaFlow | LavaSquare | LavaGloop | Fireball2 | Meteor | FireRollElement => FIRE,
+            SpriteCircles | ShapeSparkle | ConversionFlash | HypnosisFlash | Sparkle
+            | SmallSparkle | Orbiter | BigSparkle | ConvertWild | Teleport | Hypnotism => SPARKLE,
+            Invisibility => PlaceholderVisual { color: [180, 200, 255], ..SPARKLE },
+            Shield => PlaceholderVisual { color: [80, 140, 255], ..SPARKLE },
+            Bloodlust => PlaceholderVisual { color: [220, 30, 30], ..SPARKLE },
+            LightningElement | LightningBolt | Lightning | LightningStrand => LIGHTNING,
+            ReinRockDebris | TumblingBranch => DEBRIS,
+            Splash => PlaceholderVisual { color: [150, 190, 255], ..DEBRIS },
+            GhostArmy | Swamp | AngelOfDeath | Whirlwind | InsectPlague | Erosion | WrathOfGod
+            | Earthquake | FlyThingummy | WhirlwindElement | SwampReeds | AtlantisInvoke => SPELL_AREA,
+            FlattenLand | MoveRsPillar | PrepareRsLand | LandBridge | Flatten | General
+            | PurifyLand | UnpurifyLand | RaiseLand | LowerLand | Hill | Valley | PlaceTree
+            | Rise | ClearMapwho | PlaceShaman | PlaceWildMan | DeleteRsPillars | PlayerDead
+            | RevealFogArea | BoatHutRepair | AtlantisSet | StatueToAod
+            | FillSpellOneShots => return None,
+        };
+        Some(v)
+    }
+}
@@ -9,8 +9,23 @@ pub mod selection;
 pub mod person_state;
 pub mod animation;
 pub mod coordinator;
+pub mod vehicle;
+pub mod stone_head;
+pub mod reincarnation;
+pub mod trigger;
+pub mod effect;
+pub mod effect_placeholder;
+pub mod spatial;
+pub mod avoidance;
 
 pub use unit::{Unit, UnitId};
+pub use vehicle::{Vehicle, VehicleId, VehicleState};
+pub use stone_head::{StoneHead, StoneHeadId};
+pub use reincarnation::ReincarnationPillar;
+pub use trigger::{Trigger, TriggerId, LevelEvent};
+pub use effect::{Effect, EffectId, EffectType, EffectPool};
+pub use effect_placeholder::PlaceholderVisual;
 pub use selection::{SelectionState, DragState, find_unit_at_cell};
 pub use coordinator::UnitCoordinator;
+pub use spatial::UnitGrid;
 pub use coords::{world_to_cell, cell_to_world, gpu_to_cell};
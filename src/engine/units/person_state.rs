@@ -113,6 +113,7 @@ pub fn enter_state(unit: &mut Unit, new_state: PersonState, rng: &mut GameRng) {
         PersonState::Fleeing => enter_fleeing(unit, rng),
         PersonState::Drowning => enter_drowning(unit),
         PersonState::Dead => enter_dead(unit, rng),
+        PersonState::WaitingAtReincPillar => enter_waiting_at_pillar(unit),
         _ => { /* Unimplemented states — no-op */ }
     }
 }
@@ -200,6 +201,17 @@ fn enter_dead(unit: &mut Unit, rng: &mut GameRng) {
     unit.state_counter = (rng.next() & 7) as u8;
 }
 
+/// Ticks a reincarnated shaman stands at the pillar before taking orders.
+pub const REINC_PILLAR_WAIT_TICKS: u16 = 0x20;
+
+/// WaitingAtReincPillar: freshly reincarnated shaman rises at the pillar.
+/// Original: case '\x2c' in Person_SetState.
+fn enter_waiting_at_pillar(unit: &mut Unit) {
+    unit.movement.speed = 0;
+    unit.movement.flags1 &= !0x1000; // Stop moving
+    unit.state_timer = REINC_PILLAR_WAIT_TICKS;
+}
+
 // --- Per-tick state update ---
 
 /// Result of a single tick_state call.
@@ -221,6 +233,7 @@ pub fn tick_state(unit: &mut Unit, rng: &mut GameRng) -> TickResult {
         PersonState::Fleeing => tick_fleeing(unit),
         PersonState::Drowning => tick_drowning(unit),
         PersonState::Dead => tick_dead(unit),
+        PersonState::WaitingAtReincPillar => tick_waiting_at_pillar(unit),
         _ => TickResult::Continue, // Unimplemented states hold
     }
 }
@@ -390,6 +403,16 @@ fn tick_dead(unit: &mut Unit) -> TickResult {
     TickResult::Continue
 }
 
+/// WaitingAtReincPillar: count down, then go idle.
+fn tick_waiting_at_pillar(unit: &mut Unit) -> TickResult {
+    if unit.state_timer > 0 {
+        unit.state_timer -= 1;
+        TickResult::Continue
+    } else {
+        TickResult::Transition(PersonState::Idle)
+    }
+}
+
 // --- Combat helpers ---
 
 /// Calculate melee damage from attacker to defender.
@@ -474,39 +497,10 @@ impl CombatPhase {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::engine::movement::PersonMovement;
-    use crate::data::units::ModelType;
 
     fn make_unit(subtype: u8, tribe: u8) -> Unit {
         use crate::engine::movement::WorldCoord;
-        use crate::engine::units::animation::AnimationState;
-        let defaults = person_type_defaults(subtype);
-        Unit {
-            id: 0,
-            model_type: ModelType::Person,
-            subtype,
-            tribe_index: tribe,
-            movement: PersonMovement::default(),
-            cell_x: 0.0,
-            cell_y: 0.0,
-            state: PersonState::Idle,
-            prev_state: PersonState::Idle,
-            state_timer: 0,
-            state_counter: 0,
-            health: defaults.max_health,
-            max_health: defaults.max_health,
-            target_unit: None,
-            attacker_unit: None,
-            alive: true,
-            home_pos: WorldCoord::new(0, 0),
-            behavior_flags: 0,
-            wander_duration: 0,
-            wander_range: 0,
-            linked_obj_id: None,
-            bloodlust: false,
-            shielded: false,
-            anim: AnimationState::default(),
-        }
+        Unit::new_person(0, subtype, tribe, WorldCoord::new(0, 0))
     }
 
     #[test]
@@ -576,6 +570,18 @@ mod tests {
         assert!(unit.movement.flags1 & 0x1000 != 0); // MOVING set
     }
 
+    #[test]
+    fn waiting_at_pillar_goes_idle_after_timer() {
+        let mut unit = make_unit(7, 0);
+        let mut rng = GameRng::new(1);
+        enter_state(&mut unit, PersonState::WaitingAtReincPillar, &mut rng);
+        assert_eq!(unit.movement.speed, 0);
+        for _ in 0..REINC_PILLAR_WAIT_TICKS {
+            assert!(matches!(tick_state(&mut unit, &mut rng), TickResult::Continue));
+        }
+        assert!(matches!(tick_state(&mut unit, &mut rng), TickResult::Transition(PersonState::Idle)));
+    }
+
     #[test]
     fn idle_counts_down_and_stays_idle() {
         let mut unit = make_unit(2, 0);
@@ -0,0 +1,117 @@
+// Reincarnation pillars — each tribe's shaman respawns at its pillar a
+// while after dying, as long as the pillar still stands.
+
+use crate::data::units::{ModelType, UnitRaw};
+use crate::engine::movement::WorldCoord;
+use crate::engine::state::constants::MAX_TRIBES;
+use super::unit::UnitId;
+
+/// Scenery subtype of the reincarnation site model.
+pub const SCENERY_SUBTYPE_REINC_PILLAR: u8 = 12;
+
+/// Ticks between shaman death and respawn at the pillar.
+///
+/// Unverified: this crate's own value, not taken from the binary. The
+/// original's delay comes from the shaman's WaitingAtReincPillar state
+/// (0x2C, docs/specs/person_units.md), whose timing isn't in docs/specs
+/// yet; check it against a capture.
+pub const SHAMAN_REINCARNATION_DELAY: u16 = 0xC0;
+
+/// A shaman waiting to be reincarnated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingRespawn {
+    pub shaman: UnitId,
+    /// Ticks left before the shaman reappears.
+    pub timer: u16,
+}
+
+#[derive(Clone)]
+pub struct ReincarnationPillar {
+    pub tribe_index: u8,
+    pub position: WorldCoord,
+    pub destroyed: bool,
+    pub respawn: Option<PendingRespawn>,
+    // Rendering cache — cell-space position.
+    pub cell_x: f32,
+    pub cell_y: f32,
+}
+
+impl ReincarnationPillar {
+    pub fn new(tribe_index: u8, position: WorldCoord) -> Self {
+        Self {
+            tribe_index,
+            position,
+            destroyed: false,
+            respawn: None,
+            cell_x: 0.0,
+            cell_y: 0.0,
+        }
+    }
+
+    /// Queue `shaman` for reincarnation. Returns false if the pillar is gone.
+    pub fn schedule(&mut self, shaman: UnitId) -> bool {
+        if self.destroyed {
+            return false;
+        }
+        self.respawn = Some(PendingRespawn { shaman, timer: SHAMAN_REINCARNATION_DELAY });
+        true
+    }
+
+    /// Count down a pending respawn; returns the shaman once it is due.
+    pub fn tick(&mut self) -> Option<UnitId> {
+        let pending = self.respawn.as_mut()?;
+        if pending.timer > 0 {
+            pending.timer -= 1;
+            return None;
+        }
+        self.respawn.take().map(|p| p.shaman)
+    }
+
+    /// Pillar knocked down: any pending respawn is lost.
+    pub fn destroy(&mut self) {
+        self.destroyed = true;
+        self.respawn = None;
+    }
+}
+
+/// Create one pillar per owning tribe from level objects (first one wins).
+pub fn load_pillars(units_raw: &[UnitRaw]) -> Vec<ReincarnationPillar> {
+    let mut pillars: Vec<ReincarnationPillar> = Vec::new();
+    for raw in units_raw {
+        if raw.model_type() != Some(ModelType::Scenery) || raw.subtype != SCENERY_SUBTYPE_REINC_PILLAR {
+            continue;
+        }
+        let tribe = raw.tribe_index();
+        if (tribe as usize) >= MAX_TRIBES || pillars.iter().any(|p| p.tribe_index == tribe) {
+            continue;
+        }
+        let pos = WorldCoord::new(raw.loc_x() as i16, raw.loc_y() as i16);
+        pillars.push(ReincarnationPillar::new(tribe, pos));
+    }
+    pillars
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn respawn_due_after_delay() {
+        let mut pillar = ReincarnationPillar::new(0, WorldCoord::new(0x100, 0x100));
+        assert!(pillar.schedule(5));
+        for _ in 0..SHAMAN_REINCARNATION_DELAY {
+            assert_eq!(pillar.tick(), None);
+        }
+        assert_eq!(pillar.tick(), Some(5));
+        assert_eq!(pillar.respawn, None);
+    }
+
+    #[test]
+    fn destroyed_pillar_cancels_respawn() {
+        let mut pillar = ReincarnationPillar::new(1, WorldCoord::new(0, 0));
+        pillar.schedule(3);
+        pillar.destroy();
+        assert_eq!(pillar.tick(), None);
+        assert!(!pillar.schedule(3));
+    }
+}
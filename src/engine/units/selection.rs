@@ -3,6 +3,7 @@
 use cgmath::Point2;
 use super::unit::{Unit, UnitId};
 
+#[derive(Clone)]
 pub struct SelectionState {
     pub selected: Vec<UnitId>,
 }
@@ -31,6 +32,7 @@ impl SelectionState {
 }
 
 /// Drag-box state machine for rubber-band multi-select.
+#[derive(Clone)]
 pub enum DragState {
     None,
     /// Left button pressed — not yet dragging (waiting for threshold).
@@ -59,38 +61,10 @@ pub fn find_unit_at_cell(units: &[Unit], cell_x: f32, cell_y: f32, threshold: f3
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::engine::movement::PersonMovement;
-    use crate::data::units::ModelType;
 
     fn make_unit(id: usize, cx: f32, cy: f32) -> Unit {
-        use super::super::person_state::PersonState;
         use crate::engine::movement::WorldCoord;
-        Unit {
-            id,
-            model_type: ModelType::Person,
-            subtype: 2,
-            tribe_index: 0,
-            movement: PersonMovement::default(),
-            cell_x: cx,
-            cell_y: cy,
-            state: PersonState::Idle,
-            prev_state: PersonState::Idle,
-            state_timer: 0,
-            state_counter: 0,
-            health: 1400,
-            max_health: 1400,
-            target_unit: None,
-            attacker_unit: None,
-            alive: true,
-            home_pos: WorldCoord::new(0, 0),
-            behavior_flags: 0,
-            wander_duration: 0,
-            wander_range: 0,
-            linked_obj_id: None,
-            bloodlust: false,
-            shielded: false,
-            anim: super::super::animation::AnimationState::default(),
-        }
+        Unit { cell_x: cx, cell_y: cy, ..Unit::new_person(id, 2, 0, WorldCoord::new(0, 0)) }
     }
 
     #[test]
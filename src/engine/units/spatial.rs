@@ -0,0 +1,137 @@
+// Spatial index of live units — per-cell linked lists like the original's
+// MapWho table (one list head per map cell, a `next` link per object).
+//
+// Rebuilt once per tick from unit positions; neighbour queries only visit
+// the cells overlapping the query square, so separation and blocking checks
+// stay cheap for large armies. Cells wrap around the torus.
+
+use crate::engine::movement::WorldCoord;
+use super::coords::toroidal_delta;
+use super::unit::UnitId;
+
+/// Cells per side (one per map cell, 512 world units each).
+pub const GRID_SIZE: usize = 128;
+/// World units per grid cell (log2).
+const CELL_SHIFT: u32 = 9;
+
+const NONE: u32 = u32::MAX;
+
+#[derive(Clone)]
+pub struct UnitGrid {
+    heads: Vec<u32>,
+    next: Vec<u32>,
+    positions: Vec<WorldCoord>,
+}
+
+impl Default for UnitGrid {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UnitGrid {
+    pub fn new() -> Self {
+        Self { heads: vec![NONE; GRID_SIZE * GRID_SIZE], next: Vec::new(), positions: Vec::new() }
+    }
+
+    fn cell_of(pos: WorldCoord) -> (usize, usize) {
+        ((pos.x as u16 >> CELL_SHIFT) as usize, (pos.z as u16 >> CELL_SHIFT) as usize)
+    }
+
+    /// Empty the grid, ready for `unit_count` units.
+    pub fn clear(&mut self, unit_count: usize) {
+        self.heads.fill(NONE);
+        self.next.clear();
+        self.next.resize(unit_count, NONE);
+        self.positions.clear();
+        self.positions.resize(unit_count, WorldCoord::default());
+    }
+
+    /// Link a unit into the list of the cell at `pos`.
+    pub fn insert(&mut self, id: UnitId, pos: WorldCoord) {
+        if id >= self.next.len() {
+            self.next.resize(id + 1, NONE);
+            self.positions.resize(id + 1, WorldCoord::default());
+        }
+        let (cx, cz) = Self::cell_of(pos);
+        let head = &mut self.heads[cz * GRID_SIZE + cx];
+        self.next[id] = *head;
+        *head = id as u32;
+        self.positions[id] = pos;
+    }
+
+    /// Call `f` for every unit within `radius` (per axis) of `center`, at the
+    /// position it was inserted with.
+    pub fn for_each_near(&self, center: WorldCoord, radius: i32, mut f: impl FnMut(UnitId)) {
+        let (cx, cz) = Self::cell_of(center);
+        let reach = ((radius >> CELL_SHIFT) + 1) as isize;
+        let reach = reach.min(GRID_SIZE as isize / 2);
+        for dz in -reach..=reach {
+            for dx in -reach..=reach {
+                let x = (cx as isize + dx).rem_euclid(GRID_SIZE as isize) as usize;
+                let z = (cz as isize + dz).rem_euclid(GRID_SIZE as isize) as usize;
+                let mut id = self.heads[z * GRID_SIZE + x];
+                while id != NONE {
+                    let pos = self.positions[id as usize];
+                    if toroidal_delta(center.x, pos.x).abs() <= radius
+                        && toroidal_delta(center.z, pos.z).abs() <= radius
+                    {
+                        f(id as usize);
+                    }
+                    id = self.next[id as usize];
+                }
+            }
+        }
+    }
+
+    /// Units within `radius` of `center`, in no particular order.
+    pub fn near(&self, center: WorldCoord, radius: i32) -> Vec<UnitId> {
+        let mut ids = Vec::new();
+        self.for_each_near(center, radius, |id| ids.push(id));
+        ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_finds_units_in_range_only() {
+        let mut grid = UnitGrid::new();
+        grid.clear(4);
+        grid.insert(0, WorldCoord::new(1000, 1000));
+        grid.insert(1, WorldCoord::new(1100, 1050));
+        grid.insert(2, WorldCoord::new(1000, 1600));
+        grid.insert(3, WorldCoord::new(5000, 5000));
+        let mut near = grid.near(WorldCoord::new(1000, 1000), 200);
+        near.sort();
+        assert_eq!(near, vec![0, 1]);
+        assert_eq!(grid.near(WorldCoord::new(1000, 1000), 700).len(), 3);
+    }
+
+    #[test]
+    fn query_wraps_around_the_world_edge() {
+        let mut grid = UnitGrid::new();
+        grid.clear(2);
+        grid.insert(0, WorldCoord::new(i16::MAX - 20, 0));
+        grid.insert(1, WorldCoord::new(i16::MIN + 20, -30));
+        // 0x7FEB and 0x8014 are 41 units apart across the sign boundary
+        assert_eq!(grid.near(WorldCoord::new(i16::MAX, 0), 64).len(), 2);
+        grid.clear(2);
+        grid.insert(0, WorldCoord::new(-40, -40));
+        assert_eq!(grid.near(WorldCoord::new(40, 40), 100), vec![0]);
+    }
+
+    #[test]
+    fn clear_empties_every_cell() {
+        let mut grid = UnitGrid::new();
+        grid.clear(1);
+        grid.insert(0, WorldCoord::new(300, 300));
+        grid.clear(1);
+        assert!(grid.near(WorldCoord::new(300, 300), 1000).is_empty());
+        // Inserting beyond the reserved count grows the links
+        grid.insert(5, WorldCoord::new(300, 300));
+        assert_eq!(grid.near(WorldCoord::new(300, 300), 10), vec![5]);
+    }
+}
@@ -0,0 +1,167 @@
+// Stone heads — worship sites that grant a discovery to the first tribe
+// whose followers finish worshipping them.
+//
+// UnitCoordinator sends ordered units to the head, sits them down around it
+// and feeds the per-tick worshipper count into `StoneHead::worship`.
+
+use crate::data::units::{ModelType, UnitRaw};
+use crate::engine::movement::WorldCoord;
+use crate::engine::state::constants::MAX_TRIBES;
+use crate::engine::state::discovery::Discovery;
+use super::unit::UnitId;
+use super::coords::toroidal_delta;
+
+pub type StoneHeadId = usize;
+
+/// Scenery subtype of the stone head model.
+pub const SCENERY_SUBTYPE_STONE_HEAD: u8 = 9;
+/// General subtypes carrying discovery definitions.
+pub const GENERAL_SUBTYPE_DISCOVERY: u8 = 2;
+pub const GENERAL_SUBTYPE_DISCOVERY_MARKER: u8 = 10;
+
+/// Worship needed to complete a discovery: one point per worshipper per tick.
+///
+/// Unverified: this crate's own threshold and rate. Discovery_Check
+/// (0x004bed50) hasn't been decompiled into docs/specs yet; check both
+/// against a capture of a head being worshipped.
+pub const WORSHIP_PROGRESS_MAX: u32 = 0x800;
+/// Manhattan distance from the head within which a follower can worship.
+pub const WORSHIP_RANGE: i32 = 0x300;
+/// Manhattan distance within which a move order targets the head.
+pub const STONE_HEAD_PICK_RANGE: i32 = 0x200;
+
+#[derive(Clone)]
+pub struct StoneHead {
+    pub id: StoneHeadId,
+    pub position: WorldCoord,
+    /// What the head grants; None for decorative heads.
+    pub discovery: Option<Discovery>,
+    /// Per-tribe worship progress.
+    /// Original: g_WorshipProgress (0x00885810)
+    pub progress: [u32; MAX_TRIBES],
+    /// Tribe that completed the discovery. Claimed heads can't be worshipped.
+    pub claimed_by: Option<u8>,
+    /// Followers ordered to worship here (walking there or worshipping).
+    pub worshippers: Vec<UnitId>,
+    // Rendering cache — cell-space position.
+    pub cell_x: f32,
+    pub cell_y: f32,
+}
+
+impl StoneHead {
+    pub fn new(id: StoneHeadId, position: WorldCoord, discovery: Option<Discovery>) -> Self {
+        Self {
+            id,
+            position,
+            discovery,
+            progress: [0; MAX_TRIBES],
+            claimed_by: None,
+            worshippers: Vec::new(),
+            cell_x: 0.0,
+            cell_y: 0.0,
+        }
+    }
+
+    /// Whether followers can still worship here for a discovery.
+    pub fn is_worshippable(&self) -> bool {
+        self.discovery.is_some() && self.claimed_by.is_none()
+    }
+
+    /// Manhattan distance from the head, toroidal.
+    pub fn distance_to(&self, pos: WorldCoord) -> i32 {
+        let dx = toroidal_delta(self.position.x, pos.x);
+        let dz = toroidal_delta(self.position.z, pos.z);
+        dx.abs() + dz.abs()
+    }
+
+    /// Add one tick of worship from `worshippers` followers of `tribe`.
+    /// Returns the discovery once the tribe's progress reaches the threshold;
+    /// the head is then claimed by that tribe.
+    /// Original: Discovery_Check @ 0x004bed50
+    pub fn worship(&mut self, tribe: u8, worshippers: u32) -> Option<Discovery> {
+        if !self.is_worshippable() || (tribe as usize) >= MAX_TRIBES || worshippers == 0 {
+            return None;
+        }
+        let progress = &mut self.progress[tribe as usize];
+        *progress = (*progress + worshippers).min(WORSHIP_PROGRESS_MAX);
+        if *progress < WORSHIP_PROGRESS_MAX {
+            return None;
+        }
+        self.claimed_by = Some(tribe);
+        self.discovery
+    }
+}
+
+/// Read the discovery a level object carries.
+/// Discovery type and model index are taken from the first two bytes of the
+/// object's extra data block.
+///
+/// Unverified: a guess at the layout, not read from the original's
+/// Discovery_Init; no level has been checked against it.
+fn raw_discovery(raw: &UnitRaw) -> Option<Discovery> {
+    let fd = raw.fd();
+    Discovery::from_raw(fd[0], fd[1])
+}
+
+/// Create stone heads from level objects.
+/// Original: Discovery_Init @ 0x004bec80 — clears progress and records head
+/// locations. Discovery objects (general subtypes 2 and 10) placed in the
+/// level override the discovery of the nearest head.
+pub fn discovery_init(units_raw: &[UnitRaw]) -> Vec<StoneHead> {
+    let mut heads: Vec<StoneHead> = Vec::new();
+    for raw in units_raw {
+        if raw.model_type() != Some(ModelType::Scenery) || raw.subtype != SCENERY_SUBTYPE_STONE_HEAD {
+            continue;
+        }
+        let pos = WorldCoord::new(raw.loc_x() as i16, raw.loc_y() as i16);
+        heads.push(StoneHead::new(heads.len(), pos, raw_discovery(raw)));
+    }
+
+    for raw in units_raw {
+        if raw.model_type() != Some(ModelType::General) { continue; }
+        if raw.subtype != GENERAL_SUBTYPE_DISCOVERY && raw.subtype != GENERAL_SUBTYPE_DISCOVERY_MARKER {
+            continue;
+        }
+        let discovery = match raw_discovery(raw) {
+            Some(d) => d,
+            None => continue,
+        };
+        let pos = WorldCoord::new(raw.loc_x() as i16, raw.loc_y() as i16);
+        if let Some(head) = heads.iter_mut().min_by_key(|h| h.distance_to(pos)) {
+            head.discovery = Some(discovery);
+        }
+    }
+    heads
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn worship_completes_at_threshold() {
+        let mut head = StoneHead::new(0, WorldCoord::new(0x1000, 0x1000), Some(Discovery::Spell(3)));
+        assert_eq!(head.worship(1, WORSHIP_PROGRESS_MAX - 1), None);
+        assert_eq!(head.worship(1, 1), Some(Discovery::Spell(3)));
+        assert_eq!(head.claimed_by, Some(1));
+        // Claimed heads grant nothing further
+        assert_eq!(head.worship(2, WORSHIP_PROGRESS_MAX), None);
+    }
+
+    #[test]
+    fn worship_progress_is_per_tribe() {
+        let mut head = StoneHead::new(0, WorldCoord::new(0, 0), Some(Discovery::Building(4)));
+        head.worship(0, 10);
+        head.worship(2, 3);
+        assert_eq!(head.progress[0], 10);
+        assert_eq!(head.progress[2], 3);
+        assert_eq!(head.progress[1], 0);
+    }
+
+    #[test]
+    fn decorative_head_not_worshippable() {
+        let mut head = StoneHead::new(0, WorldCoord::new(0, 0), None);
+        assert!(!head.is_worshippable());
+        assert_eq!(head.worship(0, WORSHIP_PROGRESS_MAX), None);
+    }
+}
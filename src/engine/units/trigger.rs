@@ -0,0 +1,266 @@
+// Level triggers (general subtype 6) — fire when a tribe's units come
+// within range or after a delay, and wake the level objects linked to them.
+//
+// Objects linked from a trigger start dormant: persons aren't spawned and
+// scenery isn't shown until the trigger fires. UnitCoordinator owns the
+// triggers and applies their effects.
+
+use crate::data::units::{ModelType, UnitRaw};
+use crate::engine::movement::{WorldCoord, distance};
+
+pub type TriggerId = usize;
+
+/// General subtype of trigger objects.
+pub const GENERAL_SUBTYPE_TRIGGER: u8 = 6;
+/// Linked object slots per trigger.
+pub const MAX_TRIGGER_LINKS: usize = 10;
+/// Empty link slot.
+pub const TRIGGER_NO_LINK: u16 = 0xFFFF;
+/// Zero-filled link slot, also read as empty: with the layout unverified, a
+/// trigger whose unused slots are zeroed must not hide level object 0.
+pub const TRIGGER_ZERO_LINK: u16 = 0x0000;
+/// World units per map cell, for the trigger radius.
+const CELL_WORLD_SIZE: i32 = 0x200;
+
+// Trigger init data — extra data block of the level object. UNVERIFIED:
+// this layout is the crate's own reading, with no decomp reference in
+// docs/specs (which only give general subtype 6 = Trigger,
+// core_data_structures.md). `--debug` logs every trigger's raw block at
+// level load so it can be checked against real levels.
+//   +0      kind (0 = proximity, 1 = timed)
+//   +1      radius in cells
+//   +2      number of firings (0 = unlimited)
+//   +3      flags (bit 0 = starts inactive)
+//   +4..+6  delay in ticks (u16 LE): time to fire, or re-arm time
+//   +6      script action (0 = none)
+//   +8..+28 linked level-object indices (10 x u16 LE, 0xFFFF or 0 = empty)
+const TRIGGER_FLAG_INACTIVE: u8 = 0x01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TriggerKind {
+    /// Fires when a unit of the owning tribe (any tribe if unowned) is in range.
+    Proximity = 0,
+    /// Fires once its delay has elapsed after activation.
+    Timed     = 1,
+}
+
+/// What a firing trigger does to one of its links.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerEffect {
+    /// Bring a dormant level object into play (level-object index).
+    Wake(usize),
+    /// Arm another trigger.
+    Activate(TriggerId),
+    /// Hand a script action to the level script.
+    Script { trigger: TriggerId, action: u8 },
+}
+
+/// Level-object changes the renderer has to follow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelEvent {
+    /// A dormant static object (scenery, building, ...) appears.
+    Reveal(usize),
+    /// A trigger fired a script action.
+    ScriptAction { trigger: TriggerId, action: u8 },
+}
+
+#[derive(Clone)]
+pub struct Trigger {
+    pub id: TriggerId,
+    /// Index of the trigger in the level's object list.
+    pub level_index: usize,
+    pub position: WorldCoord,
+    pub kind: TriggerKind,
+    /// Trigger radius in world units.
+    pub radius: i32,
+    /// Tribe whose units set it off; None = any tribe.
+    pub tribe: Option<u8>,
+    /// Firings left; None = unlimited.
+    pub remaining: Option<u8>,
+    pub active: bool,
+    pub delay: u16,
+    /// Countdown to firing (timed) or re-arming (proximity).
+    pub timer: u16,
+    pub script_action: u8,
+    /// Linked level-object indices.
+    pub links: Vec<usize>,
+}
+
+impl Trigger {
+    /// Decode a trigger from its level object. Returns None for other objects.
+    pub fn from_raw(id: TriggerId, level_index: usize, raw: &UnitRaw) -> Option<Self> {
+        if raw.model_type() != Some(ModelType::General) || raw.subtype != GENERAL_SUBTYPE_TRIGGER {
+            return None;
+        }
+        let fd = raw.fd();
+        let kind = if fd[0] == TriggerKind::Timed as u8 { TriggerKind::Timed } else { TriggerKind::Proximity };
+        let delay = u16::from_le_bytes([fd[4], fd[5]]);
+        let links = (0..MAX_TRIGGER_LINKS)
+            .map(|i| u16::from_le_bytes([fd[8 + i * 2], fd[9 + i * 2]]))
+            .filter(|&l| l != TRIGGER_NO_LINK && l != TRIGGER_ZERO_LINK)
+            .map(|l| l as usize)
+            .collect();
+        let tribe = raw.tribe_index();
+        Some(Self {
+            id,
+            level_index,
+            position: WorldCoord::new(raw.loc_x() as i16, raw.loc_y() as i16),
+            kind,
+            radius: fd[1] as i32 * CELL_WORLD_SIZE,
+            tribe: if tribe == 0xFF { None } else { Some(tribe) },
+            remaining: if fd[2] == 0 { None } else { Some(fd[2]) },
+            active: fd[3] & TRIGGER_FLAG_INACTIVE == 0,
+            delay,
+            timer: if kind == TriggerKind::Timed { delay } else { 0 },
+            script_action: fd[6],
+            links,
+        })
+    }
+
+    /// Arm the trigger (from another trigger firing).
+    pub fn activate(&mut self) {
+        if self.remaining == Some(0) { return; }
+        self.active = true;
+        self.timer = if self.kind == TriggerKind::Timed { self.delay } else { 0 };
+    }
+
+    /// Whether a unit of `tribe` at `pos` sets off this trigger.
+    pub fn senses(&self, tribe: u8, pos: WorldCoord) -> bool {
+        self.tribe.is_none_or(|t| t == tribe)
+            && distance(&self.position, &pos) <= self.radius
+    }
+
+    /// Advance one tick. `unit_in_range` is whether any unit the trigger
+    /// senses is inside its radius. Returns true if it fires this tick.
+    pub fn tick(&mut self, unit_in_range: bool) -> bool {
+        if !self.active { return false; }
+        if self.timer > 0 {
+            self.timer -= 1;
+            if self.kind == TriggerKind::Proximity || self.timer > 0 {
+                return false;
+            }
+        }
+        if self.kind == TriggerKind::Proximity && !unit_in_range {
+            return false;
+        }
+
+        if let Some(left) = self.remaining.as_mut() {
+            *left -= 1;
+            if *left == 0 {
+                self.active = false;
+            }
+        }
+        self.timer = self.delay;
+        true
+    }
+
+    /// Effects of one firing, in link order, followed by the script action.
+    pub fn effects(&self, triggers_by_level_index: impl Fn(usize) -> Option<TriggerId>) -> Vec<TriggerEffect> {
+        let mut effects: Vec<TriggerEffect> = self.links.iter()
+            .map(|&l| match triggers_by_level_index(l) {
+                Some(t) => TriggerEffect::Activate(t),
+                None => TriggerEffect::Wake(l),
+            })
+            .collect();
+        if self.script_action != 0 {
+            effects.push(TriggerEffect::Script { trigger: self.id, action: self.script_action });
+        }
+        effects
+    }
+}
+
+/// Create triggers from level objects, in level order.
+pub fn load_triggers(units_raw: &[UnitRaw]) -> Vec<Trigger> {
+    let mut triggers = Vec::new();
+    for (i, raw) in units_raw.iter().enumerate() {
+        if let Some(t) = Trigger::from_raw(triggers.len(), i, raw) {
+            log::debug!("[trigger] object {}: {:?} radius {} delay {} links {:?}, raw {:02x?}",
+                i, t.kind, t.radius, t.delay, t.links, &raw.fd()[..8 + MAX_TRIGGER_LINKS * 2]);
+            triggers.push(t);
+        }
+    }
+    triggers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trigger(kind: TriggerKind, delay: u16, remaining: Option<u8>) -> Trigger {
+        Trigger {
+            id: 0,
+            level_index: 0,
+            position: WorldCoord::new(0x4000, 0x4000),
+            kind,
+            radius: 4 * CELL_WORLD_SIZE,
+            tribe: Some(0),
+            remaining,
+            active: true,
+            delay,
+            timer: if kind == TriggerKind::Timed { delay } else { 0 },
+            script_action: 0,
+            links: vec![3],
+        }
+    }
+
+    #[test]
+    fn proximity_fires_once_when_unit_in_range() {
+        let mut t = trigger(TriggerKind::Proximity, 0, Some(1));
+        assert!(!t.tick(false));
+        assert!(t.tick(true));
+        assert!(!t.active);
+        assert!(!t.tick(true));
+    }
+
+    #[test]
+    fn proximity_rearms_after_delay() {
+        let mut t = trigger(TriggerKind::Proximity, 3, None);
+        assert!(t.tick(true));
+        assert!(!t.tick(true));
+        assert!(!t.tick(true));
+        assert!(!t.tick(true));
+        assert!(t.tick(true));
+    }
+
+    #[test]
+    fn timed_fires_after_delay() {
+        let mut t = trigger(TriggerKind::Timed, 5, Some(1));
+        for _ in 0..4 {
+            assert!(!t.tick(false));
+        }
+        assert!(t.tick(false));
+        assert!(!t.active);
+    }
+
+    #[test]
+    fn senses_only_owning_tribe_in_radius() {
+        let t = trigger(TriggerKind::Proximity, 0, None);
+        assert!(t.senses(0, WorldCoord::new(0x4100, 0x4000)));
+        assert!(!t.senses(1, WorldCoord::new(0x4100, 0x4000)));
+        assert!(!t.senses(0, WorldCoord::new(0x6000, 0x4000)));
+    }
+
+    #[test]
+    fn effects_split_triggers_from_objects() {
+        let mut t = trigger(TriggerKind::Proximity, 0, None);
+        t.links = vec![3, 7];
+        t.script_action = 2;
+        let effects = t.effects(|l| if l == 7 { Some(1) } else { None });
+        assert_eq!(effects, vec![
+            TriggerEffect::Wake(3),
+            TriggerEffect::Activate(1),
+            TriggerEffect::Script { trigger: 0, action: 2 },
+        ]);
+    }
+
+    #[test]
+    fn zero_filled_link_slots_are_empty() {
+        // Links 4 and 9, the remaining slots zeroed as in a fresh level object
+        let mut fd = [0u8; 28];
+        fd[8..12].copy_from_slice(&[4, 0, 9, 0]);
+        let raw = UnitRaw::for_test(ModelType::General, GENERAL_SUBTYPE_TRIGGER, 0xFF, 0x4000, 0x4000, &fd);
+        let t = Trigger::from_raw(0, 1, &raw).unwrap();
+        assert_eq!(t.links, vec![4, 9]);
+    }
+}
@@ -2,11 +2,12 @@
 
 use crate::engine::movement::{PersonMovement, WorldCoord};
 use crate::data::units::ModelType;
-use super::person_state::PersonState;
+use super::person_state::{PersonState, person_type_defaults};
 use super::animation::AnimationState;
 
 pub type UnitId = usize;
 
+#[derive(Clone)]
 pub struct Unit {
     pub id: UnitId,
     pub model_type: ModelType,
@@ -37,6 +38,8 @@ pub struct Unit {
     // Wander state (offsets 0x7B/0x7C)
     pub wander_duration: u8,   // decrements each tick while wandering
     pub wander_range: u8,      // random walk range (subtype-dependent)
+    // Ticks spent blocked by other units (local avoidance)
+    pub blocked_ticks: u8,
     // Linked object (offset 0x72) — vehicle, effect, etc.
     pub linked_obj_id: Option<UnitId>,
     // Combat modifiers
@@ -46,3 +49,38 @@ pub struct Unit {
     // Animation state (offsets +0x33..+0x3a in original binary)
     pub anim: AnimationState,
 }
+
+impl Unit {
+    /// A person of `subtype` standing idle at `position`, with its type's
+    /// health and speed. Render cache and animation start zeroed.
+    pub fn new_person(id: UnitId, subtype: u8, tribe_index: u8, position: WorldCoord) -> Self {
+        let defaults = person_type_defaults(subtype);
+        Self {
+            id,
+            model_type: ModelType::Person,
+            subtype,
+            tribe_index,
+            movement: PersonMovement { position, unit_type: subtype, speed: defaults.speed, ..Default::default() },
+            cell_x: 0.0,
+            cell_y: 0.0,
+            state: PersonState::Idle,
+            prev_state: PersonState::Idle,
+            state_timer: 0,
+            state_counter: 0,
+            health: defaults.max_health,
+            max_health: defaults.max_health,
+            target_unit: None,
+            attacker_unit: None,
+            alive: true,
+            home_pos: position,
+            behavior_flags: 0,
+            wander_duration: 0,
+            wander_range: 0,
+            blocked_ticks: 0,
+            linked_obj_id: None,
+            bloodlust: false,
+            shielded: false,
+            anim: AnimationState::default(),
+        }
+    }
+}
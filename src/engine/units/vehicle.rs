@@ -0,0 +1,162 @@
+// Live vehicle entity — boats and airships placed by the level.
+//
+// Only boats take part in movement so far: UnitCoordinator uses them to
+// ferry persons between islands (see movement::transport).
+
+use crate::engine::movement::{WorldCoord, Landing};
+use super::unit::UnitId;
+
+pub type VehicleId = usize;
+
+/// Vehicle subtypes (ModelType::Vehicle).
+pub const VEHICLE_SUBTYPE_BOAT_1: u8 = 1;
+pub const VEHICLE_SUBTYPE_BOAT_2: u8 = 2;
+pub const VEHICLE_SUBTYPE_AIRSHIP_1: u8 = 3;
+pub const VEHICLE_SUBTYPE_AIRSHIP_2: u8 = 4;
+
+/// Max passengers per boat (vehicle type data +0x00).
+pub const BOAT_MAX_PASSENGERS: usize = 8;
+
+/// Sailing speed in world units per tick.
+pub const BOAT_SPEED: u16 = 0x40;
+
+/// Vehicle states, stored at offset 0x2C like the person state.
+/// Original: Vehicle_SetState (0x00497bd0).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum VehicleState {
+    Idle      = 0x01,
+    Moving    = 0x02,
+    Loading   = 0x03,
+    Unloading = 0x04,
+    Sinking   = 0x05,
+    Rising    = 0x06,
+    Burning   = 0x07,
+    Landing   = 0x08,
+    TakingOff = 0x09,
+}
+
+/// A person booked on a ferry trip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rider {
+    pub unit: UnitId,
+    /// Where the person walks to after disembarking.
+    pub target: WorldCoord,
+    pub aboard: bool,
+}
+
+/// An island-to-island trip the boat is currently running.
+#[derive(Debug, Clone)]
+pub struct Ferry {
+    pub embark: Landing,
+    pub disembark: Landing,
+    /// Sailing waypoints from embark.water to disembark.water.
+    pub crossing: Vec<WorldCoord>,
+    pub target_island: u16,
+    pub riders: Vec<Rider>,
+    /// Set once the boat leaves the embark landing with its passengers.
+    pub departed: bool,
+}
+
+impl Ferry {
+    pub fn aboard_count(&self) -> usize {
+        self.riders.iter().filter(|r| r.aboard).count()
+    }
+}
+
+#[derive(Clone)]
+pub struct Vehicle {
+    pub id: VehicleId,
+    /// Index of the level object the vehicle was created from; the renderer
+    /// draws the vehicle in that object's place.
+    pub level_index: usize,
+    pub subtype: u8,
+    pub tribe_index: u8,
+    pub position: WorldCoord,
+    pub facing_angle: u16,
+    pub speed: u16,
+    pub state: VehicleState,
+    // Rendering cache — cell-space position, updated from world coords each tick.
+    pub cell_x: f32,
+    pub cell_y: f32,
+
+    /// Remaining sailing waypoints (index 0 = next).
+    pub route: Vec<WorldCoord>,
+    pub ferry: Option<Ferry>,
+}
+
+impl Vehicle {
+    pub fn new(id: VehicleId, level_index: usize, subtype: u8, tribe_index: u8, position: WorldCoord) -> Self {
+        Self {
+            id,
+            level_index,
+            subtype,
+            tribe_index,
+            position,
+            facing_angle: 0,
+            speed: 0,
+            state: VehicleState::Idle,
+            cell_x: 0.0,
+            cell_y: 0.0,
+            route: Vec::new(),
+            ferry: None,
+        }
+    }
+
+    pub fn is_boat(&self) -> bool {
+        self.subtype == VEHICLE_SUBTYPE_BOAT_1 || self.subtype == VEHICLE_SUBTYPE_BOAT_2
+    }
+
+    /// Whether a person of `tribe` may use this vehicle.
+    /// Unowned vehicles (tribe 255) are free for anyone.
+    pub fn usable_by(&self, tribe: u8) -> bool {
+        self.tribe_index == tribe || self.tribe_index == 0xFF
+    }
+
+    /// Idle boat with no trip booked.
+    pub fn is_available(&self) -> bool {
+        self.is_boat() && self.state == VehicleState::Idle && self.ferry.is_none()
+    }
+
+    /// Start sailing along `route`.
+    pub fn sail(&mut self, route: Vec<WorldCoord>) {
+        self.route = route;
+        self.speed = BOAT_SPEED;
+        self.state = VehicleState::Moving;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vehicle_state_enum_values() {
+        assert_eq!(VehicleState::Idle as u8, 0x01);
+        assert_eq!(VehicleState::Moving as u8, 0x02);
+        assert_eq!(VehicleState::Loading as u8, 0x03);
+        assert_eq!(VehicleState::Unloading as u8, 0x04);
+        assert_eq!(VehicleState::TakingOff as u8, 0x09);
+    }
+
+    #[test]
+    fn only_idle_boats_available() {
+        let mut boat = Vehicle::new(0, 0, VEHICLE_SUBTYPE_BOAT_1, 0, WorldCoord::new(0, 0));
+        assert!(boat.is_available());
+        boat.sail(vec![WorldCoord::new(0x100, 0x100)]);
+        assert!(!boat.is_available());
+
+        let airship = Vehicle::new(1, 1, VEHICLE_SUBTYPE_AIRSHIP_1, 0, WorldCoord::new(0, 0));
+        assert!(!airship.is_available());
+    }
+
+    #[test]
+    fn unowned_vehicle_usable_by_all() {
+        let boat = Vehicle::new(0, 0, VEHICLE_SUBTYPE_BOAT_1, 0xFF, WorldCoord::new(0, 0));
+        assert!(boat.usable_by(0));
+        assert!(boat.usable_by(3));
+        let owned = Vehicle::new(1, 1, VEHICLE_SUBTYPE_BOAT_1, 1, WorldCoord::new(0, 0));
+        assert!(owned.usable_by(1));
+        assert!(!owned.usable_by(0));
+    }
+}
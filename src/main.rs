@@ -1,8 +1,12 @@
+use std::net::SocketAddr;
 use std::path::PathBuf;
 
 use clap::{Arg, ArgAction, Command};
 
+use pop3::engine::movement::PathfindingMode;
+use pop3::engine::net::NetConfig;
 use pop3::render::app::{App, AppConfig};
+use pop3::render::golden::{self, ImageTolerance};
 
 fn parse_light(s: &str) -> Option<(i16, i16)> {
     let parts: Vec<&str> = s.split(';').collect();
@@ -51,7 +55,92 @@ fn cli() -> Command {
             .action(ArgAction::Set)
             .value_name("SCRIPT_PATH")
             .value_parser(clap::value_parser!(PathBuf))
-            .help("Replay key events from a script file"),
+            .help("Run a script of key events, game commands and assertions"),
+        Arg::new("pathfinding")
+            .long("pathfinding")
+            .action(ArgAction::Set)
+            .value_name("MODE")
+            .value_parser(["original", "enhanced"])
+            .default_value("original")
+            .help("Route provider for unit moves: original wall follower or enhanced jump point search"),
+        Arg::new("avoidance")
+            .long("avoidance")
+            .action(ArgAction::SetTrue)
+            .help("Keep walking units from passing through each other (not in the original)"),
+        Arg::new("net-peers")
+            .long("net-peers")
+            .action(ArgAction::Set)
+            .value_name("ADDRS")
+            .value_delimiter(',')
+            .value_parser(clap::value_parser!(SocketAddr))
+            .requires("net-player")
+            .conflicts_with("headless")
+            .help("Play lockstep multiplayer: every player's UDP address, in player order (comma separated)"),
+        Arg::new("net-player")
+            .long("net-player")
+            .action(ArgAction::Set)
+            .value_name("INDEX")
+            .value_parser(clap::value_parser!(u8).range(0..4))
+            .requires("net-peers")
+            .help("Our index into --net-peers (also our tribe)"),
+        Arg::new("net-rebuild")
+            .long("net-rebuild")
+            .action(ArgAction::SetTrue)
+            .requires("net-peers")
+            .help("After a desync, rebuild from the host's input history instead of playing on"),
+        Arg::new("net-teams")
+            .long("net-teams")
+            .action(ArgAction::Set)
+            .value_name("TEAMS")
+            .value_delimiter(',')
+            .value_parser(clap::value_parser!(u8))
+            .requires("net-peers")
+            .help("Each player's team, in player order (comma separated, e.g. 0,1,0,1 for 2v2); teammates are allied"),
+        Arg::new("record-replay")
+            .long("record-replay")
+            .action(ArgAction::Set)
+            .value_name("REPLAY_PATH")
+            .value_parser(clap::value_parser!(PathBuf))
+            .conflicts_with_all(["net-peers", "replay"])
+            .help("Record the level's commands to a replay file (written at level change and on exit)"),
+        Arg::new("replay")
+            .long("replay")
+            .action(ArgAction::Set)
+            .value_name("REPLAY_PATH")
+            .value_parser(clap::value_parser!(PathBuf))
+            .conflicts_with_all(["net-peers", "level"])
+            .help("Play back a replay file (F5 pauses, +/- change speed, comma/period seek)"),
+        Arg::new("headless")
+            .long("headless")
+            .action(ArgAction::Set)
+            .value_name("OUT_DIR")
+            .value_parser(clap::value_parser!(PathBuf))
+            .requires("script")
+            .help("Run the script without a window, writing screenshots and camera.jsonl to OUT_DIR"),
+        Arg::new("golden")
+            .long("golden")
+            .action(ArgAction::Set)
+            .value_name("GOLDEN_DIR")
+            .value_parser(clap::value_parser!(PathBuf))
+            .requires("headless")
+            .help("Compare the headless run against the golden images and camera log in GOLDEN_DIR"),
+        Arg::new("update-golden")
+            .long("update-golden")
+            .action(ArgAction::SetTrue)
+            .requires("golden")
+            .help("Overwrite GOLDEN_DIR with the headless run instead of comparing"),
+        Arg::new("threshold")
+            .long("threshold")
+            .action(ArgAction::Set)
+            .value_name("THRESHOLD")
+            .value_parser(clap::value_parser!(f32))
+            .help("Per-pixel colour threshold for golden comparison, 0-1 (default 0.1)"),
+        Arg::new("max-diff")
+            .long("max-diff")
+            .action(ArgAction::Set)
+            .value_name("RATIO")
+            .value_parser(clap::value_parser!(f32))
+            .help("Share of pixels allowed to differ from the golden image (default 0.001)"),
     ];
     Command::new("pop3")
         .about("POP3 wgpu renderer")
@@ -70,6 +159,18 @@ fn main() {
         debug: matches.get_flag("debug"),
         light: matches.get_one::<String>("light").and_then(|s| parse_light(s)),
         script: matches.get_one("script").cloned(),
+        pathfinding: matches.get_one::<String>("pathfinding")
+            .and_then(|s| PathfindingMode::from_name(s))
+            .unwrap_or_default(),
+        avoidance: matches.get_flag("avoidance"),
+        net: matches.get_one::<u8>("net-player").map(|&player| NetConfig {
+            player,
+            peers: matches.get_many::<SocketAddr>("net-peers").into_iter().flatten().copied().collect(),
+            rebuild: matches.get_flag("net-rebuild"),
+            teams: matches.get_many::<u8>("net-teams").into_iter().flatten().copied().collect(),
+        }),
+        record_replay: matches.get_one("record-replay").cloned(),
+        replay: matches.get_one("replay").cloned(),
     };
 
     let log_level: &str = if config.debug { "debug" } else { "info" };
@@ -78,5 +179,47 @@ fn main() {
         .write_style_or("F_LOG_STYLE", "always");
     env_logger::init_from_env(env);
 
-    App::run(config);
+    let Some(out_dir) = matches.get_one::<PathBuf>("headless") else {
+        if let Err(e) = App::run(config) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    };
+    if let Err(e) = App::run_headless(config, out_dir) {
+        eprintln!("headless run failed: {}", e);
+        std::process::exit(2);
+    }
+    let Some(golden_dir) = matches.get_one::<PathBuf>("golden") else { return };
+    if matches.get_flag("update-golden") {
+        match golden::update_golden(out_dir, golden_dir) {
+            Ok(n) => println!("updated {} golden files in {}", n, golden_dir.display()),
+            Err(e) => {
+                eprintln!("golden update failed: {}", e);
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+    let defaults = ImageTolerance::default();
+    let tolerance = ImageTolerance {
+        threshold: matches.get_one("threshold").copied().unwrap_or(defaults.threshold),
+        max_diff_ratio: matches.get_one("max-diff").copied().unwrap_or(defaults.max_diff_ratio),
+    };
+    match golden::compare_run(out_dir, golden_dir, &tolerance) {
+        Ok(report) if report.failures.is_empty() => {
+            println!("golden: {} checks passed", report.checked);
+        }
+        Ok(report) => {
+            for failure in &report.failures {
+                eprintln!("golden: {}", failure);
+            }
+            eprintln!("golden: {} failure(s) in {} checks", report.failures.len(), report.checked);
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("golden comparison failed: {}", e);
+            std::process::exit(2);
+        }
+    }
 }
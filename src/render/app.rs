@@ -1,13 +1,14 @@
 use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
 use winit::application::ApplicationHandler;
 use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, EventLoop};
-use winit::keyboard::{KeyCode, PhysicalKey};
+use winit::keyboard::PhysicalKey;
 use winit::window::{Window, WindowAttributes};
 
 use cgmath::{Point2, Point3, Vector3, Vector4, Matrix4, SquareMatrix};
@@ -26,30 +27,36 @@ use crate::data::animation::{
 };
 use crate::engine::state::constants::*;
 use crate::engine::movement::constants::CELL_HAS_BUILDING;
+use crate::engine::movement::PathfindingMode;
 
 use crate::render::picking::intersect_iter;
 
 use crate::render::terrain::{
     LandscapeMesh, LandscapeModel,
     LandscapeUniformData, LandscapeVariant, LandscapeProgramContainer,
-    make_landscape_model, LANDSCAPE_SCALE, LANDSCAPE_OFFSET,
+    make_landscape_model, LANDSCAPE_SCALE, LANDSCAPE_OFFSET, DEFAULT_CURVATURE_SCALE,
 };
 use crate::data::level::{LevelRes, ObjectPaths};
 use crate::data::units::{ModelType, object_3d_index};
 use crate::data::objects::{Object3D, Shape, ShapeFootprints};
-use crate::data::bl320::make_bl320_texture_rgba;
 use crate::data::landscape::{make_texture_land, draw_texture_u8};
 
-use crate::engine::units::{UnitCoordinator, DragState, Unit};
+use crate::engine::units::{UnitCoordinator, DragState, Unit, LevelEvent};
 use crate::engine::units::coords::{cell_to_world, cell_to_tile, triangle_to_cell, project_to_screen, ScreenRect};
-use crate::render::buildings::build_building_meshes;
+#[cfg(feature = "audio-device")]
+use crate::audio::{AudioDevice, Listener};
+#[cfg(feature = "audio-device")]
+use crate::data::sdt::SoundBank;
+use crate::render::buildings::{build_building_meshes, building_atlas_rgba};
+use crate::render::software::{LevelTextures, LevelView, sun_direction};
 use crate::render::sprites::{
-    LevelObject, UnitTypeRender,
+    LevelObject, UnitTypeRender, UnitRenderData,
     obj_colors, convert_palette,
     pack_palette_rgba, rgb_to_rgba,
     extract_level_objects,
-    build_spawn_model,
-    build_object_markers, build_unit_markers, build_selection_outlines,
+    build_spawn_model, SpriteSheet,
+    build_object_markers, build_unit_markers, build_selection_outlines, build_effect_billboards,
+    DEFAULT_SPRITE_Z_OFFSET, DEFAULT_SPRITE_SCALE,
 };
 
 use crate::render::gpu::context::GpuContext;
@@ -62,10 +69,13 @@ use crate::render::gpu::bind_groups::{
 };
 use crate::render::envelop::*;
 
-use crate::engine::state::tick::{GameWorld, StdTimeSource, TickSubsystems};
+use crate::engine::state::tick::{GameWorld, ManualTimeSource, StdTimeSource, TickSubsystems, TimeSource};
 use crate::engine::state::state_machine::GameState;
-use crate::engine::state::traits::NoOp;
+use crate::engine::state::traits::{NetworkTick, NoOp};
+use crate::engine::net::{Lockstep, NetConfig, UdpTransport, DEFAULT_INPUT_DELAY, DEFAULT_LEVEL_SEED};
 use crate::engine::{GameCommand, FrameState, translate_key};
+use crate::engine::script::{ScriptRunner, ScriptStep, ScriptView};
+use crate::engine::replay::{Replay, ReplayPlayback, ReplayRecorder, SimSnapshot, DEFAULT_SNAPSHOT_INTERVAL};
 
 use crate::render::hud::{
     self, HudTab, HudState, HudRenderer,
@@ -144,6 +154,24 @@ pub struct AppConfig {
     pub debug: bool,
     pub light: Option<(i16, i16)>,
     pub script: Option<PathBuf>,
+    pub pathfinding: PathfindingMode,
+    /// Local collision avoidance between units (GameWorld::unit_avoidance).
+    pub avoidance: bool,
+    /// Join a lockstep multiplayer game instead of playing alone.
+    pub net: Option<NetConfig>,
+    /// Record the game's commands to this replay file.
+    pub record_replay: Option<PathBuf>,
+    /// Play this replay file instead of taking input.
+    pub replay: Option<PathBuf>,
+}
+
+/// What replay playback restores when seeking: the simulation plus the
+/// level objects triggers have revealed by then.
+#[derive(Clone)]
+struct ReplayState {
+    sim: SimSnapshot,
+    level_objects: Vec<LevelObject>,
+    dormant_objects: Vec<LevelObject>,
 }
 
 /// All game-logic state — no GPU types. Produces FrameState for the renderer.
@@ -171,10 +199,18 @@ pub struct GameEngine {
     // Game simulation
     unit_coordinator: UnitCoordinator,
     game_world: GameWorld,
-    game_time: StdTimeSource,
+    // Multiplayer session; game commands go through it instead of straight
+    // to the coordinator
+    lockstep: Option<Lockstep<UdpTransport>>,
+    // Replay being recorded, or played back instead of live input
+    recorder: Option<ReplayRecorder>,
+    playback: Option<ReplayPlayback<ReplayState>>,
+    game_time: Rc<dyn TimeSource>,
 
     // Level data
     level_objects: Vec<LevelObject>,
+    // Objects held back until a level trigger reveals them
+    dormant_objects: Vec<LevelObject>,
     building_objects: Vec<Option<Object3D>>,  // from OBJS bank 0 (building models)
     scenery_objects: Vec<Option<Object3D>>,   // from level-specific OBJS bank (scenery models)
     shapes: Vec<Shape>,
@@ -229,8 +265,7 @@ impl GameEngine {
 
     /// World-space center of the terrain (accounting for model transform).
     fn world_center(&self) -> f32 {
-        let center_model = (self.landscape_mesh.width() - 1) as f32 * self.landscape_mesh.step() / 2.0;
-        LANDSCAPE_SCALE * center_model + LANDSCAPE_OFFSET
+        self.landscape_mesh.world_center()
     }
 
     fn camera_focus_vertex(&self) -> f32 {
@@ -239,23 +274,7 @@ impl GameEngine {
     }
 
     fn camera_min_z(&self) -> f32 {
-        let center = self.world_center();
-        let az = (self.camera.angle_z as f32).to_radians();
-        let ax = (self.camera.angle_x as f32).to_radians();
-        let radius = 1.5 / self.zoom;
-        let eye_x = center + radius * ax.cos() * az.sin();
-        let eye_y = center + radius * ax.cos() * az.cos();
-        // Convert world-space eye position back to grid coords for height lookup
-        let model_x = (eye_x - LANDSCAPE_OFFSET) / LANDSCAPE_SCALE;
-        let model_y = (eye_y - LANDSCAPE_OFFSET) / LANDSCAPE_SCALE;
-        let step = self.landscape_mesh.step();
-        let n = self.landscape_mesh.width();
-        let gx = (model_x / step).clamp(0.0, (n - 1) as f32) as usize;
-        let gy = (model_y / step).clamp(0.0, (n - 1) as f32) as usize;
-        let shift = self.landscape_mesh.get_shift_vector();
-        let sx = (gx + shift.x as usize) % n;
-        let sy = (gy + shift.y as usize) % n;
-        self.landscape_mesh.height_at(sx, sy) as f32 * self.landscape_mesh.height_scale() + 0.05
+        self.landscape_mesh.camera_min_z(&self.camera, self.zoom)
     }
 
     fn screen_to_cell(&self, mouse_pos: &Point2<f32>) -> Option<(f32, f32)> {
@@ -372,6 +391,84 @@ impl GameEngine {
         (right, up)
     }
 
+    /// Load the building and scenery OBJS banks and the footprint shapes of
+    /// the level's object bank.
+    fn load_object_banks(&mut self, base: &Path, level_res: &LevelRes) {
+        // Load dual OBJS banks: bank 0 for buildings, level bank for scenery.
+        // Bank 0 has building models at indices 117-193 (building_obj_index).
+        // Level banks have scenery at different indices (scenery_obj_index).
+        // Shape_LoadBank @ 0x49b990 remaps bank 0 → 2.
+        let (building_objects, scenery_objects) = Object3D::load_dual_banks(base, level_res.obj_bank);
+        let level_bank = if level_res.obj_bank == 0 { 2 } else { level_res.obj_bank };
+        let bld_count = building_objects.iter().filter(|o| o.is_some()).count();
+        let scn_count = scenery_objects.iter().filter(|o| o.is_some()).count();
+        eprintln!("[OBJS] buildings: bank=0 entries={} non-empty={}",
+            building_objects.len(), bld_count);
+        eprintln!("[OBJS] scenery:   bank={} entries={} non-empty={}",
+            level_bank, scenery_objects.len(), scn_count);
+        let bank_str = level_bank.to_string();
+        let obj_paths = ObjectPaths::from_default_dir(base, &bank_str);
+        let shape_footprints = ShapeFootprints::from_file(&obj_paths.shapes);
+        let shapes: Vec<Shape> = shape_footprints.shapes().to_vec();
+        eprintln!("[shapes] loaded {} entries with footprint bitmaps", shapes.len());
+        for (i, s) in shapes.iter().take(10).enumerate() {
+            let sref = s.shape_ref;
+            eprintln!("[shapes] [{}] {}x{} origin=({},{}) ref={}",
+                i, s.width, s.height, s.origin_x, s.origin_z, sref);
+        }
+        self.building_objects = building_objects;
+        self.scenery_objects = scenery_objects;
+        self.shapes = shapes;
+        self.shape_footprints = shape_footprints;
+    }
+
+    /// Move trigger-linked objects out of the static object list until a
+    /// trigger reveals them.
+    fn stash_dormant_objects(&mut self) {
+        let hidden = self.unit_coordinator.hidden_objects();
+        let (dormant, visible) = std::mem::take(&mut self.level_objects)
+            .into_iter()
+            .partition(|obj| hidden.contains(&obj.level_index));
+        self.level_objects = visible;
+        self.dormant_objects = dormant;
+    }
+
+    /// Draw vehicles where the coordinator has them: each live vehicle takes
+    /// the place of the static object it was created from.
+    fn sync_vehicle_objects(&mut self) {
+        let live: Vec<usize> = self.unit_coordinator.vehicles.iter().map(|v| v.level_index).collect();
+        self.level_objects.retain(|obj| obj.model_type != ModelType::Vehicle || !live.contains(&obj.level_index));
+        self.level_objects.extend(self.unit_coordinator.vehicles.iter().map(|vehicle| LevelObject {
+            level_index: vehicle.level_index,
+            cell_x: vehicle.cell_x,
+            cell_y: vehicle.cell_y,
+            model_type: ModelType::Vehicle,
+            subtype: vehicle.subtype,
+            tribe_index: vehicle.tribe_index,
+            angle: vehicle.facing_angle as u32,
+        }));
+    }
+
+    /// Apply level events raised by triggers during the last ticks.
+    fn apply_level_events(&mut self) {
+        for event in self.unit_coordinator.take_level_events() {
+            match event {
+                LevelEvent::Reveal(level_index) => {
+                    if let Some(pos) = self.dormant_objects.iter().position(|o| o.level_index == level_index) {
+                        let obj = self.dormant_objects.remove(pos);
+                        log::info!("[trigger] revealed {:?} subtype={} (object {})",
+                            obj.model_type, obj.subtype, level_index);
+                        self.level_objects.push(obj);
+                    }
+                }
+                LevelEvent::ScriptAction { trigger, action } => {
+                    // No level script interpreter yet — record the action
+                    log::info!("[trigger] trigger {} script action {}", trigger, action);
+                }
+            }
+        }
+    }
+
     fn find_unit_at_screen_pos(&self, mouse: &Point2<f32>) -> Option<usize> {
         let pvm = self.unit_pvm();
         let (right, up) = self.billboard_axes();
@@ -490,9 +587,30 @@ impl GameEngine {
         }
     }
 
+    fn script_view(&self) -> ScriptView<'_> {
+        ScriptView {
+            units: &self.unit_coordinator.units,
+            selected: &self.unit_coordinator.selection.selected,
+            tribes: &self.game_world.tribes,
+            angle_x: self.camera.angle_x,
+            angle_z: self.camera.angle_z,
+            zoom: self.zoom,
+            game_tick: self.game_world.game_tick,
+        }
+    }
+
     /// Process a game command. Returns true if the renderer needs to redraw.
     /// Sets dirty flags for specific rebuilds.
     fn apply_command(&mut self, cmd: &GameCommand) -> bool {
+        if cmd.is_networked() {
+            // During playback only the replay drives the simulation
+            if self.playback.is_some() {
+                return false;
+            }
+            if let Some(recorder) = &mut self.recorder {
+                recorder.record(self.game_world.game_tick, cmd);
+            }
+        }
         match cmd {
             GameCommand::RotateCamera { delta_z } => {
                 self.camera.angle_z += delta_z;
@@ -552,6 +670,7 @@ impl GameEngine {
                 eprintln!("[SPRITE] z_offset={:.4} scale={:.2}", self.sprite_z_offset, self.sprite_scale);
                 true
             }
+            GameCommand::NextLevel | GameCommand::PrevLevel if self.playback.is_some() => false,
             GameCommand::NextLevel => {
                 self.level_num = (self.level_num + 1) % 26;
                 if self.level_num == 0 { self.level_num = 1; }
@@ -600,6 +719,12 @@ impl GameEngine {
                 true
             }
             GameCommand::OrderMove { x, z } => {
+                if let Some(session) = &mut self.lockstep {
+                    // Selection stays local until it's used: send it with the order
+                    session.queue(GameCommand::SelectMultiple(self.unit_coordinator.selection.selected.clone()));
+                    session.queue(cmd.clone());
+                    return false;
+                }
                 let target = crate::engine::movement::WorldCoord::new(*x as i16, *z as i16);
                 self.unit_coordinator.order_move(target);
                 true
@@ -626,6 +751,20 @@ impl GameEngine {
                 println!("game speed: {} ticks/sec", self.game_world.game_speed);
                 false
             }
+            GameCommand::SeekReplay { ticks } => {
+                let Some(playback) = &mut self.playback else { return false };
+                let current = self.game_world.game_tick;
+                let target = current.saturating_add_signed(*ticks).min(playback.replay().ticks);
+                if let Some((tick, state)) = playback.seek(current, target) {
+                    state.sim.restore(&mut self.game_world, &mut self.unit_coordinator);
+                    self.level_objects = state.level_objects;
+                    self.dormant_objects = state.dormant_objects;
+                    log::info!("[replay] seeking to tick {} from tick {}", target, tick);
+                } else {
+                    log::info!("[replay] seeking to tick {}", target);
+                }
+                true
+            }
             GameCommand::SetHudTab(tab) => {
                 self.hud_tab = *tab;
                 true
@@ -741,6 +880,7 @@ pub struct App {
     // Unit rendering
     model_unit_markers: Option<ModelEnvelop<ColorModel>>,
     model_selection_outlines: Option<ModelEnvelop<ColorModel>>,
+    model_effects: Option<ModelEnvelop<ColorModel>>,
     model_walkability: Option<ModelEnvelop<ColorModel>>,
     walkability_pipeline: Option<wgpu::RenderPipeline>,
 
@@ -750,10 +890,13 @@ pub struct App {
     // Debug logging
     debug_log: BufWriter<File>,
     start_time: Instant,
+    // Virtual clock of headless runs (None when driven by wall-clock time)
+    clock: Option<Rc<ManualTimeSource>>,
 
     // Script replay
-    script_commands: Vec<String>,
-    script_index: usize,
+    script: Option<ScriptRunner>,
+    // Assertion and script errors, reported when the script ends
+    script_failures: Vec<String>,
 
     // Smooth camera pan to shaman
     shaman_pan: Option<ShamanPanAnimation>,
@@ -761,8 +904,12 @@ pub struct App {
     // Screenshot capture
     screenshot_path: Option<String>,
     screenshot_counter: u32,
-    // Script wait (wall-clock based)
-    script_wait_until: Option<Instant>,
+    // Script wait: simulation ticks left before the next step
+    script_wait_ticks: Option<u32>,
+
+    // Sound output (None when no device could be opened)
+    #[cfg(feature = "audio-device")]
+    audio: Option<AudioDevice>,
 }
 
 struct ShamanPanAnimation {
@@ -793,25 +940,29 @@ impl App {
             File::create("/tmp/pop3_debug.jsonl").expect("failed to create debug log"),
         );
 
-        let script_commands: Vec<String> = config.script.as_ref()
-            .map(|path| {
-                std::fs::read_to_string(path)
-                    .unwrap_or_else(|e| panic!("failed to read script {:?}: {}", path, e))
-                    .lines()
-                    .map(|l| l.trim().to_string())
-                    .filter(|l| !l.is_empty() && !l.starts_with('#'))
-                    .collect()
-            })
-            .unwrap_or_default();
+        let script = config.script.as_ref().map(|path| {
+            let text = std::fs::read_to_string(path)
+                .unwrap_or_else(|e| panic!("failed to read script {:?}: {}", path, e));
+            ScriptRunner::parse(&text).unwrap_or_else(|e| panic!("script {:?}: {}", path, e))
+        });
 
-        let level_num = config.level.unwrap_or(1);
+        let playback = config.replay.as_ref().map(|path| {
+            let replay = std::fs::read_to_string(path).map_err(|e| e.to_string())
+                .and_then(|text| Replay::parse(&text))
+                .unwrap_or_else(|e| panic!("replay {:?}: {}", path, e));
+            log::info!("[replay] level {}, {} ticks, {} commands, {:?}",
+                replay.level, replay.ticks, replay.commands.len(), replay.rules);
+            ReplayPlayback::new(replay, DEFAULT_SNAPSHOT_INTERVAL)
+        });
+        // A replay plays on its own level
+        let level_num = playback.as_ref().map_or(config.level.unwrap_or(1), |p| p.replay().level);
 
         let mut app = App {
             engine: GameEngine {
                 landscape_mesh,
                 camera,
                 screen: Screen { width: 800, height: 600 },
-                curvature_scale: 0.0512,
+                curvature_scale: DEFAULT_CURVATURE_SCALE,
                 curvature_enabled: true,
                 zoom: 1.0,
                 level_num,
@@ -820,8 +971,8 @@ impl App {
                 show_shadows: true,
                 show_lighting: true,
                 show_markers: false,
-                sprite_z_offset: 0.005,
-                sprite_scale: 0.65,
+                sprite_z_offset: DEFAULT_SPRITE_Z_OFFSET,
+                sprite_scale: DEFAULT_SPRITE_SCALE,
                 hud_tab: HudTab::Spells,
                 hud_visible: false,
                 compass_visible: false,
@@ -831,10 +982,20 @@ impl App {
                 game_world: {
                     let mut w = GameWorld::new(20);
                     w.state = GameState::InGame;
+                    w.pathfinding = config.pathfinding;
+                    w.unit_avoidance = config.avoidance;
+                    // A replay plays by the rules it was recorded with
+                    if let Some(playback) = &playback {
+                        w.set_rules(playback.replay().rules);
+                    }
                     w
                 },
-                game_time: StdTimeSource::new(),
+                lockstep: None,
+                recorder: None,
+                playback,
+                game_time: Rc::new(StdTimeSource::new()),
                 level_objects: Vec::new(),
+                dormant_objects: Vec::new(),
                 building_objects: Vec::new(),
                 scenery_objects: Vec::new(),
                 shapes: Vec::new(),
@@ -885,27 +1046,32 @@ impl App {
             watdisp_buffer: None,
             model_unit_markers: None,
             model_selection_outlines: None,
+            model_effects: None,
             model_walkability: None,
             walkability_pipeline: None,
             do_render: true,
             debug_log,
             start_time: Instant::now(),
-            script_commands,
-            script_index: 0,
+            clock: None,
+            script,
+            script_failures: Vec::new(),
             shaman_pan: None,
             screenshot_path: None,
             screenshot_counter: 0,
-            script_wait_until: None,
+            script_wait_ticks: None,
+            #[cfg(feature = "audio-device")]
+            audio: AudioDevice::open(),
         };
         app.engine.reset_camera();
+        app.engine.unit_coordinator.set_pathfinding_mode(app.engine.game_world.pathfinding);
+        app.engine.unit_coordinator.set_avoidance(app.engine.game_world.unit_avoidance);
         app
     }
 
     fn center_on_tribe0_shaman(&mut self) {
-        let shaman_cell = self.unit_renders.iter()
-            .find(|ur| ur.subtype == PERSON_SUBTYPE_SHAMAN)
-            .and_then(|ur| ur.cells.iter().find(|c| c.tribe_index == 0));
-        let shaman_pos = shaman_cell.map(|c| (c.cell_x, c.cell_y));
+        let shaman_pos = self.engine.unit_coordinator.units.iter()
+            .find(|u| u.alive && u.subtype == PERSON_SUBTYPE_SHAMAN && u.tribe_index == 0)
+            .map(|u| (u.cell_x, u.cell_y));
         if let Some((cx, cy)) = shaman_pos {
             let n = self.engine.landscape_mesh.width() as i32;
             let v = self.engine.camera_focus_vertex() as i32;
@@ -926,10 +1092,44 @@ impl App {
                 duration: 0.5,
             });
         } else {
-            log::warn!("[center] no tribe 0 shaman in unit_renders");
+            log::warn!("[center] no tribe 0 shaman in unit_coordinator");
         }
     }
 
+    /// Load the sound bank into the output mixer.
+    #[cfg(feature = "audio-device")]
+    fn load_sounds(&mut self, base: &Path) {
+        let Some(audio) = &self.audio else { return };
+        let path = base.join("sound").join("soundd2.sdt");
+        match SoundBank::from_file(&path) {
+            Some(bank) => audio.mixer().load_bank(&bank),
+            None => log::warn!("[audio] failed to load {}", path.display()),
+        }
+    }
+
+    #[cfg(not(feature = "audio-device"))]
+    fn load_sounds(&mut self, _base: &Path) {}
+
+    /// Hand the sounds raised this frame to the mixer, heard from the
+    /// landscape cell the camera looks at.
+    #[cfg(feature = "audio-device")]
+    fn play_sound_events(&mut self) {
+        let events = self.engine.unit_coordinator.take_sound_events();
+        let Some(audio) = &self.audio else { return };
+        let n = self.engine.landscape_mesh.width() as f32;
+        let shift = self.engine.landscape_mesh.get_shift_vector();
+        let v = self.engine.camera_focus_vertex();
+        let focus = cell_to_world((shift.x as f32 + v) % n, (shift.y as f32 + v) % n, n);
+        let mut mixer = audio.mixer();
+        mixer.set_listener(Listener::from_camera(&self.engine.camera, focus));
+        mixer.handle_events(&events);
+    }
+
+    #[cfg(not(feature = "audio-device"))]
+    fn play_sound_events(&mut self) {
+        self.engine.unit_coordinator.take_sound_events();
+    }
+
     fn tick_shaman_pan(&mut self) {
         let Some(anim) = &self.shaman_pan else { return };
 
@@ -960,6 +1160,14 @@ impl App {
         }
     }
 
+    /// Jump to the end of a running shaman pan.
+    fn finish_shaman_pan(&mut self) {
+        let Some(anim) = self.shaman_pan.take() else { return };
+        self.engine.landscape_mesh.set_shift(anim.target_shift.0, anim.target_shift.1);
+        self.rebuild_spawn_model();
+        self.do_render = true;
+    }
+
     fn update_level(&mut self) {
         self.shaman_pan = None;
         self.engine.reset_camera();
@@ -970,9 +1178,7 @@ impl App {
         let shores = level_res.landscape.make_shores();
         self.engine.landscape_mesh.set_heights(&shores.height);
 
-        {
-            let gpu = self.gpu.as_ref().unwrap();
-
+        if let Some(gpu) = self.gpu.as_ref() {
             // Update heights buffer
             let heights_vec = shores.to_vec();
             let heights_bytes: &[u8] = bytemuck::cast_slice(&heights_vec);
@@ -991,17 +1197,52 @@ impl App {
 
         // Rebuild per-unit-type sprite atlases with new palette
         self.rebuild_unit_atlases(&base, &level_res.params.palette);
+        self.load_sounds(&base);
 
-        // Rebuild unit cells and object markers
-        self.engine.level_objects = extract_level_objects(&level_res);
+        self.load_level_entities(&level_res);
 
-        // Extract person units into the coordinator (they become live entities)
-        self.engine.unit_coordinator.load_level(&level_res.units, &shores.height, level_res.landscape.land_size());
+        // Rebuild HUD atlas with new palette
+        let panel_path = base.join("data").join("plspanel.spr");
+        if let Some(panel_container) = ContainerPSFB::from_file(&panel_path) {
+            self.engine.hud_panel_sprite_count = panel_container.len();
+            if let Some(ref mut hud) = self.hud {
+                let gpu = self.gpu.as_ref().unwrap();
+                hud.build_atlas(&gpu.device, &gpu.queue, &panel_container, &level_res.params.palette);
+            }
+        }
+    }
+
+    /// Level state shared by the windowed and headless paths: static objects,
+    /// live units, building footprints, then the camera pan to the shaman.
+    /// Expects the terrain heights to be set already.
+    fn load_level_entities(&mut self, level_res: &LevelRes) {
+        // Rebuild unit cells and object markers
+        self.engine.level_objects = extract_level_objects(level_res);
+
+        // Extract person units into the coordinator (they become live entities).
+        // A replay starts from the RNG seed it was recorded with, a
+        // multiplayer level from the one the session agreed on.
+        let seed = match (&self.engine.playback, &self.engine.lockstep) {
+            (Some(playback), _) => playback.replay().rng_seed,
+            (None, Some(session)) => session.level_seed().unwrap_or(DEFAULT_LEVEL_SEED),
+            (None, None) => self.engine.unit_coordinator.rng.seed(),
+        };
+        let shores = level_res.landscape.make_shores();
+        self.engine.unit_coordinator.load_level(seed, &level_res.units, &shores.height, level_res.landscape.land_size());
+        if self.engine.config.record_replay.is_some() {
+            // Each level is recorded from its start; the file keeps the last
+            self.save_replay();
+            self.engine.recorder = Some(ReplayRecorder::new(self.engine.level_num, seed,
+                self.engine.game_world.rules(), self.engine.game_world.game_tick));
+        }
+        self.engine.game_world.tribes.start_level(self.engine.unit_coordinator.tribe_populations());
+        self.engine.stash_dormant_objects();
         // Remove persons from static markers — they're now rendered by the coordinator
         self.engine.level_objects.retain(|obj| obj.model_type != ModelType::Person);
 
         // Populate unit_renders cells from live coordinator units
         self.sync_unit_render_cells();
+        self.engine.sync_vehicle_objects();
 
         // Flatten terrain under buildings (modifies heightmap + re-uploads GPU buffer)
         self.flatten_terrain_under_buildings();
@@ -1011,20 +1252,175 @@ impl App {
 
         self.rebuild_spawn_model();
         self.center_on_tribe0_shaman();
+    }
 
-        // Rebuild HUD atlas with new palette
-        let panel_path = base.join("data").join("plspanel.spr");
-        if let Some(panel_container) = ContainerPSFB::from_file(&panel_path) {
-            self.engine.hud_panel_sprite_count = panel_container.len();
-            if let Some(ref mut hud) = self.hud {
-                let gpu = self.gpu.as_ref().unwrap();
-                hud.build_atlas(&gpu.device, &gpu.queue, &panel_container, &level_res.params.palette);
+    /// Multiplayer rebuild: reload the level's objects and replay the host's
+    /// input history up to the tick we had reached. The camera stays put.
+    fn rebuild_from_host(&mut self) {
+        let base = self.engine.config.base.clone().unwrap_or_else(|| Path::new("/opt/sandbox/pop").to_path_buf());
+        let level_type = self.engine.config.landtype.as_deref();
+        let level_res = LevelRes::new(&base, self.engine.level_num, level_type);
+        // Tick 0 first, so a replay being recorded restarts from there
+        self.engine.game_world.rewind();
+        self.load_level_entities(&level_res);
+        self.shaman_pan = None;
+        self.engine.unit_coordinator.set_pathfinding_mode(self.engine.game_world.pathfinding);
+        self.engine.unit_coordinator.set_avoidance(self.engine.game_world.unit_avoidance);
+
+        let Some(session) = &mut self.engine.lockstep else { return };
+        let ticks = session.begin_rebuild();
+        let (mut a, mut c, mut e, mut f, mut g, mut h, mut i, mut j, mut k) =
+            (NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp);
+        let mut subs = TickSubsystems {
+            terrain: &mut a, objects: &mut self.engine.unit_coordinator,
+            water: &mut c,
+            network: session, actions: &mut e, game_time: &mut f,
+            single_player: &mut g, tutorial: &mut h, ai: &mut i,
+            population: &mut j, mana: &mut k,
+        };
+        let run = self.engine.game_world.fast_forward(ticks, &mut subs);
+        log::info!("[net] rebuilt: replayed {} ticks", run);
+    }
+
+    /// Write the replay being recorded to its file.
+    fn save_replay(&self) {
+        let (Some(path), Some(recorder)) = (&self.engine.config.record_replay, &self.engine.recorder) else { return };
+        let replay = recorder.finish(self.engine.game_world.game_tick);
+        match std::fs::write(path, replay.to_jsonl()) {
+            Ok(()) => log::info!("[replay] saved level {} ({} ticks, {} commands) to {}",
+                replay.level, replay.ticks, replay.commands.len(), path.display()),
+            Err(e) => log::warn!("[replay] {}: {}", path.display(), e),
+        }
+    }
+
+    /// Tell the other players we're going and wait, briefly, until they
+    /// have run our last batches.
+    fn leave_game(&mut self) {
+        let Some(session) = &mut self.engine.lockstep else { return };
+        session.leave();
+        let start = std::time::Instant::now();
+        while !session.finish_leaving() {
+            if start.elapsed() > std::time::Duration::from_secs(2) {
+                log::warn!("[net] left without every player's acknowledgement");
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+    }
+
+    /// Run the replay's next tick: snapshot if due, apply the commands
+    /// recorded for it, then step the simulation once.
+    fn step_replay(&mut self) {
+        let engine = &mut self.engine;
+        let Some(playback) = &mut engine.playback else { return };
+        let tick = engine.game_world.game_tick;
+        if playback.wants_snapshot(tick) {
+            playback.store_snapshot(tick, ReplayState {
+                sim: SimSnapshot::capture(&engine.game_world, &engine.unit_coordinator),
+                level_objects: engine.level_objects.clone(),
+                dormant_objects: engine.dormant_objects.clone(),
+            });
+        }
+        let commands: Vec<_> = playback.replay().commands_at(tick).cloned().collect();
+        // Out of the way, so apply_command takes the replay's input
+        let playback = engine.playback.take();
+        for cmd in &commands {
+            engine.apply_command(cmd);
+        }
+        engine.playback = playback;
+
+        let (mut a, mut c, mut d, mut e, mut f, mut g, mut h, mut i, mut j, mut k) =
+            (NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp);
+        let mut subs = TickSubsystems {
+            terrain: &mut a, objects: &mut engine.unit_coordinator,
+            water: &mut c,
+            network: &mut d, actions: &mut e, game_time: &mut f,
+            single_player: &mut g, tutorial: &mut h, ai: &mut i,
+            population: &mut j, mana: &mut k,
+        };
+        engine.game_world.fast_forward(1, &mut subs);
+        engine.apply_level_events();
+    }
+
+    /// Replay playback in place of the clock-driven tick loop: pause with
+    /// the simulation toggle, pace by the game speed. Returns the number of
+    /// ticks run.
+    fn tick_replay(&mut self) -> u32 {
+        let now = self.engine.game_time.now_ms();
+        let world = &self.engine.game_world;
+        let (tick, speed, running) = (world.game_tick, world.game_speed, world.state == GameState::InGame);
+        let Some(playback) = &mut self.engine.playback else { return 0 };
+        let seeking = playback.is_seeking();
+        let ticks = playback.due_ticks(tick, now, speed, running);
+        for _ in 0..ticks {
+            self.step_replay();
+        }
+        if seeking {
+            // Don't play the sounds of everything skipped over
+            self.engine.unit_coordinator.take_sound_events();
+        }
+        let tick = self.engine.game_world.game_tick;
+        if self.engine.playback.as_ref().is_some_and(|p| p.is_finished(tick)) {
+            if ticks > 0 {
+                log::info!("[replay] end of replay at tick {}", tick);
+            }
+            // Nothing runs past the end, so a script wait never would finish
+            if self.script_wait_ticks.take().is_some_and(|remaining| remaining > ticks) {
+                log::warn!("[script] wait cut short: the replay has ended");
             }
         }
+        ticks
+    }
+
+    /// Tick game simulation via GameWorld tick loop. Returns the number of
+    /// ticks run.
+    fn tick_simulation(&mut self) -> u32 {
+        if self.engine.lockstep.as_ref().is_some_and(|session| session.rebuild_ready()) {
+            self.rebuild_from_host();
+        }
+        // UnitCoordinator implements ObjectTick and is plugged into the
+        // objects slot, so person state machines run inside the proper
+        // tick order (after terrain, before water).
+        self.engine.unit_coordinator.set_pathfinding_mode(self.engine.game_world.pathfinding);
+        self.engine.unit_coordinator.set_avoidance(self.engine.game_world.unit_avoidance);
+        let ticks = if self.engine.playback.is_some() {
+            self.tick_replay()
+        } else {
+            let (mut a, mut c, mut d, mut e, mut f, mut g, mut h, mut i, mut j, mut k) =
+                (NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp);
+            let network: &mut dyn NetworkTick = match &mut self.engine.lockstep {
+                Some(session) => session,
+                None => &mut d,
+            };
+            let mut subs = TickSubsystems {
+                terrain: &mut a, objects: &mut self.engine.unit_coordinator,
+                water: &mut c,
+                network, actions: &mut e, game_time: &mut f,
+                single_player: &mut g, tutorial: &mut h, ai: &mut i,
+                population: &mut j, mana: &mut k,
+            };
+            self.engine.game_world.simulation_tick(&*self.engine.game_time, &mut subs)
+        };
+        self.engine.apply_level_events();
+        self.play_sound_events();
+        if let Some(remaining) = &mut self.script_wait_ticks {
+            *remaining = remaining.saturating_sub(ticks);
+        }
+        if ticks > 0 {
+            self.sync_unit_render_cells();
+            self.engine.sync_vehicle_objects();
+            self.rebuild_spawn_model();
+            self.rebuild_unit_models();
+            self.do_render = true;
+        }
+        ticks
     }
 
     fn log_camera_state(&mut self, event: &str) {
-        let t = self.start_time.elapsed().as_secs_f64();
+        let t = match &self.clock {
+            Some(clock) => clock.now_ms() as f64 / 1000.0,
+            None => self.start_time.elapsed().as_secs_f64(),
+        };
         let center = self.engine.world_center();
         let az = (self.engine.camera.angle_z as f32).to_radians();
         let ax = (self.engine.camera.angle_x as f32).to_radians();
@@ -1049,206 +1445,176 @@ impl App {
     }
 
     fn is_script_mode(&self) -> bool {
-        !self.script_commands.is_empty()
+        self.script.is_some()
     }
 
-    fn run_script_step(&mut self) -> bool {
-        if self.script_index >= self.script_commands.len() {
-            return false; // done
+    /// Hold the script for `ticks` simulation ticks. A stopped or paused
+    /// simulation would never finish the wait, so it is skipped then.
+    fn start_script_wait(&mut self, ticks: u32) {
+        let world = &self.engine.game_world;
+        if world.state != GameState::InGame || world.flags.is_paused() {
+            log::warn!("[script] wait skipped: simulation is not running");
+            return;
         }
-        // If we're in a timed wait, check if it's expired
-        if let Some(deadline) = self.script_wait_until {
-            if Instant::now() < deadline {
-                self.do_render = true;
-                return true; // keep waiting
-            }
-            self.script_wait_until = None;
+        if ticks > 0 {
+            self.script_wait_ticks = Some(ticks);
         }
+    }
 
-        let cmd = self.script_commands[self.script_index].clone();
-        self.script_index += 1;
+    fn script_failure(&mut self, message: String) {
+        log::error!("[script] {}", message);
+        self.script_failures.push(message);
+    }
 
-        // Parse wait command: "wait N" — pause for N seconds (wall-clock)
-        if let Some(val) = cmd.strip_prefix("wait ") {
-            if let Ok(secs) = val.trim().parse::<f32>() {
-                self.script_wait_until = Some(Instant::now() + Duration::from_secs_f32(secs));
+    fn run_script_step(&mut self) -> bool {
+        // Hold while a wait still has simulation ticks to run
+        if let Some(remaining) = self.script_wait_ticks {
+            if remaining > 0 {
                 self.do_render = true;
                 return true;
             }
+            self.script_wait_ticks = None;
         }
-
-        // Parse click command: "click X Y" — left-click at screen position
-        if let Some(coords) = cmd.strip_prefix("click ") {
-            let parts: Vec<&str> = coords.trim().split_whitespace().collect();
-            if parts.len() == 2 {
-                if let (Ok(x), Ok(y)) = (parts[0].parse::<f32>(), parts[1].parse::<f32>()) {
-                    self.input.mouse_pos = Point2::new(x, y);
-                    log::info!("[script] click at ({}, {})", x, y);
-                    // Simulate left press + release (selection)
-                    match self.engine.find_unit_at_screen_pos(&self.input.mouse_pos) {
-                        Some(id) => {
-                            self.engine.unit_coordinator.selection.select_single(id);
-                            log::info!("[script] selected unit {}", id);
-                        }
-                        None => {
-                            self.engine.unit_coordinator.selection.clear();
-                            log::info!("[script] no unit at click, selection cleared");
-                        }
+        let Some(script) = self.script.as_mut() else { return false };
+        let step = match script.next_step() {
+            None => return false, // done
+            Some(Ok(step)) => step,
+            Some(Err(e)) => {
+                self.script_failure(e);
+                return true;
+            }
+        };
+        let line = script.line();
+
+        match step {
+            ScriptStep::WaitTicks(ticks) => self.start_script_wait(ticks),
+            ScriptStep::WaitSeconds(secs) => {
+                let ticks = (secs.max(0.0) * self.engine.game_world.game_speed as f32).ceil() as u32;
+                self.start_script_wait(ticks);
+            }
+            ScriptStep::Click { x, y } => {
+                self.input.mouse_pos = Point2::new(x, y);
+                log::info!("[script] click at ({}, {})", x, y);
+                // Simulate left press + release (selection), as the mouse
+                // handler does, so replays record it
+                let cmd = match self.engine.find_unit_at_screen_pos(&self.input.mouse_pos) {
+                    Some(id) => {
+                        log::info!("[script] selected unit {}", id);
+                        GameCommand::SelectUnit(id)
                     }
-                    self.rebuild_unit_models();
-                    self.do_render = true;
-                    return true;
+                    None => {
+                        log::info!("[script] no unit at click, selection cleared");
+                        GameCommand::ClearSelection
+                    }
+                };
+                self.run_script_command(&cmd, "click");
+            }
+            ScriptStep::RightClick { x, y } => {
+                self.input.mouse_pos = Point2::new(x, y);
+                log::info!("[script] rightclick at ({}, {})", x, y);
+                if let Some((cx, cy)) = self.engine.screen_to_cell(&self.input.mouse_pos) {
+                    let target = cell_to_world(cx, cy, self.engine.landscape_mesh.width() as f32);
+                    let walkable = self.engine.unit_coordinator.region_map().is_walkable(target.to_tile());
+                    log::info!("[script] rightclick cell=({:.1}, {:.1}) → world=({}, {}) walkable={}",
+                        cx, cy, target.x, target.z, walkable);
+                    self.engine.apply_command(&GameCommand::OrderMove {
+                        x: target.x as f32, z: target.z as f32,
+                    });
+                } else {
+                    log::warn!("[script] rightclick: screen_to_cell returned None");
                 }
             }
-        }
-
-        // Parse rightclick command: "rightclick X Y" — right-click move order
-        if let Some(coords) = cmd.strip_prefix("rightclick ") {
-            let parts: Vec<&str> = coords.trim().split_whitespace().collect();
-            if parts.len() == 2 {
-                if let (Ok(x), Ok(y)) = (parts[0].parse::<f32>(), parts[1].parse::<f32>()) {
-                    self.input.mouse_pos = Point2::new(x, y);
-                    log::info!("[script] rightclick at ({}, {})", x, y);
-                    if let Some((cx, cy)) = self.engine.screen_to_cell(&self.input.mouse_pos) {
-                        let target = cell_to_world(cx, cy, self.engine.landscape_mesh.width() as f32);
-                        let walkable = self.engine.unit_coordinator.region_map().is_walkable(target.to_tile());
-                        log::info!("[script] rightclick cell=({:.1}, {:.1}) → world=({}, {}) walkable={}",
-                            cx, cy, target.x, target.z, walkable);
-                        self.engine.apply_command(&GameCommand::OrderMove {
-                            x: target.x as f32, z: target.z as f32,
-                        });
+            // Log all unit screen positions
+            ScriptStep::DumpUnits => {
+                let pvm = self.engine.unit_pvm();
+                for unit in &self.engine.unit_coordinator.units {
+                    if let Some((sx, sy)) = self.engine.unit_screen_pos(unit, &pvm) {
+                        log::info!("[dump] unit {} tribe={} cell=({:.2}, {:.2}) screen=({:.0}, {:.0})",
+                            unit.id, unit.tribe_index, unit.cell_x, unit.cell_y, sx, sy);
                     } else {
-                        log::warn!("[script] rightclick: screen_to_cell returned None");
+                        log::info!("[dump] unit {} tribe={} cell=({:.2}, {:.2}) behind camera",
+                            unit.id, unit.tribe_index, unit.cell_x, unit.cell_y);
                     }
-                    self.do_render = true;
-                    return true;
                 }
             }
-        }
-
-        // Parse dump command: log all unit screen positions
-        if cmd.trim() == "dump_units" {
-            let pvm = self.engine.unit_pvm();
-            for unit in &self.engine.unit_coordinator.units {
-                if let Some((sx, sy)) = self.engine.unit_screen_pos(unit, &pvm) {
-                    log::info!("[dump] unit {} tribe={} cell=({:.2}, {:.2}) screen=({:.0}, {:.0})",
-                        unit.id, unit.tribe_index, unit.cell_x, unit.cell_y, sx, sy);
-                } else {
-                    log::info!("[dump] unit {} tribe={} cell=({:.2}, {:.2}) behind camera",
-                        unit.id, unit.tribe_index, unit.cell_x, unit.cell_y);
+            ScriptStep::DumpBuildings => self.dump_building_footprints(),
+            ScriptStep::Screenshot(path) => {
+                let path = path.unwrap_or_else(|| {
+                    self.screenshot_counter += 1;
+                    format!("screenshot_{:04}.png", self.screenshot_counter - 1)
+                });
+                self.screenshot_path = Some(path);
+            }
+            ScriptStep::Echo(text) => log::info!("[script] {}", text),
+            ScriptStep::Assert(assertion) => {
+                match assertion.check(&self.engine.script_view()) {
+                    Ok(()) => log::info!("[script] line {}: assert ok", line),
+                    Err(e) => self.script_failure(format!("line {}: assertion failed: {}", line, e)),
+                }
+            }
+            ScriptStep::Command { cmd, label } => {
+                if !self.run_script_command(&cmd, &label) {
+                    return false;
                 }
             }
-            return true;
-        }
-
-        // Parse dump_buildings command: log footprint coordinate details
-        if cmd.trim() == "dump_buildings" {
-            self.dump_building_footprints();
-            return true;
         }
+        self.do_render = true;
+        true
+    }
 
-        // Parse screenshot command: "screenshot [path]"
-        if let Some(path) = cmd.strip_prefix("screenshot ") {
-            self.screenshot_path = Some(path.trim().to_string());
-            self.do_render = true;
-            return true;
-        }
-        if cmd.trim() == "screenshot" {
-            let path = format!("screenshot_{:04}.png", self.screenshot_counter);
-            self.screenshot_counter += 1;
-            self.screenshot_path = Some(path);
-            self.do_render = true;
-            return true;
-        }
+    /// Replay a script command through apply_command plus the app-level
+    /// side effects of the keyboard handler. Returns false on Quit.
+    fn run_script_command(&mut self, cmd: &GameCommand, label: &str) -> bool {
+        let prev_shift = self.engine.landscape_mesh.get_shift_vector();
+        self.engine.apply_command(cmd);
 
-        // Parse zoom command
-        if let Some(val) = cmd.strip_prefix("zoom ") {
-            if let Ok(z) = val.trim().parse::<f32>() {
-                self.engine.apply_command(&GameCommand::SetZoom(z));
+        match cmd {
+            GameCommand::Quit => { return false; }
+            GameCommand::NextShader => { self.program_container.next(); }
+            GameCommand::PrevShader => { self.program_container.prev(); }
+            GameCommand::NextLevel | GameCommand::PrevLevel if self.engine.playback.is_none() => {
+                self.update_level();
+            }
+            GameCommand::CenterOnShaman => {
+                self.center_on_tribe0_shaman();
+                self.log_camera_state("space_center");
+            }
+            GameCommand::ResetCamera => {
+                self.rebuild_spawn_model();
+                self.log_camera_state("reset");
+            }
+            GameCommand::TopDownView => {
+                self.log_camera_state(label);
+            }
+            GameCommand::SetZoom(_) => {
                 self.log_camera_state("zoom");
-                self.do_render = true;
-                return true;
             }
-        }
-
-        // Parse key name to KeyCode
-        let key = match cmd.as_str() {
-            "W" => KeyCode::KeyW,
-            "A" => KeyCode::KeyA,
-            "S" => KeyCode::KeyS,
-            "D" => KeyCode::KeyD,
-            "Q" => KeyCode::KeyQ,
-            "E" => KeyCode::KeyE,
-            "R" => KeyCode::KeyR,
-            "T" => KeyCode::KeyT,
-            "N" => KeyCode::KeyN,
-            "M" => KeyCode::KeyM,
-            "B" => KeyCode::KeyB,
-            "V" => KeyCode::KeyV,
-            "C" => KeyCode::KeyC,
-            "Space" => KeyCode::Space,
-            "ArrowUp" => KeyCode::ArrowUp,
-            "ArrowDown" => KeyCode::ArrowDown,
-            "BracketLeft" => KeyCode::BracketLeft,
-            "BracketRight" => KeyCode::BracketRight,
-            "F8" => KeyCode::F8,
-            "Escape" => KeyCode::Escape,
-            other => {
-                log::warn!("script: unknown command {:?}", other);
-                return true; // skip, continue
+            GameCommand::ToggleCurvature | GameCommand::AdjustCurvature { .. }
+            | GameCommand::AdjustSpriteOffset { .. } | GameCommand::AdjustSpriteScale { .. } => {
+                self.rebuild_spawn_model();
             }
-        };
-
-        // Replay through translate_key → apply_command
-        if let Some(cmd) = translate_key(key) {
-            let prev_shift = self.engine.landscape_mesh.get_shift_vector();
-            self.engine.apply_command(&cmd);
-
-            // App-level side effects (same as keyboard handler)
-            match &cmd {
-                GameCommand::Quit => { return false; }
-                GameCommand::NextShader => { self.program_container.next(); }
-                GameCommand::PrevShader => { self.program_container.prev(); }
-                GameCommand::NextLevel | GameCommand::PrevLevel => {
-                    self.update_level();
-                }
-                GameCommand::CenterOnShaman => {
-                    self.center_on_tribe0_shaman();
-                    self.log_camera_state("space_center");
-                }
-                GameCommand::ResetCamera => {
+            GameCommand::PanScreen { .. } | GameCommand::PanTerrain { .. } => {
+                self.shaman_pan = None;
+                let new_shift = self.engine.landscape_mesh.get_shift_vector();
+                if new_shift != prev_shift {
                     self.rebuild_spawn_model();
-                    self.log_camera_state("reset");
+                    self.log_camera_state(label);
                 }
-                GameCommand::TopDownView => {
-                    self.log_camera_state("KeyT");
-                }
-                GameCommand::ToggleCurvature | GameCommand::AdjustCurvature { .. }
-                | GameCommand::AdjustSpriteOffset { .. } | GameCommand::AdjustSpriteScale { .. } => {
-                    self.rebuild_spawn_model();
-                }
-                GameCommand::PanScreen { .. } | GameCommand::PanTerrain { .. } => {
-                    self.shaman_pan = None;
-                    let new_shift = self.engine.landscape_mesh.get_shift_vector();
-                    if new_shift != prev_shift {
-                        self.rebuild_spawn_model();
-                        self.log_camera_state(&format!("{:?}", key));
-                    }
-                }
-                GameCommand::RotateCamera { .. } | GameCommand::TiltCamera { .. } => {
-                    self.rebuild_spawn_model();
-                    self.log_camera_state(&format!("{:?}", key));
-                }
-                _ => {}
             }
-            self.do_render = true;
+            GameCommand::RotateCamera { .. } | GameCommand::TiltCamera { .. } => {
+                self.rebuild_spawn_model();
+                self.log_camera_state(label);
+            }
+            GameCommand::SelectUnit(_) | GameCommand::SelectMultiple(_) | GameCommand::ClearSelection => {
+                self.rebuild_unit_models();
+            }
+            _ => {}
         }
         true
     }
 
     /// Sync unit_renders cells from live coordinator units.
     fn sync_unit_render_cells(&mut self) {
-        use crate::render::sprites::UnitRenderData;
         for ur in &mut self.unit_renders {
             ur.cells.clear();
         }
@@ -1272,12 +1638,14 @@ impl App {
             let cs = if self.engine.curvature_enabled { self.engine.curvature_scale } else { 0.0 };
             for ur in &mut self.unit_renders {
                 if !ur.cells.is_empty() {
+                    let sheet = SpriteSheet {
+                        frame_w: ur.frame_width, frame_h: ur.frame_height, frames_per_dir: ur.frames_per_dir,
+                        anim_offsets: &ur.anim_offsets,
+                        z_offset: self.engine.sprite_z_offset, scale: self.engine.sprite_scale,
+                    };
                     ur.model = Some(build_spawn_model(
                         &gpu.device, &ur.cells, &self.engine.landscape_mesh, cs,
-                        self.engine.camera.angle_x, self.engine.camera.angle_z,
-                        ur.frame_width, ur.frame_height, ur.frames_per_dir,
-                        &ur.anim_offsets,
-                        self.engine.sprite_z_offset, self.engine.sprite_scale,
+                        self.engine.camera.angle_x, self.engine.camera.angle_z, &sheet,
                     ));
                 } else {
                     ur.model = None;
@@ -1415,6 +1783,11 @@ impl App {
                 &self.engine.landscape_mesh, cs,
                 self.engine.camera.angle_x, self.engine.camera.angle_z,
             );
+            self.model_effects = build_effect_billboards(
+                &gpu.device, &self.engine.unit_coordinator.effects.effects,
+                &self.engine.landscape_mesh, cs,
+                self.engine.camera.angle_x, self.engine.camera.angle_z,
+            );
         }
     }
 
@@ -1846,7 +2219,10 @@ impl App {
     }
 
     fn rebuild_landscape_variants(&mut self, level_res: &LevelRes) {
-        let gpu = self.gpu.as_ref().unwrap();
+        let gpu = match self.gpu.as_ref() {
+            Some(g) => g,
+            None => return,
+        };
         let device = &gpu.device;
         let group0_layout = self.landscape_group0_layout.as_ref().unwrap();
         let shadow_group2_layout = self.shadow_recv_group2_layout.as_ref().unwrap();
@@ -2236,7 +2612,7 @@ impl App {
                 }
             }
 
-            // Draw selection outlines (always) and unit marker billboards (toggled)
+            // Draw selection outlines and effects (always) and unit marker billboards (toggled)
             if let Some(ref marker_pipeline) = self.objects_marker_pipeline {
                 render_pass.set_pipeline(marker_pipeline);
                 render_pass.set_bind_group(0, self.objects_group0_bind_group.as_ref().unwrap(), &[]);
@@ -2248,6 +2624,9 @@ impl App {
                 if let Some(ref model) = self.model_selection_outlines {
                     model.draw(&mut render_pass);
                 }
+                if let Some(ref model) = self.model_effects {
+                    model.draw(&mut render_pass);
+                }
             }
 
             // Draw walkability debug overlay (F8 toggle)
@@ -2719,42 +3098,12 @@ impl ApplicationHandler for App {
 
         // Unit sprite atlases are built after self.gpu is set (see below)
 
-        // Load dual OBJS banks: bank 0 for buildings, level bank for scenery.
-        // Bank 0 has building models at indices 117-193 (building_obj_index).
-        // Level banks have scenery at different indices (scenery_obj_index).
-        // Shape_LoadBank @ 0x49b990 remaps bank 0 → 2.
-        let (building_objects, scenery_objects) = Object3D::load_dual_banks(&base, level_res.obj_bank);
-        let level_bank = if level_res.obj_bank == 0 { 2 } else { level_res.obj_bank };
-        let bld_count = building_objects.iter().filter(|o| o.is_some()).count();
-        let scn_count = scenery_objects.iter().filter(|o| o.is_some()).count();
-        eprintln!("[OBJS] buildings: bank=0 entries={} non-empty={}",
-            building_objects.len(), bld_count);
-        eprintln!("[OBJS] scenery:   bank={} entries={} non-empty={}",
-            level_bank, scenery_objects.len(), scn_count);
-        let bank_str = level_bank.to_string();
-        let obj_paths = ObjectPaths::from_default_dir(&base, &bank_str);
-        let shape_footprints = ShapeFootprints::from_file(&obj_paths.shapes);
-        let shapes: Vec<Shape> = shape_footprints.shapes().to_vec();
-        eprintln!("[shapes] loaded {} entries with footprint bitmaps", shapes.len());
-        for (i, s) in shapes.iter().take(10).enumerate() {
-            let sref = s.shape_ref;
-            eprintln!("[shapes] [{}] {}x{} origin=({},{}) ref={}",
-                i, s.width, s.height, s.origin_x, s.origin_z, sref);
-        }
+        self.engine.load_object_banks(&base, &level_res);
 
-        let (bl320_w, bl320_h, mut bl320_data) = make_bl320_texture_rgba(
-            &level_res.paths.bl320, &level_res.params.palette);
-
-        // Mark transparent pixels (palette index 0) with alpha=255 so the shader
+        // Transparent pixels (palette index 0) carry alpha=255 so the shader
         // can discard them via `if (color.w > 0.0) { discard; }`.
-        let key_r = level_res.params.palette[0];
-        let key_g = level_res.params.palette[1];
-        let key_b = level_res.params.palette[2];
-        for pixel in bl320_data.chunks_exact_mut(4) {
-            if pixel[0] == key_r && pixel[1] == key_g && pixel[2] == key_b && pixel[3] == 0 {
-                pixel[3] = 255;
-            }
-        }
+        let (bl320_w, bl320_h, bl320_data) = building_atlas_rgba(
+            &level_res.paths.bl320, &level_res.params.palette);
 
         let bl320_gpu_tex = GpuTexture::new_2d(
             device, &gpu.queue,
@@ -2986,10 +3335,6 @@ impl ApplicationHandler for App {
         self.lighting_buffer = Some(lighting_buffer);
         self.objects_marker_pipeline = Some(objects_marker_pipeline);
         self.walkability_pipeline = Some(walkability_pipeline);
-        self.engine.building_objects = building_objects;
-        self.engine.scenery_objects = scenery_objects;
-        self.engine.shapes = shapes;
-        self.engine.shape_footprints = shape_footprints;
         self.building_pipeline = Some(building_pipeline);
         self.building_bind_group_1 = Some(building_bind_group_1);
         self.sky_pipeline = sky_pipeline;
@@ -3008,25 +3353,9 @@ impl ApplicationHandler for App {
 
         // Build per-unit-type sprite atlases
         self.rebuild_unit_atlases(&base2, &level_res2.params.palette);
+        self.load_sounds(&base2);
 
-        self.engine.level_objects = extract_level_objects(&level_res2);
-
-        // Extract person units into the coordinator (they become live entities)
-        let shores2 = level_res2.landscape.make_shores();
-        self.engine.unit_coordinator.load_level(&level_res2.units, &shores2.height, level_res2.landscape.land_size());
-        self.engine.level_objects.retain(|obj| obj.model_type != ModelType::Person);
-
-        // Populate unit_renders cells from live coordinator units
-        self.sync_unit_render_cells();
-
-        // Flatten terrain under buildings (modifies heightmap + re-uploads GPU buffer)
-        self.flatten_terrain_under_buildings();
-
-        // Mark building footprints in region map for pathfinding walkability
-        self.populate_buildings_in_region_map();
-
-        self.rebuild_spawn_model();
-        self.center_on_tribe0_shaman();
+        self.load_level_entities(&level_res2);
 
         // Build HUD sprite atlas from plspanel.spr
         {
@@ -3168,7 +3497,7 @@ impl ApplicationHandler for App {
                                 GameCommand::Quit => { event_loop.exit(); return; }
                                 GameCommand::NextShader => { self.program_container.next(); }
                                 GameCommand::PrevShader => { self.program_container.prev(); }
-                                GameCommand::NextLevel | GameCommand::PrevLevel => {
+                                GameCommand::NextLevel | GameCommand::PrevLevel if self.engine.playback.is_none() => {
                                     self.update_level();
                                 }
                                 GameCommand::CenterOnShaman => {
@@ -3203,28 +3532,7 @@ impl ApplicationHandler for App {
                 }
             },
             WindowEvent::RedrawRequested => {
-                // Tick game simulation via GameWorld tick loop.
-                // UnitCoordinator implements ObjectTick and is plugged into the
-                // objects slot, so person state machines run inside the proper
-                // tick order (after terrain, before water).
-                {
-                    let (mut a, mut c, mut d, mut e, mut f, mut g, mut h, mut i, mut j, mut k) =
-                        (NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp, NoOp);
-                    let mut subs = TickSubsystems {
-                        terrain: &mut a, objects: &mut self.engine.unit_coordinator,
-                        water: &mut c,
-                        network: &mut d, actions: &mut e, game_time: &mut f,
-                        single_player: &mut g, tutorial: &mut h, ai: &mut i,
-                        population: &mut j, mana: &mut k,
-                    };
-                    let ticks = self.engine.game_world.simulation_tick(&self.engine.game_time, &mut subs);
-                    if ticks > 0 {
-                        self.sync_unit_render_cells();
-                        self.rebuild_spawn_model();
-                        self.rebuild_unit_models();
-                        self.do_render = true;
-                    }
-                }
+                self.tick_simulation();
 
                 // Smooth camera pan to shaman
                 self.tick_shaman_pan();
@@ -3255,11 +3563,139 @@ impl ApplicationHandler for App {
     }
 }
 
+/******************************************************************************/
+// Headless script replay — no window or GPU. Frames advance a virtual clock
+// and screenshots come from the software renderer, so a script produces the
+// same images and camera log on every run.
+
+const HEADLESS_FRAME_MS: u64 = 16;
+
+impl App {
+    /// Replay `config.script` without a window, one step per 16 ms frame.
+    /// Screenshots are written to `out_dir` under their file name and the
+    /// camera log to `out_dir/camera.jsonl`. Returns the screenshot paths in
+    /// script order, or an error if a script step or assertion failed.
+    pub fn run_headless(config: AppConfig, out_dir: &Path) -> Result<Vec<PathBuf>, String> {
+        std::fs::create_dir_all(out_dir).map_err(|e| format!("{}: {}", out_dir.display(), e))?;
+        let mut app = App::new(config);
+        if !app.is_script_mode() {
+            return Err("headless mode needs a --script".to_string());
+        }
+        let log_path = out_dir.join("camera.jsonl");
+        app.debug_log = BufWriter::new(
+            File::create(&log_path).map_err(|e| format!("{}: {}", log_path.display(), e))?,
+        );
+        let clock = Rc::new(ManualTimeSource::new());
+        app.engine.game_time = clock.clone();
+        app.clock = Some(clock.clone());
+
+        let base = app.engine.config.base.clone().unwrap_or_else(|| Path::new("/opt/sandbox/pop").to_path_buf());
+        let level_res = LevelRes::new(&base, app.engine.level_num, app.engine.config.landtype.as_deref());
+        app.engine.landscape_mesh.set_heights(&level_res.landscape.make_shores().height);
+        app.engine.load_object_banks(&base, &level_res);
+        app.load_level_entities(&level_res);
+        app.finish_shaman_pan();
+        let mut textures = LevelTextures::load(&base, &level_res);
+        let mut level_num = app.engine.level_num;
+
+        let mut screenshots = Vec::new();
+        loop {
+            let running = app.run_script_step();
+            app.finish_shaman_pan();
+            if app.engine.level_num != level_num {
+                level_num = app.engine.level_num;
+                let level_res = LevelRes::new(&base, level_num, app.engine.config.landtype.as_deref());
+                textures = LevelTextures::load(&base, &level_res);
+            }
+            if let Some(path) = app.screenshot_path.take() {
+                let name = Path::new(&path).file_name()
+                    .ok_or_else(|| format!("bad screenshot path {:?}", path))?;
+                let out = out_dir.join(name);
+                app.render_software(&textures).save(&out)
+                    .map_err(|e| format!("{}: {}", out.display(), e))?;
+                log::info!("[headless] screenshot {}", out.display());
+                screenshots.push(out);
+            }
+            if !running {
+                break;
+            }
+            clock.advance(HEADLESS_FRAME_MS);
+            app.tick_simulation();
+        }
+        app.save_replay();
+        app.debug_log.flush().map_err(|e| format!("{}: {}", log_path.display(), e))?;
+        app.script_result()?;
+        Ok(screenshots)
+    }
+
+    /// Draw the current frame with the software renderer: terrain, buildings
+    /// and live units. HUD and overlays are not drawn.
+    fn render_software(&self, textures: &LevelTextures) -> image::RgbaImage {
+        let mut units: Vec<(u8, Vec<UnitRenderData>)> = Vec::new();
+        for unit in self.engine.unit_coordinator.units.iter().filter(|u| u.alive) {
+            let cell = UnitRenderData {
+                cell_x: unit.cell_x,
+                cell_y: unit.cell_y,
+                tribe_index: unit.tribe_index,
+                facing_angle: unit.movement.facing_angle,
+                frame_index: unit.anim.frame_index,
+                animation_id: unit.anim.animation_id,
+            };
+            match units.iter_mut().find(|(subtype, _)| *subtype == unit.subtype) {
+                Some((_, cells)) => cells.push(cell),
+                None => units.push((unit.subtype, vec![cell])),
+            }
+        }
+        let engine = &self.engine;
+        let view = LevelView {
+            landscape: &engine.landscape_mesh,
+            objects: if engine.show_objects { &engine.level_objects } else { &[] },
+            building_bank: &engine.building_objects,
+            scenery_bank: &engine.scenery_objects,
+            units: &units,
+            curvature_scale: if engine.curvature_enabled { engine.curvature_scale } else { 0.0 },
+            sun_dir: engine.show_lighting.then(|| sun_direction(engine.sunlight.x, engine.sunlight.y)),
+        };
+        textures.render(&view, &engine.screen, &engine.camera, engine.zoom)
+    }
+}
+
 impl App {
-    pub fn run(config: AppConfig) {
+    /// Run the windowed app. Fails if a `--script` step or assertion failed.
+    pub fn run(config: AppConfig) -> Result<(), String> {
         let event_loop = EventLoop::new().unwrap();
         let mut app = App::new(config);
+        if let Some(net) = app.engine.config.net.clone() {
+            let mut session = net.connect()?
+                .with_level_seed(app.engine.unit_coordinator.rng.seed())
+                .with_rules(app.engine.game_world.rules());
+            log::info!("[net] player {} of {}, input delay {} ticks", session.player(), session.players(), DEFAULT_INPUT_DELAY);
+            // Levels load with the host's seed: wait for it
+            if session.level_seed().is_none() {
+                log::info!("[net] waiting for player {}'s level seed", session.host());
+            }
+            while session.poll_level_seed()?.is_none() {
+                std::thread::sleep(std::time::Duration::from_millis(5));
+            }
+            app.engine.game_world.flags.set_multiplayer(true);
+            app.engine.game_world.player_tribe = session.player();
+            if !net.teams.is_empty() {
+                log::info!("[net] teams {:?}", net.teams);
+                app.engine.game_world.tribes.set_teams(&net.teams);
+            }
+            app.engine.lockstep = Some(session);
+        }
         event_loop.run_app(&mut app).unwrap();
+        app.save_replay();
+        app.leave_game();
+        app.script_result()
+    }
+
+    fn script_result(&self) -> Result<(), String> {
+        match self.script_failures.len() {
+            0 => Ok(()),
+            n => Err(format!("{} script failure(s): {}", n, self.script_failures.join("; "))),
+        }
     }
 }
 
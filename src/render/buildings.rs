@@ -1,9 +1,12 @@
+use std::path::Path;
+
 use cgmath::Vector3;
 
 use crate::render::model::{VertexModel, MeshModel};
 use crate::render::tex_model::{TexModel, TexVertex};
 use crate::render::envelop::{ModelEnvelop, RenderType};
 use crate::render::terrain::LandscapeMesh;
+use crate::data::bl320::make_bl320_texture_rgba;
 use crate::data::objects::{Object3D, Shape, mk_pop_object};
 use crate::data::units::{ModelType, building_obj_index, scenery_obj_index};
 
@@ -14,6 +17,18 @@ pub fn build_building_meshes(
     building_bank: &[Option<Object3D>], scenery_bank: &[Option<Object3D>],
     _shapes: &[Shape], landscape: &LandscapeMesh<128>, curvature_scale: f32,
 ) -> ModelEnvelop<TexModel> {
+    let combined = building_mesh(objects, building_bank, scenery_bank, landscape, curvature_scale);
+    let m = vec![(RenderType::Triangles, combined)];
+    ModelEnvelop::<TexModel>::new(device, m)
+}
+
+/// Building and scenery geometry in landscape model space, draped over the
+/// (curved) terrain. Shared by the GPU path and the software renderer.
+pub fn building_mesh(
+    objects: &[LevelObject],
+    building_bank: &[Option<Object3D>], scenery_bank: &[Option<Object3D>],
+    landscape: &LandscapeMesh<128>, curvature_scale: f32,
+) -> TexModel {
     let mut combined: TexModel = MeshModel::new();
     let step = landscape.step();
     let w = landscape.width() as f32;
@@ -112,6 +127,18 @@ pub fn build_building_meshes(
         eprintln!("[buildings] bbox x=[{:.3}..{:.3}] y=[{:.3}..{:.3}] z=[{:.3}..{:.3}]",
             min_x, max_x, min_y, max_y, min_z, max_z);
     }
-    let m = vec![(RenderType::Triangles, combined)];
-    ModelEnvelop::<TexModel>::new(device, m)
+    combined
+}
+
+/// BL320 atlas as the building pipeline samples it: palette index 0 pixels
+/// get alpha 255 so objects_tex.wgsl can discard them.
+pub fn building_atlas_rgba(bl320_path: &Path, palette: &[u8]) -> (usize, usize, Vec<u8>) {
+    let (width, height, mut data) = make_bl320_texture_rgba(bl320_path, palette);
+    let (key_r, key_g, key_b) = (palette[0], palette[1], palette[2]);
+    for pixel in data.chunks_exact_mut(4) {
+        if pixel[0] == key_r && pixel[1] == key_g && pixel[2] == key_b && pixel[3] == 0 {
+            pixel[3] = 255;
+        }
+    }
+    (width, height, data)
 }
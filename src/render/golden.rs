@@ -0,0 +1,261 @@
+use std::path::{Path, PathBuf};
+
+use image::{Rgba, RgbaImage};
+use serde_json::Value;
+
+/******************************************************************************/
+// Golden-image checks for the headless script runs (see App::run_headless).
+//
+// Screenshots are compared pixel by pixel with the YIQ colour distance used by
+// pixelmatch, so small shading differences pass while visible changes do not;
+// a run fails when more than a given share of pixels differ. Camera logs are
+// compared field by field with a float tolerance, ignoring the timestamp.
+
+/// Camera log written next to the screenshots of a headless run.
+pub const CAMERA_LOG: &str = "camera.jsonl";
+
+/// Maximum squared YIQ distance (black against white).
+const MAX_YIQ_DELTA: f32 = 35215.0;
+
+pub struct ImageTolerance {
+    /// Per-pixel colour threshold, 0.0 (exact) to 1.0 (anything passes).
+    pub threshold: f32,
+    /// Share of pixels allowed over the threshold.
+    pub max_diff_ratio: f32,
+}
+
+impl Default for ImageTolerance {
+    fn default() -> Self {
+        Self { threshold: 0.1, max_diff_ratio: 0.001 }
+    }
+}
+
+pub struct ImageDiff {
+    pub diff_pixels: usize,
+    pub total_pixels: usize,
+    /// Expected image faded to grey with differing pixels in red.
+    pub image: RgbaImage,
+}
+
+impl ImageDiff {
+    pub fn ratio(&self) -> f32 {
+        self.diff_pixels as f32 / self.total_pixels.max(1) as f32
+    }
+}
+
+fn blend_white(c: u8, alpha: f32) -> f32 {
+    255.0 + (c as f32 - 255.0) * alpha
+}
+
+fn yiq(p: &Rgba<u8>) -> (f32, f32, f32) {
+    let a = p[3] as f32 / 255.0;
+    let (r, g, b) = (blend_white(p[0], a), blend_white(p[1], a), blend_white(p[2], a));
+    (
+        r * 0.298_895_3 + g * 0.586_622_5 + b * 0.114_482_2,
+        r * 0.595_978 - g * 0.274_176_1 - b * 0.321_801_9,
+        r * 0.211_470_2 - g * 0.522_617_1 + b * 0.311_147,
+    )
+}
+
+/// Squared perceptual distance of two pixels, 0.0 to MAX_YIQ_DELTA.
+pub fn color_delta(a: &Rgba<u8>, b: &Rgba<u8>) -> f32 {
+    let (ya, ia, qa) = yiq(a);
+    let (yb, ib, qb) = yiq(b);
+    let (y, i, q) = (ya - yb, ia - ib, qa - qb);
+    0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q
+}
+
+pub fn diff_images(actual: &RgbaImage, expected: &RgbaImage, threshold: f32) -> Result<ImageDiff, String> {
+    if actual.dimensions() != expected.dimensions() {
+        return Err(format!("size {:?} differs from golden {:?}", actual.dimensions(), expected.dimensions()));
+    }
+    let max_delta = MAX_YIQ_DELTA * threshold * threshold;
+    let mut image = RgbaImage::new(expected.width(), expected.height());
+    let mut diff_pixels = 0;
+    for ((a, e), out) in actual.pixels().zip(expected.pixels()).zip(image.pixels_mut()) {
+        *out = if color_delta(a, e) > max_delta {
+            diff_pixels += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            let (y, _, _) = yiq(e);
+            let grey = blend_white(y.clamp(0.0, 255.0) as u8, 0.1) as u8;
+            Rgba([grey, grey, grey, 255])
+        };
+    }
+    Ok(ImageDiff { diff_pixels, total_pixels: (expected.width() * expected.height()) as usize, image })
+}
+
+fn values_match(a: &Value, b: &Value, epsilon: f64) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => match (x.as_f64(), y.as_f64()) {
+            (Some(x), Some(y)) => (x - y).abs() <= epsilon,
+            _ => x == y,
+        },
+        (Value::Array(x), Value::Array(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(x, y)| values_match(x, y, epsilon))
+        }
+        _ => a == b,
+    }
+}
+
+/// Compare two camera logs line by line; every field but "t" must match
+/// within `epsilon`. Returns one message per mismatch.
+pub fn compare_camera_logs(actual: &str, expected: &str, epsilon: f64) -> Vec<String> {
+    let parse = |text: &str| -> Result<Vec<serde_json::Map<String, Value>>, String> {
+        text.lines().filter(|l| !l.trim().is_empty()).enumerate()
+            .map(|(i, l)| match serde_json::from_str(l) {
+                Ok(Value::Object(map)) => Ok(map),
+                _ => Err(format!("line {}: not a JSON object", i + 1)),
+            })
+            .collect()
+    };
+    let (actual, expected) = match (parse(actual), parse(expected)) {
+        (Ok(a), Ok(e)) => (a, e),
+        (Err(e), _) => return vec![format!("camera log: {}", e)],
+        (_, Err(e)) => return vec![format!("golden camera log: {}", e)],
+    };
+    let mut mismatches = Vec::new();
+    if actual.len() != expected.len() {
+        mismatches.push(format!("camera log has {} entries, golden has {}", actual.len(), expected.len()));
+    }
+    for (i, (a, e)) in actual.iter().zip(&expected).enumerate() {
+        let keys = a.keys().chain(e.keys().filter(|k| !a.contains_key(*k)));
+        for key in keys.filter(|k| *k != "t") {
+            let (va, ve) = (a.get(key), e.get(key));
+            let same = match (va, ve) {
+                (Some(va), Some(ve)) => values_match(va, ve, epsilon),
+                _ => false,
+            };
+            if !same {
+                mismatches.push(format!("camera log line {}: {} is {} (golden {})", i + 1, key,
+                    va.map_or("missing".to_string(), Value::to_string),
+                    ve.map_or("missing".to_string(), Value::to_string)));
+            }
+        }
+    }
+    mismatches
+}
+
+/// Screenshots of a run directory (diff images excluded), sorted by name.
+fn screenshots(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = std::fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let mut paths: Vec<PathBuf> = entries.filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "png"))
+        .filter(|p| !p.file_name().unwrap().to_string_lossy().starts_with("diff_"))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+pub struct GoldenReport {
+    pub checked: usize,
+    pub failures: Vec<String>,
+}
+
+/// Check a headless run in `out_dir` against `golden_dir`. Differing
+/// screenshots get a `diff_<name>` image in `out_dir`.
+pub fn compare_run(out_dir: &Path, golden_dir: &Path, tolerance: &ImageTolerance) -> Result<GoldenReport, String> {
+    let mut report = GoldenReport { checked: 0, failures: Vec::new() };
+    let goldens = screenshots(golden_dir)?;
+    for golden in &goldens {
+        let name = golden.file_name().unwrap();
+        let actual = out_dir.join(name);
+        report.checked += 1;
+        if !actual.exists() {
+            report.failures.push(format!("{}: not produced", name.to_string_lossy()));
+            continue;
+        }
+        let load = |p: &Path| image::open(p).map(|i| i.to_rgba8()).map_err(|e| format!("{}: {}", p.display(), e));
+        let diff = match diff_images(&load(&actual)?, &load(golden)?, tolerance.threshold) {
+            Ok(diff) => diff,
+            Err(e) => {
+                report.failures.push(format!("{}: {}", name.to_string_lossy(), e));
+                continue;
+            }
+        };
+        if diff.ratio() > tolerance.max_diff_ratio {
+            let diff_path = out_dir.join(format!("diff_{}", name.to_string_lossy()));
+            diff.image.save(&diff_path).map_err(|e| format!("{}: {}", diff_path.display(), e))?;
+            report.failures.push(format!("{}: {} of {} pixels differ ({:.3}%), see {}",
+                name.to_string_lossy(), diff.diff_pixels, diff.total_pixels,
+                diff.ratio() * 100.0, diff_path.display()));
+        }
+    }
+    for actual in screenshots(out_dir)? {
+        let name = actual.file_name().unwrap();
+        if !golden_dir.join(name).exists() {
+            report.failures.push(format!("{}: no golden image", name.to_string_lossy()));
+        }
+    }
+
+    let golden_log = golden_dir.join(CAMERA_LOG);
+    if golden_log.exists() {
+        report.checked += 1;
+        let read = |p: &Path| std::fs::read_to_string(p).map_err(|e| format!("{}: {}", p.display(), e));
+        let mismatches = compare_camera_logs(&read(&out_dir.join(CAMERA_LOG))?, &read(&golden_log)?, 1e-3);
+        report.failures.extend(mismatches);
+    }
+    Ok(report)
+}
+
+/// Replace the goldens in `golden_dir` with the screenshots and camera log
+/// of `out_dir`. Returns the number of files written.
+pub fn update_golden(out_dir: &Path, golden_dir: &Path) -> Result<usize, String> {
+    std::fs::create_dir_all(golden_dir).map_err(|e| format!("{}: {}", golden_dir.display(), e))?;
+    for stale in screenshots(golden_dir)? {
+        std::fs::remove_file(&stale).map_err(|e| format!("{}: {}", stale.display(), e))?;
+    }
+    let mut files = screenshots(out_dir)?;
+    files.push(out_dir.join(CAMERA_LOG));
+    let mut written = 0;
+    for src in files.iter().filter(|p| p.exists()) {
+        let dst = golden_dir.join(src.file_name().unwrap());
+        std::fs::copy(src, &dst).map_err(|e| format!("{} -> {}: {}", src.display(), dst.display(), e))?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filled(w: u32, h: u32, c: [u8; 4]) -> RgbaImage {
+        RgbaImage::from_pixel(w, h, Rgba(c))
+    }
+
+    #[test]
+    fn small_shading_changes_pass_and_colour_changes_fail() {
+        let expected = filled(4, 4, [100, 120, 140, 255]);
+        let mut actual = expected.clone();
+        actual.put_pixel(0, 0, Rgba([102, 121, 139, 255]));
+        assert_eq!(diff_images(&actual, &expected, 0.1).unwrap().diff_pixels, 0);
+
+        actual.put_pixel(1, 1, Rgba([220, 30, 30, 255]));
+        let diff = diff_images(&actual, &expected, 0.1).unwrap();
+        assert_eq!(diff.diff_pixels, 1);
+        assert_eq!(*diff.image.get_pixel(1, 1), Rgba([255, 0, 0, 255]));
+        assert_ne!(*diff.image.get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+        assert!((diff.ratio() - 1.0 / 16.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn size_mismatch_is_an_error() {
+        assert!(diff_images(&filled(4, 4, [0; 4]), &filled(4, 3, [0; 4]), 0.1).is_err());
+    }
+
+    #[test]
+    fn camera_logs_ignore_time_and_catch_changed_fields() {
+        let golden = r#"{"t":1.000,"event":"KeyQ","angle_x":-55,"eye":[1.0,2.0,3.0]}"#;
+        let same = r#"{"t":7.250,"event":"KeyQ","angle_x":-55,"eye":[1.0,2.0,3.0004]}"#;
+        assert!(compare_camera_logs(same, golden, 1e-3).is_empty());
+
+        let moved = r#"{"t":1.000,"event":"KeyQ","angle_x":-60,"eye":[1.0,2.0,3.0]}"#;
+        let mismatches = compare_camera_logs(moved, golden, 1e-3);
+        assert_eq!(mismatches.len(), 1);
+        assert!(mismatches[0].contains("angle_x"));
+
+        let extra = format!("{}\n{}", golden, golden);
+        assert_eq!(compare_camera_logs(&extra, golden, 1e-3).len(), 1);
+    }
+}
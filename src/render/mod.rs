@@ -12,3 +12,5 @@ pub mod hud;
 pub mod gpu;
 pub mod geometry;
 pub mod app;
+pub mod software;
+pub mod golden;
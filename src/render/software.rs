@@ -0,0 +1,558 @@
+use std::path::Path;
+
+use cgmath::{InnerSpace, Matrix4, Vector2, Vector3, Vector4};
+use image::RgbaImage;
+
+use crate::data::animation::{AnimationsData, AnimationSequence, SHAMAN_ANIMS, UNIT_IDLE_ANIMS, UNIT_MULTI_ANIMS,
+                             build_direct_multi_anim_atlas, build_multi_anim_atlas};
+use crate::data::constants::PERSON_SUBTYPE_SHAMAN;
+use crate::data::landscape::{draw_texture_u8, make_texture_land};
+use crate::data::level::LevelRes;
+use crate::data::objects::Object3D;
+use crate::data::psfb::ContainerPSFB;
+use crate::data::types::BinDeserializer;
+use crate::data::units::ModelType;
+use crate::render::buildings::{building_atlas_rgba, building_mesh};
+use crate::render::camera::{Camera, Screen, MVP};
+use crate::render::sprites::{UnitRenderData, convert_palette, extract_all_unit_cells, extract_level_objects,
+                             rgb_to_rgba, spawn_mesh, tribe_facing_direction, LevelObject,
+                             DEFAULT_SPRITE_SCALE, DEFAULT_SPRITE_Z_OFFSET, SpriteSheet};
+use crate::render::terrain::{LandscapeMesh, LANDSCAPE_OFFSET, LANDSCAPE_SCALE};
+use crate::render::tex_model::{TexModel, TexVertex};
+
+/******************************************************************************/
+// Software renderer — draws a level view into an RGBA image without a GPU.
+//
+// Geometry is what the wgpu pipelines get: the landscape grid (as in
+// landscape_full.wgsl), building_mesh and spawn_mesh, all in landscape model
+// space under projection * view * (LANDSCAPE_SCALE, LANDSCAPE_OFFSET).
+// Shading follows landscape_full.wgsl, objects_tex.wgsl and shaman_sprite.wgsl with
+// nearest-texel sampling; shadow mapping, the sky and the water animation
+// are left out.
+
+/// Background where no geometry is drawn.
+pub const CLEAR_COLOR: [u8; 4] = [0, 0, 0, 255];
+/// Ambient term of the building and unit lighting (as in App::render).
+const BUILDING_AMBIENT: f32 = 0.35;
+/// Near clip distance in clip-space w.
+const NEAR_W: f32 = 0.1;
+/// Light position the app starts with when --light is not given.
+pub const DEFAULT_SUNLIGHT: (f32, f32) = (0x93 as f32, 0x93 as f32);
+
+/// RGBA texture sampled with repeat addressing and nearest filtering.
+pub struct Texture {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u8>,
+}
+
+impl Texture {
+    pub fn new(width: usize, height: usize, data: Vec<u8>) -> Self {
+        Self { width, height, data }
+    }
+
+    fn sample(&self, u: f32, v: f32) -> [u8; 4] {
+        if self.width == 0 || self.height == 0 {
+            return [0, 0, 0, 0];
+        }
+        let x = ((u * self.width as f32).floor() as i64).rem_euclid(self.width as i64) as usize;
+        let y = ((v * self.height as f32).floor() as i64).rem_euclid(self.height as i64) as usize;
+        let off = (y * self.width + x) * 4;
+        [self.data[off], self.data[off + 1], self.data[off + 2], self.data[off + 3]]
+    }
+}
+
+/// Colour and depth targets.
+pub struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    color: Vec<u8>,
+    depth: Vec<f32>,
+}
+
+#[derive(Copy, Clone)]
+struct ClipVertex<const A: usize> {
+    pos: Vector4<f32>,
+    attrs: [f32; A],
+}
+
+impl<const A: usize> ClipVertex<A> {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        let mut attrs = self.attrs;
+        for (a, b) in attrs.iter_mut().zip(other.attrs) {
+            *a += (b - *a) * t;
+        }
+        Self { pos: self.pos + (other.pos - self.pos) * t, attrs }
+    }
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize, clear: [u8; 4]) -> Self {
+        let color = clear.iter().copied().cycle().take(width * height * 4).collect();
+        Self { width, height, color, depth: vec![f32::INFINITY; width * height] }
+    }
+
+    pub fn into_image(self) -> RgbaImage {
+        RgbaImage::from_raw(self.width as u32, self.height as u32, self.color).unwrap()
+    }
+
+    /// Clip a triangle against the near plane and rasterize it with a depth
+    /// test. `shade` gets perspective-correct attributes and returns the
+    /// pixel colour, or None to discard.
+    fn draw_triangle<const A: usize, F>(&mut self, tri: [ClipVertex<A>; 3], shade: &F)
+        where F: Fn(&[f32; A]) -> Option<[u8; 4]> {
+        let mut poly: Vec<ClipVertex<A>> = Vec::with_capacity(4);
+        for i in 0..3 {
+            let (a, b) = (&tri[i], &tri[(i + 1) % 3]);
+            let (a_in, b_in) = (a.pos.w >= NEAR_W, b.pos.w >= NEAR_W);
+            if a_in {
+                poly.push(*a);
+            }
+            if a_in != b_in {
+                poly.push(a.lerp(b, (NEAR_W - a.pos.w) / (b.pos.w - a.pos.w)));
+            }
+        }
+        for i in 1..poly.len().saturating_sub(1) {
+            self.raster([poly[0], poly[i], poly[i + 1]], shade);
+        }
+    }
+
+    fn raster<const A: usize, F>(&mut self, tri: [ClipVertex<A>; 3], shade: &F)
+        where F: Fn(&[f32; A]) -> Option<[u8; 4]> {
+        let (w, h) = (self.width as f32, self.height as f32);
+        // Screen position, depth and 1/w per vertex
+        let s: Vec<(f32, f32, f32, f32)> = tri.iter().map(|v| {
+            let inv_w = 1.0 / v.pos.w;
+            ((v.pos.x * inv_w * 0.5 + 0.5) * w, (0.5 - v.pos.y * inv_w * 0.5) * h, v.pos.z * inv_w, inv_w)
+        }).collect();
+        let area = (s[1].0 - s[0].0) * (s[2].1 - s[0].1) - (s[2].0 - s[0].0) * (s[1].1 - s[0].1);
+        if area.abs() < 1e-12 {
+            return;
+        }
+        let min_x = s.iter().map(|p| p.0).fold(f32::MAX, f32::min).floor().max(0.0) as usize;
+        let max_x = s.iter().map(|p| p.0).fold(f32::MIN, f32::max).ceil().min(w) as usize;
+        let min_y = s.iter().map(|p| p.1).fold(f32::MAX, f32::min).floor().max(0.0) as usize;
+        let max_y = s.iter().map(|p| p.1).fold(f32::MIN, f32::max).ceil().min(h) as usize;
+
+        for py in min_y..max_y {
+            for px in min_x..max_x {
+                let (x, y) = (px as f32 + 0.5, py as f32 + 0.5);
+                let edge = |a: usize, b: usize| (s[b].0 - s[a].0) * (y - s[a].1) - (s[b].1 - s[a].1) * (x - s[a].0);
+                let b0 = edge(1, 2) / area;
+                let b1 = edge(2, 0) / area;
+                let b2 = edge(0, 1) / area;
+                if b0 < 0.0 || b1 < 0.0 || b2 < 0.0 {
+                    continue;
+                }
+                let z = b0 * s[0].2 + b1 * s[1].2 + b2 * s[2].2;
+                let idx = py * self.width + px;
+                if z >= self.depth[idx] {
+                    continue;
+                }
+                let inv_w = b0 * s[0].3 + b1 * s[1].3 + b2 * s[2].3;
+                let mut attrs = [0.0f32; A];
+                for (k, a) in attrs.iter_mut().enumerate() {
+                    *a = (b0 * tri[0].attrs[k] * s[0].3 + b1 * tri[1].attrs[k] * s[1].3
+                          + b2 * tri[2].attrs[k] * s[2].3) / inv_w;
+                }
+                if let Some(c) = shade(&attrs) {
+                    self.depth[idx] = z;
+                    self.color[idx * 4..idx * 4 + 4].copy_from_slice(&c);
+                }
+            }
+        }
+    }
+}
+
+fn scale_rgb(c: [u8; 4], k: f32) -> [u8; 4] {
+    let s = |v: u8| (v as f32 * k).round().clamp(0.0, 255.0) as u8;
+    [s(c[0]), s(c[1]), s(c[2]), 255]
+}
+
+/// Viewport fade of the shaders: 1 inside `start`, 0 beyond `end`.
+fn fade(dist: f32, start: f32, end: f32) -> f32 {
+    ((end - dist) / (end - start)).clamp(0.0, 1.0)
+}
+
+fn mesh_triangles(mesh: &TexModel) -> Vec<[&TexVertex; 3]> {
+    if mesh.indices.is_empty() {
+        mesh.vertices.chunks_exact(3).map(|t| [&t[0], &t[1], &t[2]]).collect()
+    } else {
+        mesh.indices.chunks_exact(3)
+            .map(|t| [&mesh.vertices[t[0] as usize], &mesh.vertices[t[1] as usize], &mesh.vertices[t[2] as usize]])
+            .collect()
+    }
+}
+
+/******************************************************************************/
+
+/// One unit type's billboards and the atlas they sample.
+pub struct SpriteLayer<'a> {
+    pub mesh: TexModel,
+    pub atlas: &'a Texture,
+}
+
+/// Everything drawn by `render_scene`.
+pub struct SoftwareScene<'a> {
+    pub landscape: &'a LandscapeMesh<128>,
+    /// Full CPU land texture (texture_land), 32 texels per cell.
+    pub land_texture: &'a Texture,
+    pub curvature_scale: f32,
+    /// Building/scenery mesh and the atlas from `building_atlas_rgba`.
+    pub buildings: Option<(&'a TexModel, &'a Texture)>,
+    pub sprites: &'a [SpriteLayer<'a>],
+    /// Normalised direction towards the sun; None draws buildings unlit.
+    pub sun_dir: Option<Vector3<f32>>,
+}
+
+/// Projection * view * landscape model transform for the orbit camera,
+/// as the app computes it for `screen`, `camera` and `zoom`.
+pub fn scene_pvm(screen: &Screen, camera: &Camera, zoom: f32, landscape: &LandscapeMesh<128>) -> Matrix4<f32> {
+    let center = landscape.world_center();
+    let focus = Vector3::new(center, center, 0.0);
+    let min_z = landscape.camera_min_z(camera, zoom);
+    let mvp = MVP::with_zoom(screen, camera, zoom, focus, min_z);
+    let model_transform = Matrix4::from_translation(Vector3::new(LANDSCAPE_OFFSET, LANDSCAPE_OFFSET, 0.0))
+        * Matrix4::from_scale(LANDSCAPE_SCALE);
+    mvp.projection * mvp.view * mvp.transform * model_transform
+}
+
+/// Direction towards the sun for the light position (x, y), as App::render
+/// feeds objects_tex.wgsl.
+pub fn sun_direction(sun_x: f32, sun_y: f32) -> Vector3<f32> {
+    Vector3::new(-sun_x, -sun_y, 200.0).normalize()
+}
+
+pub fn render_scene(scene: &SoftwareScene, pvm: &Matrix4<f32>, width: usize, height: usize) -> RgbaImage {
+    let mut fb = Framebuffer::new(width, height, CLEAR_COLOR);
+    draw_landscape(&mut fb, scene, pvm);
+    if let Some((mesh, atlas)) = scene.buildings {
+        draw_buildings(&mut fb, scene, pvm, mesh, atlas);
+    }
+    for layer in scene.sprites {
+        draw_sprites(&mut fb, scene, pvm, layer);
+    }
+    fb.into_image()
+}
+
+fn draw_landscape(fb: &mut Framebuffer, scene: &SoftwareScene, pvm: &Matrix4<f32>) {
+    let mesh = scene.landscape;
+    let n = mesh.width();
+    let step = mesh.step();
+    let shift = mesh.get_shift_vector();
+    let center = (n - 1) as f32 * step / 2.0;
+    let radius = center * 0.9;
+    let vertex = |x: usize, y: usize| -> ClipVertex<3> {
+        let (gx, gy) = (x as f32 * step, y as f32 * step);
+        let h = mesh.height_at(x + shift.x as usize, y + shift.y as usize) as f32 * mesh.height_scale();
+        let (dx, dy) = (gx - center, gy - center);
+        let dist_sq = dx * dx + dy * dy;
+        let pos = pvm * Vector4::new(gx, gy, h - dist_sq * scene.curvature_scale, 1.0);
+        let u = (x as f32 + shift.x as f32) / n as f32;
+        let v = (y as f32 + shift.y as f32) / n as f32;
+        ClipVertex { pos, attrs: [u, v, fade(dist_sq.sqrt(), radius * 0.85, radius)] }
+    };
+    let texture = scene.land_texture;
+    let shade = |a: &[f32; 3]| -> Option<[u8; 4]> {
+        if a[2] < 0.01 {
+            return None;
+        }
+        Some(scale_rgb(texture.sample(a[0], a[1]), a[2]))
+    };
+    // Same split as LandscapeMesh::gen_mesh
+    for i in 0..n - 1 {
+        for j in 0..n - 1 {
+            let (v00, v01, v10, v11) = (vertex(i, j), vertex(i, j + 1), vertex(i + 1, j), vertex(i + 1, j + 1));
+            fb.draw_triangle([v00, v01, v10], &shade);
+            fb.draw_triangle([v11, v01, v10], &shade);
+        }
+    }
+}
+
+fn draw_buildings(fb: &mut Framebuffer, scene: &SoftwareScene, pvm: &Matrix4<f32>, mesh: &TexModel, atlas: &Texture) {
+    let n = scene.landscape.width();
+    let center = (n - 1) as f32 * scene.landscape.step() / 2.0;
+    let radius = center * 0.9;
+    for tri in mesh_triangles(mesh) {
+        let clip: Vec<ClipVertex<3>> = tri.iter().map(|v| {
+            let (dx, dy) = (v.coord.x - center, v.coord.y - center);
+            let dist = (dx * dx + dy * dy).sqrt();
+            ClipVertex { pos: pvm * v.coord.extend(1.0), attrs: [v.uv.x, v.uv.y, fade(dist, radius * 1.3, radius * 1.5)] }
+        }).collect();
+
+        // objects_tex.wgsl takes cross(dpdx, dpdy) of the world position: the
+        // face normal, flipped when the triangle is wound clockwise on screen.
+        let brightness = match scene.sun_dir {
+            Some(sun) => {
+                let s: Vec<Vector2<f32>> = clip.iter().map(|c| Vector2::new(c.pos.x / c.pos.w, -c.pos.y / c.pos.w)).collect();
+                let det = (s[1].x - s[0].x) * (s[2].y - s[0].y) - (s[2].x - s[0].x) * (s[1].y - s[0].y);
+                let normal = (tri[1].coord - tri[0].coord).cross(tri[2].coord - tri[0].coord) * det.signum();
+                let ndotl = if normal.magnitude2() > 0.0 { normal.normalize().dot(sun).max(0.0) } else { 0.0 };
+                BUILDING_AMBIENT + (1.0 - BUILDING_AMBIENT) * ndotl
+            }
+            None => 1.0,
+        };
+        let tex_id = tri[0].tex_id;
+        let shade = |a: &[f32; 3]| -> Option<[u8; 4]> {
+            if a[2] < 0.01 {
+                return None;
+            }
+            if !(0..=255).contains(&tex_id) {
+                let grey = (0.6 * 255.0) as u8;
+                return Some(scale_rgb([grey, grey, grey, 255], brightness * a[2]));
+            }
+            let (column, row) = ((tex_id % 8) as f32, (tex_id / 8) as f32);
+            let c = atlas.sample((column + a[0]) / 8.0, (row + a[1]) / 32.0);
+            if c[3] > 0 {
+                return None;
+            }
+            Some(scale_rgb(c, brightness * a[2]))
+        };
+        fb.draw_triangle([clip[0], clip[1], clip[2]], &shade);
+    }
+}
+
+fn draw_sprites(fb: &mut Framebuffer, scene: &SoftwareScene, pvm: &Matrix4<f32>, layer: &SpriteLayer) {
+    let center = (scene.landscape.width() - 1) as f32 * scene.landscape.step() / 2.0;
+    let radius = center * 0.9;
+    let brightness = scene.sun_dir.map_or(1.0, |sun| BUILDING_AMBIENT + (1.0 - BUILDING_AMBIENT) * sun.z.max(0.0));
+    let shade = |a: &[f32; 3]| -> Option<[u8; 4]> {
+        if a[2] < 0.01 {
+            return None;
+        }
+        let c = layer.atlas.sample(a[0], a[1]);
+        if c[3] < 128 { None } else { Some(scale_rgb(c, brightness * a[2])) }
+    };
+    for tri in mesh_triangles(&layer.mesh) {
+        let clip = tri.map(|v| {
+            let (dx, dy) = (v.coord.x - center, v.coord.y - center);
+            let dist = (dx * dx + dy * dy).sqrt();
+            ClipVertex { pos: pvm * v.coord.extend(1.0), attrs: [v.uv.x, v.uv.y, fade(dist, radius * 0.85, radius)] }
+        });
+        fb.draw_triangle(clip, &shade);
+    }
+}
+
+/******************************************************************************/
+
+/// Sprite atlas of one person subtype, laid out as for `spawn_mesh`.
+pub struct UnitAtlas {
+    pub subtype: u8,
+    pub atlas: Texture,
+    pub frame_width: u32,
+    pub frame_height: u32,
+    pub frames_per_dir: u32,
+    pub anim_offsets: Vec<(u16, u32, u32)>,
+}
+
+/// Textures a level is drawn with: the land texture, the BL320 building
+/// atlas and the unit atlases (empty without HSPR0-0.DAT).
+pub struct LevelTextures {
+    pub land: Texture,
+    pub buildings: Texture,
+    pub units: Vec<UnitAtlas>,
+}
+
+/// Level state to draw. `units` holds the persons of each subtype.
+pub struct LevelView<'a> {
+    pub landscape: &'a LandscapeMesh<128>,
+    pub objects: &'a [LevelObject],
+    pub building_bank: &'a [Option<Object3D>],
+    pub scenery_bank: &'a [Option<Object3D>],
+    pub units: &'a [(u8, Vec<UnitRenderData>)],
+    pub curvature_scale: f32,
+    pub sun_dir: Option<Vector3<f32>>,
+}
+
+impl LevelTextures {
+    pub fn load(base: &Path, level_res: &LevelRes) -> Self {
+        let size = level_res.landscape.land_size() * 32;
+        let land_rgb = draw_texture_u8(&level_res.params.palette, size, &make_texture_land(level_res, None));
+        let (aw, ah, atlas) = building_atlas_rgba(&level_res.paths.bl320, &level_res.params.palette);
+        Self {
+            land: Texture::new(size, size, rgb_to_rgba(&land_rgb)),
+            buildings: Texture::new(aw, ah, atlas),
+            units: load_unit_atlases(base, &level_res.params.palette),
+        }
+    }
+
+    pub fn render(&self, view: &LevelView, screen: &Screen, camera: &Camera, zoom: f32) -> RgbaImage {
+        let buildings = building_mesh(view.objects, view.building_bank, view.scenery_bank,
+                                      view.landscape, view.curvature_scale);
+        let sprites: Vec<SpriteLayer> = view.units.iter().filter_map(|(subtype, cells)| {
+            let u = self.units.iter().find(|u| u.subtype == *subtype)?;
+            Some(SpriteLayer {
+                mesh: spawn_mesh(cells, view.landscape, view.curvature_scale, camera.angle_x, camera.angle_z,
+                                 &SpriteSheet {
+                                     frame_w: u.frame_width, frame_h: u.frame_height,
+                                     frames_per_dir: u.frames_per_dir, anim_offsets: &u.anim_offsets,
+                                     z_offset: DEFAULT_SPRITE_Z_OFFSET, scale: DEFAULT_SPRITE_SCALE,
+                                 }),
+                atlas: &u.atlas,
+            })
+        }).collect();
+        let scene = SoftwareScene {
+            landscape: view.landscape,
+            land_texture: &self.land,
+            curvature_scale: view.curvature_scale,
+            buildings: Some((&buildings, &self.buildings)),
+            sprites: &sprites,
+            sun_dir: view.sun_dir,
+        };
+        let pvm = scene_pvm(screen, camera, zoom, view.landscape);
+        render_scene(&scene, &pvm, screen.width as usize, screen.height as usize)
+    }
+}
+
+/// Atlases for the shaman and every subtype in UNIT_MULTI_ANIMS, as
+/// App::rebuild_unit_atlases builds them.
+pub fn load_unit_atlases(base: &Path, raw_palette: &[u8]) -> Vec<UnitAtlas> {
+    let hspr_path = base.join("data").join("HSPR0-0.DAT");
+    let Some(container) = hspr_path.exists().then(|| ContainerPSFB::from_file(&hspr_path)).flatten() else {
+        return Vec::new();
+    };
+    let palette = convert_palette(raw_palette);
+    let sequences = AnimationSequence::from_data(&AnimationsData::from_path(&base.join("data")));
+    let shaman = build_direct_multi_anim_atlas(&container, &palette, &SHAMAN_ANIMS)
+        .map(|atlas| (PERSON_SUBTYPE_SHAMAN, atlas));
+    let others = UNIT_MULTI_ANIMS.iter().filter_map(|(subtype, anims)| {
+        build_multi_anim_atlas(&sequences, &container, &palette, anims).map(|atlas| (*subtype, atlas))
+    });
+    shaman.into_iter().chain(others)
+        .map(|(subtype, (aw, ah, rgba, fw, fh, total_cols, offsets, _))| UnitAtlas {
+            subtype,
+            atlas: Texture::new(aw as usize, ah as usize, rgba),
+            frame_width: fw,
+            frame_height: fh,
+            frames_per_dir: total_cols,
+            anim_offsets: offsets.iter().map(|(id, off, fc)| (*id as u16, *off, *fc)).collect(),
+        })
+        .collect()
+}
+
+/// A level loaded for software rendering: terrain, buildings and the persons
+/// of the level standing idle, facing their tribe direction. Terrain is not
+/// flattened under buildings.
+pub struct LevelScene {
+    pub landscape: LandscapeMesh<128>,
+    pub objects: Vec<LevelObject>,
+    building_bank: Vec<Option<Object3D>>,
+    scenery_bank: Vec<Option<Object3D>>,
+    textures: LevelTextures,
+    units: Vec<(u8, Vec<UnitRenderData>)>,
+    pub sunlight: (f32, f32),
+}
+
+impl LevelScene {
+    pub fn load(base: &Path, level_num: u8, level_type: Option<&str>) -> Self {
+        let level_res = LevelRes::new(base, level_num, level_type);
+        let mut landscape = LandscapeMesh::new(1.0 / 16.0, (1.0 / 16.0) * 4.0 / 1024.0);
+        landscape.set_heights(&level_res.landscape.make_shores().height);
+
+        let (building_bank, scenery_bank) = Object3D::load_dual_banks(base, level_res.obj_bank);
+        let objects: Vec<LevelObject> = extract_level_objects(&level_res).into_iter()
+            .filter(|obj| obj.model_type != ModelType::Person)
+            .collect();
+        let units: Vec<(u8, Vec<UnitRenderData>)> = extract_all_unit_cells(&level_res).into_iter()
+            .map(|(subtype, cells)| {
+                let idle = if subtype == PERSON_SUBTYPE_SHAMAN {
+                    SHAMAN_ANIMS[0].0
+                } else {
+                    UNIT_IDLE_ANIMS.iter().find(|(st, _)| *st == subtype).map_or(0, |(_, id)| *id as u16)
+                };
+                let cells = cells.into_iter().map(|(cell_x, cell_y, tribe_index)| UnitRenderData {
+                    cell_x, cell_y, tribe_index,
+                    facing_angle: tribe_facing_direction(tribe_index),
+                    frame_index: 0,
+                    animation_id: idle,
+                }).collect();
+                (subtype, cells)
+            })
+            .collect();
+        let textures = LevelTextures::load(base, &level_res);
+        log::info!("[software] level {} loaded: {} objects, {} unit atlases",
+                   level_num, objects.len(), textures.units.len());
+        Self {
+            landscape, objects, building_bank, scenery_bank, textures, units,
+            sunlight: DEFAULT_SUNLIGHT,
+        }
+    }
+
+    /// Shift the terrain so the first tribe 0 shaman is at the view centre,
+    /// like the app does after loading a level.
+    pub fn center_on_tribe0_shaman(&mut self) {
+        let n = self.landscape.width() as i32;
+        let v = ((n - 1) as f32 / 2.0) as i32;
+        let shaman = self.units.iter()
+            .find(|(subtype, _)| *subtype == PERSON_SUBTYPE_SHAMAN)
+            .and_then(|(_, cells)| cells.iter().find(|c| c.tribe_index == 0));
+        if let Some(c) = shaman {
+            let (cx, cy) = (c.cell_x as i32, c.cell_y as i32);
+            self.landscape.set_shift(((cx - v) % n + n) as usize, ((cy - v) % n + n) as usize);
+        }
+    }
+
+    pub fn render(&self, screen: &Screen, camera: &Camera, zoom: f32, curvature_scale: f32, lighting: bool) -> RgbaImage {
+        let view = LevelView {
+            landscape: &self.landscape,
+            objects: &self.objects,
+            building_bank: &self.building_bank,
+            scenery_bank: &self.scenery_bank,
+            units: &self.units,
+            curvature_scale,
+            sun_dir: lighting.then(|| sun_direction(self.sunlight.0, self.sunlight.1)),
+        };
+        self.textures.render(&view, screen, camera, zoom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat_scene_image(texture: &Texture, landscape: &LandscapeMesh<128>, camera: &Camera, zoom: f32) -> RgbaImage {
+        let scene = SoftwareScene {
+            landscape, land_texture: texture, curvature_scale: 0.0, buildings: None, sprites: &[], sun_dir: None,
+        };
+        let screen = Screen { width: 64, height: 48 };
+        render_scene(&scene, &scene_pvm(&screen, camera, zoom, landscape), 64, 48)
+    }
+
+    #[test]
+    fn depth_test_keeps_nearest_triangle() {
+        let mut fb = Framebuffer::new(8, 8, CLEAR_COLOR);
+        let quad = |z: f32| -> [ClipVertex<1>; 3] {
+            [Vector4::new(-1.0, -1.0, z, 1.0), Vector4::new(3.0, -1.0, z, 1.0), Vector4::new(-1.0, 3.0, z, 1.0)]
+                .map(|pos| ClipVertex { pos, attrs: [z] })
+        };
+        let shade = |a: &[f32; 1]| Some(if a[0] < 0.0 { [255, 0, 0, 255] } else { [0, 0, 255, 255] });
+        fb.draw_triangle(quad(-0.5), &shade);
+        fb.draw_triangle(quad(0.5), &shade);
+        let img = fb.into_image();
+        assert!(img.pixels().all(|p| p.0 == [255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn triangles_behind_the_camera_are_clipped() {
+        let mut fb = Framebuffer::new(8, 8, CLEAR_COLOR);
+        let tri = [Vector4::new(0.0, 0.0, 0.5, -1.0), Vector4::new(1.0, 0.0, 0.5, -1.0), Vector4::new(0.0, 1.0, 0.5, -2.0)]
+            .map(|pos| ClipVertex { pos, attrs: [] });
+        fb.draw_triangle(tri, &|_: &[f32; 0]| Some([255, 255, 255, 255]));
+        assert!(fb.into_image().pixels().all(|p| p.0 == CLEAR_COLOR));
+    }
+
+    #[test]
+    fn landscape_fills_view_with_texture_and_fades_at_edge() {
+        let texture = Texture::new(1, 1, vec![200, 100, 50, 255]);
+        let landscape = LandscapeMesh::<128>::new(1.0 / 16.0, (1.0 / 16.0) * 4.0 / 1024.0);
+        let camera = Camera { angle_x: -55, angle_y: 0, angle_z: 0, pos: Vector3::new(0.0, 0.0, 0.0) };
+        let img = flat_scene_image(&texture, &landscape, &camera, 1.0);
+        // Zoomed in, the view is all terrain at full brightness.
+        assert!(img.pixels().all(|p| p.0 == [200, 100, 50, 255]));
+
+        // Zoomed out, the terrain beyond the viewport radius is left clear.
+        let img = flat_scene_image(&texture, &landscape, &camera, 0.1);
+        assert_eq!(img.get_pixel(32, 24).0, [200, 100, 50, 255]);
+        assert_eq!(img.get_pixel(32, 0).0, CLEAR_COLOR);
+    }
+}
@@ -12,10 +12,15 @@ use crate::data::units::{ModelType, object_3d_index};
 use crate::data::animation::{NUM_TRIBES, STORED_DIRECTIONS};
 use crate::engine::state::constants::*;
 
-use crate::engine::units::{UnitCoordinator, Unit};
+use crate::engine::units::{UnitCoordinator, Unit, Effect};
 
 /******************************************************************************/
 
+/// Height of unit billboards above the terrain.
+pub const DEFAULT_SPRITE_Z_OFFSET: f32 = 0.005;
+/// Size of unit billboards relative to the sprite frame.
+pub const DEFAULT_SPRITE_SCALE: f32 = 0.65;
+
 pub fn obj_colors() -> Vec<Vector3<u8>> {
     vec![ Vector3{x: 255, y: 0, z: 0}
         , Vector3{x: 128, y: 0, z: 128}
@@ -169,7 +174,10 @@ pub fn extract_all_unit_cells(level_res: &LevelRes) -> Vec<(u8, Vec<(f32, f32, u
     result
 }
 
+#[derive(Clone)]
 pub struct LevelObject {
+    /// Index of the object in the level's object list.
+    pub level_index: usize,
     pub cell_x: f32,
     pub cell_y: f32,
     pub model_type: ModelType,
@@ -182,7 +190,7 @@ pub struct LevelObject {
 pub fn extract_level_objects(level_res: &LevelRes) -> Vec<LevelObject> {
     let n = level_res.landscape.land_size() as f32;
     let mut objects = Vec::new();
-    for unit in &level_res.units {
+    for (level_index, unit) in level_res.units.iter().enumerate() {
         let model_type = match unit.model_type() {
             Some(mt) if mt.is_visible() => mt,
             _ => continue,
@@ -198,6 +206,7 @@ pub fn extract_level_objects(level_res: &LevelRes) -> Vec<LevelObject> {
             model_type, unit.subtype, unit.tribe_index(), unit.angle(),
             unit.loc_x(), unit.loc_y());
         objects.push(LevelObject {
+            level_index,
             cell_x,
             cell_y,
             model_type,
@@ -244,16 +253,39 @@ pub struct UnitTypeRender {
 /******************************************************************************/
 // GPU billboard builders
 
+/// How a unit type's billboards are cut from its atlas and placed.
+pub struct SpriteSheet<'a> {
+    pub frame_w: u32,
+    pub frame_h: u32,
+    /// Total columns in the atlas.
+    pub frames_per_dir: u32,
+    /// Maps animation_id → (column_offset, frame_count) within the atlas.
+    pub anim_offsets: &'a [(u16, u32, u32)],
+    /// Height of the billboards above the terrain.
+    pub z_offset: f32,
+    /// Size of the billboards relative to the sprite frame.
+    pub scale: f32,
+}
+
 /// Build camera-facing billboard quads for unit sprites.
 /// Each unit gets a single quad (6 vertices) oriented to face the camera.
-/// `frames_per_dir` and `frame_w`/`frame_h` come from the unit type's atlas.
+/// `sheet` describes the unit type's atlas.
 pub fn build_spawn_model(device: &wgpu::Device, cells: &[UnitRenderData],
                      landscape: &LandscapeMesh<128>, curvature_scale: f32,
-                     angle_x: i16, angle_z: i16,
-                     frame_w: u32, frame_h: u32, frames_per_dir: u32,
-                     anim_offsets: &[(u16, u32, u32)],
-                     sprite_z_offset: f32, sprite_scale: f32,
+                     angle_x: i16, angle_z: i16, sheet: &SpriteSheet,
 ) -> ModelEnvelop<TexModel> {
+    let model = spawn_mesh(cells, landscape, curvature_scale, angle_x, angle_z, sheet);
+    let m = vec![(RenderType::Triangles, model)];
+    ModelEnvelop::<TexModel>::new(device, m)
+}
+
+/// Billboard quads of `build_spawn_model` in landscape model space; `tex_id`
+/// carries the tribe, UVs point into the unit type's atlas.
+pub fn spawn_mesh(cells: &[UnitRenderData],
+                  landscape: &LandscapeMesh<128>, curvature_scale: f32,
+                  angle_x: i16, angle_z: i16, sheet: &SpriteSheet,
+) -> TexModel {
+    let SpriteSheet { frame_w, frame_h, frames_per_dir, anim_offsets, z_offset: sprite_z_offset, scale: sprite_scale } = *sheet;
     let mut model: TexModel = MeshModel::new();
     let step = landscape.step();
 
@@ -348,8 +380,7 @@ pub fn build_spawn_model(device: &wgpu::Device, cells: &[UnitRenderData],
         model.push_vertex(v(tr, u_right, v_top));
         model.push_vertex(v(tl, u_left,  v_top));
     }
-    let m = vec![(RenderType::Triangles, model)];
-    ModelEnvelop::<TexModel>::new(device, m)
+    model
 }
 
 pub fn build_object_markers(
@@ -503,6 +534,82 @@ pub fn build_unit_markers(
     Some(ModelEnvelop::<ColorModel>::new(device, m))
 }
 
+/// Build camera-facing billboards for live visual effects. Placeholder: each
+/// effect is a flat-coloured quad (units::effect_placeholder), not the
+/// original's sprite sequence; logic-only effects draw nothing.
+pub fn build_effect_billboards(
+    device: &wgpu::Device, effects: &[Effect],
+    landscape: &LandscapeMesh<128>, curvature_scale: f32,
+    angle_x: i16, angle_z: i16,
+) -> Option<ModelEnvelop<ColorModel>> {
+    let mut model: ColorModel = MeshModel::new();
+    let step = landscape.step();
+
+    let w = landscape.width() as f32;
+    let shift = landscape.get_shift_vector();
+    let center = (w - 1.0) * step / 2.0;
+
+    let az = (angle_z as f32).to_radians();
+    let ax = (angle_x as f32).to_radians();
+    let eye = Point3::new(
+        center + ax.cos() * az.sin(),
+        center + ax.cos() * az.cos(),
+        -ax.sin(),
+    );
+    let target = Point3::new(center, center, 0.0);
+    let view = Matrix4::look_at_rh(eye, target, Vector3::new(0.0, 0.0, 1.0));
+    let right = Vector3::new(view.x.x, view.y.x, view.z.x);
+    let up = Vector3::new(view.x.y, view.y.y, view.z.y);
+
+    for effect in effects {
+        let (visual, color_rgb) = match (effect.kind.placeholder_visual(), effect.color()) {
+            (Some(v), Some(c)) => (v, c),
+            _ => continue,
+        };
+        let vis_x = ((effect.cell_x - shift.x as f32) % w + w) % w;
+        let vis_y = ((effect.cell_y - shift.y as f32) % w + w) % w;
+        let gx = vis_x * step;
+        let gy = vis_y * step;
+
+        let gz = landscape.interpolate_height_at(effect.cell_x, effect.cell_y);
+
+        let dx = gx - center;
+        let dy = gy - center;
+        let curvature_offset = (dx * dx + dy * dy) * curvature_scale;
+        // Effect height is in world units; 0x200 world units per cell
+        let lift = effect.height as f32 / 512.0 * step;
+        let z_base = gz - curvature_offset + 0.001 + lift;
+
+        let t = effect.progress();
+        let pulse = 1.0 + 0.1 * ((effect.frame_index() % 2) as f32);
+        let size = step * visual.size * (1.0 + (visual.grow - 1.0) * t) * pulse;
+        let half_w = size / 2.0;
+
+        let color = Vector3::new(
+            color_rgb[0] as f32 / 255.0,
+            color_rgb[1] as f32 / 255.0,
+            color_rgb[2] as f32 / 255.0,
+        );
+
+        let base_pos = Vector3::new(gx, gy, z_base);
+        let bl = base_pos - right * half_w;
+        let br = base_pos + right * half_w;
+        let tl = bl + up * size;
+        let tr = br + up * size;
+
+        let v = |p: Vector3<f32>| ColorVertex { coord: p, color };
+        model.push_vertex(v(bl));
+        model.push_vertex(v(br));
+        model.push_vertex(v(tr));
+        model.push_vertex(v(bl));
+        model.push_vertex(v(tr));
+        model.push_vertex(v(tl));
+    }
+    if model.vertices.is_empty() { return None; }
+    let m = vec![(RenderType::Triangles, model)];
+    Some(ModelEnvelop::<ColorModel>::new(device, m))
+}
+
 /// Build a billboard-shaped outline (wireframe quad) around each selected unit.
 /// Uses the same billboard geometry as `build_unit_markers` so the outline
 /// matches exactly what gets picked.
@@ -4,6 +4,7 @@ use std::slice::Chunks;
 
 use cgmath::{Vector4, Vector3, Vector2};
 
+use crate::render::camera::Camera;
 use crate::render::model::{Triangle, VertexModel, MeshModel};
 use crate::render::envelop::{GpuModel, ModelEnvelop, RenderType};
 use crate::data::objects::{Shape, ShapeFootprints};
@@ -374,6 +375,30 @@ impl<const N: usize> LandscapeMesh<N> {
         Vector4::new(self.shift_x as i32, self.shift_y as i32, 0, 0)
     }
 
+    /// World-space center of the terrain (after the landscape model transform).
+    pub fn world_center(&self) -> f32 {
+        let center_model = (N - 1) as f32 * self.step / 2.0;
+        LANDSCAPE_SCALE * center_model + LANDSCAPE_OFFSET
+    }
+
+    /// Lowest eye height for the orbit camera: just above the terrain under it.
+    pub fn camera_min_z(&self, camera: &Camera, zoom: f32) -> f32 {
+        let center = self.world_center();
+        let az = (camera.angle_z as f32).to_radians();
+        let ax = (camera.angle_x as f32).to_radians();
+        let radius = 1.5 / zoom;
+        let eye_x = center + radius * ax.cos() * az.sin();
+        let eye_y = center + radius * ax.cos() * az.cos();
+        // Convert world-space eye position back to grid coords for height lookup
+        let model_x = (eye_x - LANDSCAPE_OFFSET) / LANDSCAPE_SCALE;
+        let model_y = (eye_y - LANDSCAPE_OFFSET) / LANDSCAPE_SCALE;
+        let gx = (model_x / self.step).clamp(0.0, (N - 1) as f32) as usize;
+        let gy = (model_y / self.step).clamp(0.0, (N - 1) as f32) as usize;
+        let sx = (gx + self.shift_x) % N;
+        let sy = (gy + self.shift_y) % N;
+        self.height_at(sx, sy) as f32 * self.height_scale + 0.05
+    }
+
     pub fn to_model(&self, m: &mut LandscapeModel) {
         for v2 in &self.vertices {
             m.push_vertex(*v2);
@@ -428,6 +453,8 @@ impl<'a, const N: usize> Iterator for LandscapeTriangleIterator<'a, N> {
 /// Landscape model transform: world = LANDSCAPE_SCALE * model + LANDSCAPE_OFFSET.
 pub const LANDSCAPE_SCALE: f32 = 2.5;
 pub const LANDSCAPE_OFFSET: f32 = -2.0;
+/// Globe curvature the app starts with.
+pub const DEFAULT_CURVATURE_SCALE: f32 = 0.0512;
 
 /// Packed landscape uniform data matching the WGSL LandscapeParams struct.
 #[repr(C)]
@@ -0,0 +1,286 @@
+// Fixture-driven checks of the faithful reimplementations.
+//
+// Every tests/fixtures/*.json file holds cases for one function:
+//
+//   {
+//     "description": "...",
+//     "function": "atan2",              // handler name, see `handler`
+//     "binary_address": "0x00564074",   // original function
+//     "source": "frida_capture",        // or "regression", see below
+//     "cases": [
+//       { "label": "capture_1",
+//         "input": { "dx": 14271, "dy": -7871 },
+//         "expected": { "result": 348, "tolerance": 0 } }
+//     ]
+//   }
+//
+// "frida_capture" cases were recorded from popTB.exe at runtime.
+// "regression" expected values were produced by this crate's own
+// implementation. They are regression guards, not ground truth, and stay
+// that way until a capture replaces them.
+//
+// A handler turns `input` into a result; it must equal `expected.result`,
+// numbers within `expected.tolerance` (default 0). Object results only
+// compare the keys the fixture lists.
+
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use pop3::engine::movement::{
+    angle_difference, atan2, distance, move_point_by_angle, pathfind, route_table_lookup,
+    rotation_direction, FailureCache, PathfindResult, PersonMovement, RegionMap, RouteResult,
+    SegmentPool, TileCoord, WorldCoord,
+};
+use pop3::engine::movement::math::int_sqrt;
+use pop3::engine::state::flags::GameFlags;
+use pop3::engine::state::rng::GameRng;
+use pop3::engine::state::tribe::TribeArray;
+use pop3::engine::state::victory::check_victory_conditions;
+use pop3::engine::units::person_state::{calculate_melee_damage, PersonState};
+use pop3::engine::units::Unit;
+
+type Handler = fn(&Value) -> Result<Value, String>;
+
+/// Functions with fixtures that cannot be replayed here, and why.
+const SKIPPED: &[(&str, &str)] = &[
+    ("height_at", "needs the heightmap of the level the capture ran on"),
+];
+
+const SOURCES: &[&str] = &["frida_capture", "regression"];
+
+fn handler(function: &str) -> Option<Handler> {
+    Some(match function {
+        "atan2" => |i| Ok(json!(atan2(int(i, "dx")? as i32, int(i, "dy")? as i32))),
+        "distance" => |i| Ok(json!(distance(&coord(i, "p1")?, &coord(i, "p2")?))),
+        "int_sqrt" => |i| Ok(json!(int_sqrt(int(i, "n")? as u32))),
+        "move_point" => |i| {
+            let mut p = coord(i, "start")?;
+            move_point_by_angle(&mut p, int(i, "angle")? as u16, int(i, "distance")? as i16);
+            Ok(json!([p.x, p.z]))
+        },
+        "angle_difference" => |i| Ok(json!(angle_difference(int(i, "a")? as i16, int(i, "b")? as i16))),
+        "rotation_direction" => {
+            |i| Ok(json!(rotation_direction(int(i, "target")? as i16, int(i, "current")? as i16)))
+        }
+        "game_rng" => |i| {
+            let mut rng = GameRng::new(int(i, "seed")? as u32);
+            Ok(json!((0..int(i, "count")?).map(|_| rng.next()).collect::<Vec<_>>()))
+        },
+        "calculate_melee_damage" => melee_damage,
+        "pathfind" => pathfind_case,
+        "route_table_lookup" => route_lookup_case,
+        "check_victory_conditions" => victory_case,
+        _ => return None,
+    })
+}
+
+fn int(input: &Value, key: &str) -> Result<i64, String> {
+    input[key].as_i64().ok_or_else(|| format!("input.{} is not an integer", key))
+}
+
+fn flag(input: &Value, key: &str) -> bool {
+    input[key].as_bool().unwrap_or(false)
+}
+
+fn pair(value: &Value, what: &str) -> Result<(i64, i64), String> {
+    match value.as_array().map(|a| a.iter().map(Value::as_i64).collect::<Vec<_>>()).as_deref() {
+        Some([Some(a), Some(b)]) => Ok((*a, *b)),
+        _ => Err(format!("{} is not an [x, z] pair", what)),
+    }
+}
+
+fn coord(input: &Value, key: &str) -> Result<WorldCoord, String> {
+    let (x, z) = pair(&input[key], key)?;
+    Ok(WorldCoord::new(x as i16, z as i16))
+}
+
+fn tile(input: &Value, key: &str) -> Result<TileCoord, String> {
+    let (x, z) = pair(&input[key], key)?;
+    Ok(TileCoord::new(x as u8, z as u8))
+}
+
+/// Region map from `blocked` ([x0, z0, x1, z1] inclusive tile rectangles,
+/// unwalkable) and `regions` ([x, z, region] per tile).
+fn region_map(input: &Value) -> Result<RegionMap, String> {
+    const BLOCKED_CLASS: u8 = 1;
+    let mut map = RegionMap::new();
+    map.set_terrain_flags(BLOCKED_CLASS, 0x00);
+    for rect in input["blocked"].as_array().into_iter().flatten() {
+        let r: Vec<i64> = rect.as_array().into_iter().flatten().filter_map(Value::as_i64).collect();
+        let [x0, z0, x1, z1] = r[..] else { return Err("blocked entries are [x0, z0, x1, z1]".into()) };
+        for x in (x0..=x1).step_by(2) {
+            for z in (z0..=z1).step_by(2) {
+                map.get_cell_mut(TileCoord::new(x as u8, z as u8)).terrain_type = BLOCKED_CLASS;
+            }
+        }
+    }
+    for entry in input["regions"].as_array().into_iter().flatten() {
+        let r: Vec<i64> = entry.as_array().into_iter().flatten().filter_map(Value::as_i64).collect();
+        let [x, z, region] = r[..] else { return Err("regions entries are [x, z, region]".into()) };
+        map.set_cell_region(TileCoord::new(x as u8, z as u8), region as u16);
+    }
+    Ok(map)
+}
+
+fn melee_damage(input: &Value) -> Result<Value, String> {
+    let health = int(input, "health")? as u16;
+    let attacker = Unit {
+        state: PersonState::Fighting,
+        health,
+        max_health: int(input, "max_health")? as u16,
+        bloodlust: flag(input, "bloodlust"),
+        ..Unit::new_person(0, int(input, "subtype")? as u8, 0, WorldCoord::default())
+    };
+    Ok(json!(calculate_melee_damage(&attacker)))
+}
+
+fn pathfind_case(input: &Value) -> Result<Value, String> {
+    let map = region_map(input)?;
+    Ok(match pathfind(&map, tile(input, "start")?, tile(input, "goal")?) {
+        PathfindResult::Found(waypoints) => {
+            json!(waypoints.iter().map(|w| [w.tile_x, w.tile_z]).collect::<Vec<_>>())
+        }
+        PathfindResult::NotFound => json!("NotFound"),
+    })
+}
+
+fn route_lookup_case(input: &Value) -> Result<Value, String> {
+    let map = region_map(input)?;
+    let mut pool = SegmentPool::new();
+    for seg in input["segments"].as_array().into_iter().flatten() {
+        let idx = pool.allocate().ok_or("segment pool full")?;
+        pool.add_ref(idx);
+        let (start, end) = (tile(seg, "start")?, tile(seg, "end")?);
+        let waypoints = seg["waypoints"].as_array().cloned().unwrap_or_default();
+        let s = pool.get_mut(idx).unwrap();
+        (s.start_tile_x, s.start_tile_z, s.end_tile_x, s.end_tile_z) = (start.x, start.z, end.x, end.z);
+        s.flags = seg["flags"].as_u64().unwrap_or(0) as u8;
+        s.waypoint_count = waypoints.len() as u8;
+        for (k, wp) in waypoints.iter().enumerate() {
+            let (x, z) = pair(wp, "segment waypoint")?;
+            (s.waypoints[k].tile_x, s.waypoints[k].tile_z) = (x as u8, z as u8);
+        }
+    }
+    let mut failures = FailureCache::new();
+    for entry in input["failed"].as_array().into_iter().flatten() {
+        failures.record_failure(tile(entry, "start")?, tile(entry, "end")?);
+    }
+    let mut person = PersonMovement { position: coord(input, "position")?, ..Default::default() };
+    let (route, segment) = match route_table_lookup(&map, &mut pool, &failures, &mut person, coord(input, "target")?) {
+        RouteResult::DirectWalk => ("DirectWalk", 0),
+        RouteResult::Segment(idx) => ("Segment", idx),
+        RouteResult::NoRoute => ("NoRoute", 0),
+    };
+    Ok(json!({
+        "route": route,
+        "segment_index": segment,
+        "next_waypoint": [person.next_waypoint.x, person.next_waypoint.z],
+    }))
+}
+
+fn victory_case(input: &Value) -> Result<Value, String> {
+    let mut flags = GameFlags::new();
+    flags.set_multiplayer(flag(input, "multiplayer"));
+    let mut tribes = TribeArray::new();
+    for (tribe, t) in tribes.tribes.iter_mut().zip(input["tribes"].as_array().into_iter().flatten()) {
+        tribe.active = flag(t, "active");
+        tribe.population = int(t, "population").unwrap_or(0) as u32;
+        tribe.reincarnation_timer = int(t, "reincarnation_timer").unwrap_or(0) as i32;
+        tribe.shaman_respawning = flag(t, "shaman_respawning");
+        tribe.reincarnation_site = flag(t, "reincarnation_site");
+    }
+    check_victory_conditions(int(input, "tick_counter")? as u32, &mut flags, &mut tribes, int(input, "player_tribe")? as u8);
+    Ok(json!({
+        "won": flags.has_won(),
+        "lost": flags.has_lost(),
+        "reincarnation_timers": tribes.tribes.iter().map(|t| t.reincarnation_timer).collect::<Vec<_>>(),
+    }))
+}
+
+fn matches(actual: &Value, expected: &Value, tolerance: f64) -> bool {
+    match (actual, expected) {
+        (Value::Number(a), Value::Number(e)) => {
+            (a.as_f64().unwrap_or(f64::NAN) - e.as_f64().unwrap_or(f64::NAN)).abs() <= tolerance
+        }
+        (Value::Array(a), Value::Array(e)) => {
+            a.len() == e.len() && a.iter().zip(e).all(|(a, e)| matches(a, e, tolerance))
+        }
+        (Value::Object(a), Value::Object(e)) => {
+            e.iter().all(|(k, e)| a.get(k).is_some_and(|a| matches(a, e, tolerance)))
+        }
+        (a, e) => a == e,
+    }
+}
+
+fn fixture_files() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let mut files: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("{}: {}", dir.display(), e))
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+    files
+}
+
+fn load(path: &Path) -> Value {
+    let text = std::fs::read_to_string(path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    serde_json::from_str(&text).unwrap_or_else(|e| panic!("{}: {}", path.display(), e))
+}
+
+#[test]
+fn fixture_files_follow_the_schema() {
+    let files = fixture_files();
+    assert!(!files.is_empty());
+    for path in files {
+        let fixture = load(&path);
+        let name = path.display();
+        let function = fixture["function"].as_str().unwrap_or_else(|| panic!("{}: no function", name));
+        assert!(handler(function).is_some() || SKIPPED.iter().any(|(f, _)| *f == function),
+            "{}: no handler for {:?}", name, function);
+        assert!(fixture["description"].is_string(), "{}: no description", name);
+        let source = fixture["source"].as_str().unwrap_or_default();
+        assert!(SOURCES.contains(&source), "{}: unknown source {:?}", name, source);
+
+        let cases = fixture["cases"].as_array().unwrap_or_else(|| panic!("{}: no cases", name));
+        assert!(!cases.is_empty(), "{}: no cases", name);
+        let mut labels = std::collections::HashSet::new();
+        for case in cases {
+            let label = case["label"].as_str().unwrap_or_else(|| panic!("{}: case without label", name));
+            assert!(labels.insert(label), "{}: duplicate label {}", name, label);
+            assert!(case["input"].is_object(), "{} {}: no input", name, label);
+            assert!(!case["expected"]["result"].is_null(), "{} {}: no expected.result", name, label);
+        }
+    }
+}
+
+#[test]
+fn fixtures_match_the_reimplementation() {
+    let mut failures = Vec::new();
+    let mut checked = 0;
+    for path in fixture_files() {
+        let fixture = load(&path);
+        let function = fixture["function"].as_str().unwrap_or_default();
+        let Some(run) = handler(function) else {
+            let reason = SKIPPED.iter().find(|(f, _)| *f == function).map_or("no handler", |(_, r)| r);
+            println!("skipped {}: {}", path.display(), reason);
+            continue;
+        };
+        for case in fixture["cases"].as_array().into_iter().flatten() {
+            let label = case["label"].as_str().unwrap_or("?");
+            let expected = &case["expected"];
+            let tolerance = expected["tolerance"].as_f64().unwrap_or(0.0);
+            checked += 1;
+            match run(&case["input"]) {
+                Ok(actual) if matches(&actual, &expected["result"], tolerance) => {}
+                Ok(actual) => failures.push(format!("{} {} {}: got {}, expected {}",
+                    function, label, case["input"], actual, expected["result"])),
+                Err(e) => failures.push(format!("{} {}: bad input: {}", function, label, e)),
+            }
+        }
+    }
+    println!("{} fixture cases checked", checked);
+    assert!(failures.is_empty(), "{} of {} fixture cases failed:\n{}",
+        failures.len(), checked, failures.join("\n"));
+}